@@ -0,0 +1,14 @@
+-- CUSTOM TYPES
+CREATE TYPE operation_status AS ENUM ('pending', 'running', 'succeeded', 'failed');
+
+-- TABLE: operations
+
+ALTER TABLE operations
+    ADD COLUMN status operation_status NOT NULL DEFAULT 'pending',
+    ADD COLUMN started_at timestamp with time zone,
+    ADD COLUMN finished_at timestamp with time zone,
+    ADD COLUMN failure_reason text;
+
+-- operations created before this migration were applied synchronously
+UPDATE operations
+SET status = 'succeeded', started_at = created_at, finished_at = created_at;
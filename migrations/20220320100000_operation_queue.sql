@@ -0,0 +1,10 @@
+-- TABLE: operations
+
+ALTER TABLE operations
+    ADD COLUMN attempts integer NOT NULL DEFAULT 0,
+    ADD COLUMN locked_by text,
+    ADD COLUMN locked_until timestamp with time zone;
+
+-- workers only look for unfinished operations, oldest first
+CREATE INDEX operations_queue ON operations (created_at)
+    WHERE status IN ('pending', 'running');
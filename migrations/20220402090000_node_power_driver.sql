@@ -0,0 +1,8 @@
+-- CUSTOM TYPES
+CREATE TYPE power_driver AS ENUM ('simulated', 'ipmi', 'redfish');
+
+-- TABLE: nodes
+
+ALTER TABLE nodes
+    ADD COLUMN driver power_driver NOT NULL DEFAULT 'simulated',
+    ADD COLUMN bmc_endpoint text;
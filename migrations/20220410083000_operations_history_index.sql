@@ -0,0 +1,3 @@
+-- TABLE: operations
+
+CREATE INDEX operations_node_id_created_at ON operations (node_id, created_at);
@@ -0,0 +1,7 @@
+-- Keyset pagination sorts by these expressions and breaks the ties with the id.
+
+CREATE INDEX clusters_created_at_id ON clusters (COALESCE(created_at, 'epoch'), id);
+CREATE INDEX clusters_updated_at_id ON clusters (COALESCE(updated_at, created_at, 'epoch'), id);
+
+CREATE INDEX nodes_created_at_id ON nodes (COALESCE(created_at, 'epoch'), id);
+CREATE INDEX nodes_updated_at_id ON nodes (COALESCE(updated_at, created_at, 'epoch'), id);
@@ -0,0 +1,7 @@
+-- Versions used for optimistic concurrency (ETag / If-Match)
+
+ALTER TABLE clusters
+    ADD COLUMN version bigint NOT NULL DEFAULT 1;
+
+ALTER TABLE nodes
+    ADD COLUMN version bigint NOT NULL DEFAULT 1;
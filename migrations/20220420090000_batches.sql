@@ -0,0 +1,18 @@
+-- TABLE: batches
+
+CREATE TABLE batches
+(
+    id uuid NOT NULL PRIMARY KEY,
+    operation_type operation_type NOT NULL,
+    atomic boolean NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP
+);
+
+-- TABLE: operations
+
+ALTER TABLE operations
+    ADD COLUMN batch_id uuid CONSTRAINT operations_batches_id_fk
+        REFERENCES batches
+        ON DELETE SET NULL;
+
+CREATE INDEX operations_batch_id ON operations (batch_id);
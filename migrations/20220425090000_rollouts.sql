@@ -0,0 +1,41 @@
+-- CUSTOM TYPES
+CREATE TYPE rollout_status AS ENUM ('running', 'paused', 'succeeded', 'failed', 'aborted');
+CREATE TYPE rollout_node_status AS ENUM ('pending', 'rebooting', 'succeeded', 'failed', 'skipped');
+
+-- TABLE: rollouts
+
+CREATE TABLE rollouts
+(
+    id uuid NOT NULL PRIMARY KEY,
+    cluster_id uuid NOT NULL CONSTRAINT rollouts_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    status rollout_status NOT NULL DEFAULT 'running',
+    max_in_flight integer NOT NULL,
+    max_failures integer NOT NULL,
+    locked_by text,
+    locked_until timestamp with time zone,
+    version bigint NOT NULL DEFAULT 1,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP,
+    updated_at timestamp with time zone
+);
+
+-- workers only look for unfinished rollouts, the ones that have waited the longest first
+CREATE INDEX rollouts_queue ON rollouts (locked_until)
+    WHERE status IN ('running', 'paused');
+
+-- TABLE: rollout_nodes
+
+-- there's no foreign key to the nodes so the rollouts keep track of the nodes deleted in the middle
+CREATE TABLE rollout_nodes
+(
+    rollout_id uuid NOT NULL CONSTRAINT rollout_nodes_rollouts_id_fk
+            REFERENCES rollouts
+            ON DELETE CASCADE,
+    node_id uuid NOT NULL,
+    position integer NOT NULL,
+    status rollout_node_status NOT NULL DEFAULT 'pending',
+    operation_id uuid,
+    failure_reason text,
+    PRIMARY KEY (rollout_id, node_id)
+);
@@ -0,0 +1,38 @@
+-- CUSTOM TYPES
+CREATE TYPE missed_run_policy AS ENUM ('run_once', 'skip');
+
+-- TABLE: schedules
+
+-- the target is either a node or a cluster, and the schedule either runs once or follows a cron expression
+CREATE TABLE schedules
+(
+    id uuid NOT NULL PRIMARY KEY,
+    operation_type operation_type NOT NULL,
+    node_id uuid CONSTRAINT schedules_nodes_id_fk
+            REFERENCES nodes
+            ON DELETE CASCADE,
+    cluster_id uuid CONSTRAINT schedules_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    run_at timestamp with time zone,
+    cron text,
+    missed_run_policy missed_run_policy NOT NULL DEFAULT 'run_once',
+    enabled boolean NOT NULL DEFAULT true,
+    next_run_at timestamp with time zone,
+    last_run_at timestamp with time zone,
+    last_batch_id uuid CONSTRAINT schedules_batches_id_fk
+            REFERENCES batches
+            ON DELETE SET NULL,
+    last_error text,
+    locked_by text,
+    locked_until timestamp with time zone,
+    version bigint NOT NULL DEFAULT 1,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP,
+    updated_at timestamp with time zone,
+    CONSTRAINT schedules_target CHECK ((node_id IS NULL) <> (cluster_id IS NULL)),
+    CONSTRAINT schedules_timing CHECK ((run_at IS NULL) <> (cron IS NULL))
+);
+
+-- the scheduler only looks for the enabled schedules that will run again
+CREATE INDEX schedules_queue ON schedules (next_run_at)
+    WHERE enabled AND next_run_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Weekly time ranges in which the nodes of a cluster can be powered off or rebooted
+
+ALTER TABLE clusters
+    ADD COLUMN maintenance_windows jsonb NOT NULL DEFAULT '[]';
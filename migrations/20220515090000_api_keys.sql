@@ -0,0 +1,16 @@
+-- TABLE: api_keys
+
+-- only a hash of the secret of each key is stored
+CREATE TABLE api_keys
+(
+    id uuid NOT NULL PRIMARY KEY,
+    owner text NOT NULL,
+    admin boolean NOT NULL DEFAULT false,
+    secret_hash text NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP,
+    expires_at timestamp with time zone,
+    last_used_at timestamp with time zone,
+    revoked_at timestamp with time zone
+);
+
+CREATE UNIQUE INDEX api_keys_secret_hash ON api_keys (secret_hash);
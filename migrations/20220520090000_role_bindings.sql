@@ -0,0 +1,21 @@
+-- CUSTOM TYPES
+CREATE TYPE rbac_role AS ENUM ('viewer', 'operator', 'admin');
+
+-- TABLE: role_bindings
+
+-- bindings without cluster give the role in every cluster
+CREATE TABLE role_bindings
+(
+    id uuid NOT NULL PRIMARY KEY,
+    subject text NOT NULL,
+    role rbac_role NOT NULL,
+    cluster_id uuid CONSTRAINT role_bindings_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX role_bindings_subject_role_cluster ON role_bindings (subject, role, cluster_id)
+    WHERE cluster_id IS NOT NULL;
+CREATE UNIQUE INDEX role_bindings_subject_role ON role_bindings (subject, role)
+    WHERE cluster_id IS NULL;
@@ -0,0 +1,34 @@
+-- TABLE: organizations
+
+CREATE TABLE organizations
+(
+    id uuid NOT NULL PRIMARY KEY,
+    name text NOT NULL,
+    created_at timestamp with time zone DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX organization_name ON organizations (name);
+
+-- clusters without a tenant belong to the operators of the API
+ALTER TABLE clusters
+    ADD COLUMN tenant_id uuid CONSTRAINT clusters_organizations_id_fk
+            REFERENCES organizations;
+
+-- copy of the tenant of the cluster, so the names of the nodes can be unique per tenant.
+-- The tenant of a cluster never changes, and the repositories set it when a node is
+-- written.
+ALTER TABLE nodes ADD COLUMN tenant_id uuid;
+
+ALTER TABLE api_keys
+    ADD COLUMN tenant_id uuid CONSTRAINT api_keys_organizations_id_fk
+            REFERENCES organizations
+            ON DELETE CASCADE;
+
+-- names are unique per tenant
+DROP INDEX cluster_name;
+CREATE UNIQUE INDEX cluster_name ON clusters (name) WHERE tenant_id IS NULL;
+CREATE UNIQUE INDEX cluster_tenant_name ON clusters (tenant_id, name) WHERE tenant_id IS NOT NULL;
+
+DROP INDEX node_name;
+CREATE UNIQUE INDEX node_name ON nodes (name) WHERE tenant_id IS NULL;
+CREATE UNIQUE INDEX node_tenant_name ON nodes (tenant_id, name) WHERE tenant_id IS NOT NULL;
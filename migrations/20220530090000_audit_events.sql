@@ -0,0 +1,42 @@
+-- CUSTOM TYPES
+CREATE TYPE audit_action AS ENUM ('create', 'update', 'delete', 'poweron', 'poweroff', 'reboot', 'execute', 'fail');
+CREATE TYPE audit_target_type AS ENUM ('cluster', 'node', 'operation', 'batch');
+CREATE TYPE audit_outcome AS ENUM ('success', 'failure');
+
+-- TABLE: audit_events
+
+-- the events outlive their targets and their organizations, so there are no foreign keys
+CREATE TABLE audit_events
+(
+    id uuid NOT NULL PRIMARY KEY,
+    actor text NOT NULL,
+    tenant_id uuid,
+    action audit_action NOT NULL,
+    target_type audit_target_type NOT NULL,
+    target_id uuid,
+    before jsonb,
+    after jsonb,
+    request_id text,
+    source_ip text,
+    outcome audit_outcome NOT NULL,
+    reason text,
+    created_at timestamp with time zone NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE INDEX audit_events_created_at ON audit_events (created_at, id);
+CREATE INDEX audit_events_target ON audit_events (target_id, created_at);
+
+-- the events are append-only
+CREATE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit events can''t be changed or deleted';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+    BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
+
+CREATE TRIGGER audit_events_no_truncate
+    BEFORE TRUNCATE ON audit_events
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
@@ -0,0 +1,7 @@
+-- the idempotency keys belong to the caller that made the request, so callers can't get the
+-- responses of the others. The keys stored so far don't tell whose they are, so they're dropped.
+DELETE FROM idempotency_keys;
+
+ALTER TABLE idempotency_keys ADD COLUMN owner text NOT NULL;
+ALTER TABLE idempotency_keys DROP CONSTRAINT idempotency_keys_pkey;
+ALTER TABLE idempotency_keys ADD PRIMARY KEY (owner, key);
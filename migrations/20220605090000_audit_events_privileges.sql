@@ -0,0 +1,4 @@
+-- besides the triggers, the role that owns the table (the one of the app, which runs the
+-- migrations) loses the privileges to change the events, so the changes are refused before
+-- any trigger runs. Superusers bypass the privileges, so the app shouldn't run as one.
+REVOKE UPDATE, DELETE, TRUNCATE ON audit_events FROM PUBLIC, CURRENT_USER;
@@ -0,0 +1,30 @@
+-- nodes.tenant_id is a copy of the tenant of the cluster of the node, which the
+-- repositories set. The triggers refuse the writes that would make them differ, so a
+-- bug can't show a node to another tenant.
+UPDATE nodes n SET tenant_id = c.tenant_id
+FROM clusters c
+WHERE n.cluster_id = c.id AND n.tenant_id IS DISTINCT FROM c.tenant_id;
+
+CREATE FUNCTION nodes_tenant_of_cluster() RETURNS trigger AS $$
+BEGIN
+    IF NEW.tenant_id IS DISTINCT FROM (SELECT tenant_id FROM clusters WHERE id = NEW.cluster_id) THEN
+        RAISE EXCEPTION 'the tenant of a node must be the tenant of its cluster';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER nodes_tenant_of_cluster
+    BEFORE INSERT OR UPDATE OF cluster_id, tenant_id ON nodes
+    FOR EACH ROW EXECUTE FUNCTION nodes_tenant_of_cluster();
+
+CREATE FUNCTION clusters_tenant_never_changes() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'the tenant of a cluster can''t be changed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER clusters_tenant_never_changes
+    BEFORE UPDATE OF tenant_id ON clusters
+    FOR EACH ROW WHEN (OLD.tenant_id IS DISTINCT FROM NEW.tenant_id)
+    EXECUTE FUNCTION clusters_tenant_never_changes();
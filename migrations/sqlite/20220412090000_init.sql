@@ -0,0 +1,59 @@
+-- SQLite has no enum types, so they are stored as text with a CHECK constraint.
+-- Ids are stored as 16 byte blobs and timestamps as text, which is how sqlx
+-- encodes uuids and chrono dates for SQLite.
+
+-- TABLE: clusters
+
+CREATE TABLE clusters
+(
+    id blob NOT NULL PRIMARY KEY,
+    name text NOT NULL,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at text
+);
+
+CREATE UNIQUE INDEX cluster_name ON clusters (name);
+
+-- TABLE: nodes
+
+CREATE TABLE nodes
+(
+    id blob NOT NULL PRIMARY KEY,
+    name text NOT NULL,
+    cluster_id blob NOT NULL CONSTRAINT nodes_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    status text CHECK (status IN ('poweron', 'poweroff', 'rebooting')),
+    driver text NOT NULL DEFAULT 'simulated' CHECK (driver IN ('simulated', 'ipmi', 'redfish')),
+    bmc_endpoint text,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at text
+);
+
+CREATE UNIQUE INDEX node_name ON nodes (name);
+
+-- TABLE: operations
+
+CREATE TABLE operations
+(
+    id blob NOT NULL PRIMARY KEY,
+    operation_type text NOT NULL CHECK (operation_type IN ('poweron', 'poweroff', 'reboot')),
+    node_id blob NOT NULL CONSTRAINT operations_nodes_id_fk
+            REFERENCES nodes
+            ON DELETE CASCADE,
+    status text NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'succeeded', 'failed')),
+    started_at text,
+    finished_at text,
+    failure_reason text,
+    attempts integer NOT NULL DEFAULT 0,
+    locked_by text,
+    locked_until text,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at text
+);
+
+-- workers only look for unfinished operations, oldest first
+CREATE INDEX operations_queue ON operations (created_at)
+    WHERE status IN ('pending', 'running');
+
+CREATE INDEX operations_node_id_created_at ON operations (node_id, created_at);
@@ -0,0 +1,5 @@
+-- Versions used for optimistic concurrency (ETag / If-Match)
+
+ALTER TABLE clusters ADD COLUMN version integer NOT NULL DEFAULT 1;
+
+ALTER TABLE nodes ADD COLUMN version integer NOT NULL DEFAULT 1;
@@ -0,0 +1,18 @@
+-- TABLE: batches
+
+CREATE TABLE batches
+(
+    id blob NOT NULL PRIMARY KEY,
+    operation_type text NOT NULL CHECK (operation_type IN ('poweron', 'poweroff', 'reboot')),
+    atomic boolean NOT NULL,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+-- TABLE: operations
+
+ALTER TABLE operations
+    ADD COLUMN batch_id blob CONSTRAINT operations_batches_id_fk
+        REFERENCES batches
+        ON DELETE SET NULL;
+
+CREATE INDEX operations_batch_id ON operations (batch_id);
@@ -0,0 +1,37 @@
+-- TABLE: rollouts
+
+CREATE TABLE rollouts
+(
+    id blob NOT NULL PRIMARY KEY,
+    cluster_id blob NOT NULL CONSTRAINT rollouts_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    status text NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'paused', 'succeeded', 'failed', 'aborted')),
+    max_in_flight integer NOT NULL,
+    max_failures integer NOT NULL,
+    locked_by text,
+    locked_until text,
+    version integer NOT NULL DEFAULT 1,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at text
+);
+
+-- workers only look for unfinished rollouts, the ones that have waited the longest first
+CREATE INDEX rollouts_queue ON rollouts (locked_until)
+    WHERE status IN ('running', 'paused');
+
+-- TABLE: rollout_nodes
+
+-- there's no foreign key to the nodes so the rollouts keep track of the nodes deleted in the middle
+CREATE TABLE rollout_nodes
+(
+    rollout_id blob NOT NULL CONSTRAINT rollout_nodes_rollouts_id_fk
+            REFERENCES rollouts
+            ON DELETE CASCADE,
+    node_id blob NOT NULL,
+    position integer NOT NULL,
+    status text NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'rebooting', 'succeeded', 'failed', 'skipped')),
+    operation_id blob,
+    failure_reason text,
+    PRIMARY KEY (rollout_id, node_id)
+);
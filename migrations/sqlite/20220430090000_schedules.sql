@@ -0,0 +1,35 @@
+-- TABLE: schedules
+
+-- the target is either a node or a cluster, and the schedule either runs once or follows a cron expression
+CREATE TABLE schedules
+(
+    id blob NOT NULL PRIMARY KEY,
+    operation_type text NOT NULL CHECK (operation_type IN ('poweron', 'poweroff', 'reboot')),
+    node_id blob CONSTRAINT schedules_nodes_id_fk
+            REFERENCES nodes
+            ON DELETE CASCADE,
+    cluster_id blob CONSTRAINT schedules_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    run_at text,
+    cron text,
+    missed_run_policy text NOT NULL DEFAULT 'run_once' CHECK (missed_run_policy IN ('run_once', 'skip')),
+    enabled boolean NOT NULL DEFAULT true,
+    next_run_at text,
+    last_run_at text,
+    last_batch_id blob CONSTRAINT schedules_batches_id_fk
+            REFERENCES batches
+            ON DELETE SET NULL,
+    last_error text,
+    locked_by text,
+    locked_until text,
+    version integer NOT NULL DEFAULT 1,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    updated_at text,
+    CONSTRAINT schedules_target CHECK ((node_id IS NULL) <> (cluster_id IS NULL)),
+    CONSTRAINT schedules_timing CHECK ((run_at IS NULL) <> (cron IS NULL))
+);
+
+-- the scheduler only looks for the enabled schedules that will run again
+CREATE INDEX schedules_queue ON schedules (next_run_at)
+    WHERE enabled AND next_run_at IS NOT NULL;
@@ -0,0 +1,16 @@
+-- TABLE: idempotency_keys
+
+-- requests made with an `Idempotency-Key` header, the response is NULL while the request is being processed
+CREATE TABLE idempotency_keys
+(
+    key text NOT NULL PRIMARY KEY,
+    request_hash text NOT NULL,
+    status integer,
+    headers text,
+    body blob,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    expires_at text NOT NULL
+);
+
+-- the expired keys are deleted as new ones are claimed
+CREATE INDEX idempotency_keys_expires_at ON idempotency_keys (expires_at);
@@ -0,0 +1,16 @@
+-- TABLE: api_keys
+
+-- only a hash of the secret of each key is stored
+CREATE TABLE api_keys
+(
+    id blob NOT NULL PRIMARY KEY,
+    owner text NOT NULL,
+    admin boolean NOT NULL DEFAULT false,
+    secret_hash text NOT NULL,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    expires_at text,
+    last_used_at text,
+    revoked_at text
+);
+
+CREATE UNIQUE INDEX api_keys_secret_hash ON api_keys (secret_hash);
@@ -0,0 +1,18 @@
+-- TABLE: role_bindings
+
+-- bindings without cluster give the role in every cluster
+CREATE TABLE role_bindings
+(
+    id blob NOT NULL PRIMARY KEY,
+    subject text NOT NULL,
+    role text NOT NULL CHECK (role IN ('viewer', 'operator', 'admin')),
+    cluster_id blob CONSTRAINT role_bindings_clusters_id_fk
+            REFERENCES clusters
+            ON DELETE CASCADE,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now'))
+);
+
+CREATE UNIQUE INDEX role_bindings_subject_role_cluster ON role_bindings (subject, role, cluster_id)
+    WHERE cluster_id IS NOT NULL;
+CREATE UNIQUE INDEX role_bindings_subject_role ON role_bindings (subject, role)
+    WHERE cluster_id IS NULL;
@@ -0,0 +1,33 @@
+-- TABLE: audit_events
+
+-- the events outlive their targets and their organizations, so there are no foreign keys
+CREATE TABLE audit_events
+(
+    id blob NOT NULL PRIMARY KEY,
+    actor text NOT NULL,
+    tenant_id blob,
+    action text NOT NULL CHECK (action IN ('create', 'update', 'delete', 'poweron', 'poweroff', 'reboot', 'execute', 'fail')),
+    target_type text NOT NULL CHECK (target_type IN ('cluster', 'node', 'operation', 'batch')),
+    target_id blob,
+    before text,
+    after text,
+    request_id text,
+    source_ip text,
+    outcome text NOT NULL CHECK (outcome IN ('success', 'failure')),
+    reason text,
+    created_at text NOT NULL
+);
+
+CREATE INDEX audit_events_created_at ON audit_events (created_at, id);
+CREATE INDEX audit_events_target ON audit_events (target_id, created_at);
+
+-- the events are append-only
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'audit events can''t be changed or deleted');
+END;
+
+CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'audit events can''t be changed or deleted');
+END;
@@ -0,0 +1,18 @@
+-- the idempotency keys belong to the caller that made the request, so callers can't get the
+-- responses of the others. The keys stored so far don't tell whose they are, so they're dropped.
+DROP TABLE idempotency_keys;
+
+CREATE TABLE idempotency_keys
+(
+    owner text NOT NULL,
+    key text NOT NULL,
+    request_hash text NOT NULL,
+    status integer,
+    headers text,
+    body blob,
+    created_at text DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    expires_at text NOT NULL,
+    PRIMARY KEY (owner, key)
+);
+
+CREATE INDEX idempotency_keys_expires_at ON idempotency_keys (expires_at);
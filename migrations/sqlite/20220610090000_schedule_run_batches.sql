@@ -0,0 +1,3 @@
+-- the batch reserved for the due run of a schedule, so a run that's tried again after its
+-- lease expired doesn't enqueue the operations twice
+ALTER TABLE schedules ADD COLUMN run_batch_id blob;
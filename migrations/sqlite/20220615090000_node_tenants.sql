@@ -0,0 +1,22 @@
+-- nodes.tenant_id is a copy of the tenant of the cluster of the node, which the
+-- repositories set. The triggers refuse the writes that would make them differ, so a
+-- bug can't show a node to another tenant.
+UPDATE nodes SET tenant_id = (SELECT tenant_id FROM clusters WHERE id = nodes.cluster_id);
+
+CREATE TRIGGER nodes_tenant_of_cluster_insert BEFORE INSERT ON nodes
+WHEN NEW.tenant_id IS NOT (SELECT tenant_id FROM clusters WHERE id = NEW.cluster_id)
+BEGIN
+    SELECT RAISE(ABORT, 'the tenant of a node must be the tenant of its cluster');
+END;
+
+CREATE TRIGGER nodes_tenant_of_cluster_update BEFORE UPDATE OF cluster_id, tenant_id ON nodes
+WHEN NEW.tenant_id IS NOT (SELECT tenant_id FROM clusters WHERE id = NEW.cluster_id)
+BEGIN
+    SELECT RAISE(ABORT, 'the tenant of a node must be the tenant of its cluster');
+END;
+
+CREATE TRIGGER clusters_tenant_never_changes BEFORE UPDATE OF tenant_id ON clusters
+WHEN OLD.tenant_id IS NOT NEW.tenant_id
+BEGIN
+    SELECT RAISE(ABORT, 'the tenant of a cluster can''t be changed');
+END;
@@ -0,0 +1,8 @@
+-- the subjects are prefixed with where the callers come from, `apikey:` for the owners of
+-- the API keys and `jwt:` for the `sub` of the tokens, so one can't be mistaken for the
+-- other. The bindings made before keep applying to both.
+UPDATE role_bindings SET subject = 'apikey:' || subject;
+
+INSERT INTO role_bindings (id, subject, role, cluster_id, created_at)
+SELECT randomblob(16), 'jwt:' || substr(subject, 8), role, cluster_id, created_at
+FROM role_bindings;
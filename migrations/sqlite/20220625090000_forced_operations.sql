@@ -0,0 +1,2 @@
+-- the operations requested with `force` don't check the status of the node when they run
+ALTER TABLE operations ADD COLUMN forced boolean NOT NULL DEFAULT false;
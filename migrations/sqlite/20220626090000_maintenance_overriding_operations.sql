@@ -0,0 +1,3 @@
+-- the operations requested with `override_maintenance` don't check the maintenance windows
+-- of the cluster when they run
+ALTER TABLE operations ADD COLUMN overrides_maintenance boolean NOT NULL DEFAULT false;
@@ -0,0 +1,471 @@
+use crate::domain::{
+    models::{ApiKey, MintedApiKey},
+    repository::{ApiKeyRepository, RepositoryError},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Start of the secrets of the keys, so they're easy to spot, e.g. in leaked files.
+pub const SECRET_PREFIX: &str = "cna_";
+/// Shortest secret accepted for the bootstrap key.
+pub const MIN_BOOTSTRAP_SECRET_LEN: usize = 32;
+/// How often `last_used_at` is written while a key keeps being used.
+const LAST_USE_INTERVAL_SECS: i64 = 60;
+
+#[derive(Error, Debug)]
+pub enum ApiKeyServiceError {
+    #[error("The API key is not valid")]
+    InvalidKey,
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+pub type ApiKeyServiceResult = Result<ApiKey, ApiKeyServiceError>;
+
+/// Checks the bearer tokens of the requests.
+#[async_trait]
+pub trait Authenticator: Send + Sync + 'static {
+    /// Returns the key of the token, or [`ApiKeyServiceError::InvalidKey`] if it can't be used.
+    async fn authenticate(&self, token: &str) -> ApiKeyServiceResult;
+}
+
+/// Hash of a secret, which is what the repositories store.
+///
+/// The secrets are random and long, so a fast hash is as safe as a slow one like argon2
+/// and keeps the authentication of every request cheap.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[derive(Debug, Clone)]
+struct CachedKey {
+    key: ApiKey,
+    cached_at: Instant,
+}
+
+/// Mints, revokes and checks the API keys.
+///
+/// The keys that were used recently are cached for `cache_ttl`, so a key revoked by
+/// another replica of the API may still work for that long. `last_used_at` is updated
+/// in the background, at most once a minute per key and replica, cached or not.
+#[derive(Clone)]
+pub struct ApiKeyService<K: ApiKeyRepository> {
+    repository: Arc<K>,
+    cache: Arc<RwLock<HashMap<String, CachedKey>>>,
+    cache_ttl: Duration,
+}
+
+impl<K: ApiKeyRepository> ApiKeyService<K> {
+    pub fn new(repository: K, cache_ttl: Duration) -> Self {
+        Self {
+            repository: Arc::new(repository),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache_ttl,
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_all(&self) -> Result<Vec<ApiKey>, ApiKeyServiceError> {
+        let keys = self.repository.get_api_keys().await?;
+        Ok(keys)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, api_key_id: &Uuid) -> ApiKeyServiceResult {
+        let key = self.repository.get_api_key(api_key_id).await?;
+        Ok(key)
+    }
+
+    /// Creates a key with a new random secret, which is returned only this time.
+    /// The key of a tenant is limited to the clusters of the organization.
+    #[instrument(skip(self))]
+    pub async fn mint(
+        &self,
+        owner: &str,
+        admin: bool,
+        tenant_id: Option<Uuid>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<MintedApiKey, ApiKeyServiceError> {
+        if owner.trim().is_empty() {
+            return Err(ApiKeyServiceError::InvalidRequest(
+                "The owner of an API key can't be empty".to_string(),
+            ));
+        }
+        if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(ApiKeyServiceError::InvalidRequest(
+                "`expires_at` must be in the future".to_string(),
+            ));
+        }
+
+        let secret = format!(
+            "{}{}",
+            SECRET_PREFIX,
+            base64::encode_config(rand::random::<[u8; 32]>(), base64::URL_SAFE_NO_PAD)
+        );
+        let key = ApiKey {
+            id: Uuid::new_v4(),
+            owner: owner.to_string(),
+            admin,
+            tenant_id,
+            created_at: None,
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        let key = self
+            .repository
+            .create_api_key(&key, &hash_secret(&secret))
+            .await?;
+        Ok(MintedApiKey { key, secret })
+    }
+
+    /// Stores the given secret as an admin key if it isn't stored yet, so the first keys
+    /// can be minted.
+    #[instrument(skip(self, secret))]
+    pub async fn bootstrap(&self, secret: &str) -> ApiKeyServiceResult {
+        if secret.len() < MIN_BOOTSTRAP_SECRET_LEN {
+            return Err(ApiKeyServiceError::InvalidRequest(format!(
+                "The bootstrap key must have at least {} characters",
+                MIN_BOOTSTRAP_SECRET_LEN
+            )));
+        }
+        let secret_hash = hash_secret(secret);
+        match self
+            .repository
+            .get_api_key_by_secret_hash(&secret_hash)
+            .await
+        {
+            Ok(key) => Ok(key),
+            Err(RepositoryError::DoesNotExist) => {
+                let key = ApiKey {
+                    id: Uuid::new_v4(),
+                    owner: "bootstrap".to_string(),
+                    admin: true,
+                    tenant_id: None,
+                    created_at: None,
+                    expires_at: None,
+                    last_used_at: None,
+                    revoked_at: None,
+                };
+                let key = self.repository.create_api_key(&key, &secret_hash).await?;
+                Ok(key)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Mints an admin key owned by `bootstrap` if there are no keys at all, so the first
+    /// keys can be minted without a bootstrap secret.
+    #[instrument(skip(self))]
+    pub async fn bootstrap_if_empty(&self) -> Result<Option<MintedApiKey>, ApiKeyServiceError> {
+        if self.has_keys().await? {
+            return Ok(None);
+        }
+        self.mint("bootstrap", true, None, None).await.map(Some)
+    }
+
+    /// Whether any key was ever minted, revoked or not.
+    #[instrument(skip(self))]
+    pub async fn has_keys(&self) -> Result<bool, ApiKeyServiceError> {
+        Ok(!self.repository.get_api_keys().await?.is_empty())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn revoke(&self, api_key_id: &Uuid) -> ApiKeyServiceResult {
+        let key = self.repository.revoke_api_key(api_key_id).await?;
+        self.cache
+            .write()
+            .map_err(RepositoryError::from)?
+            .retain(|_, cached| cached.key.id != key.id);
+        Ok(key)
+    }
+
+    /// Drops the cached keys of the tenant, whose organization was deleted along with its
+    /// keys. Like revoked keys, they may still work for `cache_ttl` in other replicas.
+    pub fn forget_tenant(&self, tenant_id: &Uuid) -> Result<(), ApiKeyServiceError> {
+        self.cache
+            .write()
+            .map_err(RepositoryError::from)?
+            .retain(|_, cached| cached.key.tenant_id.as_ref() != Some(tenant_id));
+        Ok(())
+    }
+
+    fn cached(&self, secret_hash: &str) -> Option<ApiKey> {
+        let cache = self.cache.read().ok()?;
+        cache
+            .get(secret_hash)
+            .filter(|cached| cached.cached_at.elapsed() < self.cache_ttl)
+            .map(|cached| cached.key.clone())
+    }
+
+    /// Records the use of the key in the background, so it doesn't slow the request down,
+    /// unless it was recorded less than [`LAST_USE_INTERVAL_SECS`] ago.
+    fn record_use(&self, key: &ApiKey, secret_hash: &str, used_at: DateTime<Utc>) {
+        let interval = chrono::Duration::seconds(LAST_USE_INTERVAL_SECS);
+        if key
+            .last_used_at
+            .is_some_and(|last_used_at| used_at - last_used_at < interval)
+        {
+            return;
+        }
+        // the next uses of the cached key are only recorded after the interval
+        if let Ok(mut cache) = self.cache.write() {
+            if let Some(cached) = cache.get_mut(secret_hash) {
+                cached.key.last_used_at = Some(used_at);
+            }
+        }
+        let api_key_id = key.id;
+        let repository = self.repository.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(e) = repository.record_use(&api_key_id, used_at).await {
+                tracing::error!("Error recording the use of API key {}: {:?}", api_key_id, e);
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl<K: ApiKeyRepository> Authenticator for ApiKeyService<K> {
+    #[instrument(skip(self, token))]
+    async fn authenticate(&self, token: &str) -> ApiKeyServiceResult {
+        let secret_hash = hash_secret(token);
+        let now = Utc::now();
+        let key = match self.cached(&secret_hash) {
+            Some(key) => key,
+            None => {
+                let key = match self
+                    .repository
+                    .get_api_key_by_secret_hash(&secret_hash)
+                    .await
+                {
+                    Ok(key) => key,
+                    Err(RepositoryError::DoesNotExist) => {
+                        return Err(ApiKeyServiceError::InvalidKey)
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                // only the keys that exist are cached, so random tokens can't fill the cache
+                let mut cache = self.cache.write().map_err(RepositoryError::from)?;
+                cache.retain(|_, cached| cached.cached_at.elapsed() < self.cache_ttl);
+                cache.insert(
+                    secret_hash.clone(),
+                    CachedKey {
+                        key: key.clone(),
+                        cached_at: Instant::now(),
+                    },
+                );
+                key
+            }
+        };
+
+        if !key.is_active(now) {
+            return Err(ApiKeyServiceError::InvalidKey);
+        }
+        self.record_use(&key, &secret_hash, now);
+        Ok(ApiKey {
+            last_used_at: Some(now),
+            ..key
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repository::api_key_repository::MockApiKeyRepository;
+    use chrono::Duration as ChronoDuration;
+
+    fn create_test_key(revoked_at: Option<DateTime<Utc>>) -> ApiKey {
+        ApiKey {
+            id: Uuid::new_v4(),
+            owner: "ops-team".to_string(),
+            admin: false,
+            tenant_id: None,
+            created_at: Some(Utc::now()),
+            expires_at: None,
+            last_used_at: None,
+            revoked_at,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn minted_keys_are_stored_hashed() {
+        let stored_hash = Arc::new(std::sync::Mutex::new(String::new()));
+        let stored = stored_hash.clone();
+        let mut repo = MockApiKeyRepository::default();
+        repo.expect_create_api_key()
+            .withf(|key, _| key.owner == "ops-team" && key.admin)
+            .once()
+            .returning(move |key, secret_hash| {
+                *stored.lock().unwrap() = secret_hash.to_string();
+                Ok(key.clone())
+            });
+        let service = ApiKeyService::new(repo, Duration::from_secs(60));
+
+        let minted = service.mint("ops-team", true, None, None).await.unwrap();
+        assert!(minted.secret.starts_with(SECRET_PREFIX));
+        assert_eq!(*stored_hash.lock().unwrap(), hash_secret(&minted.secret));
+
+        for (owner, expires_at) in [
+            ("", None),
+            ("ops-team", Some(Utc::now() - ChronoDuration::hours(1))),
+        ] {
+            assert!(matches!(
+                service.mint(owner, false, None, expires_at).await,
+                Err(ApiKeyServiceError::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn authenticate_caches_the_keys() {
+        let key = create_test_key(None);
+        let key_id = key.id;
+        let mut repo = MockApiKeyRepository::default();
+        repo.expect_get_api_key_by_secret_hash()
+            .withf(|secret_hash| *secret_hash == hash_secret("cna_secret"))
+            .once()
+            .returning(move |_| Ok(key.clone()));
+        // the next uses come too soon to be recorded again
+        repo.expect_record_use()
+            .withf(move |id, _| *id == key_id)
+            .once()
+            .returning(|_, _| Ok(()));
+        let service = ApiKeyService::new(repo, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            let key = service.authenticate("cna_secret").await.unwrap();
+            assert_eq!(key.id, key_id);
+            assert!(key.last_used_at.is_some());
+        }
+        // lets the uses be recorded in the background
+        actix_rt::task::yield_now().await;
+    }
+
+    #[actix_rt::test]
+    async fn authenticate_only_records_the_uses_once_a_minute() {
+        let recently_used = ApiKey {
+            last_used_at: Some(Utc::now() - ChronoDuration::seconds(10)),
+            ..create_test_key(None)
+        };
+        let long_unused = ApiKey {
+            last_used_at: Some(Utc::now() - ChronoDuration::minutes(2)),
+            ..create_test_key(None)
+        };
+        let long_unused_id = long_unused.id;
+        let mut repo = MockApiKeyRepository::default();
+        repo.expect_get_api_key_by_secret_hash()
+            .returning(move |secret_hash| match secret_hash {
+                hash if hash == hash_secret("cna_recent") => Ok(recently_used.clone()),
+                _ => Ok(long_unused.clone()),
+            });
+        repo.expect_record_use()
+            .withf(move |id, _| *id == long_unused_id)
+            .once()
+            .returning(|_, _| Ok(()));
+        let service = ApiKeyService::new(repo, Duration::from_secs(60));
+
+        for token in ["cna_recent", "cna_unused"] {
+            service.authenticate(token).await.unwrap();
+        }
+        actix_rt::task::yield_now().await;
+    }
+
+    #[actix_rt::test]
+    async fn forget_tenant_drops_the_cached_keys_of_the_tenant() {
+        let tenant_id = Uuid::new_v4();
+        let tenant_key = ApiKey {
+            tenant_id: Some(tenant_id),
+            ..create_test_key(None)
+        };
+        let other_key = create_test_key(None);
+        let mut repo = MockApiKeyRepository::default();
+        let mut seq = mockall::Sequence::new();
+        repo.expect_get_api_key_by_secret_hash()
+            .withf(|secret_hash| *secret_hash == hash_secret("cna_tenant"))
+            .once()
+            .in_sequence(&mut seq)
+            .returning(move |_| Ok(tenant_key.clone()));
+        repo.expect_get_api_key_by_secret_hash()
+            .withf(|secret_hash| *secret_hash == hash_secret("cna_other"))
+            .once()
+            .in_sequence(&mut seq)
+            .returning(move |_| Ok(other_key.clone()));
+        repo.expect_get_api_key_by_secret_hash()
+            .withf(|secret_hash| *secret_hash == hash_secret("cna_tenant"))
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+        repo.expect_record_use().returning(|_, _| Ok(()));
+        let service = ApiKeyService::new(repo, Duration::from_secs(60));
+
+        for token in ["cna_tenant", "cna_other"] {
+            service.authenticate(token).await.unwrap();
+        }
+        service.forget_tenant(&tenant_id).unwrap();
+        assert!(matches!(
+            service.authenticate("cna_tenant").await,
+            Err(ApiKeyServiceError::InvalidKey)
+        ));
+        assert!(service.authenticate("cna_other").await.is_ok());
+        actix_rt::task::yield_now().await;
+    }
+
+    #[actix_rt::test]
+    async fn bootstrap_if_empty_mints_the_first_key() {
+        let mut repo = MockApiKeyRepository::default();
+        let mut seq = mockall::Sequence::new();
+        repo.expect_get_api_keys()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| Ok(vec![]));
+        repo.expect_create_api_key()
+            .withf(|key, _| key.owner == "bootstrap" && key.admin && key.tenant_id.is_none())
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|key, _| Ok(key.clone()));
+        repo.expect_get_api_keys()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|| Ok(vec![create_test_key(None)]));
+        let service = ApiKeyService::new(repo, Duration::from_secs(60));
+
+        let minted = service.bootstrap_if_empty().await.unwrap().unwrap();
+        assert!(minted.secret.starts_with(SECRET_PREFIX));
+        assert!(service.bootstrap_if_empty().await.unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn authenticate_rejects_unknown_and_revoked_keys() {
+        let revoked = create_test_key(Some(Utc::now()));
+        let mut repo = MockApiKeyRepository::default();
+        repo.expect_get_api_key_by_secret_hash()
+            .returning(move |secret_hash| {
+                if *secret_hash == hash_secret("cna_revoked") {
+                    Ok(revoked.clone())
+                } else {
+                    Err(RepositoryError::DoesNotExist)
+                }
+            });
+        repo.expect_record_use().never();
+        let service = ApiKeyService::new(repo, Duration::from_secs(60));
+
+        for token in ["cna_revoked", "im_a_valid_user"] {
+            assert!(matches!(
+                service.authenticate(token).await,
+                Err(ApiKeyServiceError::InvalidKey)
+            ));
+        }
+    }
+}
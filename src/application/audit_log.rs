@@ -0,0 +1,166 @@
+use crate::{
+    application::metrics::Metrics,
+    domain::{models::AuditEvent, repository::AuditRepository},
+};
+use async_trait::async_trait;
+use std::future::Future;
+use uuid::Uuid;
+
+/// Who is making the changes being audited, and from where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditContext {
+    pub actor: String,
+    /// Organization of the caller. The events get the one of their target instead when
+    /// it's known, so the tenants see what the workers and the platform did to theirs.
+    pub tenant_id: Option<Uuid>,
+    pub request_id: Option<String>,
+    pub source_ip: Option<String>,
+}
+
+impl AuditContext {
+    /// Context of the changes that aren't made for a request, like the ones of the workers.
+    pub fn system(actor: impl Into<String>) -> Self {
+        Self {
+            actor: actor.into(),
+            tenant_id: None,
+            request_id: None,
+            source_ip: None,
+        }
+    }
+
+    /// Fills in the details of the event that come from the context.
+    pub fn stamp(&self, event: AuditEvent) -> AuditEvent {
+        AuditEvent {
+            actor: self.actor.clone(),
+            tenant_id: self.tenant_id,
+            request_id: self.request_id.clone(),
+            source_ip: self.source_ip.clone(),
+            ..event
+        }
+    }
+}
+
+tokio::task_local! {
+    static CONTEXT: AuditContext;
+}
+
+/// Runs the future in the context, which the services that record events pick up with
+/// [`current`].
+pub async fn scope<F: Future>(context: AuditContext, f: F) -> F::Output {
+    CONTEXT.scope(context, f).await
+}
+
+/// Context set with [`scope`], or the one of the `system` outside of it.
+pub fn current() -> AuditContext {
+    CONTEXT
+        .try_with(|context| context.clone())
+        .unwrap_or_else(|_| AuditContext::system("system"))
+}
+
+/// Keeps the audit events.
+#[async_trait]
+pub trait AuditLog: Send + Sync + 'static {
+    /// The change was already made when it's recorded, so failing to record it doesn't
+    /// fail the request, which its caller would retry, making the change again.
+    async fn record(&self, event: AuditEvent);
+}
+
+/// Discards the events.
+pub struct NoAuditLog;
+
+#[async_trait]
+impl AuditLog for NoAuditLog {
+    async fn record(&self, _: AuditEvent) {}
+}
+
+/// Stores the events in the repository. The events that can't be stored are logged and
+/// counted in the metrics, so they can be alerted on.
+pub struct AuditTrail<A: AuditRepository> {
+    audit_repository: A,
+    metrics: Metrics,
+}
+
+impl<A: AuditRepository> AuditTrail<A> {
+    pub fn new(audit_repository: A) -> Self {
+        Self {
+            audit_repository,
+            metrics: Metrics::default(),
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
+
+#[async_trait]
+impl<A: AuditRepository> AuditLog for AuditTrail<A> {
+    async fn record(&self, event: AuditEvent) {
+        if let Err(e) = self.audit_repository.record_event(&event).await {
+            self.metrics.count_unrecorded_audit_event();
+            tracing::error!(
+                "Can't record the audit event {:?} of {} on {:?} {:?}: {:?}",
+                event.action,
+                event.actor,
+                event.target_type,
+                event.target_id,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        models::{AuditAction, AuditTargetType},
+        repository::{audit_repository::MockAuditRepository, RepositoryError},
+    };
+
+    #[actix_rt::test]
+    async fn events_are_stamped_with_the_context_of_the_scope() {
+        assert_eq!(current(), AuditContext::system("system"));
+
+        let context = AuditContext {
+            actor: "jane".to_string(),
+            tenant_id: Some(Uuid::new_v4()),
+            request_id: Some("my-request".to_string()),
+            source_ip: Some("10.0.0.1".to_string()),
+        };
+        let mut repo = MockAuditRepository::default();
+        repo.expect_record_event()
+            .withf(|event| {
+                event.actor == "jane"
+                    && event.request_id.as_deref() == Some("my-request")
+                    && event.source_ip.as_deref() == Some("10.0.0.1")
+            })
+            .once()
+            .returning(|event| Ok(event.clone()));
+        let log = AuditTrail::new(repo);
+
+        let event = AuditEvent::new(AuditAction::Reboot, AuditTargetType::Node, None);
+        scope(context, async { log.record(current().stamp(event)).await }).await;
+    }
+
+    #[actix_rt::test]
+    async fn events_that_cant_be_recorded_are_counted() {
+        let mut repo = MockAuditRepository::default();
+        repo.expect_record_event()
+            .returning(|_| Err(RepositoryError::Unavailable("down".to_string())));
+        let metrics = Metrics::default();
+        let log = AuditTrail::new(repo).with_metrics(metrics.clone());
+
+        for _ in 0..2 {
+            let event = AuditEvent::new(AuditAction::Reboot, AuditTargetType::Node, None);
+            log.record(event).await;
+        }
+        let text = metrics.render().await;
+        assert!(
+            text.lines().any(|l| l == "audit_events_unrecorded_total 2"),
+            "{}",
+            text
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::domain::{
+    models::OperationType,
+    repository::{ClusterRepository, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Decides when operations can run on the nodes of a cluster.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait MaintenancePolicy: Send + Sync + 'static {
+    async fn allows(
+        &self,
+        cluster_id: &Uuid,
+        operation_type: OperationType,
+        at: DateTime<Utc>,
+    ) -> RepositoryResult<bool>;
+}
+
+/// Allows every operation at any time.
+pub struct AlwaysAllowed;
+
+#[async_trait]
+impl MaintenancePolicy for AlwaysAllowed {
+    async fn allows(&self, _: &Uuid, _: OperationType, _: DateTime<Utc>) -> RepositoryResult<bool> {
+        Ok(true)
+    }
+}
+
+/// Only allows disruptive operations while one of the maintenance windows of the cluster
+/// is open. Clusters without maintenance windows allow them at any time.
+pub struct MaintenanceWindowPolicy<C: ClusterRepository> {
+    cluster_repository: C,
+}
+
+impl<C: ClusterRepository> MaintenanceWindowPolicy<C> {
+    pub fn new(cluster_repository: C) -> Self {
+        Self { cluster_repository }
+    }
+}
+
+#[async_trait]
+impl<C: ClusterRepository> MaintenancePolicy for MaintenanceWindowPolicy<C> {
+    async fn allows(
+        &self,
+        cluster_id: &Uuid,
+        operation_type: OperationType,
+        at: DateTime<Utc>,
+    ) -> RepositoryResult<bool> {
+        if !operation_type.is_disruptive() {
+            return Ok(true);
+        }
+        let cluster = self.cluster_repository.get_cluster(cluster_id).await?;
+        if cluster.maintenance_windows.is_empty() {
+            return Ok(true);
+        }
+        Ok(cluster.maintenance_windows.iter().any(|window| {
+            // the windows are validated when they're saved
+            window.is_open(at).unwrap_or_else(|e| {
+                tracing::error!(
+                    "Invalid maintenance window of cluster {}: {}",
+                    cluster_id,
+                    e
+                );
+                false
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        models::{Cluster, MaintenanceWindow},
+        repository::cluster_repository::MockClusterRepository,
+    };
+    use chrono::{NaiveTime, TimeZone};
+
+    fn create_policy(
+        maintenance_windows: Vec<MaintenanceWindow>,
+    ) -> MaintenanceWindowPolicy<MockClusterRepository> {
+        let mut cluster_repo = MockClusterRepository::default();
+        cluster_repo.expect_get_cluster().returning(move |id| {
+            Ok(Cluster {
+                id: *id,
+                name: "CLUSTER".to_string(),
+                tenant_id: None,
+                maintenance_windows: maintenance_windows.clone(),
+                created_at: None,
+                updated_at: None,
+                version: 1,
+            })
+        });
+        MaintenanceWindowPolicy::new(cluster_repo)
+    }
+
+    #[actix_rt::test]
+    async fn disruptive_operations_wait_for_a_window() {
+        // every night from 01:00 to 03:00 UTC
+        let policy = create_policy(vec![MaintenanceWindow {
+            days: vec![],
+            start: NaiveTime::from_hms(1, 0, 0),
+            duration_minutes: 120,
+            timezone: "UTC".to_string(),
+        }]);
+        let cluster_id = Uuid::new_v4();
+        let night = Utc.ymd(2022, 5, 5).and_hms(2, 0, 0);
+        let noon = Utc.ymd(2022, 5, 5).and_hms(12, 0, 0);
+
+        for operation_type in [OperationType::PowerOff, OperationType::Reboot] {
+            assert!(policy
+                .allows(&cluster_id, operation_type, night)
+                .await
+                .unwrap());
+            assert!(!policy
+                .allows(&cluster_id, operation_type, noon)
+                .await
+                .unwrap());
+        }
+        assert!(policy
+            .allows(&cluster_id, OperationType::PowerOn, noon)
+            .await
+            .unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn clusters_without_windows_allow_everything() {
+        let policy = create_policy(vec![]);
+        assert!(policy
+            .allows(&Uuid::new_v4(), OperationType::Reboot, Utc::now())
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,394 @@
+use crate::domain::{models::OperationType, repository::ClusterRepository};
+use async_trait::async_trait;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// Upper bounds of the buckets of the request durations, in seconds.
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+        }
+    }
+}
+
+/// A metric and all its samples, as they are exposed to Prometheus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricFamily {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricKind,
+    samples: Vec<Sample>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Sample {
+    suffix: &'static str,
+    labels: Vec<(&'static str, String)>,
+    value: f64,
+}
+
+impl MetricFamily {
+    pub fn new(name: &'static str, help: &'static str, kind: MetricKind) -> Self {
+        Self {
+            name,
+            help,
+            kind,
+            samples: vec![],
+        }
+    }
+
+    pub fn add(&mut self, labels: Vec<(&'static str, String)>, value: f64) {
+        self.add_with_suffix("", labels, value)
+    }
+
+    fn add_with_suffix(
+        &mut self,
+        suffix: &'static str,
+        labels: Vec<(&'static str, String)>,
+        value: f64,
+    ) {
+        self.samples.push(Sample {
+            suffix,
+            labels,
+            value,
+        })
+    }
+
+    fn add_histogram(&mut self, labels: Vec<(&'static str, String)>, histogram: &Histogram) {
+        let buckets = DURATION_BUCKETS.iter().map(|bound| bound.to_string());
+        for (le, count) in buckets.zip(&histogram.buckets) {
+            let mut labels = labels.clone();
+            labels.push(("le", le));
+            self.add_with_suffix("_bucket", labels, *count as f64);
+        }
+        let mut inf_labels = labels.clone();
+        inf_labels.push(("le", "+Inf".to_string()));
+        self.add_with_suffix("_bucket", inf_labels, histogram.count as f64);
+        self.add_with_suffix("_sum", labels.clone(), histogram.sum);
+        self.add_with_suffix("_count", labels, histogram.count as f64);
+    }
+
+    /// Writes the metric in the Prometheus text format.
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, self.kind.as_str());
+        for sample in &self.samples {
+            out.push_str(self.name);
+            out.push_str(sample.suffix);
+            if !sample.labels.is_empty() {
+                let labels: Vec<_> = sample
+                    .labels
+                    .iter()
+                    .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+                    .collect();
+                let _ = write!(out, "{{{}}}", labels.join(","));
+            }
+            let _ = writeln!(out, " {}", sample.value);
+        }
+    }
+}
+
+fn escape(label_value: &str) -> String {
+    label_value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Buckets are cumulative, each one counts the values up to its bound.
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bound, bucket) in DURATION_BUCKETS.iter().zip(&mut self.buckets) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// How an operation went, from the point of view of the API: either it was requested
+/// or rejected, and the requested ones end up succeeding or failing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperationOutcome {
+    Requested,
+    Rejected,
+    Succeeded,
+    Failed,
+}
+
+impl OperationOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationOutcome::Requested => "requested",
+            OperationOutcome::Rejected => "rejected",
+            OperationOutcome::Succeeded => "succeeded",
+            OperationOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// Metrics that are read from somewhere else when they are scraped instead of being
+/// counted by the API, like the state of the nodes.
+#[async_trait]
+pub trait Collector: Send + Sync + 'static {
+    /// The metrics that can't be read are left out, so the rest are still exposed.
+    async fn collect(&self) -> Vec<MetricFamily>;
+}
+
+/// Metrics of the API. Clones share the same values.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Registry>,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// By method, route and status.
+    http_requests: Mutex<BTreeMap<(String, String, u16), Histogram>>,
+    /// By type and outcome.
+    operations: Mutex<BTreeMap<(&'static str, OperationOutcome), u64>>,
+    unrecorded_audit_events: Mutex<u64>,
+    collectors: Mutex<Vec<Arc<dyn Collector>>>,
+}
+
+/// The values are only ever incremented, so they're still good if a thread panicked
+/// while holding the lock.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Metrics {
+    pub fn register(&self, collector: impl Collector) {
+        lock(&self.registry.collectors).push(Arc::new(collector));
+    }
+
+    /// The route is the pattern of the path (e.g. `/v1/nodes/{node_id}`) so the requests
+    /// to every node share the same metrics.
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, duration: Duration) {
+        lock(&self.registry.http_requests)
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn count_operation(&self, operation_type: OperationType, outcome: OperationOutcome) {
+        *lock(&self.registry.operations)
+            .entry((operation_type.as_str(), outcome))
+            .or_default() += 1;
+    }
+
+    /// Audit event of a change that was made but couldn't be recorded.
+    pub fn count_unrecorded_audit_event(&self) {
+        *lock(&self.registry.unrecorded_audit_events) += 1;
+    }
+
+    /// All the metrics in the Prometheus text format.
+    pub async fn render(&self) -> String {
+        let mut families = self.families();
+        let collectors = lock(&self.registry.collectors).clone();
+        for collector in collectors {
+            families.extend(collector.collect().await);
+        }
+        let mut out = String::new();
+        for family in &families {
+            family.render(&mut out);
+        }
+        out
+    }
+
+    fn families(&self) -> Vec<MetricFamily> {
+        let mut requests = MetricFamily::new(
+            "http_requests_total",
+            "Number of HTTP requests by method, route and status.",
+            MetricKind::Counter,
+        );
+        let mut durations = MetricFamily::new(
+            "http_request_duration_seconds",
+            "Time taken to answer the HTTP requests by method, route and status.",
+            MetricKind::Histogram,
+        );
+        for ((method, route, status), histogram) in lock(&self.registry.http_requests).iter() {
+            let labels = vec![
+                ("method", method.clone()),
+                ("route", route.clone()),
+                ("status", status.to_string()),
+            ];
+            requests.add(labels.clone(), histogram.count as f64);
+            durations.add_histogram(labels, histogram);
+        }
+
+        let mut operations = MetricFamily::new(
+            "operations_total",
+            "Number of operations by type and outcome.",
+            MetricKind::Counter,
+        );
+        for ((operation_type, outcome), count) in lock(&self.registry.operations).iter() {
+            let labels = vec![
+                ("operation_type", operation_type.to_string()),
+                ("outcome", outcome.as_str().to_string()),
+            ];
+            operations.add(labels, *count as f64);
+        }
+
+        let mut unrecorded_audit_events = MetricFamily::new(
+            "audit_events_unrecorded_total",
+            "Number of changes whose audit event couldn't be recorded.",
+            MetricKind::Counter,
+        );
+        let count = *lock(&self.registry.unrecorded_audit_events);
+        unrecorded_audit_events.add(vec![], count as f64);
+        vec![requests, durations, operations, unrecorded_audit_events]
+    }
+}
+
+/// Number of nodes in each status of every cluster.
+pub struct NodeStatusCollector<C: ClusterRepository> {
+    cluster_repository: C,
+}
+
+impl<C: ClusterRepository> NodeStatusCollector<C> {
+    pub fn new(cluster_repository: C) -> Self {
+        Self { cluster_repository }
+    }
+}
+
+#[async_trait]
+impl<C: ClusterRepository> Collector for NodeStatusCollector<C> {
+    async fn collect(&self) -> Vec<MetricFamily> {
+        let counts = match self.cluster_repository.get_node_status_counts().await {
+            Ok(counts) => counts,
+            Err(e) => {
+                tracing::error!("Can't count the nodes for the metrics: {:?}", e);
+                return vec![];
+            }
+        };
+        let mut nodes = MetricFamily::new(
+            "nodes",
+            "Number of nodes by cluster and status.",
+            MetricKind::Gauge,
+        );
+        let counts: BTreeMap<_, _> = counts.into_iter().collect();
+        for (cluster_id, counts) in counts {
+            for (status, count) in [
+                ("poweron", counts.poweron),
+                ("poweroff", counts.poweroff),
+                ("rebooting", counts.rebooting),
+            ] {
+                let labels = vec![
+                    ("cluster_id", cluster_id.to_string()),
+                    ("status", status.to_string()),
+                ];
+                nodes.add(labels, count as f64);
+            }
+        }
+        vec![nodes]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        models::NodeStatusCounts,
+        repository::{cluster_repository::MockClusterRepository, RepositoryError},
+    };
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    #[actix_rt::test]
+    async fn metrics_are_rendered_in_the_prometheus_text_format() {
+        let metrics = Metrics::default();
+        let route = "/v1/nodes/{node_id}";
+        metrics.observe_request("GET", route, 200, Duration::from_millis(20));
+        metrics.observe_request("GET", route, 200, Duration::from_millis(300));
+        metrics.count_operation(OperationType::Reboot, OperationOutcome::Requested);
+        metrics.count_operation(OperationType::Reboot, OperationOutcome::Requested);
+
+        let text = metrics.render().await;
+        let labels = r#"method="GET",route="/v1/nodes/{node_id}",status="200""#;
+        for line in [
+            "# TYPE http_requests_total counter".to_string(),
+            format!("http_requests_total{{{}}} 2", labels),
+            "# TYPE http_request_duration_seconds histogram".to_string(),
+            format!(
+                "http_request_duration_seconds_bucket{{{},le=\"0.01\"}} 0",
+                labels
+            ),
+            format!(
+                "http_request_duration_seconds_bucket{{{},le=\"0.025\"}} 1",
+                labels
+            ),
+            format!(
+                "http_request_duration_seconds_bucket{{{},le=\"0.5\"}} 2",
+                labels
+            ),
+            format!(
+                "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+                labels
+            ),
+            format!("http_request_duration_seconds_count{{{}}} 2", labels),
+            r#"operations_total{operation_type="reboot",outcome="requested"} 2"#.to_string(),
+        ] {
+            assert!(text.lines().any(|l| l == line), "{} not in\n{}", line, text);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn nodes_are_counted_when_scraped() {
+        let cluster_id = Uuid::new_v4();
+        let mut repo = MockClusterRepository::default();
+        repo.expect_get_node_status_counts()
+            .once()
+            .returning(move || {
+                let counts = NodeStatusCounts {
+                    poweron: 3,
+                    poweroff: 1,
+                    rebooting: 0,
+                };
+                Ok(HashMap::from([(cluster_id, counts)]))
+            });
+        repo.expect_get_node_status_counts()
+            .returning(|| Err(RepositoryError::Unavailable("down".to_string())));
+        let metrics = Metrics::default();
+        metrics.register(NodeStatusCollector::new(repo));
+
+        let text = metrics.render().await;
+        let line = format!(
+            "nodes{{cluster_id=\"{}\",status=\"poweron\"}} 3",
+            cluster_id
+        );
+        assert!(text.lines().any(|l| l == line), "{}", text);
+
+        // the metrics of the API are still there when the nodes can't be counted
+        let text = metrics.render().await;
+        assert!(!text.contains("# TYPE nodes gauge"));
+        assert!(text.contains("# TYPE operations_total counter"));
+    }
+}
@@ -1 +1,12 @@
+pub mod api_key_service;
+pub mod audit_log;
+pub mod maintenance_policy;
+pub mod metrics;
 pub mod operation_service;
+pub mod operation_worker;
+pub mod power_driver;
+pub mod rbac_service;
+pub mod rollout_service;
+pub mod rollout_worker;
+pub mod schedule_service;
+pub mod scheduler;
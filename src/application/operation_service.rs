@@ -1,6 +1,28 @@
-use crate::domain::{
-    models::{Node, Operation, OperationType},
-    repository::{NodeRepository, RepositoryError},
+use crate::{
+    application::{
+        audit_log::{self, AuditLog, NoAuditLog},
+        maintenance_policy::{AlwaysAllowed, MaintenancePolicy},
+        metrics::{Metrics, OperationOutcome},
+        power_driver::{PowerDriverError, PowerDrivers},
+    },
+    domain::{
+        models::{
+            AuditAction, AuditEvent, AuditTargetType, Batch, BatchItem, BatchResult, Node,
+            NodeStatus, Operation, OperationStatus, OperationType,
+        },
+        repository::{
+            node_repository::NodeFilter,
+            pagination::{Cursor, MAX_PAGE_LIMIT},
+            NodeRepository, PageRequest, RepositoryError, TenantScoped,
+        },
+    },
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
 };
 use thiserror::Error;
 use tracing::instrument;
@@ -10,39 +32,164 @@ use uuid::Uuid;
 pub enum OperationServiceError {
     #[error("Node not found: `{0}`")]
     NodeNotFound(Uuid),
+    #[error("Can't {op:?} a node in `{from:?}` status")]
+    InvalidTransition { from: NodeStatus, op: OperationType },
+    #[error("Cluster `{0}` is outside its maintenance windows")]
+    OutsideMaintenanceWindow(Uuid),
+    #[error("Operation can't go from `{from:?}` to `{to:?}`")]
+    InvalidStatusTransition {
+        from: OperationStatus,
+        to: OperationStatus,
+    },
+    #[error("The batch has no nodes")]
+    EmptyBatch,
+    #[error("A batch can't have more than {0} nodes")]
+    BatchTooLarge(usize),
+    #[error(
+        "Nodes of the batch that can't run the operation: {}",
+        .0.iter().filter(|item| item.error.is_some()).count()
+    )]
+    BatchRejected(Vec<BatchItem>),
+    #[error(transparent)]
+    PowerDriverError(#[from] PowerDriverError),
     #[error(transparent)]
     RepositoryError(#[from] RepositoryError),
 }
 
 pub type OperationServiceResult = Result<Operation, OperationServiceError>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct OperationOptions {
+    /// Skips the node status check. Meant for operators who know what they're doing.
+    pub force: bool,
+    /// Skips the maintenance policy, running disruptive operations outside the maintenance
+    /// windows of the cluster.
+    pub override_maintenance: bool,
+}
+
+/// Largest number of nodes of a batch.
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+/// Nodes a batch runs on.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchTarget {
+    NodeIds(Vec<Uuid>),
+    ClusterId(Uuid),
+    Filter(NodeFilter),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BatchRequest {
+    pub operation_type: OperationType,
+    pub target: BatchTarget,
+    /// Creates the operations of all the nodes or none of them. Otherwise the nodes
+    /// that can't run the operation are skipped.
+    #[serde(default)]
+    pub atomic: bool,
+    /// Id of the batch, new unless the scheduler reserved one for the run of a schedule.
+    #[serde(skip)]
+    pub batch_id: Option<Uuid>,
+}
+
+#[derive(Clone)]
 pub struct OperationService<N: NodeRepository> {
     node_repository: N,
+    drivers: PowerDrivers,
+    maintenance_policy: Arc<dyn MaintenancePolicy>,
+    audit_log: Arc<dyn AuditLog>,
+    metrics: Metrics,
+}
+
+impl<N: NodeRepository> fmt::Debug for OperationService<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OperationService")
+            .field("drivers", &self.drivers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<N: NodeRepository> TenantScoped for OperationService<N> {
+    /// Same service, limited to the nodes of the tenant.
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            node_repository: self.node_repository.for_tenant(tenant_id),
+            drivers: self.drivers.clone(),
+            maintenance_policy: self.maintenance_policy.clone(),
+            audit_log: self.audit_log.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
 }
 
 impl<N> OperationService<N>
 where
     N: NodeRepository,
 {
-    pub fn new(node_repository: N) -> Self {
-        Self { node_repository }
+    /// Creates a service that allows every operation at any time.
+    pub fn new(node_repository: N, drivers: PowerDrivers) -> Self {
+        Self {
+            node_repository,
+            drivers,
+            maintenance_policy: Arc::new(AlwaysAllowed),
+            audit_log: Arc::new(NoAuditLog),
+            metrics: Metrics::default(),
+        }
+    }
+
+    pub fn with_maintenance_policy(mut self, policy: impl MaintenancePolicy) -> Self {
+        self.maintenance_policy = Arc::new(policy);
+        self
+    }
+
+    /// Records every operation requested, executed or given up on, made by the actor of
+    /// the current [`audit_log::scope`].
+    pub fn with_audit_log(mut self, audit_log: Arc<dyn AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// Counts the operations requested or rejected, and how the ones executed ended.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
     }
 
     #[instrument(skip(self))]
-    pub async fn power_on(&self, node_id: &Uuid) -> OperationServiceResult {
-        self.create_operation(node_id, OperationType::PowerOn).await
+    pub async fn power_on(
+        &self,
+        node_id: &Uuid,
+        options: OperationOptions,
+    ) -> OperationServiceResult {
+        self.create_operation(node_id, OperationType::PowerOn, options)
+            .await
     }
 
     #[instrument(skip(self))]
-    pub async fn power_off(&self, node_id: &Uuid) -> OperationServiceResult {
-        self.create_operation(node_id, OperationType::PowerOff)
+    pub async fn power_off(
+        &self,
+        node_id: &Uuid,
+        options: OperationOptions,
+    ) -> OperationServiceResult {
+        self.create_operation(node_id, OperationType::PowerOff, options)
             .await
     }
 
     #[instrument(skip(self))]
-    pub async fn reboot(&self, node_id: &Uuid) -> OperationServiceResult {
-        self.create_operation(node_id, OperationType::Reboot).await
+    pub async fn reboot(
+        &self,
+        node_id: &Uuid,
+        options: OperationOptions,
+    ) -> OperationServiceResult {
+        self.create_operation(node_id, OperationType::Reboot, options)
+            .await
+    }
+
+    async fn audit(&self, event: AuditEvent) {
+        self.audit_log
+            .record(audit_log::current().stamp(event))
+            .await
     }
 
     #[instrument(skip(self))]
@@ -50,32 +197,1282 @@ where
         &self,
         node_id: &Uuid,
         operation_type: OperationType,
+        options: OperationOptions,
     ) -> OperationServiceResult {
-        self.node_check(node_id).await?;
-        let operation = Operation::new(node_id.to_owned(), operation_type);
+        let result = self.enqueue(node_id, operation_type, options).await;
+        let outcome = match result {
+            Ok(_) => OperationOutcome::Requested,
+            Err(_) => OperationOutcome::Rejected,
+        };
+        self.metrics.count_operation(operation_type, outcome);
+        let event = AuditEvent::new(operation_type.into(), AuditTargetType::Node, Some(*node_id));
+        self.audit(event.with_result(&result)).await;
+        result
+    }
+
+    /// Enqueues a pending operation. It will be picked up and executed by a worker.
+    async fn enqueue(
+        &self,
+        node_id: &Uuid,
+        operation_type: OperationType,
+        options: OperationOptions,
+    ) -> OperationServiceResult {
+        let node = self.node_check(node_id).await?;
+        check_status(&node, operation_type, options)?;
+        self.check_maintenance(&node.cluster_id, operation_type, options)
+            .await?;
+        let operation = Operation {
+            forced: options.force,
+            overrides_maintenance: options.override_maintenance,
+            ..Operation::new(node_id.to_owned(), operation_type)
+        };
         let operation = self.node_repository.create_operation(&operation).await?;
         Ok(operation)
     }
 
+    #[instrument(skip(self))]
+    pub async fn get_batch(&self, batch_id: &Uuid) -> Result<Batch, OperationServiceError> {
+        let batch = self.node_repository.get_batch(batch_id).await?;
+        Ok(batch)
+    }
+
+    /// Enqueues the operation on every node of the target, grouping the operations in a batch.
+    ///
+    /// Atomic batches are rejected if any node can't run the operation. Otherwise those
+    /// nodes are skipped and the batch is only rejected if none of them can.
+    ///
+    /// Each node gets its own audit event, unless the batch fails before its nodes are known.
+    #[instrument(skip(self))]
+    pub async fn create_batch(
+        &self,
+        request: &BatchRequest,
+        options: OperationOptions,
+    ) -> Result<BatchResult, OperationServiceError> {
+        let result = self.enqueue_batch(request, options).await;
+        let action = AuditAction::from(request.operation_type);
+        let items = match &result {
+            Ok(BatchResult { items, .. }) => items,
+            Err(OperationServiceError::BatchRejected(items)) => items,
+            Err(e) => {
+                let event = AuditEvent::new(action, AuditTargetType::Batch, None);
+                self.audit(event.with_result(&Err::<(), _>(e))).await;
+                return result;
+            }
+        };
+        // the nodes that could run the operation weren't either if the batch was rejected
+        let rejected = result.as_ref().err().map(|e| e.to_string());
+        for item in items {
+            let item_result = match (&item.operation, &item.error) {
+                (Some(operation), _) => Ok(operation),
+                (None, error) => Err(error
+                    .clone()
+                    .or_else(|| rejected.clone())
+                    .unwrap_or_default()),
+            };
+            let outcome = match item_result {
+                Ok(_) => OperationOutcome::Requested,
+                Err(_) => OperationOutcome::Rejected,
+            };
+            self.metrics
+                .count_operation(request.operation_type, outcome);
+            let event = AuditEvent::new(action, AuditTargetType::Node, Some(item.node_id));
+            self.audit(event.with_result(&item_result)).await;
+        }
+        result
+    }
+
+    async fn enqueue_batch(
+        &self,
+        request: &BatchRequest,
+        options: OperationOptions,
+    ) -> Result<BatchResult, OperationServiceError> {
+        let nodes = self.batch_nodes(&request.target).await?;
+        if nodes.is_empty() {
+            return Err(OperationServiceError::EmptyBatch);
+        }
+
+        let cluster_ids: HashSet<Uuid> = nodes
+            .iter()
+            .filter_map(|(_, node)| node.as_ref().map(|node| node.cluster_id))
+            .collect();
+        let mut closed_clusters = HashSet::new();
+        for cluster_id in cluster_ids {
+            match self
+                .check_maintenance(&cluster_id, request.operation_type, options)
+                .await
+            {
+                Ok(()) => {}
+                Err(OperationServiceError::OutsideMaintenanceWindow(_)) => {
+                    closed_clusters.insert(cluster_id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut batch = Batch::new(request.operation_type, request.atomic);
+        batch.id = request.batch_id.unwrap_or(batch.id);
+        let mut items: Vec<BatchItem> = nodes
+            .into_iter()
+            .map(|(node_id, node)| {
+                node.ok_or(OperationServiceError::NodeNotFound(node_id))
+                    .and_then(|node| {
+                        check_status(&node, request.operation_type, options)?;
+                        match closed_clusters.contains(&node.cluster_id) {
+                            true => Err(OperationServiceError::OutsideMaintenanceWindow(
+                                node.cluster_id,
+                            )),
+                            false => Ok(()),
+                        }
+                    })
+                    .map(|_| {
+                        let operation = Operation {
+                            batch_id: Some(batch.id),
+                            forced: options.force,
+                            overrides_maintenance: options.override_maintenance,
+                            ..Operation::new(node_id, request.operation_type)
+                        };
+                        BatchItem::created(operation)
+                    })
+                    .unwrap_or_else(|e| BatchItem::failed(node_id, e))
+            })
+            .collect();
+
+        let failed = items.iter().filter(|item| item.error.is_some()).count();
+        if failed == items.len() || (request.atomic && failed > 0) {
+            // nothing was created
+            items.iter_mut().for_each(|item| item.operation = None);
+            return Err(OperationServiceError::BatchRejected(items));
+        }
+
+        if request.atomic {
+            let operations: Vec<Operation> = items
+                .into_iter()
+                .filter_map(|item| item.operation)
+                .collect();
+            let (batch, operations) = self
+                .node_repository
+                .create_batch(&batch, &operations)
+                .await?;
+            let items = operations.into_iter().map(BatchItem::created).collect();
+            return Ok(BatchResult { batch, items });
+        }
+
+        let (batch, _) = self.node_repository.create_batch(&batch, &[]).await?;
+        for item in items.iter_mut() {
+            if let Some(operation) = item.operation.take() {
+                match self.node_repository.create_operation(&operation).await {
+                    Ok(operation) => item.operation = Some(operation),
+                    // the node was deleted after it was checked
+                    Err(RepositoryError::ForeignKeyViolation) => {
+                        item.error =
+                            Some(OperationServiceError::NodeNotFound(item.node_id).to_string())
+                    }
+                    Err(e) => item.error = Some(e.to_string()),
+                }
+            }
+        }
+        Ok(BatchResult { batch, items })
+    }
+
+    /// Nodes targeted by a batch. The ids of the nodes that don't exist come without node.
+    #[instrument(skip(self))]
+    async fn batch_nodes(
+        &self,
+        target: &BatchTarget,
+    ) -> Result<Vec<(Uuid, Option<Node>)>, OperationServiceError> {
+        let filter = match target {
+            BatchTarget::NodeIds(node_ids) => {
+                let mut seen = HashSet::new();
+                let node_ids: Vec<&Uuid> = node_ids.iter().filter(|id| seen.insert(*id)).collect();
+                if node_ids.len() > MAX_BATCH_SIZE {
+                    return Err(OperationServiceError::BatchTooLarge(MAX_BATCH_SIZE));
+                }
+                let node_ids: Vec<Uuid> = node_ids.into_iter().copied().collect();
+                let mut nodes: HashMap<Uuid, Node> = self
+                    .node_repository
+                    .get_nodes_by_id(&node_ids)
+                    .await?
+                    .into_iter()
+                    .map(|node| (node.id, node))
+                    .collect();
+                return Ok(node_ids
+                    .into_iter()
+                    .map(|node_id| (node_id, nodes.remove(&node_id)))
+                    .collect());
+            }
+            BatchTarget::ClusterId(cluster_id) => NodeFilter {
+                cluster_id: Some(*cluster_id),
+                ..Default::default()
+            },
+            BatchTarget::Filter(filter) => filter.clone(),
+        };
+
+        let nodes = find_nodes(&self.node_repository, &filter, MAX_BATCH_SIZE).await?;
+        if nodes.len() > MAX_BATCH_SIZE {
+            return Err(OperationServiceError::BatchTooLarge(MAX_BATCH_SIZE));
+        }
+        Ok(nodes
+            .into_iter()
+            .map(|node| (node.id, Some(node)))
+            .collect())
+    }
+
+    /// Fails if the maintenance policy doesn't allow the operation on the nodes of the cluster
+    /// right now, unless it's overridden.
+    #[instrument(skip(self))]
+    async fn check_maintenance(
+        &self,
+        cluster_id: &Uuid,
+        operation_type: OperationType,
+        options: OperationOptions,
+    ) -> Result<(), OperationServiceError> {
+        if self
+            .maintenance_policy
+            .allows(cluster_id, operation_type, Utc::now())
+            .await?
+        {
+            return Ok(());
+        }
+        if !options.override_maintenance {
+            return Err(OperationServiceError::OutsideMaintenanceWindow(*cluster_id));
+        }
+        tracing::warn!(
+            "Overriding the maintenance windows of cluster {} to {:?}",
+            cluster_id,
+            operation_type
+        );
+        Ok(())
+    }
+
     #[instrument(skip(self))]
     async fn node_check(&self, node_id: &Uuid) -> Result<Node, OperationServiceError> {
         let result = self.node_repository.get_node(node_id).await;
-        result.map_err(|e| {
-            tracing::error!("Node not found in database: {:?}", e);
-            OperationServiceError::NodeNotFound(node_id.to_owned())
+        result.map_err(|e| match e {
+            RepositoryError::DoesNotExist => {
+                tracing::error!("Node not found in database: {:?}", e);
+                OperationServiceError::NodeNotFound(node_id.to_owned())
+            }
+            e => e.into(),
         })
     }
 
+    /// Claims the next operation in the queue, if any, leaving it in `running` status.
     #[instrument(skip(self))]
-    pub async fn power_on_without_operation(
+    pub async fn claim_next(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> Result<Option<Operation>, OperationServiceError> {
+        let operation = self
+            .node_repository
+            .claim_operation(worker_id, lease_secs)
+            .await?;
+        Ok(operation)
+    }
+
+    /// Keeps a claimed operation for `worker_id` for `lease_secs` more, so no other worker
+    /// claims it while it's still running.
+    #[instrument(skip(self))]
+    pub async fn renew_lease(
+        &self,
+        operation_id: &Uuid,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> Result<(), OperationServiceError> {
+        self.node_repository
+            .renew_operation(operation_id, worker_id, lease_secs)
+            .await?;
+        Ok(())
+    }
+
+    /// Applies an operation claimed by `worker_id` to its node and records how it went.
+    #[instrument(skip(self))]
+    pub async fn execute(&self, operation: Operation, worker_id: &str) -> OperationServiceResult {
+        let event = AuditEvent::new(
+            AuditAction::Execute,
+            AuditTargetType::Operation,
+            Some(operation.id),
+        )
+        .with_before(Some(&operation));
+        let result = self.apply_operation(operation, worker_id).await;
+        if let Ok(operation) = &result {
+            let outcome = match operation.status {
+                OperationStatus::Succeeded => OperationOutcome::Succeeded,
+                _ => OperationOutcome::Failed,
+            };
+            self.metrics
+                .count_operation(operation.operation_type, outcome);
+        }
+        self.audit(event.with_result(&result)).await;
+        result
+    }
+
+    async fn apply_operation(
+        &self,
+        mut operation: Operation,
+        worker_id: &str,
+    ) -> OperationServiceResult {
+        if !operation
+            .status
+            .can_transition_to(OperationStatus::Succeeded)
+        {
+            return Err(OperationServiceError::InvalidStatusTransition {
+                from: operation.status,
+                to: OperationStatus::Succeeded,
+            });
+        }
+        match self.apply(&operation).await {
+            Ok(_) => transition(&mut operation, OperationStatus::Succeeded, None)?,
+            Err(e) => {
+                tracing::error!("Error executing operation: {:?}", e);
+                transition(&mut operation, OperationStatus::Failed, Some(e.to_string()))?
+            }
+        }
+        let operation = self
+            .node_repository
+            .update_operation(&operation, worker_id)
+            .await?;
+        Ok(operation)
+    }
+
+    /// Gives up on an operation claimed by `worker_id` without touching its node.
+    #[instrument(skip(self))]
+    pub async fn fail(
+        &self,
+        mut operation: Operation,
+        reason: String,
+        worker_id: &str,
+    ) -> OperationServiceResult {
+        let event = AuditEvent::new(
+            AuditAction::Fail,
+            AuditTargetType::Operation,
+            Some(operation.id),
+        )
+        .with_before(Some(&operation));
+        let result = async {
+            transition(&mut operation, OperationStatus::Failed, Some(reason))?;
+            Ok(self
+                .node_repository
+                .update_operation(&operation, worker_id)
+                .await?)
+        }
+        .await;
+        if let Ok(operation) = &result {
+            self.metrics
+                .count_operation(operation.operation_type, OperationOutcome::Failed);
+        }
+        self.audit(event.with_result(&result)).await;
+        result
+    }
+
+    #[instrument(skip(self))]
+    async fn apply(&self, operation: &Operation) -> Result<(), OperationServiceError> {
+        let node = self.node_check(&operation.node_id).await?;
+        // the node may have changed since the operation was requested, and the operation may
+        // have waited in the queue until the maintenance window closed
+        let options = OperationOptions {
+            force: operation.forced,
+            override_maintenance: operation.overrides_maintenance,
+        };
+        check_status(&node, operation.operation_type, options)?;
+        self.check_maintenance(&node.cluster_id, operation.operation_type, options)
+            .await?;
+        let driver = self.drivers.get(node.driver)?;
+        match operation.operation_type {
+            OperationType::PowerOn => {
+                driver.power_on(&node).await?;
+                self.set_node_status(&node.id, NodeStatus::PowerOn).await
+            }
+            OperationType::PowerOff => {
+                driver.power_off(&node).await?;
+                self.set_node_status(&node.id, NodeStatus::PowerOff).await
+            }
+            OperationType::Reboot => {
+                self.set_node_status(&node.id, NodeStatus::Rebooting)
+                    .await?;
+                let rebooted = driver.reboot(&node).await;
+                // whatever happened, the node shouldn't be left as rebooting, so it goes
+                // back to the status it had if its state can't be known
+                let state = driver.query_power_state(&node).await;
+                let status = state.as_ref().map_or(node.status, |state| (*state).into());
+                self.set_node_status(&node.id, status).await?;
+                rebooted?;
+                state?;
+                Ok(())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn set_node_status(
         &self,
         node_id: &Uuid,
-    ) -> Result<Node, OperationServiceError> {
-        let mut node = self.node_check(node_id).await?;
-        node.status = crate::domain::models::NodeStatus::PowerOn;
+        status: NodeStatus,
+    ) -> Result<(), OperationServiceError> {
         self.node_repository
-            .update_node(&node)
+            .update_node_status(node_id, status)
             .await
-            .map_err(OperationServiceError::RepositoryError)
+            .map(|_| ())
+            .map_err(|e| match e {
+                RepositoryError::DoesNotExist => {
+                    OperationServiceError::NodeNotFound(node_id.to_owned())
+                }
+                e => e.into(),
+            })
+    }
+}
+
+/// Nodes matching the filter, going through all the pages. It stops as soon as there
+/// are more than `max_nodes`, so the caller can tell that there are too many.
+pub async fn find_nodes<N: NodeRepository>(
+    node_repository: &N,
+    filter: &NodeFilter,
+    max_nodes: usize,
+) -> Result<Vec<Node>, RepositoryError> {
+    let mut nodes = Vec::new();
+    let mut page = PageRequest {
+        limit: MAX_PAGE_LIMIT,
+        ..Default::default()
+    };
+    loop {
+        let result = node_repository.get_nodes(filter, &page).await?;
+        let last = result
+            .next_cursor
+            .and(result.items.last())
+            .map(|last| Cursor::after(last, page.sort, page.order));
+        nodes.extend(result.items);
+        match last {
+            Some(cursor) if nodes.len() <= max_nodes => page.after = Some(cursor),
+            _ => return Ok(nodes),
+        }
+    }
+}
+
+/// Checks that the node can run the operation in its current status, unless it's forced.
+fn check_status(
+    node: &Node,
+    operation_type: OperationType,
+    options: OperationOptions,
+) -> Result<(), OperationServiceError> {
+    if !node.status.allows(operation_type) {
+        if !options.force {
+            return Err(OperationServiceError::InvalidTransition {
+                from: node.status,
+                op: operation_type,
+            });
+        }
+        tracing::warn!(
+            "Forcing {:?} on node {} in {:?} status",
+            operation_type,
+            node.id,
+            node.status
+        );
+    }
+    Ok(())
+}
+
+/// Moves the operation to the next status, keeping track of when it started and finished.
+fn transition(
+    operation: &mut Operation,
+    next: OperationStatus,
+    failure_reason: Option<String>,
+) -> Result<(), OperationServiceError> {
+    if !operation.status.can_transition_to(next) {
+        return Err(OperationServiceError::InvalidStatusTransition {
+            from: operation.status,
+            to: next,
+        });
+    }
+    let now = Utc::now();
+    match next {
+        OperationStatus::Running => operation.started_at = Some(now),
+        OperationStatus::Succeeded | OperationStatus::Failed => operation.finished_at = Some(now),
+        OperationStatus::Pending => {}
+    }
+    operation.status = next;
+    operation.failure_reason = failure_reason;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::{
+            audit_log::AuditTrail,
+            maintenance_policy::MockMaintenancePolicy,
+            power_driver::{PowerDriver, PowerDriverResult, PowerState, SimulatedPowerDriver},
+        },
+        domain::{
+            models::PowerDriverKind,
+            repository::{
+                audit_repository::MockAuditRepository, node_repository::MockNodeRepository, Page,
+            },
+        },
+    };
+    use std::time::Duration;
+
+    fn simulated_drivers(driver: SimulatedPowerDriver) -> PowerDrivers {
+        PowerDrivers::new().with(PowerDriverKind::Simulated, driver)
+    }
+
+    fn running_operation(operation_type: OperationType) -> Operation {
+        let mut operation = Operation::new(Uuid::new_v4(), operation_type);
+        transition(&mut operation, OperationStatus::Running, None).unwrap();
+        operation
+    }
+
+    fn create_test_node(id: Uuid, status: NodeStatus) -> Node {
+        Node {
+            id,
+            name: "my_node".to_string(),
+            cluster_id: Uuid::new_v4(),
+            status,
+            driver: PowerDriverKind::Simulated,
+            bmc_endpoint: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn transition_tracks_timestamps() {
+        let mut operation = Operation::new(Uuid::new_v4(), OperationType::PowerOn);
+        transition(&mut operation, OperationStatus::Running, None).unwrap();
+        assert!(operation.started_at.is_some());
+        assert!(operation.finished_at.is_none());
+
+        transition(
+            &mut operation,
+            OperationStatus::Failed,
+            Some("boom".to_string()),
+        )
+        .unwrap();
+        assert!(operation.finished_at.is_some());
+        assert_eq!(operation.failure_reason, Some("boom".to_string()));
+    }
+
+    #[test]
+    fn transition_rejects_invalid_status() {
+        let mut operation = Operation::new(Uuid::new_v4(), OperationType::PowerOn);
+        let result = transition(&mut operation, OperationStatus::Succeeded, None);
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::InvalidStatusTransition {
+                from: OperationStatus::Pending,
+                to: OperationStatus::Succeeded
+            })
+        ));
+        assert_eq!(operation.status, OperationStatus::Pending);
+    }
+
+    #[actix_rt::test]
+    async fn power_on_enqueues_a_pending_operation() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOff)));
+        node_repo
+            .expect_create_operation()
+            .returning(|op| Ok(op.clone()));
+        node_repo.expect_update_node_status().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let operation = svc
+            .power_on(&Uuid::new_v4(), OperationOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Pending);
+        assert!(operation.started_at.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_enqueued_even_if_their_audit_event_cant_be_recorded() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_create_operation()
+            .once()
+            .returning(|op| Ok(op.clone()));
+        let mut audit_repo = MockAuditRepository::default();
+        audit_repo
+            .expect_record_event()
+            .returning(|_| Err(RepositoryError::Unavailable("down".to_string())));
+
+        let metrics = Metrics::default();
+        let audit_log = AuditTrail::new(audit_repo).with_metrics(metrics.clone());
+        let svc = OperationService::new(node_repo, PowerDrivers::new())
+            .with_audit_log(Arc::new(audit_log));
+        // the caller would retry it otherwise, rebooting the node twice
+        svc.reboot(&Uuid::new_v4(), OperationOptions::default())
+            .await
+            .unwrap();
+
+        let text = metrics.render().await;
+        assert!(text.lines().any(|l| l == "audit_events_unrecorded_total 1"));
+    }
+
+    #[actix_rt::test]
+    async fn operations_report_unknown_nodes_and_unavailable_storage() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .times(1)
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+        node_repo
+            .expect_get_node()
+            .returning(|_| Err(RepositoryError::Unavailable("pool timed out".to_string())));
+        node_repo.expect_create_operation().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let result = svc
+            .power_on(&Uuid::new_v4(), OperationOptions::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::NodeNotFound(_))
+        ));
+
+        let result = svc
+            .power_on(&Uuid::new_v4(), OperationOptions::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::RepositoryError(
+                RepositoryError::Unavailable(_)
+            ))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn reboot_is_rejected_if_node_is_rebooting() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::Rebooting)));
+        node_repo.expect_create_operation().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let result = svc
+            .reboot(&Uuid::new_v4(), OperationOptions::default())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::InvalidTransition {
+                from: NodeStatus::Rebooting,
+                op: OperationType::Reboot
+            })
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn forced_operations_skip_the_status_check() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOff)));
+        node_repo
+            .expect_create_operation()
+            .once()
+            .returning(|op| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let operation = svc
+            .power_off(
+                &Uuid::new_v4(),
+                OperationOptions {
+                    force: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(operation.operation_type, OperationType::PowerOff);
+        assert!(operation.forced);
+    }
+
+    /// Policy that only allows operations on the given cluster.
+    fn maintenance_policy(open_cluster_id: Uuid) -> MockMaintenancePolicy {
+        let mut policy = MockMaintenancePolicy::default();
+        policy
+            .expect_allows()
+            .returning(move |cluster_id, _, _| Ok(*cluster_id == open_cluster_id));
+        policy
+    }
+
+    #[actix_rt::test]
+    async fn operations_wait_for_the_maintenance_windows() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_create_operation()
+            .once()
+            .returning(|op| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new())
+            .with_maintenance_policy(maintenance_policy(Uuid::new_v4()));
+        let result = svc
+            .reboot(&Uuid::new_v4(), OperationOptions::default())
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::OutsideMaintenanceWindow(_))
+        ));
+
+        let options = OperationOptions {
+            override_maintenance: true,
+            ..Default::default()
+        };
+        let operation = svc.reboot(&Uuid::new_v4(), options).await.unwrap();
+        assert_eq!(operation.operation_type, OperationType::Reboot);
+        assert!(operation.overrides_maintenance);
+    }
+
+    fn batch_request(target: BatchTarget, atomic: bool) -> BatchRequest {
+        BatchRequest {
+            operation_type: OperationType::PowerOff,
+            target,
+            atomic,
+            batch_id: None,
+        }
+    }
+
+    /// Mock with a node powered on, a node powered off and no other node.
+    fn batch_node_repo(on: Uuid, off: Uuid) -> MockNodeRepository {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_get_nodes_by_id().returning(move |ids| {
+            Ok(ids
+                .iter()
+                .filter_map(|id| match *id {
+                    id if id == on => Some(create_test_node(id, NodeStatus::PowerOn)),
+                    id if id == off => Some(create_test_node(id, NodeStatus::PowerOff)),
+                    _ => None,
+                })
+                .collect())
+        });
+        node_repo
+    }
+
+    #[actix_rt::test]
+    async fn best_effort_batches_skip_the_nodes_that_fail() {
+        let (on, off, missing) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut node_repo = batch_node_repo(on, off);
+        node_repo
+            .expect_create_batch()
+            .withf(|batch, operations| !batch.atomic && operations.is_empty())
+            .once()
+            .returning(|batch, _| Ok((batch.clone(), vec![])));
+        node_repo
+            .expect_create_operation()
+            .withf(move |op| op.node_id == on && op.batch_id.is_some())
+            .once()
+            .returning(|op| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let request = batch_request(BatchTarget::NodeIds(vec![off, on, missing, on]), false);
+        let result = svc
+            .create_batch(&request, OperationOptions::default())
+            .await
+            .unwrap();
+
+        let nodes: Vec<Uuid> = result.items.iter().map(|item| item.node_id).collect();
+        assert_eq!(nodes, vec![off, on, missing]);
+        assert!(result.items[0].operation.is_none());
+        assert!(result.items[0].error.is_some());
+        assert_eq!(
+            result.items[1].operation.as_ref().unwrap().batch_id,
+            Some(result.batch.id)
+        );
+        assert_eq!(
+            result.items[2].error,
+            Some(OperationServiceError::NodeNotFound(missing).to_string())
+        );
+    }
+
+    #[actix_rt::test]
+    async fn batches_skip_the_clusters_outside_their_maintenance_windows() {
+        let (open, closed) = (Uuid::new_v4(), Uuid::new_v4());
+        let (open_node, closed_node) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_get_nodes_by_id().returning(move |ids| {
+            Ok(ids
+                .iter()
+                .map(|id| {
+                    let cluster_id = if *id == open_node { open } else { closed };
+                    Node {
+                        cluster_id,
+                        ..create_test_node(*id, NodeStatus::PowerOn)
+                    }
+                })
+                .collect())
+        });
+        node_repo
+            .expect_create_batch()
+            .returning(|batch, _| Ok((batch.clone(), vec![])));
+        node_repo
+            .expect_create_operation()
+            .withf(move |op| op.node_id == open_node)
+            .once()
+            .returning(|op| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new())
+            .with_maintenance_policy(maintenance_policy(open));
+        let request = batch_request(BatchTarget::NodeIds(vec![open_node, closed_node]), false);
+        let result = svc
+            .create_batch(&request, OperationOptions::default())
+            .await
+            .unwrap();
+
+        assert!(result.items[0].operation.is_some());
+        assert_eq!(
+            result.items[1].error,
+            Some(OperationServiceError::OutsideMaintenanceWindow(closed).to_string())
+        );
+    }
+
+    #[actix_rt::test]
+    async fn atomic_batches_are_rejected_if_a_node_fails() {
+        let (on, off) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut node_repo = batch_node_repo(on, off);
+        node_repo.expect_create_batch().never();
+        node_repo.expect_create_operation().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let request = batch_request(BatchTarget::NodeIds(vec![on, off]), true);
+        let result = svc
+            .create_batch(&request, OperationOptions::default())
+            .await;
+
+        match result {
+            Err(OperationServiceError::BatchRejected(items)) => {
+                assert!(items.iter().all(|item| item.operation.is_none()));
+                assert!(items[0].error.is_none());
+                assert!(items[1].error.is_some());
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // it can be forced, like a single operation
+        let mut node_repo = batch_node_repo(on, off);
+        node_repo
+            .expect_create_batch()
+            .withf(|batch, operations| batch.atomic && operations.len() == 2)
+            .once()
+            .returning(|batch, operations| Ok((batch.clone(), operations.to_vec())));
+        node_repo.expect_create_operation().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let result = svc
+            .create_batch(
+                &request,
+                OperationOptions {
+                    force: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(result.items.iter().all(|item| item.operation.is_some()));
+    }
+
+    #[actix_rt::test]
+    async fn batches_without_nodes_to_run_on_are_rejected() {
+        let (on, off) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut node_repo = batch_node_repo(on, off);
+        node_repo.expect_create_batch().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let result = svc
+            .create_batch(
+                &batch_request(BatchTarget::NodeIds(vec![]), false),
+                OperationOptions::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(OperationServiceError::EmptyBatch)));
+
+        let result = svc
+            .create_batch(
+                &batch_request(BatchTarget::NodeIds(vec![off]), false),
+                OperationOptions::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::BatchRejected(_))
+        ));
+
+        let too_many = (0..=MAX_BATCH_SIZE).map(|_| Uuid::new_v4()).collect();
+        let result = svc
+            .create_batch(
+                &batch_request(BatchTarget::NodeIds(too_many), false),
+                OperationOptions::default(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::BatchTooLarge(MAX_BATCH_SIZE))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn cluster_batches_go_through_all_the_pages() {
+        let cluster_id = Uuid::new_v4();
+        let first = create_test_node(Uuid::new_v4(), NodeStatus::PowerOn);
+        let second = create_test_node(Uuid::new_v4(), NodeStatus::PowerOn);
+        let first_id = first.id;
+
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_nodes()
+            .withf(move |filter, page| {
+                filter.cluster_id == Some(cluster_id) && page.after.is_none()
+            })
+            .once()
+            .returning(move |_, _| {
+                Ok(Page {
+                    items: vec![first.clone()],
+                    next_cursor: Some("next".to_string()),
+                    total: None,
+                })
+            });
+        node_repo
+            .expect_get_nodes()
+            .withf(move |_, page| page.after.as_ref().is_some_and(|c| c.id == first_id))
+            .once()
+            .returning(move |_, _| {
+                Ok(Page {
+                    items: vec![second.clone()],
+                    next_cursor: None,
+                    total: None,
+                })
+            });
+        node_repo
+            .expect_create_batch()
+            .withf(|_, operations| operations.len() == 2)
+            .once()
+            .returning(|batch, operations| Ok((batch.clone(), operations.to_vec())));
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let request = batch_request(BatchTarget::ClusterId(cluster_id), true);
+        let result = svc
+            .create_batch(&request, OperationOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(result.items.len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn execute_reboot_powers_the_node_on_again() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        let mut seq = mockall::Sequence::new();
+        node_repo
+            .expect_update_node_status()
+            .withf(|_, status| *status == NodeStatus::Rebooting)
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_node_status()
+            .withf(|_, status| *status == NodeStatus::PowerOn)
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let driver = SimulatedPowerDriver::new(Duration::from_millis(1));
+        let svc = OperationService::new(node_repo, simulated_drivers(driver));
+        let operation = svc
+            .execute(running_operation(OperationType::Reboot), "worker")
+            .await
+            .unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Succeeded);
+        assert!(operation.finished_at.is_some());
+    }
+
+    /// Reboots the nodes but can't tell their power state afterwards.
+    struct UnreachableAfterReboot;
+
+    #[async_trait::async_trait]
+    impl PowerDriver for UnreachableAfterReboot {
+        async fn power_on(&self, _: &Node) -> PowerDriverResult<()> {
+            Ok(())
+        }
+
+        async fn power_off(&self, _: &Node) -> PowerDriverResult<()> {
+            Ok(())
+        }
+
+        async fn reboot(&self, _: &Node) -> PowerDriverResult<()> {
+            Ok(())
+        }
+
+        async fn query_power_state(&self, _: &Node) -> PowerDriverResult<PowerState> {
+            Err(PowerDriverError::Request("timed out".to_string()))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn execute_reboot_restores_the_node_status_if_its_state_is_unknown() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        let mut seq = mockall::Sequence::new();
+        node_repo
+            .expect_update_node_status()
+            .withf(|_, status| *status == NodeStatus::Rebooting)
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_node_status()
+            .withf(|_, status| *status == NodeStatus::PowerOn)
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let drivers = PowerDrivers::new().with(PowerDriverKind::Simulated, UnreachableAfterReboot);
+        let svc = OperationService::new(node_repo, drivers);
+        let operation = svc
+            .execute(running_operation(OperationType::Reboot), "worker")
+            .await
+            .unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Failed);
+        assert!(operation.failure_reason.unwrap().contains("timed out"));
+    }
+
+    #[actix_rt::test]
+    async fn execute_fails_operation_if_node_is_gone() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+        node_repo.expect_update_node_status().never();
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, simulated_drivers(Default::default()));
+        let operation = svc
+            .execute(running_operation(OperationType::PowerOff), "worker")
+            .await
+            .unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Failed);
+        assert!(operation.failure_reason.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_counted_by_type_and_outcome() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_create_operation()
+            .returning(|op| Ok(op.clone()));
+        node_repo
+            .expect_update_node_status()
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let metrics = Metrics::default();
+        let svc = OperationService::new(node_repo, simulated_drivers(Default::default()))
+            .with_metrics(metrics.clone());
+        let options = OperationOptions::default();
+        svc.power_off(&Uuid::new_v4(), options).await.unwrap();
+        svc.power_on(&Uuid::new_v4(), options).await.unwrap_err();
+        svc.execute(running_operation(OperationType::PowerOff), "worker")
+            .await
+            .unwrap();
+        svc.fail(
+            running_operation(OperationType::Reboot),
+            "boom".to_string(),
+            "worker",
+        )
+        .await
+        .unwrap();
+
+        let text = metrics.render().await;
+        for (operation_type, outcome) in [
+            ("poweroff", "requested"),
+            ("poweron", "rejected"),
+            ("poweroff", "succeeded"),
+            ("reboot", "failed"),
+        ] {
+            let line = format!(
+                "operations_total{{operation_type=\"{}\",outcome=\"{}\"}} 1",
+                operation_type, outcome
+            );
+            assert!(text.lines().any(|l| l == line), "{} not in\n{}", line, text);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn execute_fails_operation_if_driver_fails() {
+        let operation = running_operation(OperationType::PowerOff);
+        let driver = SimulatedPowerDriver::default().failing_for(operation.node_id);
+
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo.expect_update_node_status().never();
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, simulated_drivers(driver));
+        let operation = svc.execute(operation, "worker").await.unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Failed);
+        assert!(operation.failure_reason.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn execute_fails_operation_if_there_is_no_driver() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_get_node().returning(|id| {
+            let mut node = create_test_node(*id, NodeStatus::PowerOn);
+            node.driver = PowerDriverKind::Redfish;
+            Ok(node)
+        });
+        node_repo.expect_update_node_status().never();
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, simulated_drivers(Default::default()));
+        let operation = svc
+            .execute(running_operation(OperationType::PowerOff), "worker")
+            .await
+            .unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Failed);
+    }
+
+    #[actix_rt::test]
+    async fn execute_fails_operation_if_node_status_changed() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOff)));
+        node_repo.expect_update_node_status().never();
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, simulated_drivers(Default::default()));
+        let operation = svc
+            .execute(running_operation(OperationType::PowerOff), "worker")
+            .await
+            .unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Failed);
+        assert!(operation.failure_reason.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn execute_runs_forced_operations_whatever_the_node_status() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOff)));
+        node_repo
+            .expect_update_node_status()
+            .withf(|_, status| *status == NodeStatus::PowerOff)
+            .once()
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        let svc = OperationService::new(node_repo, simulated_drivers(Default::default()));
+        let operation = Operation {
+            forced: true,
+            ..running_operation(OperationType::PowerOff)
+        };
+        let operation = svc.execute(operation, "worker").await.unwrap();
+
+        assert_eq!(operation.status, OperationStatus::Succeeded);
+    }
+
+    #[actix_rt::test]
+    async fn execute_checks_the_maintenance_windows_again_unless_they_were_overridden() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_update_node_status()
+            .once()
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_operation()
+            .returning(|op, _| Ok(op.clone()));
+
+        // the window closed while the operation was in the queue
+        let svc = OperationService::new(node_repo, simulated_drivers(Default::default()))
+            .with_maintenance_policy(maintenance_policy(Uuid::new_v4()));
+        // forcing only skips the status of the node
+        for operation in [
+            running_operation(OperationType::PowerOff),
+            Operation {
+                forced: true,
+                ..running_operation(OperationType::PowerOff)
+            },
+        ] {
+            let operation = svc.execute(operation, "worker").await.unwrap();
+            assert_eq!(operation.status, OperationStatus::Failed);
+            assert!(operation
+                .failure_reason
+                .unwrap()
+                .contains("maintenance window"));
+        }
+
+        let operation = Operation {
+            overrides_maintenance: true,
+            ..running_operation(OperationType::PowerOff)
+        };
+        let operation = svc.execute(operation, "worker").await.unwrap();
+        assert_eq!(operation.status, OperationStatus::Succeeded);
+    }
+
+    #[actix_rt::test]
+    async fn execute_rejects_finished_operations() {
+        let mut operation = running_operation(OperationType::PowerOn);
+        transition(&mut operation, OperationStatus::Succeeded, None).unwrap();
+
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_update_node_status().never();
+        node_repo.expect_update_operation().never();
+
+        let svc = OperationService::new(node_repo, PowerDrivers::new());
+        let result = svc.execute(operation, "worker").await;
+
+        assert!(matches!(
+            result,
+            Err(OperationServiceError::InvalidStatusTransition { .. })
+        ));
     }
 }
@@ -0,0 +1,309 @@
+use crate::{
+    application::{
+        audit_log::{self, AuditContext},
+        operation_service::{OperationService, OperationServiceError},
+    },
+    domain::repository::{NodeRepository, RepositoryError},
+};
+use futures::future::{select, Either};
+use std::time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct OperationWorkerConfig {
+    /// How long to wait before polling again when the queue is empty.
+    pub poll_interval: Duration,
+    /// How long a claimed operation is reserved for this worker.
+    /// If the process dies, the operation is picked up again once the lease expires.
+    pub lease: Duration,
+    /// How many times an operation can be claimed before giving up on it.
+    pub max_attempts: i32,
+    /// How many operations are processed at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for OperationWorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            lease: Duration::from_secs(60),
+            max_attempts: 3,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Drives the queued operations to completion.
+#[derive(Debug, Clone)]
+pub struct OperationWorker<N: NodeRepository> {
+    id: String,
+    service: OperationService<N>,
+    config: OperationWorkerConfig,
+}
+
+impl<N> OperationWorker<N>
+where
+    N: NodeRepository,
+{
+    pub fn new(service: OperationService<N>, config: OperationWorkerConfig) -> Self {
+        Self {
+            id: format!("worker-{}", Uuid::new_v4()),
+            service,
+            config,
+        }
+    }
+
+    /// Spawns as many polling loops as the configured concurrency.
+    pub fn start(self)
+    where
+        N: Clone,
+    {
+        tracing::debug!(
+            "Starting operation worker {} with concurrency {}",
+            self.id,
+            self.config.concurrency
+        );
+        for _ in 0..self.config.concurrency {
+            let worker = self.clone();
+            actix_web::rt::spawn(async move { worker.run().await });
+        }
+    }
+
+    async fn run(&self) {
+        let context = AuditContext::system(&self.id);
+        loop {
+            match audit_log::scope(context.clone(), self.process_next()).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error processing operation: {:?}", e),
+            }
+            actix_web::rt::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Processes the next operation in the queue. Returns `false` if the queue was empty.
+    #[instrument(skip(self), fields(worker_id = %self.id))]
+    pub async fn process_next(&self) -> Result<bool, OperationServiceError> {
+        let lease_secs = self.config.lease.as_secs() as i64;
+        let operation = match self.service.claim_next(&self.id, lease_secs).await? {
+            Some(operation) => operation,
+            None => return Ok(false),
+        };
+
+        let operation_id = operation.id;
+        let work = async {
+            if operation.attempts > self.config.max_attempts {
+                tracing::warn!("Giving up on operation {}", operation.id);
+                let reason = format!("Gave up after {} attempts", self.config.max_attempts);
+                self.service.fail(operation, reason, &self.id).await
+            } else {
+                self.service.execute(operation, &self.id).await
+            }
+        };
+        // the drivers can take longer than the lease (e.g. waiting for a BMC), and the
+        // operation would be claimed and sent to the node again if the lease ran out
+        let result = match select(Box::pin(work), Box::pin(self.keep_lease(&operation_id))).await {
+            Either::Left((result, _)) => result,
+            // the lease was lost, but a command that may have reached the node can't be stopped
+            Either::Right((_, work)) => work.await,
+        };
+        if let Err(OperationServiceError::RepositoryError(RepositoryError::DoesNotExist)) = result {
+            // its result is left to the worker that holds it now
+            tracing::warn!(
+                "Operation {} was claimed by another worker before it finished",
+                operation_id
+            );
+            return Ok(true);
+        }
+        result?;
+        Ok(true)
+    }
+
+    /// Renews the lease of a claimed operation every third of the lease. Only returns once
+    /// the lease is lost.
+    async fn keep_lease(&self, operation_id: &Uuid) {
+        let lease_secs = self.config.lease.as_secs() as i64;
+        loop {
+            actix_web::rt::time::sleep(self.config.lease / 3).await;
+            match self
+                .service
+                .renew_lease(operation_id, &self.id, lease_secs)
+                .await
+            {
+                Ok(()) => {}
+                Err(OperationServiceError::RepositoryError(RepositoryError::DoesNotExist)) => {
+                    tracing::warn!("Lost the lease of operation {}", operation_id);
+                    return;
+                }
+                // it's tried again before the lease runs out
+                Err(e) => tracing::error!("Error renewing operation {}: {:?}", operation_id, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::power_driver::{PowerDrivers, SimulatedPowerDriver},
+        domain::{
+            models::{
+                Node, NodeStatus, Operation, OperationStatus, OperationType, PowerDriverKind,
+            },
+            repository::node_repository::MockNodeRepository,
+        },
+    };
+
+    fn create_test_node(id: Uuid, status: NodeStatus) -> Node {
+        Node {
+            id,
+            name: "my_node".to_string(),
+            cluster_id: Uuid::new_v4(),
+            status,
+            driver: PowerDriverKind::Simulated,
+            bmc_endpoint: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    fn claimed_operation(operation_type: OperationType, attempts: i32) -> Operation {
+        let mut operation = Operation::new(Uuid::new_v4(), operation_type);
+        operation.status = OperationStatus::Running;
+        operation.attempts = attempts;
+        operation
+    }
+
+    fn create_worker(node_repo: MockNodeRepository) -> OperationWorker<MockNodeRepository> {
+        create_slow_worker(node_repo, Duration::ZERO, OperationWorkerConfig::default())
+    }
+
+    fn create_slow_worker(
+        node_repo: MockNodeRepository,
+        reboot_delay: Duration,
+        config: OperationWorkerConfig,
+    ) -> OperationWorker<MockNodeRepository> {
+        let drivers = PowerDrivers::new().with(
+            PowerDriverKind::Simulated,
+            SimulatedPowerDriver::new(reboot_delay),
+        );
+        let svc = OperationService::new(node_repo, drivers);
+        OperationWorker::new(svc, config)
+    }
+
+    #[actix_rt::test]
+    async fn process_next_returns_false_if_queue_is_empty() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_claim_operation()
+            .returning(|_, _| Ok(None));
+
+        let worker = create_worker(node_repo);
+        assert!(!worker.process_next().await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn process_next_executes_claimed_operation() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_claim_operation()
+            .returning(|_, _| Ok(Some(claimed_operation(OperationType::PowerOff, 1))));
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_update_node_status()
+            .withf(|_, status| *status == NodeStatus::PowerOff)
+            .once()
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_update_operation()
+            .withf(|op, _| op.status == OperationStatus::Succeeded)
+            .once()
+            .returning(|op, _| Ok(op.clone()));
+
+        let worker = create_worker(node_repo);
+        assert!(worker.process_next().await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn process_next_gives_up_after_max_attempts() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_claim_operation()
+            .returning(|_, _| Ok(Some(claimed_operation(OperationType::Reboot, 4))));
+        node_repo.expect_update_node_status().never();
+        node_repo
+            .expect_update_operation()
+            .withf(|op, _| op.status == OperationStatus::Failed)
+            .once()
+            .returning(|op, _| Ok(op.clone()));
+
+        let worker = create_worker(node_repo);
+        assert!(worker.process_next().await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn process_next_renews_the_lease_while_the_operation_runs() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_claim_operation()
+            .returning(|_, _| Ok(Some(claimed_operation(OperationType::Reboot, 1))));
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_update_node_status()
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        node_repo
+            .expect_renew_operation()
+            .withf(|_, worker_id, _| worker_id.starts_with("worker-"))
+            .times(1..)
+            .returning(|_, _, _| Ok(()));
+        node_repo
+            .expect_update_operation()
+            .withf(|op, _| op.status == OperationStatus::Succeeded)
+            .once()
+            .returning(|op, _| Ok(op.clone()));
+
+        let config = OperationWorkerConfig {
+            lease: Duration::from_millis(30),
+            ..Default::default()
+        };
+        let worker = create_slow_worker(node_repo, Duration::from_millis(100), config);
+        assert!(worker.process_next().await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn process_next_leaves_the_operations_reclaimed_by_another_worker() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_claim_operation()
+            .returning(|_, _| Ok(Some(claimed_operation(OperationType::Reboot, 1))));
+        node_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        node_repo
+            .expect_update_node_status()
+            .returning(|id, status| Ok(create_test_node(*id, status)));
+        // the lease ran out and the operation was claimed again
+        node_repo
+            .expect_renew_operation()
+            .once()
+            .returning(|_, _, _| Err(RepositoryError::DoesNotExist));
+        node_repo
+            .expect_update_operation()
+            .once()
+            .returning(|_, _| Err(RepositoryError::DoesNotExist));
+
+        let config = OperationWorkerConfig {
+            lease: Duration::from_millis(30),
+            ..Default::default()
+        };
+        let worker = create_slow_worker(node_repo, Duration::from_millis(100), config);
+        assert!(worker.process_next().await.unwrap());
+    }
+}
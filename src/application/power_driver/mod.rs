@@ -0,0 +1,94 @@
+mod simulated;
+
+pub use simulated::SimulatedPowerDriver;
+
+use crate::domain::models::{Node, NodeStatus, PowerDriverKind};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, sync::Arc};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub enum PowerState {
+    #[serde(rename = "on")]
+    On,
+    #[serde(rename = "off")]
+    Off,
+    /// On its way to `on`, e.g. while it's coming back from a reboot.
+    #[serde(rename = "powering_on")]
+    PoweringOn,
+    #[serde(rename = "powering_off")]
+    PoweringOff,
+}
+
+/// Nodes that are powering on or off get the status they're heading to.
+impl From<PowerState> for NodeStatus {
+    fn from(state: PowerState) -> Self {
+        match state {
+            PowerState::On | PowerState::PoweringOn => NodeStatus::PowerOn,
+            PowerState::Off | PowerState::PoweringOff => NodeStatus::PowerOff,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PowerDriverError {
+    #[error("There's no power driver for `{0:?}`")]
+    Unsupported(PowerDriverKind),
+    #[error("Node `{0}` has no BMC endpoint")]
+    MissingEndpoint(Uuid),
+    #[error("BMC request failed: {0}")]
+    Request(String),
+    #[error("Unexpected BMC response: {0}")]
+    UnexpectedResponse(String),
+}
+
+pub type PowerDriverResult<T> = Result<T, PowerDriverError>;
+
+/// Talks to the machine behind a node.
+#[async_trait]
+pub trait PowerDriver: Send + Sync + 'static {
+    async fn power_on(&self, node: &Node) -> PowerDriverResult<()>;
+    async fn power_off(&self, node: &Node) -> PowerDriverResult<()>;
+    async fn reboot(&self, node: &Node) -> PowerDriverResult<()>;
+    async fn query_power_state(&self, node: &Node) -> PowerDriverResult<PowerState>;
+}
+
+/// Power drivers available to the application, indexed by the kind of node they manage.
+#[derive(Clone, Default)]
+pub struct PowerDrivers {
+    drivers: HashMap<PowerDriverKind, Arc<dyn PowerDriver>>,
+}
+
+impl PowerDrivers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, kind: PowerDriverKind, driver: impl PowerDriver) -> Self {
+        self.drivers.insert(kind, Arc::new(driver));
+        self
+    }
+
+    pub fn get(&self, kind: PowerDriverKind) -> PowerDriverResult<&dyn PowerDriver> {
+        self.drivers
+            .get(&kind)
+            .map(|driver| driver.as_ref())
+            .ok_or(PowerDriverError::Unsupported(kind))
+    }
+}
+
+impl fmt::Debug for PowerDrivers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.drivers.keys()).finish()
+    }
+}
+
+/// Returns the BMC endpoint of the node or fails if it doesn't have one.
+pub fn bmc_endpoint(node: &Node) -> PowerDriverResult<&str> {
+    node.bmc_endpoint
+        .as_deref()
+        .filter(|endpoint| !endpoint.is_empty())
+        .ok_or(PowerDriverError::MissingEndpoint(node.id))
+}
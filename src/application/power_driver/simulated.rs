@@ -0,0 +1,131 @@
+use super::{PowerDriver, PowerDriverError, PowerDriverResult, PowerState};
+use crate::domain::models::Node;
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// Keeps the power state of every node in memory.
+/// Nodes start powered on and every command behaves the same way every time,
+/// which makes it handy for demos and tests.
+#[derive(Debug, Default)]
+pub struct SimulatedPowerDriver {
+    reboot_delay: Duration,
+    states: Mutex<HashMap<Uuid, PowerState>>,
+    failing_nodes: HashSet<Uuid>,
+}
+
+impl SimulatedPowerDriver {
+    pub fn new(reboot_delay: Duration) -> Self {
+        Self {
+            reboot_delay,
+            ..Default::default()
+        }
+    }
+
+    /// Makes every command sent to this node fail.
+    #[cfg(test)]
+    pub fn failing_for(mut self, node_id: Uuid) -> Self {
+        self.failing_nodes.insert(node_id);
+        self
+    }
+
+    fn check(&self, node: &Node) -> PowerDriverResult<()> {
+        if self.failing_nodes.contains(&node.id) {
+            return Err(PowerDriverError::Request(format!(
+                "Simulated failure for node {}",
+                node.id
+            )));
+        }
+        Ok(())
+    }
+
+    fn set_state(&self, node: &Node, state: PowerState) -> PowerDriverResult<()> {
+        self.check(node)?;
+        let mut states = self
+            .states
+            .lock()
+            .map_err(|e| PowerDriverError::Request(e.to_string()))?;
+        states.insert(node.id, state);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PowerDriver for SimulatedPowerDriver {
+    async fn power_on(&self, node: &Node) -> PowerDriverResult<()> {
+        self.set_state(node, PowerState::On)
+    }
+
+    async fn power_off(&self, node: &Node) -> PowerDriverResult<()> {
+        self.set_state(node, PowerState::Off)
+    }
+
+    async fn reboot(&self, node: &Node) -> PowerDriverResult<()> {
+        self.set_state(node, PowerState::Off)?;
+        actix_web::rt::time::sleep(self.reboot_delay).await;
+        self.set_state(node, PowerState::On)
+    }
+
+    async fn query_power_state(&self, node: &Node) -> PowerDriverResult<PowerState> {
+        self.check(node)?;
+        let states = self
+            .states
+            .lock()
+            .map_err(|e| PowerDriverError::Request(e.to_string()))?;
+        Ok(states.get(&node.id).copied().unwrap_or(PowerState::On))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::{NodeStatus, PowerDriverKind};
+
+    fn create_test_node() -> Node {
+        Node {
+            id: Uuid::new_v4(),
+            name: "my_node".to_string(),
+            cluster_id: Uuid::new_v4(),
+            status: NodeStatus::PowerOn,
+            driver: PowerDriverKind::Simulated,
+            bmc_endpoint: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn simulated_driver_keeps_power_state() {
+        let driver = SimulatedPowerDriver::new(Duration::ZERO);
+        let node = create_test_node();
+
+        assert_eq!(
+            driver.query_power_state(&node).await.unwrap(),
+            PowerState::On
+        );
+        driver.power_off(&node).await.unwrap();
+        assert_eq!(
+            driver.query_power_state(&node).await.unwrap(),
+            PowerState::Off
+        );
+        driver.reboot(&node).await.unwrap();
+        assert_eq!(
+            driver.query_power_state(&node).await.unwrap(),
+            PowerState::On
+        );
+    }
+
+    #[actix_rt::test]
+    async fn simulated_driver_fails_for_configured_nodes() {
+        let node = create_test_node();
+        let driver = SimulatedPowerDriver::new(Duration::ZERO).failing_for(node.id);
+
+        assert!(driver.power_on(&node).await.is_err());
+        assert!(driver.query_power_state(&node).await.is_err());
+    }
+}
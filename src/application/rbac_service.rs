@@ -0,0 +1,266 @@
+use crate::domain::{
+    models::{
+        Grants, Permission, Principal, Role, RoleBinding, API_KEY_SUBJECT_PREFIX,
+        JWT_SUBJECT_PREFIX,
+    },
+    repository::{RepositoryError, RoleBindingRepository},
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum RbacServiceError {
+    #[error("Missing permission `{0}`")]
+    Forbidden(Permission),
+    #[error("Missing permission `{0}` in cluster `{1}`")]
+    ForbiddenInCluster(Permission, Uuid),
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+pub type RbacServiceResult<T> = Result<T, RbacServiceError>;
+
+/// Finds out what the callers can do.
+#[async_trait]
+pub trait GrantsResolver: Send + Sync + 'static {
+    async fn grants(&self, principal: &Principal) -> RbacServiceResult<Grants>;
+}
+
+/// Fails unless the permission is granted in the cluster.
+pub fn check(grants: &Grants, permission: Permission, cluster_id: &Uuid) -> RbacServiceResult<()> {
+    if grants.allows(permission, cluster_id) {
+        Ok(())
+    } else {
+        Err(RbacServiceError::ForbiddenInCluster(
+            permission,
+            *cluster_id,
+        ))
+    }
+}
+
+/// Fails unless the permission is granted in every cluster, e.g. to list the resources
+/// of all of them.
+pub fn check_everywhere(grants: &Grants, permission: Permission) -> RbacServiceResult<()> {
+    if grants.allows_everywhere(permission) {
+        Ok(())
+    } else {
+        Err(RbacServiceError::Forbidden(permission))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedBindings {
+    bindings: Vec<RoleBinding>,
+    cached_at: Instant,
+}
+
+/// Manages the role bindings.
+///
+/// The bindings of the callers are cached for `cache_ttl`, so the bindings changed by
+/// another replica of the API may take that long to apply in this one.
+#[derive(Clone)]
+pub struct RbacService<B: RoleBindingRepository> {
+    repository: B,
+    cache: Arc<RwLock<HashMap<String, CachedBindings>>>,
+    cache_ttl: Duration,
+}
+
+impl<B: RoleBindingRepository> RbacService<B> {
+    pub fn new(repository: B, cache_ttl: Duration) -> Self {
+        Self {
+            repository,
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache_ttl,
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_all(&self, subject: Option<&str>) -> RbacServiceResult<Vec<RoleBinding>> {
+        let bindings = self.repository.get_role_bindings(subject).await?;
+        Ok(bindings)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, role_binding_id: &Uuid) -> RbacServiceResult<RoleBinding> {
+        let binding = self.repository.get_role_binding(role_binding_id).await?;
+        Ok(binding)
+    }
+
+    /// Gives the role to the subject, in the cluster or in all of them.
+    #[instrument(skip(self))]
+    pub async fn bind(
+        &self,
+        subject: &str,
+        role: Role,
+        cluster_id: Option<Uuid>,
+    ) -> RbacServiceResult<RoleBinding> {
+        let valid = [API_KEY_SUBJECT_PREFIX, JWT_SUBJECT_PREFIX]
+            .iter()
+            .any(|prefix| {
+                subject
+                    .strip_prefix(prefix)
+                    .is_some_and(|id| !id.trim().is_empty())
+            });
+        if !valid {
+            return Err(RbacServiceError::InvalidRequest(format!(
+                "The subject of a role binding must be `{}<owner of the API key>` or `{}<sub of the JWT>`",
+                API_KEY_SUBJECT_PREFIX, JWT_SUBJECT_PREFIX
+            )));
+        }
+        let binding = RoleBinding {
+            id: Uuid::new_v4(),
+            subject: subject.to_string(),
+            role,
+            cluster_id,
+            created_at: None,
+        };
+        let binding = self.repository.create_role_binding(&binding).await?;
+        self.cache
+            .write()
+            .map_err(RepositoryError::from)?
+            .remove(&binding.subject);
+        Ok(binding)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn unbind(&self, role_binding_id: &Uuid) -> RbacServiceResult<Uuid> {
+        let id = self.repository.delete_role_binding(role_binding_id).await?;
+        // the subject of the binding is gone with it
+        self.cache.write().map_err(RepositoryError::from)?.clear();
+        Ok(id)
+    }
+
+    fn cached(&self, subject: &str) -> Option<Vec<RoleBinding>> {
+        let cache = self.cache.read().ok()?;
+        cache
+            .get(subject)
+            .filter(|cached| cached.cached_at.elapsed() < self.cache_ttl)
+            .map(|cached| cached.bindings.clone())
+    }
+}
+
+#[async_trait]
+impl<B: RoleBindingRepository> GrantsResolver for RbacService<B> {
+    #[instrument(skip(self))]
+    async fn grants(&self, principal: &Principal) -> RbacServiceResult<Grants> {
+        let bindings = match self.cached(&principal.subject) {
+            Some(bindings) => bindings,
+            None => {
+                let bindings = self
+                    .repository
+                    .get_role_bindings(Some(&principal.subject))
+                    .await?;
+                let mut cache = self.cache.write().map_err(RepositoryError::from)?;
+                cache.retain(|_, cached| cached.cached_at.elapsed() < self.cache_ttl);
+                cache.insert(
+                    principal.subject.clone(),
+                    CachedBindings {
+                        bindings: bindings.clone(),
+                        cached_at: Instant::now(),
+                    },
+                );
+                bindings
+            }
+        };
+        Ok(Grants::new(principal.clone(), &bindings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repository::role_binding_repository::MockRoleBindingRepository;
+
+    #[actix_rt::test]
+    async fn grants_include_the_bindings_of_the_subject() {
+        let cluster_id = Uuid::new_v4();
+        let mut repo = MockRoleBindingRepository::default();
+        repo.expect_get_role_bindings()
+            .withf(|subject| *subject == Some("jane"))
+            .returning(move |subject| {
+                Ok(vec![RoleBinding {
+                    id: Uuid::new_v4(),
+                    subject: subject.unwrap().to_string(),
+                    role: Role::Operator,
+                    cluster_id: Some(cluster_id),
+                    created_at: None,
+                }])
+            });
+        let service = RbacService::new(repo, Duration::from_secs(60));
+        let principal = Principal {
+            subject: "jane".to_string(),
+            roles: vec!["viewer".to_string()],
+            tenant: None,
+        };
+
+        let grants = service.grants(&principal).await.unwrap();
+        assert!(check_everywhere(&grants, Permission::NodesRead).is_ok());
+        assert!(check(&grants, Permission::OperationsReboot, &cluster_id).is_ok());
+        assert!(matches!(
+            check(&grants, Permission::OperationsReboot, &Uuid::new_v4()),
+            Err(RbacServiceError::ForbiddenInCluster(
+                Permission::OperationsReboot,
+                _
+            ))
+        ));
+        assert!(matches!(
+            service.bind(" ", Role::Admin, None).await,
+            Err(RbacServiceError::InvalidRequest(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn grants_cache_the_bindings_until_they_change() {
+        let mut repo = MockRoleBindingRepository::default();
+        repo.expect_get_role_bindings()
+            .times(2)
+            .returning(|_| Ok(vec![]));
+        repo.expect_create_role_binding()
+            .returning(|binding| Ok(binding.clone()));
+        let service = RbacService::new(repo, Duration::from_secs(60));
+        let principal = Principal {
+            subject: "apikey:jane".to_string(),
+            roles: vec![],
+            tenant: None,
+        };
+
+        service.grants(&principal).await.unwrap();
+        service.grants(&principal).await.unwrap();
+        service
+            .bind("apikey:jane", Role::Viewer, None)
+            .await
+            .unwrap();
+        service.grants(&principal).await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn grants_drop_the_expired_bindings_from_the_cache() {
+        let mut repo = MockRoleBindingRepository::default();
+        repo.expect_get_role_bindings().returning(|_| Ok(vec![]));
+        let service = RbacService::new(repo, Duration::from_millis(10));
+        let principal = |subject: &str| Principal {
+            subject: subject.to_string(),
+            roles: vec![],
+            tenant: None,
+        };
+
+        service.grants(&principal("apikey:jane")).await.unwrap();
+        actix_rt::time::sleep(Duration::from_millis(20)).await;
+        service.grants(&principal("apikey:john")).await.unwrap();
+
+        let cache = service.cache.read().unwrap();
+        assert_eq!(
+            cache.keys().collect::<Vec<_>>(),
+            vec![&"apikey:john".to_string()]
+        );
+    }
+}
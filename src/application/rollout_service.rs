@@ -0,0 +1,884 @@
+use crate::{
+    application::operation_service::{
+        find_nodes, OperationOptions, OperationService, OperationServiceError,
+    },
+    domain::{
+        models::{
+            NodeStatus, OperationStatus, Rollout, RolloutNode, RolloutNodeStatus, RolloutStatus,
+        },
+        repository::{
+            node_repository::NodeFilter, NodeRepository, RepositoryError, RolloutRepository,
+        },
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Largest number of nodes of a rollout.
+pub const MAX_ROLLOUT_SIZE: usize = 1000;
+
+#[derive(Error, Debug)]
+pub enum RolloutServiceError {
+    #[error("Rollout can't go from `{from:?}` to `{to:?}`")]
+    InvalidTransition {
+        from: RolloutStatus,
+        to: RolloutStatus,
+    },
+    #[error("A rollout can't have more than {0} nodes")]
+    RolloutTooLarge(usize),
+    #[error(transparent)]
+    OperationServiceError(#[from] OperationServiceError),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+pub type RolloutServiceResult = Result<Rollout, RolloutServiceError>;
+
+/// How many nodes can be rebooting at the same time: a number of nodes, like `2`,
+/// or a percentage of the nodes of the cluster, like `"25%"`.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "ConcurrencyValue", into = "ConcurrencyValue")]
+pub enum Concurrency {
+    Nodes(u32),
+    Percent(u32),
+}
+
+impl Concurrency {
+    /// Number of nodes for a rollout of `node_count` nodes. Percentages are rounded up,
+    /// so there's always at least one node rebooting.
+    pub fn max_in_flight(&self, node_count: usize) -> i32 {
+        let nodes = match *self {
+            Concurrency::Nodes(nodes) => nodes as usize,
+            Concurrency::Percent(percent) => (node_count * percent as usize).div_ceil(100),
+        };
+        nodes.clamp(1, i32::MAX as usize) as i32
+    }
+}
+
+impl fmt::Display for Concurrency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Concurrency::Nodes(nodes) => write!(f, "{}", nodes),
+            Concurrency::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum ConcurrencyValue {
+    Number(u32),
+    Text(String),
+}
+
+impl TryFrom<ConcurrencyValue> for Concurrency {
+    type Error = String;
+
+    fn try_from(value: ConcurrencyValue) -> Result<Self, Self::Error> {
+        let concurrency = match value {
+            ConcurrencyValue::Number(nodes) => Concurrency::Nodes(nodes),
+            ConcurrencyValue::Text(text) => match text.trim().strip_suffix('%') {
+                Some(percent) => Concurrency::Percent(
+                    percent
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid percentage: `{}`", text))?,
+                ),
+                None => Concurrency::Nodes(
+                    text.trim()
+                        .parse()
+                        .map_err(|_| format!("invalid number of nodes: `{}`", text))?,
+                ),
+            },
+        };
+        match concurrency {
+            Concurrency::Nodes(0) | Concurrency::Percent(0) => {
+                Err("at least one node must be rebooting at a time".to_string())
+            }
+            Concurrency::Percent(percent) if percent > 100 => {
+                Err(format!("a percentage can't be over 100%: `{}%`", percent))
+            }
+            concurrency => Ok(concurrency),
+        }
+    }
+}
+
+impl From<Concurrency> for ConcurrencyValue {
+    fn from(concurrency: Concurrency) -> Self {
+        match concurrency {
+            Concurrency::Nodes(nodes) => ConcurrencyValue::Number(nodes),
+            Concurrency::Percent(_) => ConcurrencyValue::Text(concurrency.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RolloutRequest {
+    pub cluster_id: Uuid,
+    pub max_in_flight: Concurrency,
+    /// How many nodes can fail before the rollout is stopped.
+    #[serde(default)]
+    pub max_failures: u32,
+}
+
+/// Reboots the nodes of a cluster a few at a time, waiting for each one to be back
+/// in `poweron` status before going on with the next ones.
+#[derive(Debug, Clone)]
+pub struct RolloutService<N: NodeRepository, R: RolloutRepository> {
+    rollout_repository: R,
+    node_repository: N,
+    operation_service: OperationService<N>,
+}
+
+impl<N, R> RolloutService<N, R>
+where
+    N: NodeRepository,
+    R: RolloutRepository,
+{
+    pub fn new(
+        rollout_repository: R,
+        node_repository: N,
+        operation_service: OperationService<N>,
+    ) -> Self {
+        Self {
+            rollout_repository,
+            node_repository,
+            operation_service,
+        }
+    }
+
+    /// Creates a running rollout with the nodes the cluster has right now.
+    /// The reboots are requested by the worker that advances the rollout.
+    #[instrument(skip(self))]
+    pub async fn create(&self, request: &RolloutRequest) -> RolloutServiceResult {
+        let filter = NodeFilter {
+            cluster_id: Some(request.cluster_id),
+            ..Default::default()
+        };
+        let nodes = find_nodes(&self.node_repository, &filter, MAX_ROLLOUT_SIZE).await?;
+        if nodes.len() > MAX_ROLLOUT_SIZE {
+            return Err(RolloutServiceError::RolloutTooLarge(MAX_ROLLOUT_SIZE));
+        }
+
+        let mut rollout = Rollout::new(
+            request.cluster_id,
+            request.max_in_flight.max_in_flight(nodes.len()),
+            request.max_failures.min(i32::MAX as u32) as i32,
+            nodes.into_iter().map(|node| node.id),
+        );
+        if rollout.nodes.is_empty() {
+            // there's nothing to wait for
+            rollout.status = RolloutStatus::Succeeded;
+        }
+        let rollout = self.rollout_repository.create_rollout(&rollout).await?;
+        Ok(rollout)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, rollout_id: &Uuid) -> RolloutServiceResult {
+        let rollout = self.rollout_repository.get_rollout(rollout_id).await?;
+        Ok(rollout)
+    }
+
+    /// Stops rebooting more nodes. The nodes that are rebooting are still checked.
+    #[instrument(skip(self))]
+    pub async fn pause(
+        &self,
+        rollout_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RolloutServiceResult {
+        self.transition(rollout_id, RolloutStatus::Paused, expected_version, |_| {
+            Vec::new()
+        })
+        .await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn resume(
+        &self,
+        rollout_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RolloutServiceResult {
+        self.transition(rollout_id, RolloutStatus::Running, expected_version, |_| {
+            Vec::new()
+        })
+        .await
+    }
+
+    /// Finishes the rollout, skipping the nodes that haven't been rebooted yet.
+    /// The nodes that are rebooting finish their reboots but nobody checks them anymore.
+    #[instrument(skip(self))]
+    pub async fn abort(
+        &self,
+        rollout_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RolloutServiceResult {
+        self.transition(
+            rollout_id,
+            RolloutStatus::Aborted,
+            expected_version,
+            skip_pending_nodes,
+        )
+        .await
+    }
+
+    /// Changes the status of the rollout, along with the nodes that `change_nodes` changes
+    /// and returns. Without an expected version, the change is made on top of whatever the
+    /// rollout is by the time it's written.
+    async fn transition(
+        &self,
+        rollout_id: &Uuid,
+        next: RolloutStatus,
+        expected_version: Option<Vec<i64>>,
+        change_nodes: impl Fn(&mut Rollout) -> Vec<RolloutNode>,
+    ) -> RolloutServiceResult {
+        loop {
+            let mut rollout = self.rollout_repository.get_rollout(rollout_id).await?;
+            if !rollout.status.can_transition_to(next) {
+                return Err(RolloutServiceError::InvalidTransition {
+                    from: rollout.status,
+                    to: next,
+                });
+            }
+            let nodes = change_nodes(&mut rollout);
+            let result = self
+                .rollout_repository
+                .update_rollout(
+                    rollout_id,
+                    next,
+                    &nodes,
+                    expected_version.clone().or(Some(vec![rollout.version])),
+                )
+                .await;
+            match result {
+                // the worker changed the rollout in the meantime
+                Err(RepositoryError::VersionMismatch) if expected_version.is_none() => continue,
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    /// Claims the next rollout that needs to be checked, if any.
+    #[instrument(skip(self))]
+    pub async fn claim_next(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> Result<Option<Rollout>, RolloutServiceError> {
+        let rollout = self
+            .rollout_repository
+            .claim_rollout(worker_id, lease_secs)
+            .await?;
+        Ok(rollout)
+    }
+
+    /// Gives back a claimed rollout so it's checked again after `delay_secs`.
+    #[instrument(skip(self))]
+    pub async fn release(
+        &self,
+        rollout_id: &Uuid,
+        worker_id: &str,
+        delay_secs: i64,
+    ) -> Result<(), RolloutServiceError> {
+        self.rollout_repository
+            .release_rollout(rollout_id, worker_id, delay_secs)
+            .await?;
+        Ok(())
+    }
+
+    /// Checks the nodes that are rebooting and reboots the next ones, finishing the
+    /// rollout when every node is done or too many of them failed.
+    ///
+    /// The progress is written with the version of the claimed rollout. If the rollout was
+    /// paused, resumed or aborted in the meantime, it's written again on top of that, and an
+    /// abort wins over the status the rollout would have finished with.
+    #[instrument(skip(self, rollout), fields(rollout_id = %rollout.id))]
+    pub async fn advance(&self, mut rollout: Rollout) -> RolloutServiceResult {
+        if !matches!(
+            rollout.status,
+            RolloutStatus::Running | RolloutStatus::Paused
+        ) {
+            return Ok(rollout);
+        }
+
+        let claimed = rollout.clone();
+        let mut changed = Vec::new();
+        for node in rollout.nodes.iter_mut() {
+            if node.status == RolloutNodeStatus::Rebooting && self.check_reboot(node).await? {
+                changed.push(node.node_id);
+            }
+        }
+
+        let mut error = None;
+        if rollout.status == RolloutStatus::Running {
+            let progress = rollout.progress();
+            let (mut in_flight, mut failed) = (progress.rebooting, progress.failed);
+            for node in rollout.nodes.iter_mut() {
+                if in_flight >= rollout.max_in_flight as usize
+                    || failed > rollout.max_failures as usize
+                {
+                    break;
+                }
+                if node.status != RolloutNodeStatus::Pending {
+                    continue;
+                }
+                match self
+                    .operation_service
+                    .reboot(&node.node_id, OperationOptions::default())
+                    .await
+                {
+                    Ok(operation) => {
+                        node.status = RolloutNodeStatus::Rebooting;
+                        node.operation_id = Some(operation.id);
+                        in_flight += 1;
+                    }
+                    Err(
+                        e @ (OperationServiceError::NodeNotFound(_)
+                        | OperationServiceError::InvalidTransition { .. }),
+                    ) => {
+                        fail(node, e);
+                        failed += 1;
+                    }
+                    // the rollout waits for the next maintenance window of the cluster
+                    Err(OperationServiceError::OutsideMaintenanceWindow(_)) => break,
+                    Err(e) => {
+                        // the reboots requested so far must be saved anyway
+                        error = Some(e);
+                        break;
+                    }
+                }
+                changed.push(node.node_id);
+            }
+        }
+
+        let progress = rollout.progress();
+        let next = if progress.failed > rollout.max_failures as usize {
+            Some(RolloutStatus::Failed)
+        } else if progress.pending == 0 && progress.rebooting == 0 {
+            Some(RolloutStatus::Succeeded)
+        } else {
+            None
+        }
+        // the rollout isn't finished if it couldn't request every reboot it had to
+        .filter(|_| error.is_none());
+        if next == Some(RolloutStatus::Failed) {
+            changed.extend(skip_pending_nodes(&mut rollout).iter().map(|n| n.node_id));
+        }
+
+        let mut nodes: Vec<RolloutNode> = rollout
+            .nodes
+            .iter()
+            .filter(|node| changed.contains(&node.node_id))
+            .cloned()
+            .collect();
+        if !nodes.is_empty() || next.is_some() {
+            let mut current = claimed;
+            loop {
+                let status = match next {
+                    Some(next) if current.status.can_transition_to(next) => next,
+                    _ => current.status,
+                };
+                let result = self
+                    .rollout_repository
+                    .update_rollout(&rollout.id, status, &nodes, Some(vec![current.version]))
+                    .await;
+                match result {
+                    Ok(updated) => {
+                        rollout = updated;
+                        break;
+                    }
+                    Err(RepositoryError::VersionMismatch) => {
+                        tracing::debug!("Rollout {} changed while it was advanced", rollout.id);
+                        let previous = current;
+                        current = self.rollout_repository.get_rollout(&rollout.id).await?;
+                        // the reboots requested after an abort are still saved, but not the
+                        // changes of the nodes somebody else has advanced
+                        nodes.retain(|node| {
+                            let status_of = |rollout: &Rollout| {
+                                rollout
+                                    .nodes
+                                    .iter()
+                                    .find(|n| n.node_id == node.node_id)
+                                    .map(|n| n.status)
+                            };
+                            match (status_of(&previous), status_of(&current)) {
+                                (before, now) if before == now => true,
+                                (
+                                    Some(RolloutNodeStatus::Pending),
+                                    Some(RolloutNodeStatus::Skipped),
+                                ) => true,
+                                _ => false,
+                            }
+                        });
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e.into()),
+            None => Ok(rollout),
+        }
+    }
+
+    /// Checks how the reboot of a node went. Returns whether the node has changed.
+    async fn check_reboot(&self, node: &mut RolloutNode) -> Result<bool, RolloutServiceError> {
+        let operation_id = match node.operation_id {
+            Some(operation_id) => operation_id,
+            None => {
+                fail(node, "The reboot of the node is missing");
+                return Ok(true);
+            }
+        };
+        let operation = match self.node_repository.get_operation(&operation_id).await {
+            Ok(operation) => operation,
+            // the operations go away along with their node
+            Err(RepositoryError::DoesNotExist) => {
+                fail(node, OperationServiceError::NodeNotFound(node.node_id));
+                return Ok(true);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        match operation.status {
+            OperationStatus::Pending | OperationStatus::Running => Ok(false),
+            OperationStatus::Failed => {
+                let reason = operation
+                    .failure_reason
+                    .unwrap_or_else(|| "The reboot failed".to_string());
+                fail(node, reason);
+                Ok(true)
+            }
+            OperationStatus::Succeeded => {
+                match self.node_repository.get_node(&node.node_id).await {
+                    Ok(found) => match found.status {
+                        NodeStatus::PowerOn => {
+                            node.status = RolloutNodeStatus::Succeeded;
+                            Ok(true)
+                        }
+                        NodeStatus::Rebooting => Ok(false),
+                        NodeStatus::PowerOff => {
+                            fail(node, "The node didn't power on after the reboot");
+                            Ok(true)
+                        }
+                    },
+                    Err(RepositoryError::DoesNotExist) => {
+                        fail(node, OperationServiceError::NodeNotFound(node.node_id));
+                        Ok(true)
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+fn fail(node: &mut RolloutNode, reason: impl ToString) {
+    node.status = RolloutNodeStatus::Failed;
+    node.failure_reason = Some(reason.to_string());
+}
+
+/// Marks the nodes that haven't been rebooted as skipped, returning them.
+fn skip_pending_nodes(rollout: &mut Rollout) -> Vec<RolloutNode> {
+    rollout
+        .nodes
+        .iter_mut()
+        .filter(|node| node.status == RolloutNodeStatus::Pending)
+        .map(|node| {
+            node.status = RolloutNodeStatus::Skipped;
+            node.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::{maintenance_policy::MockMaintenancePolicy, power_driver::PowerDrivers},
+        domain::{
+            models::{Node, Operation, OperationType, PowerDriverKind},
+            repository::{
+                node_repository::MockNodeRepository, rollout_repository::MockRolloutRepository,
+                Page,
+            },
+        },
+    };
+
+    fn create_test_node(id: Uuid, status: NodeStatus) -> Node {
+        Node {
+            id,
+            name: "my_node".to_string(),
+            cluster_id: Uuid::new_v4(),
+            status,
+            driver: PowerDriverKind::Simulated,
+            bmc_endpoint: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    /// Rollout whose first node is rebooting with the returned operation.
+    fn rebooting_rollout(node_count: usize, max_failures: i32) -> (Rollout, Operation) {
+        let mut rollout = Rollout::new(
+            Uuid::new_v4(),
+            1,
+            max_failures,
+            (0..node_count).map(|_| Uuid::new_v4()),
+        );
+        rollout.version = 3;
+        let mut operation = Operation::new(rollout.nodes[0].node_id, OperationType::Reboot);
+        operation.status = OperationStatus::Running;
+        rollout.nodes[0].status = RolloutNodeStatus::Rebooting;
+        rollout.nodes[0].operation_id = Some(operation.id);
+        (rollout, operation)
+    }
+
+    /// The rollout as the repository returns it after an update.
+    fn updated(rollout: &Rollout, status: RolloutStatus, nodes: &[RolloutNode]) -> Rollout {
+        let mut rollout = rollout.clone();
+        for node in nodes {
+            if let Some(stored) = rollout.nodes.iter_mut().find(|n| n.node_id == node.node_id) {
+                *stored = node.clone();
+            }
+        }
+        rollout.status = status;
+        rollout.version += 1;
+        rollout
+    }
+
+    fn create_service(
+        rollout_repo: MockRolloutRepository,
+        node_repo: MockNodeRepository,
+        operation_repo: MockNodeRepository,
+    ) -> RolloutService<MockNodeRepository, MockRolloutRepository> {
+        let operation_service = OperationService::new(operation_repo, PowerDrivers::new());
+        RolloutService::new(rollout_repo, node_repo, operation_service)
+    }
+
+    #[test]
+    fn concurrency_is_a_number_or_a_percentage() {
+        let concurrency: Concurrency = serde_json::from_str("2").unwrap();
+        assert_eq!(concurrency, Concurrency::Nodes(2));
+        assert_eq!(concurrency.max_in_flight(10), 2);
+
+        let concurrency: Concurrency = serde_json::from_str("\"25%\"").unwrap();
+        assert_eq!(concurrency, Concurrency::Percent(25));
+        assert_eq!(concurrency.max_in_flight(10), 3);
+        assert_eq!(concurrency.max_in_flight(1), 1);
+        assert_eq!(serde_json::to_string(&concurrency).unwrap(), "\"25%\"");
+
+        for invalid in ["0", "\"0%\"", "\"101%\"", "\"abc\"", "-1"] {
+            assert!(serde_json::from_str::<Concurrency>(invalid).is_err());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_takes_the_nodes_of_the_cluster() {
+        let cluster_id = Uuid::new_v4();
+        let nodes: Vec<Node> = (0..4)
+            .map(|_| create_test_node(Uuid::new_v4(), NodeStatus::PowerOn))
+            .collect();
+        let node_ids: Vec<Uuid> = nodes.iter().map(|node| node.id).collect();
+
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_nodes()
+            .withf(move |filter, _| filter.cluster_id == Some(cluster_id))
+            .returning(move |_, _| {
+                Ok(Page {
+                    items: nodes.clone(),
+                    next_cursor: None,
+                    total: None,
+                })
+            });
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo
+            .expect_create_rollout()
+            .once()
+            .returning(|rollout| Ok(rollout.clone()));
+
+        let svc = create_service(rollout_repo, node_repo, MockNodeRepository::default());
+        let request = RolloutRequest {
+            cluster_id,
+            max_in_flight: Concurrency::Percent(50),
+            max_failures: 1,
+        };
+        let rollout = svc.create(&request).await.unwrap();
+        assert_eq!(rollout.status, RolloutStatus::Running);
+        assert_eq!(rollout.max_in_flight, 2);
+        assert_eq!(rollout.max_failures, 1);
+        let rollout_node_ids: Vec<Uuid> = rollout.nodes.iter().map(|n| n.node_id).collect();
+        assert_eq!(rollout_node_ids, node_ids);
+    }
+
+    #[actix_rt::test]
+    async fn advance_reboots_up_to_max_in_flight() {
+        let mut rollout = Rollout::new(Uuid::new_v4(), 2, 0, (0..3).map(|_| Uuid::new_v4()));
+        rollout.version = 1;
+
+        let mut operation_repo = MockNodeRepository::default();
+        operation_repo
+            .expect_get_node()
+            .times(2)
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        operation_repo
+            .expect_create_operation()
+            .times(2)
+            .returning(|op| Ok(op.clone()));
+        let mut rollout_repo = MockRolloutRepository::default();
+        let claimed = rollout.clone();
+        rollout_repo
+            .expect_update_rollout()
+            .withf(|_, status, nodes, version| {
+                *status == RolloutStatus::Running
+                    && *version == Some(vec![1])
+                    && nodes.len() == 2
+                    && nodes.iter().all(|n| {
+                        n.status == RolloutNodeStatus::Rebooting && n.operation_id.is_some()
+                    })
+            })
+            .once()
+            .returning(move |_, status, nodes, _| Ok(updated(&claimed, status, nodes)));
+
+        let svc = create_service(rollout_repo, MockNodeRepository::default(), operation_repo);
+        let rollout = svc.advance(rollout).await.unwrap();
+        assert_eq!(rollout.status, RolloutStatus::Running);
+        assert_eq!(rollout.progress().rebooting, 2);
+        assert_eq!(rollout.progress().pending, 1);
+    }
+
+    #[actix_rt::test]
+    async fn advance_saves_the_reboots_requested_before_an_abort() {
+        let mut rollout = Rollout::new(Uuid::new_v4(), 1, 0, (0..2).map(|_| Uuid::new_v4()));
+        rollout.version = 1;
+
+        let mut operation_repo = MockNodeRepository::default();
+        operation_repo
+            .expect_get_node()
+            .once()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        operation_repo
+            .expect_create_operation()
+            .once()
+            .returning(|op| Ok(op.clone()));
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo
+            .expect_update_rollout()
+            .withf(|_, _, _, version| *version == Some(vec![1]))
+            .once()
+            .returning(|_, _, _, _| Err(RepositoryError::VersionMismatch));
+        let mut aborted = rollout.clone();
+        skip_pending_nodes(&mut aborted);
+        aborted.status = RolloutStatus::Aborted;
+        aborted.version = 2;
+        let stored = aborted.clone();
+        rollout_repo
+            .expect_get_rollout()
+            .once()
+            .returning(move |_| Ok(stored.clone()));
+        rollout_repo
+            .expect_update_rollout()
+            .withf(|_, status, nodes, version| {
+                *status == RolloutStatus::Aborted
+                    && *version == Some(vec![2])
+                    && nodes.len() == 1
+                    && nodes[0].status == RolloutNodeStatus::Rebooting
+            })
+            .once()
+            .returning(move |_, status, nodes, _| Ok(updated(&aborted, status, nodes)));
+
+        let svc = create_service(rollout_repo, MockNodeRepository::default(), operation_repo);
+        let rollout = svc.advance(rollout).await.unwrap();
+        assert_eq!(rollout.status, RolloutStatus::Aborted);
+        assert_eq!(rollout.progress().rebooting, 1);
+        assert_eq!(rollout.progress().skipped, 1);
+    }
+
+    #[actix_rt::test]
+    async fn advance_waits_for_the_maintenance_windows() {
+        let mut rollout = Rollout::new(Uuid::new_v4(), 2, 0, (0..3).map(|_| Uuid::new_v4()));
+        rollout.version = 1;
+
+        let mut operation_repo = MockNodeRepository::default();
+        operation_repo
+            .expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        operation_repo.expect_create_operation().never();
+        let mut policy = MockMaintenancePolicy::default();
+        policy.expect_allows().returning(|_, _, _| Ok(false));
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo.expect_update_rollout().never();
+
+        let operation_service = OperationService::new(operation_repo, PowerDrivers::new())
+            .with_maintenance_policy(policy);
+        let svc = RolloutService::new(
+            rollout_repo,
+            MockNodeRepository::default(),
+            operation_service,
+        );
+        let rollout = svc.advance(rollout).await.unwrap();
+        assert_eq!(rollout.status, RolloutStatus::Running);
+        assert_eq!(rollout.progress().pending, 3);
+    }
+
+    #[actix_rt::test]
+    async fn advance_waits_for_nodes_to_power_on() {
+        let (rollout, mut operation) = rebooting_rollout(1, 0);
+        operation.status = OperationStatus::Succeeded;
+
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_operation()
+            .returning(move |_| Ok(operation.clone()));
+        node_repo
+            .expect_get_node()
+            .once()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::Rebooting)));
+        node_repo
+            .expect_get_node()
+            .once()
+            .returning(|id| Ok(create_test_node(*id, NodeStatus::PowerOn)));
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo.expect_update_rollout().never();
+        let svc = create_service(rollout_repo, node_repo, MockNodeRepository::default());
+        let rollout = svc.advance(rollout).await.unwrap();
+        assert_eq!(rollout.nodes[0].status, RolloutNodeStatus::Rebooting);
+
+        let mut rollout_repo = MockRolloutRepository::default();
+        let claimed = rollout.clone();
+        rollout_repo
+            .expect_update_rollout()
+            .withf(|_, status, nodes, version| {
+                *status == RolloutStatus::Succeeded
+                    && *version == Some(vec![3])
+                    && nodes[0].status == RolloutNodeStatus::Succeeded
+            })
+            .once()
+            .returning(move |_, status, nodes, _| Ok(updated(&claimed, status, nodes)));
+        let svc = RolloutService {
+            rollout_repository: rollout_repo,
+            ..svc
+        };
+        let rollout = svc.advance(rollout).await.unwrap();
+        assert_eq!(rollout.status, RolloutStatus::Succeeded);
+        assert_eq!(rollout.version, 4);
+    }
+
+    #[actix_rt::test]
+    async fn advance_stops_when_too_many_nodes_fail() {
+        let (rollout, mut operation) = rebooting_rollout(3, 0);
+        operation.status = OperationStatus::Failed;
+        operation.failure_reason = Some("BMC unreachable".to_string());
+
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_operation()
+            .returning(move |_| Ok(operation.clone()));
+        let mut operation_repo = MockNodeRepository::default();
+        operation_repo.expect_create_operation().never();
+        let mut rollout_repo = MockRolloutRepository::default();
+        let failed_nodes = |nodes: &[RolloutNode]| {
+            nodes.len() == 3
+                && nodes[0].status == RolloutNodeStatus::Failed
+                && nodes[0].failure_reason.as_deref() == Some("BMC unreachable")
+                && nodes[1..]
+                    .iter()
+                    .all(|n| n.status == RolloutNodeStatus::Skipped)
+        };
+        rollout_repo
+            .expect_update_rollout()
+            .withf(move |_, status, nodes, version| {
+                *status == RolloutStatus::Failed && *version == Some(vec![3]) && failed_nodes(nodes)
+            })
+            .once()
+            .returning(|_, _, _, _| Err(RepositoryError::VersionMismatch));
+        // the rollout was paused in the meantime, but it fails anyway
+        let paused = Rollout {
+            status: RolloutStatus::Paused,
+            version: 4,
+            ..rollout.clone()
+        };
+        let stored = paused.clone();
+        rollout_repo
+            .expect_get_rollout()
+            .once()
+            .returning(move |_| Ok(stored.clone()));
+        rollout_repo
+            .expect_update_rollout()
+            .withf(move |_, status, nodes, version| {
+                *status == RolloutStatus::Failed && *version == Some(vec![4]) && failed_nodes(nodes)
+            })
+            .once()
+            .returning(move |_, status, nodes, _| Ok(updated(&paused, status, nodes)));
+
+        let svc = create_service(rollout_repo, node_repo, operation_repo);
+        let rollout = svc.advance(rollout).await.unwrap();
+        assert_eq!(rollout.status, RolloutStatus::Failed);
+        assert_eq!(rollout.version, 5);
+        assert_eq!(rollout.progress().failed, 1);
+    }
+
+    #[actix_rt::test]
+    async fn abort_skips_the_pending_nodes() {
+        let (rollout, _) = rebooting_rollout(2, 0);
+        let rollout_id = rollout.id;
+
+        let mut rollout_repo = MockRolloutRepository::default();
+        let stored = rollout.clone();
+        rollout_repo
+            .expect_get_rollout()
+            .returning(move |_| Ok(stored.clone()));
+        rollout_repo
+            .expect_update_rollout()
+            .withf(|_, status, nodes, version| {
+                *status == RolloutStatus::Aborted
+                    && *version == Some(vec![3])
+                    && nodes.len() == 1
+                    && nodes[0].status == RolloutNodeStatus::Skipped
+            })
+            .once()
+            .returning(move |_, status, nodes, _| Ok(updated(&rollout, status, nodes)));
+
+        let svc = create_service(
+            rollout_repo,
+            MockNodeRepository::default(),
+            MockNodeRepository::default(),
+        );
+        let aborted = svc.abort(&rollout_id, None).await.unwrap();
+        assert_eq!(aborted.status, RolloutStatus::Aborted);
+        assert_eq!(aborted.nodes[0].status, RolloutNodeStatus::Rebooting);
+        assert_eq!(aborted.nodes[1].status, RolloutNodeStatus::Skipped);
+    }
+
+    #[actix_rt::test]
+    async fn finished_rollouts_cannot_be_resumed() {
+        let mut rollout = Rollout::new(Uuid::new_v4(), 1, 0, []);
+        rollout.status = RolloutStatus::Succeeded;
+        let rollout_id = rollout.id;
+
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo
+            .expect_get_rollout()
+            .returning(move |_| Ok(rollout.clone()));
+        rollout_repo.expect_update_rollout().never();
+
+        let svc = create_service(
+            rollout_repo,
+            MockNodeRepository::default(),
+            MockNodeRepository::default(),
+        );
+        let result = svc.resume(&rollout_id, None).await;
+        assert!(matches!(
+            result,
+            Err(RolloutServiceError::InvalidTransition {
+                from: RolloutStatus::Succeeded,
+                to: RolloutStatus::Running
+            })
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    application::{
+        audit_log::{self, AuditContext},
+        rollout_service::{RolloutService, RolloutServiceError},
+    },
+    domain::repository::{NodeRepository, RolloutRepository},
+};
+use std::time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct RolloutWorkerConfig {
+    /// How long to wait before polling again when there's no rollout to check.
+    pub poll_interval: Duration,
+    /// How long a claimed rollout is reserved for this worker.
+    pub lease: Duration,
+    /// How long a rollout waits between two checks of its nodes.
+    pub check_interval: Duration,
+}
+
+impl Default for RolloutWorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            lease: Duration::from_secs(60),
+            check_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Advances the unfinished rollouts, one check at a time.
+#[derive(Debug, Clone)]
+pub struct RolloutWorker<N: NodeRepository, R: RolloutRepository> {
+    id: String,
+    service: RolloutService<N, R>,
+    config: RolloutWorkerConfig,
+}
+
+impl<N, R> RolloutWorker<N, R>
+where
+    N: NodeRepository,
+    R: RolloutRepository,
+{
+    pub fn new(service: RolloutService<N, R>, config: RolloutWorkerConfig) -> Self {
+        Self {
+            id: format!("rollout-worker-{}", Uuid::new_v4()),
+            service,
+            config,
+        }
+    }
+
+    pub fn start(self) {
+        tracing::debug!("Starting rollout worker {}", self.id);
+        actix_web::rt::spawn(async move { self.run().await });
+    }
+
+    async fn run(&self) {
+        let context = AuditContext::system(&self.id);
+        loop {
+            match audit_log::scope(context.clone(), self.process_next()).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error processing rollout: {:?}", e),
+            }
+            actix_web::rt::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Advances the next rollout that needs to be checked. Returns `false` if there was none.
+    #[instrument(skip(self), fields(worker_id = %self.id))]
+    pub async fn process_next(&self) -> Result<bool, RolloutServiceError> {
+        let lease_secs = self.config.lease.as_secs() as i64;
+        let rollout = match self.service.claim_next(&self.id, lease_secs).await? {
+            Some(rollout) => rollout,
+            None => return Ok(false),
+        };
+
+        let rollout_id = rollout.id;
+        let result = self.service.advance(rollout).await;
+        // if it failed the rollout is checked again later, like every other one
+        let delay_secs = self.config.check_interval.as_secs() as i64;
+        self.service
+            .release(&rollout_id, &self.id, delay_secs)
+            .await?;
+        result.map(|_| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::{
+            operation_service::OperationService,
+            power_driver::{PowerDrivers, SimulatedPowerDriver},
+        },
+        domain::{
+            models::{PowerDriverKind, Rollout},
+            repository::{
+                node_repository::MockNodeRepository, rollout_repository::MockRolloutRepository,
+                RepositoryError,
+            },
+        },
+    };
+
+    fn create_worker(
+        node_repo: MockNodeRepository,
+        rollout_repo: MockRolloutRepository,
+    ) -> RolloutWorker<MockNodeRepository, MockRolloutRepository> {
+        let drivers = PowerDrivers::new().with(
+            PowerDriverKind::Simulated,
+            SimulatedPowerDriver::new(Duration::ZERO),
+        );
+        let operation_service = OperationService::new(MockNodeRepository::default(), drivers);
+        let service = RolloutService::new(rollout_repo, node_repo, operation_service);
+        RolloutWorker::new(service, RolloutWorkerConfig::default())
+    }
+
+    #[actix_rt::test]
+    async fn process_next_returns_false_if_there_is_no_rollout() {
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo
+            .expect_claim_rollout()
+            .returning(|_, _| Ok(None));
+        rollout_repo.expect_release_rollout().never();
+
+        let worker = create_worker(MockNodeRepository::default(), rollout_repo);
+        assert!(!worker.process_next().await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn process_next_releases_the_rollout_even_if_it_fails() {
+        let rollout = Rollout::new(Uuid::new_v4(), 1, 0, [Uuid::new_v4()]);
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_operation()
+            .returning(|_| Err(RepositoryError::Unavailable("down".to_string())));
+        let mut rollout_repo = MockRolloutRepository::default();
+        let mut claimed = rollout.clone();
+        claimed.nodes[0].status = crate::domain::models::RolloutNodeStatus::Rebooting;
+        claimed.nodes[0].operation_id = Some(Uuid::new_v4());
+        rollout_repo
+            .expect_claim_rollout()
+            .returning(move |_, _| Ok(Some(claimed.clone())));
+        let rollout_id = rollout.id;
+        rollout_repo
+            .expect_release_rollout()
+            .withf(move |id, _, delay| *id == rollout_id && *delay == 5)
+            .once()
+            .returning(|_, _, _| Ok(()));
+
+        let worker = create_worker(node_repo, rollout_repo);
+        assert!(matches!(
+            worker.process_next().await,
+            Err(RolloutServiceError::RepositoryError(
+                RepositoryError::Unavailable(_)
+            ))
+        ));
+    }
+}
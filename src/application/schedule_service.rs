@@ -0,0 +1,461 @@
+use crate::{
+    application::operation_service::{
+        BatchRequest, BatchTarget, OperationOptions, OperationService, OperationServiceError,
+    },
+    domain::{
+        models::{MissedRunPolicy, ScheduleRun, ScheduleTarget, ScheduledOperation},
+        repository::{NodeRepository, RepositoryError, ScheduleRepository},
+    },
+};
+use chrono::{Duration, Utc};
+use thiserror::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum ScheduleServiceError {
+    #[error("{0}")]
+    InvalidSchedule(String),
+    #[error(transparent)]
+    RepositoryError(#[from] RepositoryError),
+}
+
+pub type ScheduleServiceResult = Result<ScheduledOperation, ScheduleServiceError>;
+
+/// Keeps the schedules and enqueues their operations when they come due.
+#[derive(Debug, Clone)]
+pub struct ScheduleService<N: NodeRepository, S: ScheduleRepository> {
+    schedule_repository: S,
+    operation_service: OperationService<N>,
+}
+
+impl<N, S> ScheduleService<N, S>
+where
+    N: NodeRepository,
+    S: ScheduleRepository,
+{
+    pub fn new(schedule_repository: S, operation_service: OperationService<N>) -> Self {
+        Self {
+            schedule_repository,
+            operation_service,
+        }
+    }
+
+    /// Schedules targeting the tenant, or every schedule for the callers without one.
+    #[instrument(skip(self))]
+    pub async fn get_all(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledOperation>, ScheduleServiceError> {
+        let schedules = self.schedule_repository.get_schedules(tenant_id).await?;
+        Ok(schedules)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, schedule_id: &Uuid) -> ScheduleServiceResult {
+        let schedule = self.schedule_repository.get_schedule(schedule_id).await?;
+        Ok(schedule)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn create(&self, schedule: &ScheduledOperation) -> ScheduleServiceResult {
+        let schedule = with_next_run(schedule)?;
+        let schedule = self.schedule_repository.create_schedule(&schedule).await?;
+        Ok(schedule)
+    }
+
+    /// Replaces the definition of a schedule, which runs next according to the new one.
+    #[instrument(skip(self))]
+    pub async fn update(
+        &self,
+        schedule: &ScheduledOperation,
+        expected_version: Option<Vec<i64>>,
+    ) -> ScheduleServiceResult {
+        let schedule = with_next_run(schedule)?;
+        let schedule = self
+            .schedule_repository
+            .update_schedule(&schedule, expected_version)
+            .await?;
+        Ok(schedule)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete(
+        &self,
+        schedule_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> Result<Uuid, ScheduleServiceError> {
+        let id = self
+            .schedule_repository
+            .delete_schedule(schedule_id, expected_version)
+            .await?;
+        Ok(id)
+    }
+
+    /// Claims the next schedule that is due, if any.
+    #[instrument(skip(self))]
+    pub async fn claim_next(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> Result<Option<ScheduledOperation>, ScheduleServiceError> {
+        let schedule = self
+            .schedule_repository
+            .claim_schedule(worker_id, lease_secs)
+            .await?;
+        Ok(schedule)
+    }
+
+    /// Enqueues the operation of a claimed schedule as a best-effort batch and records the run.
+    ///
+    /// Runs that are more than `missed_run_grace` late were missed, so they're only made if
+    /// the policy of the schedule says so. Either way, recurring schedules run next at their
+    /// first time after now, so a schedule runs once no matter how many runs it missed.
+    #[instrument(skip(self, schedule), fields(schedule_id = %schedule.id))]
+    pub async fn run(
+        &self,
+        schedule: &ScheduledOperation,
+        worker_id: &str,
+        missed_run_grace: Duration,
+    ) -> Result<ScheduleRun, ScheduleServiceError> {
+        let now = Utc::now();
+        let due = schedule.next_run_at.unwrap_or(now);
+        let next_run_at = schedule.next_run(now).ok().flatten();
+
+        let missed = now - due > missed_run_grace;
+        let run = if missed && schedule.missed_run_policy == MissedRunPolicy::Skip {
+            tracing::warn!("Skipping the missed run of schedule {}", schedule.id);
+            ScheduleRun {
+                ran_at: now,
+                batch_id: None,
+                error: Some(format!("Skipped the run missed at {}", due.to_rfc3339())),
+                next_run_at,
+            }
+        } else {
+            let (batch_id, error) = self.enqueue(schedule, worker_id).await?;
+            ScheduleRun {
+                ran_at: now,
+                batch_id,
+                error,
+                next_run_at,
+            }
+        };
+
+        self.schedule_repository
+            .record_run(&schedule.id, worker_id, schedule.version, &run)
+            .await?;
+        Ok(run)
+    }
+
+    /// Enqueues the operation of the due run of a schedule, unless an earlier try of the
+    /// run already did, returning the batch and why some nodes can't run the operation.
+    async fn enqueue(
+        &self,
+        schedule: &ScheduledOperation,
+        worker_id: &str,
+    ) -> Result<(Option<Uuid>, Option<String>), ScheduleServiceError> {
+        let batch_id = self
+            .schedule_repository
+            .reserve_run(&schedule.id, worker_id, &Uuid::new_v4())
+            .await?;
+        let result = match self.operation_service.get_batch(&batch_id).await {
+            Err(OperationServiceError::RepositoryError(RepositoryError::DoesNotExist)) => {
+                let target = match schedule.target {
+                    ScheduleTarget::NodeId(node_id) => BatchTarget::NodeIds(vec![node_id]),
+                    ScheduleTarget::ClusterId(cluster_id) => BatchTarget::ClusterId(cluster_id),
+                };
+                let request = BatchRequest {
+                    operation_type: schedule.operation_type,
+                    target,
+                    atomic: false,
+                    batch_id: Some(batch_id),
+                };
+                self.operation_service
+                    .create_batch(&request, OperationOptions::default())
+                    .await
+                    .map(Some)
+            }
+            Ok(_) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(Some(result)) => {
+                let failed = result
+                    .items
+                    .iter()
+                    .filter(|item| item.error.is_some())
+                    .count();
+                let error = (failed > 0).then(|| {
+                    format!(
+                        "The operation was skipped on {} of the {} nodes of the batch",
+                        failed,
+                        result.items.len()
+                    )
+                });
+                Ok((Some(batch_id), error))
+            }
+            // the batch was enqueued by an earlier try of the run, which couldn't record it
+            Ok(None)
+            | Err(OperationServiceError::RepositoryError(RepositoryError::AlreadyExists)) => {
+                tracing::warn!(
+                    "Schedule {} already enqueued batch {}",
+                    schedule.id,
+                    batch_id
+                );
+                Ok((Some(batch_id), None))
+            }
+            // the lease will expire and the run will be tried again
+            Err(OperationServiceError::RepositoryError(e @ RepositoryError::Unavailable(_))) => {
+                Err(e.into())
+            }
+            Err(e) => Ok((None, Some(e.to_string()))),
+        }
+    }
+}
+
+/// Validates the schedule, setting when it runs first.
+fn with_next_run(
+    schedule: &ScheduledOperation,
+) -> Result<ScheduledOperation, ScheduleServiceError> {
+    let next_run_at = schedule
+        .next_run(Utc::now())
+        .map_err(ScheduleServiceError::InvalidSchedule)?;
+    if next_run_at.is_none() {
+        return Err(ScheduleServiceError::InvalidSchedule(
+            "The schedule would never run: `run_at` must be in the future".to_string(),
+        ));
+    }
+    Ok(ScheduledOperation {
+        next_run_at,
+        ..schedule.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::power_driver::PowerDrivers,
+        domain::{
+            models::{Batch, Node, NodeStatus, OperationType, PowerDriverKind},
+            repository::{
+                node_repository::MockNodeRepository, schedule_repository::MockScheduleRepository,
+            },
+        },
+    };
+
+    const BATCH_ID: Uuid = Uuid::from_u128(1);
+
+    fn create_test_schedule(
+        target: ScheduleTarget,
+        run_at: Option<chrono::DateTime<Utc>>,
+        cron: Option<&str>,
+    ) -> ScheduledOperation {
+        ScheduledOperation {
+            id: Uuid::new_v4(),
+            operation_type: OperationType::Reboot,
+            target,
+            run_at,
+            cron: cron.map(|cron| cron.to_string()),
+            missed_run_policy: MissedRunPolicy::RunOnce,
+            enabled: true,
+            next_run_at: None,
+            last_run_at: None,
+            last_batch_id: None,
+            last_error: None,
+            created_at: None,
+            updated_at: None,
+            version: 2,
+        }
+    }
+
+    fn create_service(
+        schedule_repo: MockScheduleRepository,
+        node_repo: MockNodeRepository,
+    ) -> ScheduleService<MockNodeRepository, MockScheduleRepository> {
+        let operation_service = OperationService::new(node_repo, PowerDrivers::new());
+        ScheduleService::new(schedule_repo, operation_service)
+    }
+
+    #[actix_rt::test]
+    async fn create_sets_the_first_run() {
+        let mut schedule_repo = MockScheduleRepository::default();
+        schedule_repo
+            .expect_create_schedule()
+            .once()
+            .returning(|schedule| Ok(schedule.clone()));
+        let service = create_service(schedule_repo, MockNodeRepository::default());
+
+        let target = ScheduleTarget::ClusterId(Uuid::new_v4());
+        let run_at = Utc::now() + Duration::hours(1);
+        let schedule = service
+            .create(&create_test_schedule(target, Some(run_at), None))
+            .await
+            .unwrap();
+        assert_eq!(schedule.next_run_at, Some(run_at));
+
+        for invalid in [
+            create_test_schedule(target, Some(Utc::now() - Duration::hours(1)), None),
+            create_test_schedule(target, None, Some("every sunday")),
+            create_test_schedule(target, None, None),
+        ] {
+            assert!(matches!(
+                service.create(&invalid).await,
+                Err(ScheduleServiceError::InvalidSchedule(_))
+            ));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn run_enqueues_the_operation_and_records_the_run() {
+        let node_id = Uuid::new_v4();
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_get_nodes_by_id().returning(|node_ids| {
+            Ok(vec![Node {
+                id: node_ids[0],
+                name: "my_node".to_string(),
+                cluster_id: Uuid::new_v4(),
+                status: NodeStatus::PowerOn,
+                driver: PowerDriverKind::Simulated,
+                bmc_endpoint: None,
+                created_at: None,
+                updated_at: None,
+                version: 1,
+            }])
+        });
+        node_repo
+            .expect_get_batch()
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+        node_repo
+            .expect_create_batch()
+            .withf(|batch, _| batch.id == BATCH_ID)
+            .returning(|batch, _| Ok((batch.clone(), vec![])));
+        node_repo
+            .expect_create_operation()
+            .once()
+            .returning(|operation| Ok(operation.clone()));
+
+        let mut schedule =
+            create_test_schedule(ScheduleTarget::NodeId(node_id), None, Some("0 3 * * Sun"));
+        schedule.next_run_at = Some(Utc::now() - Duration::seconds(5));
+        let schedule_id = schedule.id;
+        let mut schedule_repo = MockScheduleRepository::default();
+        schedule_repo
+            .expect_reserve_run()
+            .once()
+            .returning(|_, _, _| Ok(BATCH_ID));
+        schedule_repo
+            .expect_record_run()
+            .withf(move |id, worker, version, run| {
+                *id == schedule_id
+                    && worker == "worker"
+                    && *version == 2
+                    && run.batch_id == Some(BATCH_ID)
+                    && run.error.is_none()
+                    && run.next_run_at.is_some_and(|next| next > Utc::now())
+            })
+            .once()
+            .returning(|_, _, _, _| Ok(()));
+
+        let service = create_service(schedule_repo, node_repo);
+        service
+            .run(&schedule, "worker", Duration::seconds(60))
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn run_doesnt_enqueue_the_batch_of_an_earlier_try_again() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_batch()
+            .once()
+            .returning(|_| Ok(Batch::new(OperationType::Reboot, false)));
+        node_repo.expect_create_batch().never();
+        let mut schedule_repo = MockScheduleRepository::default();
+        // the earlier try reserved the batch, enqueued it and lost its lease
+        schedule_repo
+            .expect_reserve_run()
+            .withf(|_, _, batch_id| *batch_id != BATCH_ID)
+            .once()
+            .returning(|_, _, _| Ok(BATCH_ID));
+        schedule_repo
+            .expect_record_run()
+            .withf(|_, _, _, run| run.batch_id == Some(BATCH_ID) && run.error.is_none())
+            .once()
+            .returning(|_, _, _, _| Ok(()));
+
+        let mut schedule = create_test_schedule(
+            ScheduleTarget::ClusterId(Uuid::new_v4()),
+            None,
+            Some("0 * * * *"),
+        );
+        schedule.next_run_at = Some(Utc::now() - Duration::seconds(5));
+        let service = create_service(schedule_repo, node_repo);
+        service
+            .run(&schedule, "worker", Duration::seconds(60))
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn run_skips_missed_runs_if_the_policy_says_so() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_get_nodes().never();
+        let mut schedule = create_test_schedule(
+            ScheduleTarget::ClusterId(Uuid::new_v4()),
+            None,
+            Some("0 * * * *"),
+        );
+        schedule.missed_run_policy = MissedRunPolicy::Skip;
+        schedule.next_run_at = Some(Utc::now() - Duration::hours(5));
+        let mut schedule_repo = MockScheduleRepository::default();
+        schedule_repo.expect_reserve_run().never();
+        schedule_repo
+            .expect_record_run()
+            .withf(|_, _, _, run| {
+                run.batch_id.is_none() && run.error.is_some() && run.next_run_at.is_some()
+            })
+            .once()
+            .returning(|_, _, _, _| Ok(()));
+
+        let service = create_service(schedule_repo, node_repo);
+        service
+            .run(&schedule, "worker", Duration::seconds(60))
+            .await
+            .unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn run_is_retried_if_the_storage_is_unavailable() {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_batch()
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+        node_repo
+            .expect_get_nodes_by_id()
+            .returning(|_| Err(RepositoryError::Unavailable("down".to_string())));
+        let mut schedule_repo = MockScheduleRepository::default();
+        schedule_repo
+            .expect_reserve_run()
+            .returning(|_, _, batch_id| Ok(*batch_id));
+        // the run isn't recorded so the schedule is claimed again once the lease expires
+        schedule_repo.expect_record_run().never();
+
+        let mut schedule = create_test_schedule(
+            ScheduleTarget::NodeId(Uuid::new_v4()),
+            Some(Utc::now() - Duration::seconds(1)),
+            None,
+        );
+        schedule.next_run_at = schedule.run_at;
+        let service = create_service(schedule_repo, node_repo);
+        assert!(matches!(
+            service
+                .run(&schedule, "worker", Duration::seconds(60))
+                .await,
+            Err(ScheduleServiceError::RepositoryError(
+                RepositoryError::Unavailable(_)
+            ))
+        ));
+    }
+}
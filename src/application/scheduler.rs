@@ -0,0 +1,111 @@
+use crate::{
+    application::{
+        audit_log::{self, AuditContext},
+        schedule_service::{ScheduleService, ScheduleServiceError},
+    },
+    domain::repository::{NodeRepository, ScheduleRepository},
+};
+use std::time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// How long to wait before polling again when there's no schedule due.
+    pub poll_interval: Duration,
+    /// How long a claimed schedule is reserved for this scheduler.
+    pub lease: Duration,
+    /// How late a run can be before it counts as missed.
+    pub missed_run_grace: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            lease: Duration::from_secs(60),
+            missed_run_grace: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Runs the schedules that come due.
+#[derive(Debug, Clone)]
+pub struct Scheduler<N: NodeRepository, S: ScheduleRepository> {
+    id: String,
+    service: ScheduleService<N, S>,
+    config: SchedulerConfig,
+}
+
+impl<N, S> Scheduler<N, S>
+where
+    N: NodeRepository,
+    S: ScheduleRepository,
+{
+    pub fn new(service: ScheduleService<N, S>, config: SchedulerConfig) -> Self {
+        Self {
+            id: format!("scheduler-{}", Uuid::new_v4()),
+            service,
+            config,
+        }
+    }
+
+    pub fn start(self) {
+        tracing::debug!("Starting scheduler {}", self.id);
+        actix_web::rt::spawn(async move { self.run().await });
+    }
+
+    async fn run(&self) {
+        let context = AuditContext::system(&self.id);
+        loop {
+            match audit_log::scope(context.clone(), self.process_next()).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error running schedule: {:?}", e),
+            }
+            actix_web::rt::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    /// Runs the next schedule that is due. Returns `false` if there was none.
+    #[instrument(skip(self), fields(worker_id = %self.id))]
+    pub async fn process_next(&self) -> Result<bool, ScheduleServiceError> {
+        let lease_secs = self.config.lease.as_secs() as i64;
+        let schedule = match self.service.claim_next(&self.id, lease_secs).await? {
+            Some(schedule) => schedule,
+            None => return Ok(false),
+        };
+
+        let grace = chrono::Duration::from_std(self.config.missed_run_grace)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+        self.service.run(&schedule, &self.id, grace).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::{operation_service::OperationService, power_driver::PowerDrivers},
+        domain::repository::{
+            node_repository::MockNodeRepository, schedule_repository::MockScheduleRepository,
+        },
+    };
+
+    #[actix_rt::test]
+    async fn process_next_returns_false_if_there_is_no_schedule_due() {
+        let mut schedule_repo = MockScheduleRepository::default();
+        schedule_repo
+            .expect_claim_schedule()
+            .withf(|_, lease_secs| *lease_secs == 60)
+            .returning(|_, _| Ok(None));
+        schedule_repo.expect_record_run().never();
+
+        let operation_service =
+            OperationService::new(MockNodeRepository::default(), PowerDrivers::new());
+        let service = ScheduleService::new(schedule_repo, operation_service);
+        let scheduler = Scheduler::new(service, SchedulerConfig::default());
+        assert!(!scheduler.process_next().await.unwrap());
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Key used to call the API. Only a hash of its secret is stored.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ApiKey {
+    pub id: Uuid,
+    /// Who the key was given to.
+    pub owner: String,
+    /// Whether the key can manage the API keys.
+    #[serde(default)]
+    pub admin: bool,
+    /// Organization the owner belongs to, whose clusters are the only ones the key sees.
+    pub tenant_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Whether the key can be used at the given time.
+    pub fn is_active(&self, at: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| at < expires_at)
+    }
+}
+
+/// API key that was just created, the only time its secret is known.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct MintedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    pub secret: String,
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use uuid::Uuid;
+
+use super::OperationType;
+use crate::domain::repository::Change;
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    #[serde(rename = "poweron")]
+    PowerOn,
+    #[serde(rename = "poweroff")]
+    PowerOff,
+    Reboot,
+    /// A worker applied an operation to its node.
+    Execute,
+    /// A worker gave up on an operation.
+    Fail,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditTargetType {
+    Cluster,
+    Node,
+    Operation,
+    Batch,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+impl From<OperationType> for AuditAction {
+    fn from(operation_type: OperationType) -> Self {
+        match operation_type {
+            OperationType::PowerOn => AuditAction::PowerOn,
+            OperationType::PowerOff => AuditAction::PowerOff,
+            OperationType::Reboot => AuditAction::Reboot,
+        }
+    }
+}
+
+/// Record of a change made through the API, or by its workers. The events are never
+/// changed once they're recorded.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    /// Subject of the caller, or id of the worker that made the change.
+    pub actor: String,
+    /// Organization of the cluster of the target, which the repositories look up when
+    /// the event is recorded. Until then, and if the target can't be found, it's the one
+    /// of the caller.
+    pub tenant_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub target_type: AuditTargetType,
+    /// Not known when creating something fails.
+    pub target_id: Option<Uuid>,
+    /// The target before the change, if it existed.
+    pub before: Option<serde_json::Value>,
+    /// The target after the change, unless it failed or deleted the target.
+    pub after: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    pub source_ip: Option<String>,
+    pub outcome: AuditOutcome,
+    /// Why the change failed.
+    pub reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl AuditEvent {
+    /// Successful event of an unknown actor, which is set along with the rest of the
+    /// details of the request by whoever records it.
+    pub fn new(action: AuditAction, target_type: AuditTargetType, target_id: Option<Uuid>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor: String::new(),
+            tenant_id: None,
+            action,
+            target_type,
+            target_id,
+            before: None,
+            after: None,
+            request_id: None,
+            source_ip: None,
+            outcome: AuditOutcome::Success,
+            reason: None,
+            created_at: None,
+        }
+    }
+
+    pub fn with_before<T: Serialize>(mut self, before: Option<&T>) -> Self {
+        self.before = before.and_then(snapshot);
+        self
+    }
+
+    /// Takes the outcome of the change from its result, which is also the state of the
+    /// target after a successful change.
+    pub fn with_result<T: Serialize, E: Display>(mut self, result: &Result<T, E>) -> Self {
+        match result {
+            Ok(after) => self.after = snapshot(after),
+            Err(e) => {
+                self.outcome = AuditOutcome::Failure;
+                self.reason = Some(e.to_string());
+            }
+        }
+        self
+    }
+
+    /// Takes the state of the target before and after the change, and the outcome of the
+    /// change, from the result of the write.
+    pub fn with_change<T: Serialize, E: Display>(self, result: &Result<Change<T>, E>) -> Self {
+        let before = result.as_ref().ok().map(|change| &change.before);
+        self.with_before(before)
+            .with_result(&result.as_ref().map(|change| &change.after))
+    }
+
+    /// Cluster of the target: the target itself, or the cluster of its snapshots.
+    pub fn cluster_id(&self) -> Option<Uuid> {
+        match self.target_type {
+            AuditTargetType::Cluster => self.target_id,
+            _ => self.snapshot_id("cluster_id"),
+        }
+    }
+
+    /// Tenant of the target according to its snapshots, which is all that's left of a
+    /// deleted cluster.
+    pub fn snapshot_tenant_id(&self) -> Option<Uuid> {
+        self.snapshot_id("tenant_id")
+    }
+
+    fn snapshot_id(&self, field: &str) -> Option<Uuid> {
+        [&self.after, &self.before]
+            .into_iter()
+            .flatten()
+            .find_map(|snapshot| snapshot.get(field)?.as_str()?.parse().ok())
+    }
+}
+
+fn snapshot<T: Serialize>(value: &T) -> Option<serde_json::Value> {
+    // ids and other plain values aren't snapshots of the target
+    serde_json::to_value(value)
+        .ok()
+        .filter(|value| value.is_object())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::Operation;
+
+    #[test]
+    fn events_take_the_outcome_from_the_result() {
+        let operation = Operation::new(Uuid::new_v4(), OperationType::Reboot);
+        let event = AuditEvent::new(AuditAction::Reboot, AuditTargetType::Node, None)
+            .with_result(&Ok::<_, String>(operation.clone()));
+        assert_eq!(event.outcome, AuditOutcome::Success);
+        assert_eq!(event.after.unwrap()["id"], operation.id.to_string());
+
+        let event = AuditEvent::new(AuditAction::Delete, AuditTargetType::Node, None)
+            .with_before(Some(&operation))
+            .with_result(&Ok::<_, String>(operation.id));
+        assert!(event.before.is_some());
+        assert_eq!(event.after, None);
+
+        let event = AuditEvent::new(AuditAction::Create, AuditTargetType::Node, None)
+            .with_result(&Err::<Operation, _>("This entity already exists"));
+        assert_eq!(event.outcome, AuditOutcome::Failure);
+        assert_eq!(event.reason.as_deref(), Some("This entity already exists"));
+    }
+}
@@ -0,0 +1,61 @@
+use super::{Operation, OperationType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Operation requested on many nodes at once. Each node gets its own [`Operation`],
+/// which points to the batch.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Batch {
+    pub id: Uuid,
+    pub operation_type: OperationType,
+    /// Whether the operations were created all at once or the nodes that couldn't
+    /// run the operation were skipped.
+    pub atomic: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Batch {
+    pub fn new(operation_type: OperationType, atomic: bool) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            operation_type,
+            atomic,
+            created_at: None,
+        }
+    }
+}
+
+/// Result of a batch for one of its nodes.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BatchItem {
+    pub node_id: Uuid,
+    /// The operation created for the node, if any.
+    pub operation: Option<Operation>,
+    /// Why the node can't run the operation.
+    pub error: Option<String>,
+}
+
+impl BatchItem {
+    pub fn created(operation: Operation) -> Self {
+        Self {
+            node_id: operation.node_id,
+            operation: Some(operation),
+            error: None,
+        }
+    }
+
+    pub fn failed(node_id: Uuid, error: impl ToString) -> Self {
+        Self {
+            node_id,
+            operation: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BatchResult {
+    pub batch: Batch,
+    pub items: Vec<BatchItem>,
+}
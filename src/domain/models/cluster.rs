@@ -1,12 +1,61 @@
+use super::MaintenanceWindow;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Cluster {
     pub id: Uuid,
     pub name: String,
+    /// Organization that owns the cluster and its nodes. It can't be changed, and the
+    /// clusters without one are only seen by the callers without a tenant.
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+    /// When the nodes can be powered off or rebooted. Always, if there are none.
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// Incremented on every change of the cluster.
+    /// It's managed by the repositories, so the value sent by clients is ignored.
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// Changes to apply to a [`Cluster`]. The fields that are `None` are left untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClusterPatch {
+    pub name: Option<String>,
+    pub maintenance_windows: Option<Vec<MaintenanceWindow>>,
+}
+
+impl ClusterPatch {
+    pub fn apply(&self, cluster: &mut Cluster) {
+        if let Some(name) = &self.name {
+            cluster.name = name.clone();
+        }
+        if let Some(maintenance_windows) = &self.maintenance_windows {
+            cluster.maintenance_windows = maintenance_windows.clone();
+        }
+    }
+}
+
+/// Number of nodes of a cluster in each [`NodeStatus`](super::NodeStatus).
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NodeStatusCounts {
+    pub poweron: i64,
+    pub poweroff: i64,
+    pub rebooting: i64,
+}
+
+/// Aggregated state of the nodes and operations of a cluster.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClusterSummary {
+    pub cluster_id: Uuid,
+    pub node_count: i64,
+    pub nodes_by_status: NodeStatusCounts,
+    /// When the most recent operation on any node of the cluster was requested.
+    pub last_operation_at: Option<DateTime<Utc>>,
+    /// Pending and running operations.
+    pub in_flight_operations: i64,
 }
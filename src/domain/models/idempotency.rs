@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Request made with an `Idempotency-Key`, kept until it expires so retries of the
+/// same request get the same response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyRecord {
+    /// Caller that made the request. Every caller has its own keys.
+    pub owner: String,
+    pub key: String,
+    /// Hash of the method, path, query and body of the request.
+    pub request_hash: String,
+    /// None while the request is being processed. The key expires earlier then, so it's
+    /// freed if the request never ends (e.g. the API stopped while processing it).
+    pub response: Option<IdempotentResponse>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Response stored to be replayed.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IdempotentResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Longest maintenance window, a whole week.
+pub const MAX_WINDOW_MINUTES: u32 = 7 * 24 * 60;
+
+/// Time range, repeated every week, in which the nodes of a cluster can be disrupted.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// Days the window opens. Every day if empty.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// Local time the window opens, e.g. `02:00:00`.
+    pub start: NaiveTime,
+    pub duration_minutes: u32,
+    /// IANA time zone of `days` and `start`, e.g. `Europe/Madrid`.
+    pub timezone: String,
+}
+
+impl MaintenanceWindow {
+    /// Fails if the window isn't valid.
+    pub fn validate(&self) -> Result<(), String> {
+        self.tz()?;
+        if self.duration_minutes == 0 || self.duration_minutes > MAX_WINDOW_MINUTES {
+            return Err(format!(
+                "The duration of a maintenance window must be between 1 and {} minutes",
+                MAX_WINDOW_MINUTES
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the window is open at the given time. Fails if the window isn't valid.
+    pub fn is_open(&self, at: DateTime<Utc>) -> Result<bool, String> {
+        let tz = self.tz()?;
+        let duration = Duration::minutes(self.duration_minutes as i64);
+        let today = at.with_timezone(&tz).date().naive_local();
+        // the windows that opened some days ago may still be open
+        let days_back = (duration.num_minutes() - 1) / (24 * 60) + 1;
+        Ok((0..=days_back).any(|days| {
+            let day = today - Duration::days(days);
+            if !self.days.is_empty() && !self.days.contains(&day.weekday()) {
+                return false;
+            }
+            let start = day.and_time(self.start);
+            // when the clocks go forward `start` may not exist, so the window opens
+            // right after the change
+            tz.from_local_datetime(&start)
+                .earliest()
+                .or_else(|| {
+                    tz.from_local_datetime(&(start + Duration::hours(1)))
+                        .earliest()
+                })
+                .map(|start| start.with_timezone(&Utc))
+                .is_some_and(|start| start <= at && at < start + duration)
+        }))
+    }
+
+    fn tz(&self) -> Result<Tz, String> {
+        self.timezone
+            .parse()
+            .map_err(|_| format!("Unknown time zone `{}`", self.timezone))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(days: Vec<Weekday>, start: (u32, u32), duration_minutes: u32) -> MaintenanceWindow {
+        MaintenanceWindow {
+            days,
+            start: NaiveTime::from_hms(start.0, start.1, 0),
+            duration_minutes,
+            timezone: "Europe/Madrid".to_string(),
+        }
+    }
+
+    #[test]
+    fn windows_open_on_their_days_in_their_time_zone() {
+        // Saturdays from 23:00 to 01:00 in Madrid, which is UTC+2 in May
+        let window = window(vec![Weekday::Sat], (23, 0), 120);
+        let saturday = Utc.ymd(2022, 4, 30);
+        assert!(!window.is_open(saturday.and_hms(20, 59, 59)).unwrap());
+        assert!(window.is_open(saturday.and_hms(21, 0, 0)).unwrap());
+        // already Sunday in Madrid
+        assert!(window.is_open(saturday.and_hms(22, 30, 0)).unwrap());
+        assert!(!window.is_open(saturday.and_hms(23, 0, 0)).unwrap());
+        // Sunday night
+        assert!(!window
+            .is_open(Utc.ymd(2022, 5, 1).and_hms(21, 30, 0))
+            .unwrap());
+    }
+
+    #[test]
+    fn windows_without_days_open_every_day() {
+        let window = window(vec![], (2, 0), 60);
+        for day in 1..=7 {
+            assert!(window
+                .is_open(Utc.ymd(2022, 5, day).and_hms(0, 30, 0))
+                .unwrap());
+            assert!(!window
+                .is_open(Utc.ymd(2022, 5, day).and_hms(1, 30, 0))
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn invalid_windows_are_rejected() {
+        assert!(window(vec![], (2, 0), 60).validate().is_ok());
+        assert!(window(vec![], (2, 0), 0).validate().is_err());
+        assert!(window(vec![], (2, 0), MAX_WINDOW_MINUTES + 1)
+            .validate()
+            .is_err());
+        let mut unknown = window(vec![], (2, 0), 60);
+        unknown.timezone = "Europe/Atlantis".to_string();
+        assert!(unknown.validate().is_err());
+        assert!(unknown.is_open(Utc::now()).is_err());
+    }
+}
@@ -1,7 +1,27 @@
+mod api_key;
+mod audit_event;
+mod batch;
 mod cluster;
+mod idempotency;
+mod maintenance_window;
 mod node;
 mod operation;
+mod organization;
+mod principal;
+mod rbac;
+mod rollout;
+mod schedule;
 
-pub use cluster::Cluster;
-pub use node::{Node, NodeStatus};
-pub use operation::{Operation, OperationType};
+pub use api_key::{ApiKey, MintedApiKey};
+pub use audit_event::{AuditAction, AuditEvent, AuditOutcome, AuditTargetType};
+pub use batch::{Batch, BatchItem, BatchResult};
+pub use cluster::{Cluster, ClusterPatch, ClusterSummary, NodeStatusCounts};
+pub use idempotency::{IdempotencyRecord, IdempotentResponse};
+pub use maintenance_window::MaintenanceWindow;
+pub use node::{Node, NodePatch, NodeStatus, PowerDriverKind};
+pub use operation::{Operation, OperationStatus, OperationType};
+pub use organization::Organization;
+pub use principal::{Principal, API_KEY_SUBJECT_PREFIX, JWT_SUBJECT_PREFIX};
+pub use rbac::{Grants, Permission, Role, RoleBinding};
+pub use rollout::{Rollout, RolloutNode, RolloutNodeStatus, RolloutProgress, RolloutStatus};
+pub use schedule::{MissedRunPolicy, ScheduleRun, ScheduleTarget, ScheduledOperation};
@@ -1,3 +1,4 @@
+use super::OperationType;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -12,12 +13,93 @@ pub enum NodeStatus {
     Rebooting,
 }
 
+/// Operations that can be requested for a node depending on its current status.
+/// Anything not listed here is not allowed (e.g. a rebooting node can't be touched).
+const ALLOWED_OPERATIONS: &[(NodeStatus, OperationType)] = &[
+    (NodeStatus::PowerOn, OperationType::PowerOff),
+    (NodeStatus::PowerOn, OperationType::Reboot),
+    (NodeStatus::PowerOff, OperationType::PowerOn),
+];
+
+impl NodeStatus {
+    pub fn allows(&self, operation_type: OperationType) -> bool {
+        ALLOWED_OPERATIONS.contains(&(*self, operation_type))
+    }
+}
+
+/// How the machine behind a node is managed.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, Hash, Default)]
+pub enum PowerDriverKind {
+    #[default]
+    #[serde(rename = "simulated")]
+    Simulated,
+    #[serde(rename = "ipmi")]
+    Ipmi,
+    #[serde(rename = "redfish")]
+    Redfish,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Node {
     pub id: Uuid,
     pub name: String,
     pub cluster_id: Uuid,
     pub status: NodeStatus,
+    #[serde(default)]
+    pub driver: PowerDriverKind,
+    /// Address of the node's BMC. Required by the `ipmi` and `redfish` drivers.
+    #[serde(default)]
+    pub bmc_endpoint: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// Incremented on every change of the node, including the operations requested on it.
+    /// It's managed by the repositories, so the value sent by clients is ignored.
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// Changes to apply to a [`Node`]. The fields that are `None` are left untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodePatch {
+    pub name: Option<String>,
+    pub cluster_id: Option<Uuid>,
+    pub driver: Option<PowerDriverKind>,
+    /// `Some(None)` removes the endpoint.
+    pub bmc_endpoint: Option<Option<String>>,
+}
+
+impl NodePatch {
+    pub fn apply(&self, node: &mut Node) {
+        if let Some(name) = &self.name {
+            node.name = name.clone();
+        }
+        if let Some(cluster_id) = self.cluster_id {
+            node.cluster_id = cluster_id;
+        }
+        if let Some(driver) = self.driver {
+            node.driver = driver;
+        }
+        if let Some(bmc_endpoint) = &self.bmc_endpoint {
+            node.bmc_endpoint = bmc_endpoint.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_status_allows_operations_work() {
+        assert!(NodeStatus::PowerOn.allows(OperationType::PowerOff));
+        assert!(NodeStatus::PowerOn.allows(OperationType::Reboot));
+        assert!(NodeStatus::PowerOff.allows(OperationType::PowerOn));
+
+        assert!(!NodeStatus::PowerOn.allows(OperationType::PowerOn));
+        assert!(!NodeStatus::PowerOff.allows(OperationType::PowerOff));
+        assert!(!NodeStatus::PowerOff.allows(OperationType::Reboot));
+        assert!(!NodeStatus::Rebooting.allows(OperationType::PowerOn));
+        assert!(!NodeStatus::Rebooting.allows(OperationType::PowerOff));
+        assert!(!NodeStatus::Rebooting.allows(OperationType::Reboot));
+    }
 }
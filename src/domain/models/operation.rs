@@ -12,11 +12,67 @@ pub enum OperationType {
     Reboot,
 }
 
+impl OperationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationType::PowerOn => "poweron",
+            OperationType::PowerOff => "poweroff",
+            OperationType::Reboot => "reboot",
+        }
+    }
+
+    /// Whether the operation takes the node down.
+    pub fn is_disruptive(&self) -> bool {
+        matches!(self, OperationType::PowerOff | OperationType::Reboot)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub enum OperationStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "succeeded")]
+    Succeeded,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+impl OperationStatus {
+    /// An operation can only move forward: pending -> running -> succeeded/failed.
+    /// A pending operation can also fail without ever running.
+    pub fn can_transition_to(&self, next: OperationStatus) -> bool {
+        matches!(
+            (self, next),
+            (OperationStatus::Pending, OperationStatus::Running)
+                | (OperationStatus::Pending, OperationStatus::Failed)
+                | (OperationStatus::Running, OperationStatus::Succeeded)
+                | (OperationStatus::Running, OperationStatus::Failed)
+        )
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Operation {
     pub id: Uuid,
     pub node_id: Uuid,
     pub operation_type: OperationType,
+    pub status: OperationStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub failure_reason: Option<String>,
+    pub attempts: i32,
+    /// The [`Batch`](super::Batch) the operation was requested in, if any.
+    #[serde(default)]
+    pub batch_id: Option<Uuid>,
+    /// Requested with `force`, so the status of the node isn't checked when it runs either.
+    #[serde(default)]
+    pub forced: bool,
+    /// Requested with `override_maintenance`, so the maintenance windows of the cluster aren't
+    /// checked when it runs either.
+    #[serde(default)]
+    pub overrides_maintenance: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -27,8 +83,35 @@ impl Operation {
             id: Uuid::new_v4(),
             node_id,
             operation_type,
+            status: OperationStatus::Pending,
+            started_at: None,
+            finished_at: None,
+            failure_reason: None,
+            attempts: 0,
+            batch_id: None,
+            forced: false,
+            overrides_maintenance: false,
             created_at: None,
             updated_at: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operation_status_transitions_work() {
+        use OperationStatus::*;
+        assert!(Pending.can_transition_to(Running));
+        assert!(Pending.can_transition_to(Failed));
+        assert!(Running.can_transition_to(Succeeded));
+        assert!(Running.can_transition_to(Failed));
+
+        assert!(!Pending.can_transition_to(Succeeded));
+        assert!(!Running.can_transition_to(Pending));
+        assert!(!Succeeded.can_transition_to(Failed));
+        assert!(!Failed.can_transition_to(Running));
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{ApiKey, Role};
+
+/// Prefix of the subjects of the callers with an API key, followed by its owner.
+pub const API_KEY_SUBJECT_PREFIX: &str = "apikey:";
+/// Prefix of the subjects of the callers with a JWT, followed by its `sub`.
+pub const JWT_SUBJECT_PREFIX: &str = "jwt:";
+
+/// Who is calling the API, taken from its API key or JWT.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Principal {
+    /// Owner of the API key or `sub` of the JWT, prefixed with where it comes from so
+    /// they can't be mistaken for each other.
+    pub subject: String,
+    /// Names of the [`Role`]s given to the caller in every cluster.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Organization of the caller, which can only see its clusters. The callers without
+    /// one see all of them.
+    pub tenant: Option<Uuid>,
+}
+
+impl From<&ApiKey> for Principal {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            subject: format!("{}{}", API_KEY_SUBJECT_PREFIX, key.owner),
+            roles: if key.admin {
+                vec![Role::Admin.as_str().to_string()]
+            } else {
+                vec![]
+            },
+            tenant: key.tenant_id,
+        }
+    }
+}
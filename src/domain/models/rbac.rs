@@ -0,0 +1,323 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use uuid::Uuid;
+
+use super::{OperationType, Principal};
+
+/// What a caller can do with a kind of resource.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum Permission {
+    #[serde(rename = "clusters:read")]
+    ClustersRead,
+    #[serde(rename = "clusters:write")]
+    ClustersWrite,
+    #[serde(rename = "nodes:read")]
+    NodesRead,
+    #[serde(rename = "nodes:write")]
+    NodesWrite,
+    #[serde(rename = "operations:read")]
+    OperationsRead,
+    #[serde(rename = "operations:poweron")]
+    OperationsPowerOn,
+    #[serde(rename = "operations:poweroff")]
+    OperationsPowerOff,
+    #[serde(rename = "operations:reboot")]
+    OperationsReboot,
+    #[serde(rename = "rollouts:read")]
+    RolloutsRead,
+    #[serde(rename = "rollouts:write")]
+    RolloutsWrite,
+    #[serde(rename = "schedules:read")]
+    SchedulesRead,
+    #[serde(rename = "schedules:write")]
+    SchedulesWrite,
+    #[serde(rename = "api_keys:manage")]
+    ApiKeysManage,
+    #[serde(rename = "rbac:manage")]
+    RbacManage,
+    #[serde(rename = "organizations:manage")]
+    OrganizationsManage,
+    #[serde(rename = "audit:read")]
+    AuditRead,
+    #[serde(rename = "metrics:read")]
+    MetricsRead,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ClustersRead => "clusters:read",
+            Permission::ClustersWrite => "clusters:write",
+            Permission::NodesRead => "nodes:read",
+            Permission::NodesWrite => "nodes:write",
+            Permission::OperationsRead => "operations:read",
+            Permission::OperationsPowerOn => "operations:poweron",
+            Permission::OperationsPowerOff => "operations:poweroff",
+            Permission::OperationsReboot => "operations:reboot",
+            Permission::RolloutsRead => "rollouts:read",
+            Permission::RolloutsWrite => "rollouts:write",
+            Permission::SchedulesRead => "schedules:read",
+            Permission::SchedulesWrite => "schedules:write",
+            Permission::ApiKeysManage => "api_keys:manage",
+            Permission::RbacManage => "rbac:manage",
+            Permission::OrganizationsManage => "organizations:manage",
+            Permission::AuditRead => "audit:read",
+            Permission::MetricsRead => "metrics:read",
+        }
+    }
+
+    /// Permission to request the operation.
+    pub fn for_operation(operation_type: OperationType) -> Self {
+        match operation_type {
+            OperationType::PowerOn => Permission::OperationsPowerOn,
+            OperationType::PowerOff => Permission::OperationsPowerOff,
+            OperationType::Reboot => Permission::OperationsReboot,
+        }
+    }
+
+    /// Whether a binding scoped to a cluster can grant it. The rest only make sense for
+    /// the whole API.
+    pub fn is_scopable(&self) -> bool {
+        !matches!(self, Permission::ClustersWrite | Permission::AuditRead)
+            && !self.is_platform_wide()
+    }
+
+    /// Whether it reaches beyond the clusters of a tenant, so it's never granted to the
+    /// callers of one. The metrics count the nodes of every cluster, whatever its tenant.
+    pub fn is_platform_wide(&self) -> bool {
+        matches!(
+            self,
+            Permission::ApiKeysManage
+                | Permission::RbacManage
+                | Permission::OrganizationsManage
+                | Permission::MetricsRead
+        )
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Set of permissions that can be bound to a subject.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reads everything but the API keys and the role bindings.
+    Viewer,
+    /// Viewer that can also run operations, rollouts and schedules on the nodes.
+    Operator,
+    /// Can do anything, including reading the audit log.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        use Permission::*;
+        match self {
+            Role::Viewer => &[
+                ClustersRead,
+                NodesRead,
+                OperationsRead,
+                RolloutsRead,
+                SchedulesRead,
+                MetricsRead,
+            ],
+            Role::Operator => &[
+                ClustersRead,
+                NodesRead,
+                OperationsRead,
+                RolloutsRead,
+                SchedulesRead,
+                OperationsPowerOn,
+                OperationsPowerOff,
+                OperationsReboot,
+                RolloutsWrite,
+                SchedulesWrite,
+                MetricsRead,
+            ],
+            Role::Admin => &[
+                ClustersRead,
+                ClustersWrite,
+                NodesRead,
+                NodesWrite,
+                OperationsRead,
+                OperationsPowerOn,
+                OperationsPowerOff,
+                OperationsReboot,
+                RolloutsRead,
+                RolloutsWrite,
+                SchedulesRead,
+                SchedulesWrite,
+                ApiKeysManage,
+                RbacManage,
+                OrganizationsManage,
+                AuditRead,
+                MetricsRead,
+            ],
+        }
+    }
+
+    pub fn grants(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role `{}`", s)),
+        }
+    }
+}
+
+/// Gives a role to a subject, in every cluster or only in one of them.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RoleBinding {
+    pub id: Uuid,
+    /// `subject` of the [`Principal`]s the role is given to.
+    pub subject: String,
+    pub role: Role,
+    /// Cluster the role is limited to. Every cluster if there's none.
+    pub cluster_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Everything a caller can do: the roles of its token, in every cluster, and the ones
+/// bound to its subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grants {
+    pub principal: Principal,
+    roles: Vec<(Role, Option<Uuid>)>,
+}
+
+impl Grants {
+    /// Unknown role names of the principal are ignored.
+    pub fn new(principal: Principal, bindings: &[RoleBinding]) -> Self {
+        let roles = principal
+            .roles
+            .iter()
+            .filter_map(|role| role.parse().ok())
+            .map(|role| (role, None))
+            .chain(
+                bindings
+                    .iter()
+                    .filter(|binding| binding.subject == principal.subject)
+                    .map(|binding| (binding.role, binding.cluster_id)),
+            )
+            .collect();
+        Self { principal, roles }
+    }
+
+    /// Whether the permission is granted in the cluster.
+    pub fn allows(&self, permission: Permission, cluster_id: &Uuid) -> bool {
+        self.may_be_granted(permission)
+            && self.roles.iter().any(|(role, scope)| {
+                role.grants(permission)
+                    && match scope {
+                        None => true,
+                        Some(scope) => permission.is_scopable() && scope == cluster_id,
+                    }
+            })
+    }
+
+    /// Whether the permission is granted in every cluster.
+    pub fn allows_everywhere(&self, permission: Permission) -> bool {
+        self.may_be_granted(permission)
+            && self
+                .roles
+                .iter()
+                .any(|(role, scope)| scope.is_none() && role.grants(permission))
+    }
+
+    /// Whether the permission is granted in any cluster.
+    pub fn allows_anywhere(&self, permission: Permission) -> bool {
+        self.may_be_granted(permission)
+            && self.roles.iter().any(|(role, scope)| {
+                role.grants(permission) && (scope.is_none() || permission.is_scopable())
+            })
+    }
+
+    fn may_be_granted(&self, permission: Permission) -> bool {
+        self.principal.tenant.is_none() || !permission.is_platform_wide()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(subject: &str, role: Role, cluster_id: Option<Uuid>) -> RoleBinding {
+        RoleBinding {
+            id: Uuid::new_v4(),
+            subject: subject.to_string(),
+            role,
+            cluster_id,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn grants_are_scoped_to_the_clusters() {
+        let cluster_id = Uuid::new_v4();
+        let other_cluster_id = Uuid::new_v4();
+        let principal = Principal {
+            subject: "jane".to_string(),
+            roles: vec!["viewer".to_string(), "unknown".to_string()],
+            tenant: None,
+        };
+        let grants = Grants::new(
+            principal,
+            &[
+                binding("jane", Role::Operator, Some(cluster_id)),
+                binding("jane", Role::Admin, Some(cluster_id)),
+                binding("john", Role::Admin, None),
+            ],
+        );
+
+        assert!(grants.allows(Permission::NodesRead, &other_cluster_id));
+        assert!(grants.allows_everywhere(Permission::NodesRead));
+        assert!(grants.allows(Permission::OperationsReboot, &cluster_id));
+        assert!(!grants.allows(Permission::OperationsReboot, &other_cluster_id));
+        assert!(!grants.allows_everywhere(Permission::OperationsReboot));
+        assert!(grants.allows_anywhere(Permission::OperationsReboot));
+        // the admin of a cluster can't manage the whole API
+        assert!(!grants.allows(Permission::ClustersWrite, &cluster_id));
+        assert!(!grants.allows_anywhere(Permission::RbacManage));
+    }
+
+    #[test]
+    fn tenants_are_never_granted_platform_wide_permissions() {
+        let principal = Principal {
+            subject: "jane".to_string(),
+            roles: vec!["admin".to_string()],
+            tenant: Some(Uuid::new_v4()),
+        };
+        let grants = Grants::new(principal, &[]);
+        assert!(grants.allows_everywhere(Permission::ClustersWrite));
+        assert!(grants.allows_everywhere(Permission::OperationsReboot));
+        for permission in [
+            Permission::ApiKeysManage,
+            Permission::RbacManage,
+            Permission::OrganizationsManage,
+        ] {
+            assert!(!grants.allows_anywhere(permission));
+        }
+    }
+}
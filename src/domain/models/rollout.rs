@@ -0,0 +1,156 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RolloutStatus {
+    Running,
+    Paused,
+    Succeeded,
+    Failed,
+    Aborted,
+}
+
+impl RolloutStatus {
+    /// A rollout can be paused and resumed until it finishes: it succeeds, fails or it's aborted.
+    pub fn can_transition_to(&self, next: RolloutStatus) -> bool {
+        use RolloutStatus::*;
+        matches!(
+            (self, next),
+            (Running, Paused)
+                | (Paused, Running)
+                | (Running | Paused, Succeeded | Failed | Aborted)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RolloutNodeStatus {
+    Pending,
+    Rebooting,
+    Succeeded,
+    Failed,
+    /// The rollout was aborted before rebooting the node.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RolloutNode {
+    pub node_id: Uuid,
+    pub status: RolloutNodeStatus,
+    /// The reboot requested for the node, once it's rebooting.
+    pub operation_id: Option<Uuid>,
+    pub failure_reason: Option<String>,
+}
+
+/// Reboot of all the nodes of a cluster, a few of them at a time.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Rollout {
+    pub id: Uuid,
+    pub cluster_id: Uuid,
+    pub status: RolloutStatus,
+    /// How many nodes can be rebooting at the same time.
+    pub max_in_flight: i32,
+    /// How many nodes can fail before the rollout is stopped.
+    pub max_failures: i32,
+    /// The nodes of the cluster when the rollout was created, in the order they're rebooted.
+    pub nodes: Vec<RolloutNode>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Incremented on every change of the status of the rollout.
+    /// The progress of its nodes doesn't change it.
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// Number of nodes of a rollout in each [`RolloutNodeStatus`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RolloutProgress {
+    pub pending: usize,
+    pub rebooting: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl Rollout {
+    pub fn new(
+        cluster_id: Uuid,
+        max_in_flight: i32,
+        max_failures: i32,
+        node_ids: impl IntoIterator<Item = Uuid>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            cluster_id,
+            status: RolloutStatus::Running,
+            max_in_flight,
+            max_failures,
+            nodes: node_ids
+                .into_iter()
+                .map(|node_id| RolloutNode {
+                    node_id,
+                    status: RolloutNodeStatus::Pending,
+                    operation_id: None,
+                    failure_reason: None,
+                })
+                .collect(),
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    pub fn progress(&self) -> RolloutProgress {
+        let mut progress = RolloutProgress::default();
+        for node in &self.nodes {
+            let count = match node.status {
+                RolloutNodeStatus::Pending => &mut progress.pending,
+                RolloutNodeStatus::Rebooting => &mut progress.rebooting,
+                RolloutNodeStatus::Succeeded => &mut progress.succeeded,
+                RolloutNodeStatus::Failed => &mut progress.failed,
+                RolloutNodeStatus::Skipped => &mut progress.skipped,
+            };
+            *count += 1;
+        }
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollout_status_transitions_work() {
+        use RolloutStatus::*;
+        assert!(Running.can_transition_to(Paused));
+        assert!(Paused.can_transition_to(Running));
+        assert!(Paused.can_transition_to(Aborted));
+        assert!(Running.can_transition_to(Failed));
+
+        assert!(!Running.can_transition_to(Running));
+        assert!(!Succeeded.can_transition_to(Running));
+        assert!(!Aborted.can_transition_to(Paused));
+        assert!(!Failed.can_transition_to(Aborted));
+    }
+
+    #[test]
+    fn progress_counts_the_nodes() {
+        let mut rollout = Rollout::new(Uuid::new_v4(), 1, 0, (0..3).map(|_| Uuid::new_v4()));
+        rollout.nodes[0].status = RolloutNodeStatus::Succeeded;
+        rollout.nodes[1].status = RolloutNodeStatus::Rebooting;
+
+        assert_eq!(
+            rollout.progress(),
+            RolloutProgress {
+                pending: 1,
+                rebooting: 1,
+                succeeded: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use super::OperationType;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// What a schedule runs its operation on.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleTarget {
+    NodeId(Uuid),
+    /// Every node the cluster has when the schedule runs.
+    ClusterId(Uuid),
+}
+
+/// What to do with a run that was missed, usually because the API was down.
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRunPolicy {
+    /// Runs the operation as soon as possible, only once no matter how many runs were missed.
+    #[default]
+    RunOnce,
+    /// Waits for the next run.
+    Skip,
+}
+
+/// Operation requested at a given time (`run_at`) or on a recurring basis (`cron`).
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ScheduledOperation {
+    pub id: Uuid,
+    pub operation_type: OperationType,
+    pub target: ScheduleTarget,
+    pub run_at: Option<DateTime<Utc>>,
+    /// Cron expression, in UTC. The seconds can be left out, like in a crontab.
+    pub cron: Option<String>,
+    pub missed_run_policy: MissedRunPolicy,
+    pub enabled: bool,
+    /// When the schedule runs next, if it ever does.
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    /// The batch with the operations of the last run.
+    pub last_batch_id: Option<Uuid>,
+    /// Why the last run couldn't request the operation on every node.
+    pub last_error: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub version: i64,
+}
+
+/// Outcome of a run of a schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleRun {
+    pub ran_at: DateTime<Utc>,
+    pub batch_id: Option<Uuid>,
+    pub error: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+impl ScheduledOperation {
+    /// First run after `after`, if any. Fails if the schedule isn't valid.
+    pub fn next_run(&self, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+        match (&self.run_at, &self.cron) {
+            (Some(run_at), None) => Ok(Some(*run_at).filter(|run_at| *run_at > after)),
+            (None, Some(cron)) => Ok(parse_cron(cron)?.after(&after).next()),
+            _ => Err("A schedule needs either `run_at` or `cron`".to_string()),
+        }
+    }
+}
+
+fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    // the cron crate wants the seconds, which crontabs don't have
+    let with_seconds = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string(),
+    };
+    cron::Schedule::from_str(&with_seconds)
+        .map_err(|e| format!("Invalid cron expression `{}`: {}", expression, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn schedule(run_at: Option<DateTime<Utc>>, cron: Option<&str>) -> ScheduledOperation {
+        ScheduledOperation {
+            id: Uuid::new_v4(),
+            operation_type: OperationType::Reboot,
+            target: ScheduleTarget::ClusterId(Uuid::new_v4()),
+            run_at,
+            cron: cron.map(|cron| cron.to_string()),
+            missed_run_policy: MissedRunPolicy::default(),
+            enabled: true,
+            next_run_at: None,
+            last_run_at: None,
+            last_batch_id: None,
+            last_error: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn next_run_follows_the_cron_expression() {
+        // Saturday 2022-04-30 12:00 UTC
+        let now = Utc.ymd(2022, 4, 30).and_hms(12, 0, 0);
+        // every Sunday at 03:00, as a crontab line and with seconds
+        for cron in ["0 3 * * Sun", "0 0 3 * * Sun"] {
+            let next = schedule(None, Some(cron)).next_run(now).unwrap();
+            assert_eq!(next, Some(Utc.ymd(2022, 5, 1).and_hms(3, 0, 0)));
+        }
+
+        assert!(schedule(None, Some("not a cron")).next_run(now).is_err());
+    }
+
+    #[test]
+    fn one_shot_schedules_run_once() {
+        let now = Utc.ymd(2022, 4, 30).and_hms(12, 0, 0);
+        let tonight = Utc.ymd(2022, 4, 30).and_hms(22, 0, 0);
+        let one_shot = schedule(Some(tonight), None);
+        assert_eq!(one_shot.next_run(now).unwrap(), Some(tonight));
+        assert_eq!(one_shot.next_run(tonight).unwrap(), None);
+
+        assert!(schedule(None, None).next_run(now).is_err());
+        assert!(schedule(Some(tonight), Some("0 3 * * *"))
+            .next_run(now)
+            .is_err());
+    }
+}
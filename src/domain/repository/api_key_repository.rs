@@ -0,0 +1,19 @@
+use super::RepositoryResult;
+use crate::domain::models::ApiKey;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync + 'static {
+    async fn get_api_keys(&self) -> RepositoryResult<Vec<ApiKey>>;
+    async fn get_api_key(&self, api_key_id: &Uuid) -> RepositoryResult<ApiKey>;
+    /// Finds the key with the given hash of its secret, whether it's active or not.
+    async fn get_api_key_by_secret_hash(&self, secret_hash: &str) -> RepositoryResult<ApiKey>;
+    async fn create_api_key(&self, api_key: &ApiKey, secret_hash: &str)
+        -> RepositoryResult<ApiKey>;
+    /// Revokes the key. Keys that were already revoked keep their revocation time.
+    async fn revoke_api_key(&self, api_key_id: &Uuid) -> RepositoryResult<ApiKey>;
+    async fn record_use(&self, api_key_id: &Uuid, used_at: DateTime<Utc>) -> RepositoryResult<()>;
+}
@@ -0,0 +1,34 @@
+use super::{Page, PageRequest, RepositoryResult};
+use crate::domain::models::{AuditAction, AuditEvent, AuditOutcome, AuditTargetType};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Filters of the audit events. All of them must match.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub tenant_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<AuditTargetType>,
+    pub target_id: Option<Uuid>,
+    pub outcome: Option<AuditOutcome>,
+    pub request_id: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+/// The audit events are append-only: there's no way to change or delete them, and the
+/// databases reject it too.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait AuditRepository: Send + Sync + 'static {
+    /// Pages are always sorted by the date of the events.
+    async fn get_events(
+        &self,
+        filter: &AuditFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<AuditEvent>>;
+    async fn record_event(&self, event: &AuditEvent) -> RepositoryResult<AuditEvent>;
+}
@@ -1,14 +1,49 @@
-use super::RepositoryResult;
-use crate::domain::models::Cluster;
+use super::{Change, Page, PageRequest, RepositoryResult, TenantScoped};
+use crate::domain::models::{Cluster, ClusterPatch, ClusterSummary, NodeStatusCounts};
 use async_trait::async_trait;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Every change of a cluster increments its version. The writes with an `expected_version`
+/// fail with [`RepositoryError::VersionMismatch`](super::RepositoryError::VersionMismatch)
+/// if the cluster has a different one. They return the cluster they changed as it was
+/// before, read in the same write so nothing else can change it in between.
+///
+/// Limited to a tenant, the repository only has the clusters of the tenant.
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
-pub trait ClusterRepository: Send + Sync + 'static {
-    async fn get_clusters(&self) -> RepositoryResult<Vec<Cluster>>;
+pub trait ClusterRepository: TenantScoped + Send + Sync + 'static {
+    async fn get_clusters(&self, page: &PageRequest) -> RepositoryResult<Page<Cluster>>;
     async fn get_cluster(&self, cluster_id: &Uuid) -> RepositoryResult<Cluster>;
     async fn create_cluster(&self, cluster: &Cluster) -> RepositoryResult<Cluster>;
-    async fn update_cluster(&self, cluster: &Cluster) -> RepositoryResult<Cluster>;
-    async fn delete_cluster(&self, cluster_id: &Uuid) -> RepositoryResult<Uuid>;
+    async fn update_cluster(
+        &self,
+        cluster: &Cluster,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>>;
+    /// Updates only the fields set in the patch.
+    async fn patch_cluster(
+        &self,
+        cluster_id: &Uuid,
+        patch: &ClusterPatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>>;
+    /// Returns the cluster that was deleted.
+    async fn delete_cluster(
+        &self,
+        cluster_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Cluster>;
+    async fn get_cluster_summary(&self, cluster_id: &Uuid) -> RepositoryResult<ClusterSummary>;
+    /// Number of nodes in each status of every cluster, including the ones without nodes.
+    async fn get_node_status_counts(&self) -> RepositoryResult<HashMap<Uuid, NodeStatusCounts>>;
+}
+
+/// The mocks can't be limited to a tenant, so the limited mock has no expectations. The
+/// tests of the tenants use the in-memory repositories.
+#[cfg(test)]
+impl TenantScoped for MockClusterRepository {
+    fn for_tenant(&self, _: Uuid) -> Self {
+        Self::default()
+    }
 }
@@ -0,0 +1,30 @@
+use super::RepositoryResult;
+use crate::domain::models::{IdempotencyRecord, IdempotentResponse};
+use async_trait::async_trait;
+
+/// Keeps the requests made with an `Idempotency-Key` and their responses.
+/// The keys belong to the caller (`owner`) that made the request, so the same key can be
+/// used by different callers. Expired keys are deleted as new ones are claimed.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait IdempotencyRepository: Send + Sync + 'static {
+    /// Reserves the key for `lease_secs` on behalf of the request being processed.
+    /// Returns the record of the key instead if another request already has it.
+    async fn claim_key(
+        &self,
+        owner: &str,
+        key: &str,
+        request_hash: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<IdempotencyRecord>>;
+    /// Stores the response of the request that claimed the key, keeping it for `ttl_secs`.
+    async fn save_response(
+        &self,
+        owner: &str,
+        key: &str,
+        response: &IdempotentResponse,
+        ttl_secs: i64,
+    ) -> RepositoryResult<()>;
+    /// Frees the key so the request can be made again.
+    async fn release_key(&self, owner: &str, key: &str) -> RepositoryResult<()>;
+}
@@ -1,9 +1,40 @@
+pub mod api_key_repository;
+pub mod audit_repository;
 pub mod cluster_repository;
+pub mod idempotency_repository;
 pub mod node_repository;
+pub mod organization_repository;
+pub mod pagination;
 mod repository_error;
+pub mod role_binding_repository;
+pub mod rollout_repository;
+pub mod schedule_repository;
 
+pub use api_key_repository::ApiKeyRepository;
+pub use audit_repository::AuditRepository;
 pub use cluster_repository::ClusterRepository;
+pub use idempotency_repository::IdempotencyRepository;
 pub use node_repository::NodeRepository;
+pub use organization_repository::OrganizationRepository;
+pub use pagination::{Page, PageRequest};
 pub use repository_error::RepositoryError;
+pub use role_binding_repository::RoleBindingRepository;
+pub use rollout_repository::RolloutRepository;
+pub use schedule_repository::ScheduleRepository;
+
+use uuid::Uuid;
 
 pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+/// Repository, or service, that can be limited to the clusters of a tenant.
+pub trait TenantScoped {
+    /// Same repository, limited to the tenant.
+    fn for_tenant(&self, tenant_id: Uuid) -> Self;
+}
+
+/// State of what a write changed, before and after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<T> {
+    pub before: T,
+    pub after: T,
+}
@@ -1,21 +1,157 @@
-use super::RepositoryResult;
-use crate::domain::models::{Node, Operation};
+use super::{
+    pagination::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT},
+    Change, Page, PageRequest, RepositoryResult, TenantScoped,
+};
+use crate::domain::models::{Batch, Node, NodePatch, NodeStatus, Operation, OperationType};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+/// How the `name` of a [`NodeFilter`] is compared with the node and cluster names.
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NameMatch {
+    Exact,
+    Prefix,
+    #[default]
+    Contains,
+}
+
+/// Filters of the nodes. All of them must match.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct NodeFilter {
-    pub name: String,
+    /// Matches the name of the node or the name of its cluster.
+    pub name: Option<String>,
+    #[serde(default)]
+    pub name_match: NameMatch,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    pub status: Option<NodeStatus>,
+    pub cluster_id: Option<Uuid>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct OperationFilter {
+    pub operation_type: Option<OperationType>,
+    pub node_id: Option<Uuid>,
+    pub cluster_id: Option<Uuid>,
+    pub batch_id: Option<Uuid>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    /// Most operations returned, [`DEFAULT_PAGE_LIMIT`] by default and capped to
+    /// [`MAX_PAGE_LIMIT`] like the pages, since the history keeps growing.
+    pub limit: Option<u32>,
+    /// Number of operations skipped, the newest ones first.
+    pub offset: Option<u32>,
+}
+
+impl OperationFilter {
+    pub fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset.unwrap_or_default()
+    }
+}
+
+/// Every change of a node increments its version. The writes with an `expected_version`
+/// fail with [`RepositoryError::VersionMismatch`](super::RepositoryError::VersionMismatch)
+/// if the node has a different one. They return the node they changed as it was before,
+/// read in the same write so nothing else can change it in between.
+///
+/// Limited to a tenant, the repository only has the nodes, operations and batches of the
+/// clusters of the tenant. Nodes can't be created in, or moved to, the clusters of another
+/// one. The methods used by the workers (`update_node_status`, `update_operation`,
+/// `claim_operation` and `renew_operation`) aren't limited.
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
-pub trait NodeRepository: Send + Sync + 'static {
-    async fn get_nodes(&self, name: Option<NodeFilter>) -> RepositoryResult<Vec<Node>>;
+pub trait NodeRepository: TenantScoped + Send + Sync + 'static {
+    async fn get_nodes(
+        &self,
+        filter: &NodeFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<Node>>;
     async fn get_node(&self, node_id: &Uuid) -> RepositoryResult<Node>;
+    /// Nodes with the given ids, in any order. The ids without a node are left out.
+    async fn get_nodes_by_id(&self, node_ids: &[Uuid]) -> RepositoryResult<Vec<Node>>;
     async fn create_node(&self, node: &Node) -> RepositoryResult<Node>;
-    async fn update_node(&self, node: &Node) -> RepositoryResult<Node>;
-    async fn delete_node(&self, node_id: &Uuid) -> RepositoryResult<Uuid>;
+    /// Replaces the node, except its status, which only changes through operations.
+    async fn update_node(
+        &self,
+        node: &Node,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>>;
+    /// Updates only the fields set in the patch.
+    async fn patch_node(
+        &self,
+        node_id: &Uuid,
+        patch: &NodePatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>>;
+    /// Returns the node that was deleted.
+    async fn delete_node(
+        &self,
+        node_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Node>;
+    async fn update_node_status(
+        &self,
+        node_id: &Uuid,
+        status: NodeStatus,
+    ) -> RepositoryResult<Node>;
+    async fn get_operations(&self, filter: &OperationFilter) -> RepositoryResult<Vec<Operation>>;
+    async fn get_operation(&self, operation_id: &Uuid) -> RepositoryResult<Operation>;
+    /// Creates the operation and increments the version of its node.
     async fn create_operation(&self, operation: &Operation) -> RepositoryResult<Operation>;
+    /// Records how a running operation claimed by `worker_id` went and releases it. Fails with
+    /// [`RepositoryError::DoesNotExist`](super::RepositoryError::DoesNotExist) if the
+    /// worker no longer holds the operation, e.g. because its lease expired and another
+    /// worker claimed it.
+    async fn update_operation(
+        &self,
+        operation: &Operation,
+        worker_id: &str,
+    ) -> RepositoryResult<Operation>;
+    async fn get_batch(&self, batch_id: &Uuid) -> RepositoryResult<Batch>;
+    /// Creates the batch along with its operations, all of them or none, and increments
+    /// the version of their nodes.
+    async fn create_batch(
+        &self,
+        batch: &Batch,
+        operations: &[Operation],
+    ) -> RepositoryResult<(Batch, Vec<Operation>)>;
+    /// Takes the oldest pending operation (or a running one whose lease expired)
+    /// and marks it as running for `lease_secs` on behalf of `worker_id`. An operation
+    /// isn't taken while its node has another one running or requested before it.
+    async fn claim_operation(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Operation>>;
+    /// Extends the lease of a running operation claimed by `worker_id` to `lease_secs` from
+    /// now. Fails with [`RepositoryError::DoesNotExist`](super::RepositoryError::DoesNotExist)
+    /// if the worker no longer holds the operation.
+    async fn renew_operation(
+        &self,
+        operation_id: &Uuid,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<()>;
+}
+
+/// The mocks can't be limited to a tenant, so the limited mock has no expectations. The
+/// tests of the tenants use the in-memory repositories.
+#[cfg(test)]
+impl TenantScoped for MockNodeRepository {
+    fn for_tenant(&self, _: Uuid) -> Self {
+        Self::default()
+    }
 }
@@ -0,0 +1,18 @@
+use super::RepositoryResult;
+use crate::domain::models::Organization;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait OrganizationRepository: Send + Sync + 'static {
+    async fn get_organizations(&self) -> RepositoryResult<Vec<Organization>>;
+    async fn get_organization(&self, organization_id: &Uuid) -> RepositoryResult<Organization>;
+    async fn create_organization(
+        &self,
+        organization: &Organization,
+    ) -> RepositoryResult<Organization>;
+    /// Fails with [`RepositoryError::ForeignKeyViolation`](super::RepositoryError::ForeignKeyViolation)
+    /// while the organization has clusters. Its API keys are deleted with it.
+    async fn delete_organization(&self, organization_id: &Uuid) -> RepositoryResult<Uuid>;
+}
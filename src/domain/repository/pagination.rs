@@ -0,0 +1,342 @@
+use crate::domain::models::{AuditEvent, Cluster, Node, NodeStatus};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+pub const MAX_PAGE_LIMIT: u32 = 500;
+
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    Name,
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Status,
+}
+
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Value of the sort field of an entity.
+///
+/// Entities that were never updated are sorted by their creation date when
+/// sorting by `updated_at`, and missing values are sorted first.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum SortValue {
+    Text(String),
+    Timestamp(DateTime<Utc>),
+}
+
+/// Entities that can be listed with keyset pagination.
+pub trait Sortable {
+    fn id(&self) -> Uuid;
+    fn sort_value(&self, field: SortField) -> SortValue;
+}
+
+fn timestamp(value: Option<DateTime<Utc>>) -> SortValue {
+    SortValue::Timestamp(value.unwrap_or_else(|| Utc.timestamp(0, 0)))
+}
+
+impl Sortable for Cluster {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, field: SortField) -> SortValue {
+        match field {
+            SortField::Name => SortValue::Text(self.name.clone()),
+            SortField::CreatedAt => timestamp(self.created_at),
+            SortField::UpdatedAt => timestamp(self.updated_at.or(self.created_at)),
+            // clusters have no status
+            SortField::Status => SortValue::Text(String::new()),
+        }
+    }
+}
+
+impl Sortable for Node {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, field: SortField) -> SortValue {
+        match field {
+            SortField::Name => SortValue::Text(self.name.clone()),
+            SortField::CreatedAt => timestamp(self.created_at),
+            SortField::UpdatedAt => timestamp(self.updated_at.or(self.created_at)),
+            SortField::Status => SortValue::Text(status_text(self.status).to_string()),
+        }
+    }
+}
+
+impl Sortable for AuditEvent {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Audit events are only sorted by their date.
+    fn sort_value(&self, _: SortField) -> SortValue {
+        timestamp(self.created_at)
+    }
+}
+
+/// Status as it's stored in the databases, which is what they sort by.
+pub fn status_text(status: NodeStatus) -> &'static str {
+    match status {
+        NodeStatus::PowerOn => "poweron",
+        NodeStatus::PowerOff => "poweroff",
+        NodeStatus::Rebooting => "rebooting",
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CursorError {
+    #[error("The cursor is not valid")]
+    Malformed,
+    #[error("The cursor was created for a different sort or order")]
+    Mismatch,
+}
+
+/// Position of the last entity of a page. The next page starts right after it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Cursor {
+    pub sort: SortField,
+    pub order: SortOrder,
+    pub value: SortValue,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn after<T: Sortable>(item: &T, sort: SortField, order: SortOrder) -> Self {
+        Self {
+            sort,
+            order,
+            value: item.sort_value(sort),
+            id: item.id(),
+        }
+    }
+
+    /// Opaque representation sent to the clients.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursors can always be serialized");
+        base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, CursorError> {
+        let json = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| CursorError::Malformed)?;
+        serde_json::from_slice(&json).map_err(|_| CursorError::Malformed)
+    }
+}
+
+/// Page of entities requested by a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    pub limit: u32,
+    pub sort: SortField,
+    pub order: SortOrder,
+    pub after: Option<Cursor>,
+    pub with_total: bool,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_PAGE_LIMIT,
+            sort: SortField::default(),
+            order: SortOrder::default(),
+            after: None,
+            with_total: false,
+        }
+    }
+}
+
+impl PageRequest {
+    /// Validates the parameters of a page coming from a client.
+    ///
+    /// The limit is capped to [`MAX_PAGE_LIMIT`] and the cursor must have been
+    /// returned by a previous request with the same sort and order.
+    pub fn new(
+        limit: Option<u32>,
+        sort: SortField,
+        order: SortOrder,
+        cursor: Option<&str>,
+        with_total: bool,
+    ) -> Result<Self, CursorError> {
+        let after = match cursor {
+            Some(cursor) => {
+                let cursor = Cursor::decode(cursor)?;
+                let value_matches = matches!(
+                    (sort, &cursor.value),
+                    (SortField::Name | SortField::Status, SortValue::Text(_))
+                        | (
+                            SortField::CreatedAt | SortField::UpdatedAt,
+                            SortValue::Timestamp(_)
+                        )
+                );
+                if cursor.sort != sort || cursor.order != order || !value_matches {
+                    return Err(CursorError::Mismatch);
+                }
+                Some(cursor)
+            }
+            None => None,
+        };
+        Ok(Self {
+            limit: limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT),
+            sort,
+            order,
+            after,
+            with_total,
+        })
+    }
+
+    /// Repositories fetch one more row than requested to know if there's a next page.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit as i64 + 1
+    }
+
+    /// Sorts the entities in the requested order and skips the ones before the cursor.
+    ///
+    /// Used by the repositories that can't do it in the database.
+    pub fn apply<T: Sortable>(&self, mut items: Vec<T>) -> Vec<T> {
+        items.sort_by_cached_key(|item| (item.sort_value(self.sort), item.id()));
+        if self.order == SortOrder::Desc {
+            items.reverse();
+        }
+        items
+            .into_iter()
+            .filter(|item| match &self.after {
+                Some(cursor) => {
+                    let key = (item.sort_value(self.sort), item.id());
+                    let cursor_key = (cursor.value.clone(), cursor.id);
+                    match self.order {
+                        SortOrder::Asc => key > cursor_key,
+                        SortOrder::Desc => key < cursor_key,
+                    }
+                }
+                None => true,
+            })
+            .take(self.fetch_limit() as usize)
+            .collect()
+    }
+}
+
+/// A page of entities and the cursor to get the next one.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl<T: Sortable> Page<T> {
+    /// Builds the page out of the rows fetched with [`PageRequest::fetch_limit`].
+    pub fn new(mut items: Vec<T>, request: &PageRequest, total: Option<i64>) -> Self {
+        let next_cursor = if items.len() > request.limit as usize {
+            items.truncate(request.limit as usize);
+            items
+                .last()
+                .map(|last| Cursor::after(last, request.sort, request.order).encode())
+        } else {
+            None
+        };
+        Self {
+            items,
+            next_cursor,
+            total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_cluster(name: &str) -> Cluster {
+        Cluster {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            tenant_id: None,
+            maintenance_windows: vec![],
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn cursor_roundtrip_works() {
+        let cluster = create_test_cluster("CLUSTER");
+        let cursor = Cursor::after(&cluster, SortField::CreatedAt, SortOrder::Desc);
+        assert_eq!(Cursor::decode(&cursor.encode()), Ok(cursor));
+        assert_eq!(Cursor::decode("nope"), Err(CursorError::Malformed));
+    }
+
+    #[test]
+    fn page_request_validates_the_cursor() {
+        let cluster = create_test_cluster("CLUSTER");
+        let cursor = Cursor::after(&cluster, SortField::Name, SortOrder::Asc).encode();
+
+        let request = PageRequest::new(
+            Some(1000),
+            SortField::Name,
+            SortOrder::Asc,
+            Some(&cursor),
+            true,
+        )
+        .unwrap();
+        assert_eq!(request.limit, MAX_PAGE_LIMIT);
+        assert_eq!(request.after.unwrap().id, cluster.id);
+
+        let result = PageRequest::new(None, SortField::Name, SortOrder::Desc, Some(&cursor), false);
+        assert_eq!(result, Err(CursorError::Mismatch));
+        let result = PageRequest::new(None, SortField::Name, SortOrder::Asc, Some("%%"), false);
+        assert_eq!(result, Err(CursorError::Malformed));
+    }
+
+    #[test]
+    fn pages_are_built_walking_the_cursors() {
+        let clusters: Vec<Cluster> = ["C", "A", "D", "B", "E"]
+            .iter()
+            .map(|name| create_test_cluster(name))
+            .collect();
+        let mut request = PageRequest {
+            limit: 2,
+            sort: SortField::Name,
+            order: SortOrder::Desc,
+            ..Default::default()
+        };
+
+        let mut names = vec![];
+        loop {
+            let page = Page::new(request.apply(clusters.clone()), &request, None);
+            names.extend(page.items.into_iter().map(|c| c.name));
+            match page.next_cursor {
+                Some(cursor) => request.after = Some(Cursor::decode(&cursor).unwrap()),
+                None => break,
+            }
+        }
+
+        assert_eq!(names, vec!["E", "D", "C", "B", "A"]);
+    }
+
+    #[test]
+    fn last_page_has_no_cursor() {
+        let request = PageRequest {
+            limit: 2,
+            ..Default::default()
+        };
+        let page = Page::new(vec![create_test_cluster("A")], &request, Some(1));
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(page.total, Some(1));
+    }
+}
@@ -9,8 +9,14 @@ pub enum RepositoryError {
     AlreadyExists,
     #[error("This entity does not exist")]
     DoesNotExist,
+    #[error("This entity was changed by someone else")]
+    VersionMismatch,
     #[error("The id format is not valid")]
     InvalidId,
+    #[error("A related entity does not exist")]
+    ForeignKeyViolation,
+    #[error("The storage is unavailable: `{0}`")]
+    Unavailable(String),
     #[error("Repository error")]
     Generic(Box<dyn Error>),
 }
@@ -0,0 +1,20 @@
+use super::RepositoryResult;
+use crate::domain::models::RoleBinding;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait RoleBindingRepository: Send + Sync + 'static {
+    /// Bindings of the subject, or all of them if there's none.
+    async fn get_role_bindings<'a>(
+        &self,
+        subject: Option<&'a str>,
+    ) -> RepositoryResult<Vec<RoleBinding>>;
+    async fn get_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<RoleBinding>;
+    async fn create_role_binding(
+        &self,
+        role_binding: &RoleBinding,
+    ) -> RepositoryResult<RoleBinding>;
+    async fn delete_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<Uuid>;
+}
@@ -0,0 +1,39 @@
+use super::RepositoryResult;
+use crate::domain::models::{Rollout, RolloutNode, RolloutStatus};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Every change of a rollout, of its status or of the progress of its nodes, increments its
+/// version. The writes with an `expected_version` fail with
+/// [`RepositoryError::VersionMismatch`](super::RepositoryError::VersionMismatch) if the
+/// rollout has a different one.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait RolloutRepository: Send + Sync + 'static {
+    async fn get_rollout(&self, rollout_id: &Uuid) -> RepositoryResult<Rollout>;
+    /// Creates the rollout along with its nodes.
+    async fn create_rollout(&self, rollout: &Rollout) -> RepositoryResult<Rollout>;
+    /// Changes the status of the rollout and saves the progress of some of its nodes, all
+    /// at once.
+    async fn update_rollout(
+        &self,
+        rollout_id: &Uuid,
+        status: RolloutStatus,
+        nodes: &[RolloutNode],
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Rollout>;
+    /// Takes a running or paused rollout that nobody is checking, the one that has been
+    /// waiting the longest, and reserves it for `lease_secs` on behalf of `worker_id`.
+    async fn claim_rollout(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Rollout>>;
+    /// Gives back a claimed rollout, which can't be claimed again for `delay_secs`.
+    async fn release_rollout(
+        &self,
+        rollout_id: &Uuid,
+        worker_id: &str,
+        delay_secs: i64,
+    ) -> RepositoryResult<()>;
+}
@@ -0,0 +1,62 @@
+use super::RepositoryResult;
+use crate::domain::models::{ScheduleRun, ScheduledOperation};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Every change of the definition of a schedule increments its version, its runs don't.
+/// The writes with an `expected_version` fail with
+/// [`RepositoryError::VersionMismatch`](super::RepositoryError::VersionMismatch)
+/// if the schedule has a different one.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait ScheduleRepository: Send + Sync + 'static {
+    /// Schedules targeting the clusters of the tenant, and their nodes, or every schedule.
+    async fn get_schedules(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> RepositoryResult<Vec<ScheduledOperation>>;
+    async fn get_schedule(&self, schedule_id: &Uuid) -> RepositoryResult<ScheduledOperation>;
+    async fn create_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+    ) -> RepositoryResult<ScheduledOperation>;
+    /// Replaces the definition of the schedule and its next run, keeping the last one.
+    async fn update_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<ScheduledOperation>;
+    async fn delete_schedule(
+        &self,
+        schedule_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Uuid>;
+    /// Takes an enabled schedule whose next run is due, the one that has waited the longest,
+    /// and reserves it for `lease_secs` on behalf of `worker_id`.
+    async fn claim_schedule(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<ScheduledOperation>>;
+    /// Reserves `batch_id` for the batch of the due run of a schedule claimed by `worker_id`,
+    /// unless an earlier try of the run already reserved one, which is returned instead.
+    /// This way a run that's tried again, e.g. after its lease expired, doesn't enqueue
+    /// its operations twice. Fails with
+    /// [`RepositoryError::DoesNotExist`](super::RepositoryError::DoesNotExist) if the
+    /// worker no longer holds the schedule.
+    async fn reserve_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        batch_id: &Uuid,
+    ) -> RepositoryResult<Uuid>;
+    /// Records a run of a claimed schedule, which frees its reserved batch, and releases it. The next run is only changed if
+    /// the schedule still has the claimed `version`, so it isn't computed from an old definition.
+    async fn record_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        version: i64,
+        run: &ScheduleRun,
+    ) -> RepositoryResult<()>;
+}
@@ -0,0 +1,72 @@
+use crate::{
+    application::audit_log::{self, AuditContext, AuditLog, NoAuditLog},
+    domain::models::{AuditEvent, Principal},
+    infrastructure::{controllers::ApiError, request_id},
+};
+use actix_web::{dev::Payload, http::StatusCode, web, FromRequest, HttpMessage, HttpRequest};
+use futures::future::{ready, Ready};
+use std::{future::Future, sync::Arc};
+
+/// Audit log of the app along with the context of the request, for the handlers that
+/// record what they change. The events are discarded if the app has no [`AuditLog`].
+///
+/// The source IP is the address of the peer, which is the proxy if there's one in front
+/// of the API. The `Forwarded` headers aren't trusted, since the clients can set them.
+pub struct Audit {
+    log: Arc<dyn AuditLog>,
+    context: AuditContext,
+}
+
+impl Audit {
+    pub async fn record(&self, event: AuditEvent) {
+        self.log.record(self.context.stamp(event)).await
+    }
+
+    /// Runs the future in the context of the request, so the services record their
+    /// events as made by its caller.
+    pub async fn scope<F: Future>(&self, f: F) -> F::Output {
+        audit_log::scope(self.context.clone(), f).await
+    }
+}
+
+/// Needs the [`Principal`] of the caller, so it's only for the authenticated handlers.
+impl FromRequest for Audit {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let principal = match req.extensions().get::<Principal>() {
+            Some(principal) => principal.clone(),
+            None => {
+                return ready(Err(ApiError::new(
+                    StatusCode::UNAUTHORIZED,
+                    "unauthorized",
+                    "The request isn't authenticated",
+                )))
+            }
+        };
+        let log: Arc<dyn AuditLog> = match req.app_data::<web::Data<dyn AuditLog>>() {
+            Some(log) => log.clone().into_inner(),
+            None => Arc::new(NoAuditLog),
+        };
+        ready(Ok(Audit {
+            log,
+            context: AuditContext {
+                actor: principal.subject,
+                tenant_id: principal.tenant,
+                request_id: request_id::current(),
+                source_ip: req.peer_addr().map(|addr| addr.ip().to_string()),
+            },
+        }))
+    }
+}
+
+/// Audit for the tests that call the handlers directly, with the `tests` system actor as
+/// caller. Its events are discarded.
+#[cfg(test)]
+pub fn test_audit() -> Audit {
+    Audit {
+        log: Arc::new(NoAuditLog),
+        context: AuditContext::system("tests"),
+    }
+}
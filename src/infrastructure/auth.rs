@@ -1,22 +1,197 @@
-use actix_web::{dev::ServiceRequest, Error};
+use crate::{
+    application::api_key_service::{ApiKeyServiceError, Authenticator},
+    domain::models::Principal,
+    infrastructure::{
+        controllers::ApiError,
+        jwt::{self, JwtValidator},
+    },
+};
+use actix_web::{
+    dev::{Payload, ServiceRequest},
+    http::StatusCode,
+    web, Error, FromRequest, HttpMessage, HttpRequest,
+};
 use actix_web_httpauth::extractors::{
     bearer::{BearerAuth, Config},
     AuthenticationError,
 };
+use futures::future::{ready, Ready};
 
+/// Checks the bearer token, which can be a JWT, if the app has a [`JwtValidator`], or an
+/// API key checked with its [`Authenticator`]. The [`Principal`] of the caller is left in
+/// the extensions of the request.
 pub async fn validator(
     req: ServiceRequest,
     credentials: BearerAuth,
 ) -> Result<ServiceRequest, Error> {
-    if credentials.token() == "im_a_valid_user" {
-        Ok(req)
-    } else {
-        let config = req.app_data::<Config>().cloned().unwrap_or_default();
-        let error = AuthenticationError::from(config).with_error_description("Wrong Bearer token");
-        tracing::warn!(
-            "Trying to access a resource with wrong authorization token. Path: {}",
-            req.path()
+    let token = credentials.token();
+    let principal = match req.app_data::<web::Data<JwtValidator>>() {
+        Some(jwt_validator) if jwt::is_jwt(token) => jwt_validator.validate(token).map_err(|e| {
+            tracing::debug!("Invalid JWT: {}", e);
+        }),
+        _ => match req.app_data::<web::Data<dyn Authenticator>>() {
+            Some(authenticator) => match authenticator.authenticate(token).await {
+                Ok(key) => Ok(Principal::from(&key)),
+                Err(ApiKeyServiceError::RepositoryError(e)) => return Err(ApiError::from(e).into()),
+                Err(_) => Err(()),
+            },
+            None => Err(()),
+        },
+    };
+    match principal {
+        Ok(principal) => {
+            req.extensions_mut().insert(principal);
+            Ok(req)
+        }
+        Err(_) => {
+            let config = req.app_data::<Config>().cloned().unwrap_or_default();
+            let error =
+                AuthenticationError::from(config).with_error_description("Wrong Bearer token");
+            tracing::warn!(
+                "Trying to access a resource with wrong authorization token. Path: {}",
+                req.path()
+            );
+            Err(error.into())
+        }
+    }
+}
+
+/// The caller of the handlers wrapped with the [`validator`].
+impl FromRequest for Principal {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(req.extensions().get::<Principal>().cloned().ok_or_else(|| {
+            ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "The request isn't authenticated",
+            )
+        }))
+    }
+}
+
+/// Principal of the admin used in the tests of the handlers.
+#[cfg(test)]
+pub fn test_principal() -> Principal {
+    Principal {
+        subject: "apikey:tests".to_string(),
+        roles: vec![crate::domain::models::Role::Admin.as_str().to_string()],
+        tenant: None,
+    }
+}
+
+/// Authenticator for the tests of the controllers, which accepts `im_a_valid_user`
+/// as the token of an admin.
+#[cfg(test)]
+pub fn test_authenticator() -> web::Data<dyn Authenticator> {
+    test_authenticator_of("tests", true)
+}
+
+/// Authenticator for the tests of the controllers, which accepts `im_a_valid_user`
+/// as the token of the owner.
+#[cfg(test)]
+pub fn test_authenticator_of(owner: &str, admin: bool) -> web::Data<dyn Authenticator> {
+    test_tenant_authenticator(owner, admin, None)
+}
+
+/// Same as [`test_authenticator_of`], for the members of a tenant.
+#[cfg(test)]
+pub fn test_tenant_authenticator(
+    owner: &str,
+    admin: bool,
+    tenant_id: Option<uuid::Uuid>,
+) -> web::Data<dyn Authenticator> {
+    use crate::{application::api_key_service::ApiKeyServiceResult, domain::models::ApiKey};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    struct TestAuthenticator {
+        key: ApiKey,
+    }
+
+    #[async_trait]
+    impl Authenticator for TestAuthenticator {
+        async fn authenticate(&self, token: &str) -> ApiKeyServiceResult {
+            if token != "im_a_valid_user" {
+                return Err(ApiKeyServiceError::InvalidKey);
+            }
+            Ok(self.key.clone())
+        }
+    }
+
+    let authenticator: Arc<dyn Authenticator> = Arc::new(TestAuthenticator {
+        key: ApiKey {
+            id: uuid::Uuid::nil(),
+            owner: owner.to_string(),
+            admin,
+            tenant_id,
+            created_at: None,
+            expires_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        },
+    });
+    web::Data::from(authenticator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{dev::Service, App, HttpResponse};
+    use actix_web_httpauth::middleware::HttpAuthentication;
+    use serde_json::json;
+
+    async fn whoami(caller: Principal) -> HttpResponse {
+        HttpResponse::Ok().json(caller)
+    }
+
+    #[actix_rt::test]
+    async fn validator_accepts_jwts_and_api_keys() {
+        let app = App::new()
+            .app_data(test_authenticator())
+            .app_data(web::Data::new(jwt::test_validator()))
+            .service(
+                web::scope("/whoami")
+                    .wrap(HttpAuthentication::bearer(validator))
+                    .route("", web::get().to(whoami)),
+            );
+        let app = actix_web::test::init_service(app).await;
+        let exp = chrono::Utc::now().timestamp() + 300;
+
+        let token = jwt::hmac_token(json!({
+            "sub": "jane",
+            "aud": "cluster-node-api",
+            "exp": exp,
+            "roles": ["operator"],
+            "tenant": uuid::Uuid::new_v4(),
+        }));
+        for (token, subject) in [
+            (token.as_str(), "jwt:jane"),
+            ("im_a_valid_user", "apikey:tests"),
+        ] {
+            let req = actix_web::test::TestRequest::get()
+                .uri("/whoami")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .to_request();
+            let principal: Principal = actix_web::test::call_and_read_body_json(&app, req).await;
+            assert_eq!(principal.subject, subject);
+        }
+
+        let expired = jwt::hmac_token(json!({
+            "sub": "jane",
+            "aud": "cluster-node-api",
+            "exp": exp - 3600,
+        }));
+        let req = actix_web::test::TestRequest::get()
+            .uri("/whoami")
+            .insert_header(("Authorization", format!("Bearer {}", expired)))
+            .to_request();
+        let error = app.call(req).await.err().unwrap();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
         );
-        Err(error.into())
     }
 }
@@ -0,0 +1,380 @@
+use crate::{
+    application::{
+        api_key_service::ApiKeyServiceError, operation_service::OperationServiceError,
+        rbac_service::RbacServiceError, rollout_service::RolloutServiceError,
+        schedule_service::ScheduleServiceError,
+    },
+    domain::repository::{pagination::CursorError, RepositoryError},
+    infrastructure::request_id,
+};
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Error returned by the controllers.
+///
+/// It's rendered as an [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) `application/problem+json`
+/// body with a stable `code` that clients can rely on and the id of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    detail: String,
+    items: Option<Value>,
+}
+
+/// Body of the error responses.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Results of a request on many entities, telling which ones failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            detail: detail.into(),
+            items: None,
+        }
+    }
+
+    /// Adds the results of a request on many entities, which are sent in the `items` member.
+    pub fn with_items(mut self, items: impl Serialize) -> Self {
+        self.items = serde_json::to_value(items).ok();
+        self
+    }
+
+    pub fn bad_request(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, detail)
+    }
+
+    pub fn not_found(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, detail)
+    }
+
+    pub fn conflict(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, code, detail)
+    }
+
+    pub fn internal(detail: impl fmt::Debug) -> Self {
+        // the details of unexpected errors are logged but never sent to the client
+        tracing::error!(error = ?detail, "Unexpected error");
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "Something went wrong",
+        )
+    }
+
+    pub fn problem(&self) -> Problem {
+        Problem {
+            problem_type: "about:blank".to_string(),
+            title: self
+                .status
+                .canonical_reason()
+                .unwrap_or_default()
+                .to_string(),
+            status: self.status.as_u16(),
+            detail: self.detail.clone(),
+            code: self.code.to_string(),
+            request_id: request_id::current(),
+            items: self.items.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.detail)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status)
+            .content_type(PROBLEM_JSON)
+            .json(self.problem())
+    }
+}
+
+impl From<RepositoryError> for ApiError {
+    fn from(error: RepositoryError) -> Self {
+        match error {
+            RepositoryError::DoesNotExist => ApiError::not_found("not_found", error.to_string()),
+            RepositoryError::AlreadyExists => {
+                ApiError::conflict("already_exists", error.to_string())
+            }
+            RepositoryError::ForeignKeyViolation => {
+                ApiError::conflict("missing_reference", error.to_string())
+            }
+            RepositoryError::InvalidId => ApiError::bad_request("invalid_id", error.to_string()),
+            RepositoryError::VersionMismatch => ApiError::new(
+                StatusCode::PRECONDITION_FAILED,
+                "version_mismatch",
+                error.to_string(),
+            ),
+            RepositoryError::Unavailable(_) => {
+                tracing::error!(error = ?error, "The storage is unavailable");
+                ApiError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "storage_unavailable",
+                    "The storage is unavailable, try again later",
+                )
+            }
+            RepositoryError::LockError(_) | RepositoryError::Generic(_) => {
+                ApiError::internal(error)
+            }
+        }
+    }
+}
+
+impl From<OperationServiceError> for ApiError {
+    fn from(error: OperationServiceError) -> Self {
+        match error {
+            OperationServiceError::NodeNotFound(_) => {
+                ApiError::not_found("node_not_found", error.to_string())
+            }
+            OperationServiceError::InvalidTransition { .. } => {
+                ApiError::conflict("invalid_transition", error.to_string())
+            }
+            OperationServiceError::InvalidStatusTransition { .. } => {
+                ApiError::conflict("invalid_status_transition", error.to_string())
+            }
+            OperationServiceError::OutsideMaintenanceWindow(_) => ApiError::new(
+                StatusCode::LOCKED,
+                "outside_maintenance_window",
+                error.to_string(),
+            ),
+            OperationServiceError::EmptyBatch => {
+                ApiError::bad_request("empty_batch", error.to_string())
+            }
+            OperationServiceError::BatchTooLarge(_) => {
+                ApiError::bad_request("batch_too_large", error.to_string())
+            }
+            OperationServiceError::BatchRejected(ref items) => {
+                ApiError::conflict("batch_rejected", error.to_string()).with_items(items)
+            }
+            OperationServiceError::RepositoryError(e) => e.into(),
+            OperationServiceError::PowerDriverError(_) => ApiError::internal(error),
+        }
+    }
+}
+
+impl From<RolloutServiceError> for ApiError {
+    fn from(error: RolloutServiceError) -> Self {
+        match error {
+            RolloutServiceError::InvalidTransition { .. } => {
+                ApiError::conflict("invalid_rollout_transition", error.to_string())
+            }
+            RolloutServiceError::RolloutTooLarge(_) => {
+                ApiError::bad_request("rollout_too_large", error.to_string())
+            }
+            RolloutServiceError::OperationServiceError(e) => e.into(),
+            RolloutServiceError::RepositoryError(e) => e.into(),
+        }
+    }
+}
+
+impl From<ApiKeyServiceError> for ApiError {
+    fn from(error: ApiKeyServiceError) -> Self {
+        match error {
+            ApiKeyServiceError::InvalidKey => {
+                ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", error.to_string())
+            }
+            ApiKeyServiceError::InvalidRequest(_) => {
+                ApiError::bad_request("invalid_api_key", error.to_string())
+            }
+            ApiKeyServiceError::RepositoryError(e) => e.into(),
+        }
+    }
+}
+
+impl From<RbacServiceError> for ApiError {
+    fn from(error: RbacServiceError) -> Self {
+        match error {
+            RbacServiceError::Forbidden(_) | RbacServiceError::ForbiddenInCluster(..) => {
+                ApiError::new(StatusCode::FORBIDDEN, "forbidden", error.to_string())
+            }
+            RbacServiceError::InvalidRequest(_) => {
+                ApiError::bad_request("invalid_role_binding", error.to_string())
+            }
+            RbacServiceError::RepositoryError(e) => e.into(),
+        }
+    }
+}
+
+impl From<ScheduleServiceError> for ApiError {
+    fn from(error: ScheduleServiceError) -> Self {
+        match error {
+            ScheduleServiceError::InvalidSchedule(_) => {
+                ApiError::bad_request("invalid_schedule", error.to_string())
+            }
+            ScheduleServiceError::RepositoryError(e) => e.into(),
+        }
+    }
+}
+
+impl From<CursorError> for ApiError {
+    fn from(error: CursorError) -> Self {
+        ApiError::bad_request("invalid_cursor", error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::{BatchItem, NodeStatus, OperationType, RolloutStatus};
+    use actix_web::{body::MessageBody, http::header};
+
+    #[test]
+    fn repository_errors_are_mapped() {
+        let cases = [
+            (
+                RepositoryError::DoesNotExist,
+                StatusCode::NOT_FOUND,
+                "not_found",
+            ),
+            (
+                RepositoryError::AlreadyExists,
+                StatusCode::CONFLICT,
+                "already_exists",
+            ),
+            (
+                RepositoryError::ForeignKeyViolation,
+                StatusCode::CONFLICT,
+                "missing_reference",
+            ),
+            (
+                RepositoryError::InvalidId,
+                StatusCode::BAD_REQUEST,
+                "invalid_id",
+            ),
+            (
+                RepositoryError::VersionMismatch,
+                StatusCode::PRECONDITION_FAILED,
+                "version_mismatch",
+            ),
+            (
+                RepositoryError::Unavailable("pool timed out".to_string()),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "storage_unavailable",
+            ),
+            (
+                RepositoryError::LockError("poisoned".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+            ),
+        ];
+
+        for (error, status, code) in cases {
+            let error = ApiError::from(error);
+            assert_eq!(error.status_code(), status);
+            assert_eq!(error.problem().code, code);
+        }
+    }
+
+    #[test]
+    fn operation_errors_are_mapped() {
+        let error = ApiError::from(OperationServiceError::InvalidTransition {
+            from: NodeStatus::Rebooting,
+            op: OperationType::Reboot,
+        });
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+        assert_eq!(error.problem().code, "invalid_transition");
+
+        let error = ApiError::from(OperationServiceError::NodeNotFound(uuid::Uuid::new_v4()));
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+
+        let error = ApiError::from(OperationServiceError::OutsideMaintenanceWindow(
+            uuid::Uuid::new_v4(),
+        ));
+        assert_eq!(error.status_code(), StatusCode::LOCKED);
+        assert_eq!(error.problem().code, "outside_maintenance_window");
+
+        let error = ApiError::from(OperationServiceError::RepositoryError(
+            RepositoryError::Unavailable("pool timed out".to_string()),
+        ));
+        assert_eq!(error.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let node_id = uuid::Uuid::new_v4();
+        let error = ApiError::from(OperationServiceError::BatchRejected(vec![
+            BatchItem::failed(node_id, "Node not found"),
+        ]));
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+        let problem = error.problem();
+        assert_eq!(problem.code, "batch_rejected");
+        assert_eq!(
+            problem.detail,
+            "Nodes of the batch that can't run the operation: 1"
+        );
+        assert_eq!(problem.items.unwrap()[0]["node_id"], node_id.to_string());
+    }
+
+    #[test]
+    fn rollout_errors_are_mapped() {
+        let error = ApiError::from(RolloutServiceError::InvalidTransition {
+            from: RolloutStatus::Succeeded,
+            to: RolloutStatus::Paused,
+        });
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+        assert_eq!(error.problem().code, "invalid_rollout_transition");
+
+        let error = ApiError::from(RolloutServiceError::RolloutTooLarge(1000));
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.problem().code, "rollout_too_large");
+
+        let error = ApiError::from(RolloutServiceError::RepositoryError(
+            RepositoryError::VersionMismatch,
+        ));
+        assert_eq!(error.status_code(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[test]
+    fn internal_errors_hide_the_details() {
+        let error = ApiError::from(RepositoryError::Generic("connection string".into()));
+        assert_eq!(error.problem().detail, "Something went wrong");
+    }
+
+    #[test]
+    fn error_response_is_problem_json() {
+        let res = ApiError::not_found("not_found", "This entity does not exist").error_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let problem = serde_json::from_slice::<'_, Problem>(&body).unwrap();
+        assert_eq!(
+            problem,
+            Problem {
+                problem_type: "about:blank".to_string(),
+                title: "Not Found".to_string(),
+                status: 404,
+                detail: "This entity does not exist".to_string(),
+                code: "not_found".to_string(),
+                request_id: None,
+                items: None,
+            }
+        );
+    }
+}
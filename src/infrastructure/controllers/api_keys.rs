@@ -0,0 +1,186 @@
+use crate::{
+    application::api_key_service::ApiKeyService,
+    domain::{
+        models::{Permission, Principal},
+        repository::ApiKeyRepository,
+    },
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    web::{self, PathConfig},
+    HttpResponse,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::instrument;
+use uuid::Uuid;
+use web::ServiceConfig;
+
+use super::{path_config_handler, ApiError};
+
+const PATH: &str = "/v1/api-keys";
+
+pub fn configuration<K: ApiKeyRepository>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope(PATH)
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .app_data(PathConfig::default().error_handler(path_config_handler))
+            // GET
+            .route("", web::get().to(get_all::<K>))
+            .route("/{api_key_id}", web::get().to(get::<K>))
+            // POST
+            .route("", web::post().to(post::<K>))
+            // DELETE
+            .route("/{api_key_id}", web::delete().to(delete::<K>)),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct NewApiKeyDTO {
+    owner: String,
+    #[serde(default)]
+    admin: bool,
+    /// Organization the key is limited to.
+    tenant_id: Option<Uuid>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+fn required_permissions(_: &ServiceRequest) -> &'static [Permission] {
+    &[Permission::ApiKeysManage]
+}
+
+#[instrument(skip(svc))]
+async fn get_all<K: ApiKeyRepository>(
+    caller: Principal,
+    svc: web::Data<ApiKeyService<K>>,
+) -> Result<HttpResponse, ApiError> {
+    let keys = svc.get_all().await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[instrument(skip(svc))]
+async fn get<K: ApiKeyRepository>(
+    api_key_id: web::Path<Uuid>,
+    caller: Principal,
+    svc: web::Data<ApiKeyService<K>>,
+) -> Result<HttpResponse, ApiError> {
+    let key = svc.get(&api_key_id).await?;
+    Ok(HttpResponse::Ok().json(key))
+}
+
+#[instrument(skip(svc))]
+async fn post<K: ApiKeyRepository>(
+    new_key: web::Json<NewApiKeyDTO>,
+    caller: Principal,
+    svc: web::Data<ApiKeyService<K>>,
+) -> Result<HttpResponse, ApiError> {
+    let minted = svc
+        .mint(
+            &new_key.owner,
+            new_key.admin,
+            new_key.tenant_id,
+            new_key.expires_at,
+        )
+        .await?;
+    tracing::info!("API key {} minted by {}", minted.key.id, caller.subject);
+    Ok(HttpResponse::Created().json(minted))
+}
+
+#[instrument(skip(svc))]
+async fn delete<K: ApiKeyRepository>(
+    api_key_id: web::Path<Uuid>,
+    caller: Principal,
+    svc: web::Data<ApiKeyService<K>>,
+) -> Result<HttpResponse, ApiError> {
+    let key = svc.revoke(&api_key_id).await?;
+    tracing::info!("API key {} revoked by {}", key.id, caller.subject);
+    Ok(HttpResponse::Ok().json(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::api_key_service::{hash_secret, Authenticator},
+        domain::{models::ApiKey, repository::api_key_repository::MockApiKeyRepository},
+    };
+    use actix_web::{dev::Service, http::StatusCode, App};
+    use std::{sync::Arc, time::Duration};
+
+    #[actix_rt::test]
+    async fn admins_mint_keys() {
+        let mut repo = MockApiKeyRepository::default();
+        repo.expect_create_api_key()
+            .returning(|key, _| Ok(key.clone()));
+        let svc = ApiKeyService::new(repo, Duration::from_secs(60));
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(svc))
+            .configure(configuration::<MockApiKeyRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({ "owner": "ops-team" }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["owner"], "ops-team");
+        assert_eq!(body["admin"], false);
+        assert!(body["secret"].as_str().unwrap().starts_with("cna_"));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer wrong"))
+            .to_request();
+        let error = app.call(req).await.err().unwrap();
+        assert_eq!(
+            error.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_rt::test]
+    async fn only_admins_manage_keys() {
+        let secret = "cna_not_an_admin";
+        let mut repo = MockApiKeyRepository::default();
+        repo.expect_get_api_key_by_secret_hash()
+            .withf(move |secret_hash| *secret_hash == hash_secret(secret))
+            .returning(|_| {
+                Ok(ApiKey {
+                    id: Uuid::new_v4(),
+                    owner: "ops-team".to_string(),
+                    admin: false,
+                    tenant_id: None,
+                    created_at: None,
+                    expires_at: None,
+                    last_used_at: None,
+                    revoked_at: None,
+                })
+            });
+        repo.expect_record_use().returning(|_, _| Ok(()));
+        repo.expect_create_api_key().never();
+        let svc = Arc::new(ApiKeyService::new(repo, Duration::from_secs(60)));
+        let authenticator: Arc<dyn Authenticator> = svc.clone();
+        let app = App::new()
+            .app_data(web::Data::from(authenticator))
+            .app_data(web::Data::from(svc))
+            .configure(configuration::<MockApiKeyRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .insert_header(("Authorization", format!("Bearer {}", secret)))
+            .set_json(serde_json::json!({ "owner": "ops-team", "admin": true }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["code"], "forbidden");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    application::rbac_service,
+    domain::{
+        models::{Grants, Permission},
+        repository::{audit_repository::AuditFilter, pagination::SortField, AuditRepository},
+    },
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    web::{self, QueryConfig},
+    HttpResponse,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use tracing::instrument;
+use web::ServiceConfig;
+
+use super::{query_config_handler, ApiError, PageQuery};
+
+const PATH: &str = "/v1/audit";
+
+pub fn configuration<A: AuditRepository>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope(PATH)
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .app_data(QueryConfig::default().error_handler(query_config_handler))
+            // GET
+            .route("", web::get().to(get_all::<A>)),
+    );
+}
+
+fn required_permissions(_: &ServiceRequest) -> &'static [Permission] {
+    &[Permission::AuditRead]
+}
+
+/// The members of a tenant only see the events of their tenant, whatever they filter by.
+#[instrument(skip(repo))]
+async fn get_all<A: AuditRepository>(
+    filter: web::Query<AuditFilter>,
+    page: web::Query<PageQuery>,
+    grants: Grants,
+    repo: web::Data<A>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check_everywhere(&grants, Permission::AuditRead)?;
+    if page.sort != SortField::CreatedAt {
+        return Err(ApiError::bad_request(
+            "invalid_sort",
+            "Audit events can only be sorted by created_at",
+        ));
+    }
+    let mut filter = filter.into_inner();
+    if let Some(tenant_id) = grants.principal.tenant {
+        filter.tenant_id = Some(tenant_id);
+    }
+    let events = repo.get_events(&filter, &page.page_request()?).await?;
+    Ok(HttpResponse::Ok().json(events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::audit_log::{AuditLog, AuditTrail},
+        domain::{
+            models::{AuditAction, AuditEvent, AuditOutcome, AuditTargetType},
+            repository::{audit_repository::MockAuditRepository, Page},
+        },
+        infrastructure::{
+            controllers::{clusters, nodes},
+            db::{
+                InMemoryAuditRepository, InMemoryClusterRepository, InMemoryNodeRepository,
+                InMemoryStore,
+            },
+        },
+    };
+    use actix_web::{http::StatusCode, App};
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    #[actix_rt::test]
+    async fn changes_to_the_clusters_are_recorded() {
+        let store = InMemoryStore::default();
+        let audit_repo = InMemoryAuditRepository::new(store.clone());
+        let audit_log: Arc<dyn AuditLog> = Arc::new(AuditTrail::new(audit_repo.clone()));
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::from(audit_log))
+            .app_data(web::Data::new(audit_repo))
+            .app_data(web::Data::new(InMemoryClusterRepository::new(
+                store.clone(),
+            )))
+            .app_data(web::Data::new(InMemoryNodeRepository::new(store)))
+            .configure(clusters::configuration::<InMemoryClusterRepository, InMemoryNodeRepository>)
+            .configure(configuration::<InMemoryAuditRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let cluster_id = Uuid::new_v4();
+        let cluster = serde_json::json!({ "id": cluster_id, "name": "CLUSTER_NAME" });
+        for status in [StatusCode::CREATED, StatusCode::CONFLICT] {
+            let req = actix_web::test::TestRequest::post()
+                .uri("/v1/clusters")
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .set_json(&cluster)
+                .to_request();
+            let res = actix_web::test::call_service(&app, req).await;
+            assert_eq!(res.status(), status);
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?target_id={}&order=desc", PATH, cluster_id))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let events: Page<AuditEvent> = actix_web::test::read_body_json(res).await;
+        let outcomes: Vec<_> = events.items.iter().map(|event| event.outcome).collect();
+        assert_eq!(outcomes, [AuditOutcome::Failure, AuditOutcome::Success]);
+        let created = &events.items[1];
+        assert_eq!(created.actor, "apikey:tests");
+        assert_eq!(created.action, AuditAction::Create);
+        assert_eq!(created.target_type, AuditTargetType::Cluster);
+        assert_eq!(created.before, None);
+        assert_eq!(created.after.as_ref().unwrap()["name"], "CLUSTER_NAME");
+    }
+
+    #[actix_rt::test]
+    async fn rejected_changes_are_recorded() {
+        let store = InMemoryStore::default();
+        let audit_repo = InMemoryAuditRepository::new(store.clone());
+        let audit_log: Arc<dyn AuditLog> = Arc::new(AuditTrail::new(audit_repo.clone()));
+        let app = |admin| {
+            App::new()
+                .app_data(auth::test_authenticator_of("tests", admin))
+                .app_data(web::Data::from(audit_log.clone()))
+                .app_data(web::Data::new(audit_repo.clone()))
+                .app_data(web::Data::new(InMemoryClusterRepository::new(
+                    store.clone(),
+                )))
+                .app_data(web::Data::new(InMemoryNodeRepository::new(store.clone())))
+                .configure(
+                    clusters::configuration::<InMemoryClusterRepository, InMemoryNodeRepository>,
+                )
+                .configure(nodes::configuration::<InMemoryNodeRepository>)
+                .configure(configuration::<InMemoryAuditRepository>)
+        };
+        let admin = actix_web::test::init_service(app(true)).await;
+        let viewer = actix_web::test::init_service(app(false)).await;
+
+        let cluster_id = Uuid::new_v4();
+        let node_id = Uuid::new_v4();
+        let requests = [
+            // invalid maintenance window
+            actix_web::test::TestRequest::post().uri("/v1/clusters").set_json(
+                serde_json::json!({
+                    "id": cluster_id,
+                    "name": "CLUSTER_NAME",
+                    "maintenance_windows": [{ "start": "02:00:00", "duration_minutes": 0, "timezone": "UTC" }]
+                }),
+            ),
+            // invalid merge patch
+            actix_web::test::TestRequest::patch()
+                .uri(&format!("/v1/nodes/{}", node_id))
+                .set_json(serde_json::json!({ "unknown": true })),
+        ];
+        for req in requests {
+            let req = req
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .to_request();
+            let res = actix_web::test::call_service(&admin, req).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        }
+        // missing permission
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("/v1/clusters/{}", cluster_id))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&viewer, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?outcome=failure", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&admin, req).await;
+        let events: Page<AuditEvent> = actix_web::test::read_body_json(res).await;
+        let events: Vec<_> = events
+            .items
+            .iter()
+            .map(|event| (event.action, event.target_type, event.target_id))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (
+                    AuditAction::Create,
+                    AuditTargetType::Cluster,
+                    Some(cluster_id)
+                ),
+                (AuditAction::Update, AuditTargetType::Node, Some(node_id)),
+                (
+                    AuditAction::Delete,
+                    AuditTargetType::Cluster,
+                    Some(cluster_id)
+                ),
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn tenants_only_read_their_own_events() {
+        let tenant_id = Uuid::new_v4();
+        let mut repo = MockAuditRepository::default();
+        repo.expect_get_events()
+            .withf(move |filter, _| filter.tenant_id == Some(tenant_id))
+            .once()
+            .returning(|_, page| Ok(Page::new(vec![], page, None)));
+        let app = App::new()
+            .app_data(auth::test_tenant_authenticator(
+                "jane",
+                true,
+                Some(tenant_id),
+            ))
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockAuditRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?tenant_id={}", PATH, Uuid::new_v4()))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?sort=name", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,95 +1,276 @@
 use crate::{
-    domain::{models::Cluster, repository::ClusterRepository},
-    infrastructure::auth,
+    application::rbac_service,
+    domain::{
+        models::{
+            AuditAction, AuditEvent, AuditTargetType, Cluster, ClusterPatch, Grants,
+            MaintenanceWindow, Permission,
+        },
+        repository::{
+            node_repository::NodeFilter, pagination::SortField, ClusterRepository, NodeRepository,
+        },
+    },
+    infrastructure::{audit::Audit, auth, idempotency::Idempotency, rbac::Authorize},
 };
 use actix_web::{
-    web::{self, PathConfig},
+    dev::ServiceRequest,
+    http::Method,
+    web::{self, PathConfig, QueryConfig},
     HttpResponse,
 };
 use actix_web_httpauth::middleware::HttpAuthentication;
-
+use serde::Deserialize;
 use tracing::instrument;
 use uuid::Uuid;
 use web::ServiceConfig;
 
-use super::path_config_handler;
+use super::{
+    etag, merge_patch, path_config_handler, query_config_handler, ApiError, ExpectedVersion,
+    PageQuery, Scoped,
+};
 
 const PATH: &str = "/v1/clusters";
 
-pub fn configuration<R: ClusterRepository>(cfg: &mut ServiceConfig) {
+pub fn configuration<R: ClusterRepository, N: NodeRepository>(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope(PATH)
+            .wrap(Idempotency)
+            .wrap(Authorize::new(required_permissions).audited(AuditTargetType::Cluster))
             .wrap(HttpAuthentication::bearer(auth::validator))
             .app_data(PathConfig::default().error_handler(path_config_handler))
+            .app_data(QueryConfig::default().error_handler(query_config_handler))
             // GET
             .route("", web::get().to(get_all::<R>))
             .route("/{cluster_id}", web::get().to(get::<R>))
+            .route("/{cluster_id}/nodes", web::get().to(get_nodes::<R, N>))
+            .route("/{cluster_id}/summary", web::get().to(get_summary::<R>))
             // POST
             .route("", web::post().to(post::<R>))
             // PUT
             .route("", web::put().to(put::<R>))
+            // PATCH
+            .route("/{cluster_id}", web::patch().to(patch::<R>))
             // DELETE
             .route("/{cluster_id}", web::delete().to(delete::<R>)),
     );
 }
 
+fn required_permissions(req: &ServiceRequest) -> &'static [Permission] {
+    match *req.method() {
+        Method::GET if req.path().ends_with("/nodes") => &[Permission::NodesRead],
+        Method::GET => &[Permission::ClustersRead],
+        _ => &[Permission::ClustersWrite],
+    }
+}
+
+/// Body of `PATCH /v1/clusters/{cluster_id}`. Only the fields present are changed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClusterPatchDTO {
+    pub name: Option<String>,
+    pub maintenance_windows: Option<Vec<MaintenanceWindow>>,
+}
+
+impl From<ClusterPatchDTO> for ClusterPatch {
+    fn from(dto: ClusterPatchDTO) -> Self {
+        Self {
+            name: dto.name,
+            maintenance_windows: dto.maintenance_windows,
+        }
+    }
+}
+
+fn check_maintenance_windows(windows: &[MaintenanceWindow]) -> Result<(), ApiError> {
+    windows
+        .iter()
+        .try_for_each(|window| window.validate())
+        .map_err(|e| ApiError::bad_request("invalid_maintenance_window", e))
+}
+
 #[instrument(skip(repo))]
-async fn get_all<R: ClusterRepository>(repo: web::Data<R>) -> HttpResponse {
-    match repo.get_clusters().await {
-        Ok(clusters) => HttpResponse::Ok().json(clusters),
-        Err(_) => HttpResponse::NotFound().body("Not found"),
+async fn get_all<R: ClusterRepository>(
+    page: web::Query<PageQuery>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check_everywhere(&grants, Permission::ClustersRead)?;
+    if page.sort == SortField::Status {
+        return Err(ApiError::bad_request(
+            "invalid_sort",
+            "Clusters can't be sorted by status",
+        ));
     }
+    let clusters = repo.get_clusters(&page.page_request()?).await?;
+    Ok(HttpResponse::Ok().json(clusters))
 }
 
 #[instrument(skip(repo))]
 async fn get<R: ClusterRepository>(
     cluster_id: web::Path<Uuid>,
-    repo: web::Data<R>,
-) -> HttpResponse {
-    match repo.get_cluster(&cluster_id).await {
-        Ok(cluster) => HttpResponse::Ok().json(cluster),
-        Err(_) => HttpResponse::NotFound().body("Not found"),
-    }
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check(&grants, Permission::ClustersRead, &cluster_id)?;
+    let cluster = repo.get_cluster(&cluster_id).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(cluster.version))
+        .json(cluster))
+}
+
+#[instrument(skip(repo, node_repo))]
+async fn get_nodes<R: ClusterRepository, N: NodeRepository>(
+    cluster_id: web::Path<Uuid>,
+    filter: web::Query<NodeFilter>,
+    page: web::Query<PageQuery>,
+    grants: Grants,
+    repo: Scoped<R>,
+    node_repo: Scoped<N>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check(&grants, Permission::NodesRead, &cluster_id)?;
+    // an unknown cluster is a 404, not an empty list
+    repo.get_cluster(&cluster_id).await?;
+
+    let filter = NodeFilter {
+        cluster_id: Some(cluster_id.into_inner()),
+        ..filter.into_inner()
+    };
+    let nodes = node_repo.get_nodes(&filter, &page.page_request()?).await?;
+    Ok(HttpResponse::Ok().json(nodes))
 }
 
 #[instrument(skip(repo))]
+async fn get_summary<R: ClusterRepository>(
+    cluster_id: web::Path<Uuid>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check(&grants, Permission::ClustersRead, &cluster_id)?;
+    let summary = repo.get_cluster_summary(&cluster_id).await?;
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+#[instrument(skip(audit, repo))]
 async fn post<R: ClusterRepository>(
     cluster: web::Json<Cluster>,
-    repo: web::Data<R>,
-) -> HttpResponse {
-    match repo.create_cluster(&cluster).await {
-        Ok(cluster) => HttpResponse::Created().json(cluster),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Something went wrong: {}", e)),
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        check_maintenance_windows(&cluster.maintenance_windows)?;
+        Ok::<_, ApiError>(repo.create_cluster(&cluster).await?)
     }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(
+                AuditAction::Create,
+                AuditTargetType::Cluster,
+                Some(cluster.id),
+            )
+            .with_result(&result),
+        )
+        .await;
+    let cluster = result?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(cluster.version))
+        .json(cluster))
 }
 
-#[instrument(skip(repo))]
+#[instrument(skip(audit, repo))]
 async fn put<R: ClusterRepository>(
     cluster: web::Json<Cluster>,
-    repo: web::Data<R>,
-) -> HttpResponse {
-    match repo.update_cluster(&cluster).await {
-        Ok(cluster) => HttpResponse::Ok().json(cluster),
-        Err(e) => HttpResponse::NotFound().body(format!("Something went wrong: {}", e)),
+    expected_version: ExpectedVersion,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        check_maintenance_windows(&cluster.maintenance_windows)?;
+        Ok::<_, ApiError>(repo.update_cluster(&cluster, expected_version.0).await?)
     }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(
+                AuditAction::Update,
+                AuditTargetType::Cluster,
+                Some(cluster.id),
+            )
+            .with_change(&result),
+        )
+        .await;
+    let cluster = result?.after;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(cluster.version))
+        .json(cluster))
 }
 
-#[instrument(skip(repo))]
-async fn delete<R: ClusterRepository>(
+#[instrument(skip(audit, repo))]
+async fn patch<R: ClusterRepository>(
     cluster_id: web::Path<Uuid>,
-    repo: web::Data<R>,
-) -> HttpResponse {
-    match repo.delete_cluster(&cluster_id).await {
-        Ok(id) => HttpResponse::Ok().body(id.to_string()),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Something went wrong: {}", e)),
+    patch: web::Json<serde_json::Value>,
+    expected_version: ExpectedVersion,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        let patch: ClusterPatchDTO = merge_patch::parse(patch.into_inner(), &[])?;
+        check_maintenance_windows(patch.maintenance_windows.as_deref().unwrap_or_default())?;
+        Ok::<_, ApiError>(
+            repo.patch_cluster(&cluster_id, &patch.into(), expected_version.0)
+                .await?,
+        )
     }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(
+                AuditAction::Update,
+                AuditTargetType::Cluster,
+                Some(*cluster_id),
+            )
+            .with_change(&result),
+        )
+        .await;
+    let cluster = result?.after;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(cluster.version))
+        .json(cluster))
+}
+
+#[instrument(skip(audit, repo))]
+async fn delete<R: ClusterRepository>(
+    cluster_id: web::Path<Uuid>,
+    expected_version: ExpectedVersion,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = repo.delete_cluster(&cluster_id, expected_version.0).await;
+    audit
+        .record(
+            AuditEvent::new(
+                AuditAction::Delete,
+                AuditTargetType::Cluster,
+                Some(*cluster_id),
+            )
+            .with_before(result.as_ref().ok())
+            .with_result(&result.as_ref().map(|_| ())),
+        )
+        .await;
+    let cluster = result?;
+    Ok(HttpResponse::Ok().body(cluster.id.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::infrastructure::{audit, rbac};
 
     use super::*;
-    use crate::domain::repository::cluster_repository::MockClusterRepository;
+    use crate::domain::{
+        models::{ClusterSummary, Node, NodeStatus, NodeStatusCounts},
+        repository::{
+            cluster_repository::MockClusterRepository, node_repository::MockNodeRepository, Change,
+            Page, RepositoryError,
+        },
+    };
     use actix_http::Request;
     use actix_web::{body::MessageBody, dev::ServiceResponse, http::StatusCode, App};
     use chrono::Utc;
@@ -98,8 +279,11 @@ mod tests {
         Cluster {
             id,
             name,
+            tenant_id: None,
+            maintenance_windows: vec![],
             created_at: Some(Utc::now()),
             updated_at: None,
+            version: 0,
         }
     }
 
@@ -114,14 +298,21 @@ mod tests {
 
         let mut repo = MockClusterRepository::default();
         repo.expect_get_clusters()
-            .returning(move || Ok(vec![test_cluster_clone.clone()]));
+            .returning(move |page| Ok(Page::new(vec![test_cluster_clone.clone()], page, None)));
 
-        let res = get_all(web::Data::new(repo)).await;
+        let res = get_all(
+            web::Query(PageQuery::default()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = res.into_body().try_into_bytes().unwrap();
-        let clusters = serde_json::from_slice::<'_, Vec<Cluster>>(&body)
+        let clusters = serde_json::from_slice::<'_, Page<Cluster>>(&body)
             .ok()
-            .unwrap();
+            .unwrap()
+            .items;
 
         assert!(clusters.len() == 1);
         assert_eq!(clusters[0], test_cluster);
@@ -130,14 +321,15 @@ mod tests {
     async fn prepare_get_all_response(cluster: Cluster, req: Request) -> ServiceResponse {
         let mut repo = MockClusterRepository::default();
         repo.expect_get_clusters()
-            .returning(move || Ok(vec![cluster.clone()]));
+            .returning(move |page| Ok(Page::new(vec![cluster.clone()], page, Some(1))));
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
-            .configure(configuration::<MockClusterRepository>);
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -153,11 +345,63 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
 
         let body = res.into_body().try_into_bytes().unwrap();
-        let clusters = serde_json::from_slice::<'_, Vec<Cluster>>(&body)
+        let clusters = serde_json::from_slice::<'_, Page<Cluster>>(&body)
             .ok()
             .unwrap();
 
-        assert_eq!(clusters, vec![cluster]);
+        assert_eq!(clusters.items, vec![cluster]);
+        assert_eq!(clusters.next_cursor, None);
+    }
+
+    #[actix_rt::test]
+    async fn get_all_integration_returns_cursor_and_total() {
+        let cluster = create_test_cluster(uuid::Uuid::new_v4(), "CLUSTER_NAME".to_string());
+        let mut repo = MockClusterRepository::default();
+        repo.expect_get_clusters()
+            .withf(|page| page.limit == 1 && page.sort == SortField::Name && page.with_total)
+            .returning(move |page| {
+                let clusters = vec![cluster.clone(), cluster.clone()];
+                Ok(Page::new(clusters, page, Some(2)))
+            });
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
+        let svc = actix_web::test::init_service(app).await;
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?limit=1&sort=name&with_total=true", PATH))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let clusters = serde_json::from_slice::<'_, Page<Cluster>>(&body).unwrap();
+        assert_eq!(clusters.items.len(), 1);
+        assert_eq!(clusters.total, Some(2));
+        assert!(clusters.next_cursor.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn get_all_integration_rejects_invalid_pages() {
+        let cluster = create_test_cluster(uuid::Uuid::new_v4(), "CLUSTER_NAME".to_string());
+        for (query, code) in [
+            ("sort=status", "invalid_sort"),
+            ("sort=nope", "invalid_query"),
+            ("cursor=nope", "invalid_cursor"),
+        ] {
+            let req = actix_web::test::TestRequest::get()
+                .uri(&format!("{}?{}", PATH, query))
+                .insert_header(valid_bearer())
+                .to_request();
+            let res = prepare_get_all_response(cluster.clone(), req).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+            let body = res.into_body().try_into_bytes().unwrap();
+            let problem = serde_json::from_slice::<'_, serde_json::Value>(&body).unwrap();
+            assert_eq!(problem["code"], code);
+        }
     }
 
     #[actix_rt::test]
@@ -179,7 +423,13 @@ mod tests {
             Ok(cluster)
         });
 
-        let result = get(web::Path::from(cluster_id), web::Data::new(repo)).await;
+        let result = get(
+            web::Path::from(cluster_id),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let cluster = serde_json::from_slice::<'_, Cluster>(&body).ok().unwrap();
@@ -196,11 +446,12 @@ mod tests {
         });
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
-            .configure(configuration::<MockClusterRepository>);
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -214,6 +465,7 @@ mod tests {
 
         let res = prepare_get_response(expected.name.clone(), req).await;
         assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("ETag").unwrap(), "\"0\"");
 
         let body = res.into_body().try_into_bytes().unwrap();
         let cluster = serde_json::from_slice::<'_, Cluster>(&body).ok().unwrap();
@@ -241,7 +493,13 @@ mod tests {
         repo.expect_create_cluster()
             .returning(|cluster| Ok(cluster.to_owned()));
 
-        let result = post(web::Json(new_cluster.clone()), web::Data::new(repo)).await;
+        let result = post(
+            web::Json(new_cluster.clone()),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let cluster = serde_json::from_slice::<'_, Cluster>(&body).ok().unwrap();
@@ -255,11 +513,12 @@ mod tests {
             .returning(move |cluster| Ok(cluster.to_owned()));
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
-            .configure(configuration::<MockClusterRepository>);
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -281,6 +540,36 @@ mod tests {
         assert_eq!(cluster, new_cluster);
     }
 
+    #[actix_rt::test]
+    async fn create_integration_rejects_invalid_maintenance_windows() {
+        let mut new_cluster = serde_json::json!({
+            "id": uuid::Uuid::new_v4(),
+            "name": "CLUSTER_NAME",
+            "maintenance_windows": [{
+                "days": ["Sat", "Sun"],
+                "start": "02:00:00",
+                "duration_minutes": 240,
+                "timezone": "Europe/Madrid",
+            }],
+        });
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .set_json(&new_cluster)
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = prepare_create_response(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        new_cluster["maintenance_windows"][0]["timezone"] = "Mars/Olympus_Mons".into();
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .set_json(new_cluster)
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = prepare_create_response(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[actix_rt::test]
     async fn create_integration_fails_if_no_authentication() {
         let new_cluster = create_test_cluster(uuid::Uuid::new_v4(), "CLUSTER_NAME".to_string());
@@ -297,10 +586,21 @@ mod tests {
         let new_cluster = create_test_cluster(uuid::Uuid::new_v4(), "CLUSTER_NAME".to_string());
 
         let mut repo = MockClusterRepository::default();
-        repo.expect_update_cluster()
-            .returning(|cluster| Ok(cluster.to_owned()));
+        repo.expect_update_cluster().returning(|cluster, _| {
+            Ok(Change {
+                before: cluster.to_owned(),
+                after: cluster.to_owned(),
+            })
+        });
 
-        let result = put(web::Json(new_cluster.clone()), web::Data::new(repo)).await;
+        let result = put(
+            web::Json(new_cluster.clone()),
+            ExpectedVersion::default(),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let cluster = serde_json::from_slice::<'_, Cluster>(&body).ok().unwrap();
@@ -310,15 +610,20 @@ mod tests {
 
     async fn prepare_update_response(req: Request) -> ServiceResponse {
         let mut repo = MockClusterRepository::default();
-        repo.expect_update_cluster()
-            .returning(move |cluster| Ok(cluster.to_owned()));
+        repo.expect_update_cluster().returning(move |cluster, _| {
+            Ok(Change {
+                before: cluster.to_owned(),
+                after: cluster.to_owned(),
+            })
+        });
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
-            .configure(configuration::<MockClusterRepository>);
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -351,15 +656,109 @@ mod tests {
         assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
     }
 
+    async fn prepare_patch_response(req: Request) -> ServiceResponse {
+        let mut repo = MockClusterRepository::default();
+        repo.expect_patch_cluster()
+            .withf(|_, patch, _| patch.name.as_deref() == Some("RENAMED"))
+            .returning(|id, patch, _| {
+                let before = create_test_cluster(*id, "CLUSTER_NAME".to_string());
+                let mut cluster = before.clone();
+                patch.apply(&mut cluster);
+                Ok(Change {
+                    before,
+                    after: cluster,
+                })
+            });
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
+
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
+    }
+
+    #[actix_rt::test]
+    async fn patch_integration_works() {
+        let cluster_id = uuid::Uuid::new_v4();
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", PATH, cluster_id))
+            .insert_header(("Content-Type", "application/merge-patch+json"))
+            .set_payload(r#"{"name": "RENAMED"}"#)
+            .insert_header(valid_bearer())
+            .to_request();
+
+        let res = prepare_patch_response(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let cluster = serde_json::from_slice::<'_, Cluster>(&body).ok().unwrap();
+
+        assert_eq!(cluster.id, cluster_id);
+        assert_eq!(cluster.name, "RENAMED");
+    }
+
+    #[actix_rt::test]
+    async fn patch_integration_rejects_immutable_fields() {
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", PATH, uuid::Uuid::new_v4()))
+            .set_json(serde_json::json!({ "name": "RENAMED", "id": uuid::Uuid::new_v4() }))
+            .insert_header(valid_bearer())
+            .to_request();
+
+        let res = prepare_patch_response(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn patch_integration_fails_if_the_version_changed() {
+        let mut repo = MockClusterRepository::default();
+        repo.expect_patch_cluster()
+            .withf(|_, _, expected_version| *expected_version == Some(vec![1]))
+            .returning(|_, _, _| Err(RepositoryError::VersionMismatch));
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
+        let svc = actix_web::test::init_service(app).await;
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", PATH, uuid::Uuid::new_v4()))
+            .set_json(serde_json::json!({ "name": "RENAMED" }))
+            .insert_header(("If-Match", "\"1\""))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[actix_rt::test]
+    async fn patch_integration_fails_if_no_authentication() {
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", PATH, uuid::Uuid::new_v4()))
+            .set_json(serde_json::json!({ "name": "RENAMED" }))
+            .to_request();
+        let res = prepare_patch_response(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
     #[actix_rt::test]
     async fn delete_works() {
         let cluster_id = uuid::Uuid::new_v4();
 
         let mut repo = MockClusterRepository::default();
         repo.expect_delete_cluster()
-            .returning(|id| Ok(id.to_owned()));
+            .returning(|id, _| Ok(create_test_cluster(*id, "CLUSTER_NAME".to_string())));
 
-        let result = delete(web::Path::from(cluster_id), web::Data::new(repo)).await;
+        let result = delete(
+            web::Path::from(cluster_id),
+            ExpectedVersion::default(),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let id = std::str::from_utf8(&body).ok().unwrap();
@@ -370,14 +769,15 @@ mod tests {
     async fn prepare_delete_response(req: Request) -> ServiceResponse {
         let mut repo = MockClusterRepository::default();
         repo.expect_delete_cluster()
-            .returning(|id| Ok(id.to_owned()));
+            .returning(|id, _| Ok(create_test_cluster(*id, "CLUSTER_NAME".to_string())));
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
-            .configure(configuration::<MockClusterRepository>);
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -407,4 +807,119 @@ mod tests {
         let res = prepare_delete_response(req).await;
         assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
     }
+
+    async fn prepare_get_nodes_response(cluster_exists: bool, req: Request) -> ServiceResponse {
+        let mut repo = MockClusterRepository::default();
+        repo.expect_get_cluster().returning(move |id| {
+            if cluster_exists {
+                Ok(create_test_cluster(*id, "CLUSTER_NAME".to_string()))
+            } else {
+                Err(RepositoryError::DoesNotExist)
+            }
+        });
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_nodes()
+            .withf(|filter, _| {
+                filter.cluster_id.is_some() && filter.status == Some(NodeStatus::PowerOn)
+            })
+            .returning(|filter, page| {
+                let node = Node {
+                    id: uuid::Uuid::new_v4(),
+                    name: "NODE_NAME".to_string(),
+                    cluster_id: filter.cluster_id.unwrap(),
+                    status: NodeStatus::PowerOn,
+                    driver: Default::default(),
+                    bmc_endpoint: None,
+                    created_at: Some(Utc::now()),
+                    updated_at: None,
+                    version: 0,
+                };
+                Ok(Page::new(vec![node], page, None))
+            });
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(node_repo))
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
+
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_integration_works() {
+        let cluster_id = uuid::Uuid::new_v4();
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/{}/nodes?status=poweron", PATH, cluster_id))
+            .insert_header(valid_bearer())
+            .to_request();
+
+        let res = prepare_get_nodes_response(true, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body).unwrap();
+        assert_eq!(nodes.items.len(), 1);
+        assert_eq!(nodes.items[0].cluster_id, cluster_id);
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_integration_fails_if_cluster_does_not_exist() {
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/{}/nodes", PATH, uuid::Uuid::new_v4()))
+            .insert_header(valid_bearer())
+            .to_request();
+
+        let res = prepare_get_nodes_response(false, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_integration_fails_if_no_authentication() {
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/{}/nodes", PATH, uuid::Uuid::new_v4()))
+            .to_request();
+
+        let res = prepare_get_nodes_response(true, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn get_summary_integration_works() {
+        let cluster_id = uuid::Uuid::new_v4();
+        let mut repo = MockClusterRepository::default();
+        repo.expect_get_cluster_summary().returning(|id| {
+            Ok(ClusterSummary {
+                cluster_id: *id,
+                node_count: 3,
+                nodes_by_status: NodeStatusCounts {
+                    poweron: 2,
+                    poweroff: 1,
+                    rebooting: 0,
+                },
+                last_operation_at: None,
+                in_flight_operations: 1,
+            })
+        });
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockClusterRepository, MockNodeRepository>);
+        let svc = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/{}/summary", PATH, cluster_id))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let summary = serde_json::from_slice::<'_, serde_json::Value>(&body).unwrap();
+        assert_eq!(summary["cluster_id"], cluster_id.to_string());
+        assert_eq!(summary["nodes_by_status"]["poweron"], 2);
+        assert_eq!(summary["in_flight_operations"], 1);
+    }
 }
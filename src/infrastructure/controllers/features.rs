@@ -37,11 +37,11 @@ mod tests {
     #[actix_rt::test]
     async fn features_integration_works() {
         let app = App::new().configure(configuration);
-        let mut app = actix_web::test::init_service(app).await;
+        let app = actix_web::test::init_service(app).await;
         let req = actix_web::test::TestRequest::get()
             .uri("/v1/features")
             .to_request();
-        let res = actix_web::test::call_service(&mut app, req).await;
+        let res = actix_web::test::call_service(&app, req).await;
         assert!(res.status().is_success());
         assert_eq!(res.status(), StatusCode::OK);
         let body = res.into_body().try_into_bytes().unwrap();
@@ -30,11 +30,11 @@ mod tests {
     #[actix_rt::test]
     async fn health_check_integration_works() {
         let app = App::new().configure(configuration);
-        let mut app = actix_web::test::init_service(app).await;
+        let app = actix_web::test::init_service(app).await;
         let req = actix_web::test::TestRequest::get()
             .uri("/health")
             .to_request();
-        let res = actix_web::test::call_service(&mut app, req).await;
+        let res = actix_web::test::call_service(&app, req).await;
         assert!(res.status().is_success());
         assert_eq!(res.status(), StatusCode::OK);
     }
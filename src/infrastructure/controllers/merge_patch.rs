@@ -0,0 +1,104 @@
+use super::ApiError;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Fields managed by the server that clients can't change.
+const IMMUTABLE_FIELDS: &[&str] = &["id", "created_at", "updated_at"];
+
+/// Parses an [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396) JSON Merge Patch into its DTO.
+///
+/// In a merge patch `null` removes a field, so it's only accepted for the `nullable` ones.
+/// The DTOs must deny unknown fields so typos aren't silently ignored.
+pub fn parse<T: DeserializeOwned>(patch: Value, nullable: &[&str]) -> Result<T, ApiError> {
+    let fields = patch
+        .as_object()
+        .ok_or_else(|| ApiError::bad_request("invalid_patch", "The patch must be a JSON object"))?;
+    if let Some(field) = IMMUTABLE_FIELDS.iter().find(|f| fields.contains_key(**f)) {
+        return Err(ApiError::bad_request(
+            "immutable_field",
+            format!("The field `{}` can't be modified", field),
+        ));
+    }
+    if let Some((field, _)) = fields
+        .iter()
+        .find(|(field, value)| value.is_null() && !nullable.contains(&field.as_str()))
+    {
+        return Err(ApiError::bad_request(
+            "invalid_patch",
+            format!("The field `{}` can't be removed", field),
+        ));
+    }
+
+    serde_json::from_value(patch).map_err(|e| ApiError::bad_request("invalid_patch", e.to_string()))
+}
+
+/// Deserializes a field that can be removed, telling apart a missing field (`None`)
+/// from a `null` one (`Some(None)`). It must be used along with `#[serde(default)]`.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, ResponseError};
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    #[serde(deny_unknown_fields)]
+    struct TestPatch {
+        name: Option<String>,
+        #[serde(default, deserialize_with = "nullable")]
+        description: Option<Option<String>>,
+    }
+
+    fn problem_code(error: ApiError) -> String {
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        error.problem().code
+    }
+
+    #[test]
+    fn missing_and_null_fields_are_told_apart() {
+        let patch: TestPatch = parse(json!({ "name": "NAME" }), &["description"]).unwrap();
+        assert_eq!(
+            patch,
+            TestPatch {
+                name: Some("NAME".to_string()),
+                description: None
+            }
+        );
+
+        let patch: TestPatch = parse(json!({ "description": null }), &["description"]).unwrap();
+        assert_eq!(
+            patch,
+            TestPatch {
+                name: None,
+                description: Some(None)
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_patches_are_rejected() {
+        let parse = |patch| parse::<TestPatch>(patch, &["description"]).unwrap_err();
+
+        assert_eq!(
+            problem_code(parse(json!({ "id": "ID" }))),
+            "immutable_field"
+        );
+        assert_eq!(
+            problem_code(parse(json!({ "created_at": null }))),
+            "immutable_field"
+        );
+        assert_eq!(
+            problem_code(parse(json!({ "name": null }))),
+            "invalid_patch"
+        );
+        assert_eq!(problem_code(parse(json!({ "other": 1 }))), "invalid_patch");
+        assert_eq!(problem_code(parse(json!(["name"]))), "invalid_patch");
+    }
+}
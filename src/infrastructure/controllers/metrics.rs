@@ -0,0 +1,90 @@
+use crate::{
+    application::metrics::Metrics,
+    domain::models::Permission,
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    web::{self, ServiceConfig},
+    HttpResponse,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use tracing::instrument;
+
+/// Content type of the Prometheus text format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The metrics tell the clusters of every tenant apart, so they need authorization.
+#[instrument(skip(cfg), level = "trace")]
+pub fn configuration(cfg: &mut ServiceConfig) {
+    tracing::trace!("Init metrics service");
+    cfg.service(
+        web::resource("/metrics")
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .route(web::get().to(get_metrics)),
+    );
+}
+
+fn required_permissions(_: &ServiceRequest) -> &'static [Permission] {
+    &[Permission::MetricsRead]
+}
+
+#[instrument(skip(metrics), level = "trace")]
+async fn get_metrics(metrics: web::Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .body(metrics.render().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{application::metrics::OperationOutcome, domain::models::OperationType};
+    use actix_web::{http::StatusCode, App};
+    use uuid::Uuid;
+
+    #[actix_rt::test]
+    async fn metrics_need_the_permission_to_read_them() {
+        let metrics = Metrics::default();
+        metrics.count_operation(OperationType::PowerOn, OperationOutcome::Requested);
+        let app = |admin, tenant_id| {
+            App::new()
+                .app_data(auth::test_tenant_authenticator("tests", admin, tenant_id))
+                .app_data(web::Data::new(metrics.clone()))
+                .configure(configuration)
+        };
+        let admin = actix_web::test::init_service(app(true, None)).await;
+        let tenant_admin = actix_web::test::init_service(app(true, Some(Uuid::new_v4()))).await;
+        let caller_without_roles = actix_web::test::init_service(app(false, None)).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/metrics")
+            .to_request();
+        let res = actix_web::test::call_service(&admin, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // neither the callers without the role nor the ones of a tenant, whatever their role
+        for app in [&tenant_admin, &caller_without_roles] {
+            let req = actix_web::test::TestRequest::get()
+                .uri("/metrics")
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .to_request();
+            let res = actix_web::test::call_service(app, req).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/metrics")
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&admin, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("Content-Type").unwrap(), CONTENT_TYPE);
+        let body = actix_web::test::read_body(res).await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            body.contains(r#"operations_total{operation_type="poweron",outcome="requested"} 1"#)
+        );
+    }
+}
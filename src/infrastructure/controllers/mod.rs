@@ -1,10 +1,36 @@
+use crate::{
+    application::rbac_service,
+    domain::{
+        models::{Grants, Permission, Principal},
+        repository::{ClusterRepository, NodeRepository, TenantScoped},
+    },
+};
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use std::ops::Deref;
 use tracing::instrument;
+use uuid::Uuid;
 
+mod api_error;
+pub mod api_keys;
+pub mod audit;
 pub mod clusters;
 pub mod features;
 pub mod health;
+mod merge_patch;
+pub mod metrics;
 pub mod nodes;
 pub mod operations;
+pub mod organizations;
+mod pagination;
+mod precondition;
+pub mod rbac;
+pub mod rollouts;
+pub mod schedules;
+
+pub use api_error::ApiError;
+pub use pagination::PageQuery;
+pub use precondition::{etag, ExpectedVersion};
 
 #[instrument(fields( path=?_req.path()), skip(_req))]
 fn path_config_handler(
@@ -12,5 +38,97 @@ fn path_config_handler(
     _req: &actix_web::HttpRequest,
 ) -> actix_web::Error {
     tracing::error!(error=?err, "There was an error with the path");
-    actix_web::error::ErrorBadRequest(err)
+    ApiError::bad_request("invalid_path", err.to_string()).into()
+}
+
+#[instrument(fields( query=?_req.query_string()), skip(_req))]
+fn query_config_handler(
+    err: actix_web::error::QueryPayloadError,
+    _req: &actix_web::HttpRequest,
+) -> actix_web::Error {
+    tracing::error!(error=?err, "There was an error with the query");
+    ApiError::bad_request("invalid_query", err.to_string()).into()
+}
+
+/// Fails unless the permission is granted in the cluster of the node. The node is only
+/// loaded when the permission isn't granted in every cluster or the caller has a tenant,
+/// so `repo` must be limited to that tenant.
+async fn check_node<R: NodeRepository>(
+    grants: &Grants,
+    permission: Permission,
+    node_id: &Uuid,
+    repo: &R,
+) -> Result<(), ApiError> {
+    if grants.allows_everywhere(permission) && grants.principal.tenant.is_none() {
+        return Ok(());
+    }
+    let node = repo.get_node(node_id).await?;
+    rbac_service::check(grants, permission, &node.cluster_id)?;
+    Ok(())
+}
+
+/// Fails unless the permission is granted in the cluster and, for the callers with a
+/// tenant, the cluster belongs to it.
+async fn check_cluster<C: ClusterRepository>(
+    grants: &Grants,
+    permission: Permission,
+    cluster_id: &Uuid,
+    repo: &C,
+) -> Result<(), ApiError> {
+    rbac_service::check(grants, permission, cluster_id)?;
+    if let Some(tenant) = grants.principal.tenant {
+        repo.for_tenant(tenant).get_cluster(cluster_id).await?;
+    }
+    Ok(())
+}
+
+/// Repository, or service, limited to the tenant of the caller of the handler, or the
+/// shared one for the callers without a tenant. It's taken from the app data, like
+/// `web::Data<R>`.
+enum Scoped<R: 'static> {
+    Shared(web::Data<R>),
+    Tenant(R),
+}
+
+impl<R: TenantScoped + 'static> FromRequest for Scoped<R> {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let scoped = Principal::from_request(req, payload)
+            .into_inner()
+            .and_then(|caller| {
+                let data = req.app_data::<web::Data<R>>().cloned().ok_or_else(|| {
+                    ApiError::internal(format!(
+                        "No {} is configured for {}",
+                        std::any::type_name::<R>(),
+                        req.path()
+                    ))
+                })?;
+                Ok(match caller.tenant {
+                    Some(tenant) => Scoped::Tenant(data.for_tenant(tenant)),
+                    None => Scoped::Shared(data),
+                })
+            });
+        ready(scoped)
+    }
+}
+
+#[cfg(test)]
+impl<R> Scoped<R> {
+    /// Shared repository, or service, for the handlers called by the tests.
+    fn shared(repo: R) -> Self {
+        Scoped::Shared(web::Data::new(repo))
+    }
+}
+
+impl<R> Deref for Scoped<R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        match self {
+            Scoped::Shared(data) => data,
+            Scoped::Tenant(scoped) => scoped,
+        }
+    }
 }
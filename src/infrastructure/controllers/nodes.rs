@@ -1,99 +1,258 @@
 use crate::{
+    application::rbac_service,
     domain::{
-        models::{Node, NodeStatus},
-        repository::{node_repository::NodeFilter, NodeRepository},
+        models::{
+            AuditAction, AuditEvent, AuditTargetType, Grants, Node, NodePatch, Permission,
+            PowerDriverKind,
+        },
+        repository::{
+            node_repository::{NodeFilter, OperationFilter},
+            NodeRepository,
+        },
     },
-    infrastructure::auth,
+    infrastructure::{audit::Audit, auth, idempotency::Idempotency, rbac::Authorize},
 };
 use actix_web::{
-    web::{self, PathConfig},
+    dev::ServiceRequest,
+    http::Method,
+    web::{self, PathConfig, QueryConfig},
     HttpResponse,
 };
 use actix_web_httpauth::middleware::HttpAuthentication;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tracing::instrument;
 use uuid::Uuid;
 use web::ServiceConfig;
 
-use super::path_config_handler;
+use super::{
+    check_node, etag, merge_patch, path_config_handler, query_config_handler, ApiError,
+    ExpectedVersion, PageQuery, Scoped,
+};
 
 const PATH: &str = "/v1/nodes";
 
 pub fn configuration<R: NodeRepository>(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope(PATH)
+            .wrap(Idempotency)
+            .wrap(Authorize::new(required_permissions).audited(AuditTargetType::Node))
             .wrap(HttpAuthentication::bearer(auth::validator))
             .app_data(PathConfig::default().error_handler(path_config_handler))
+            .app_data(QueryConfig::default().error_handler(query_config_handler))
             // GET
             .route("", web::get().to(get_all::<R>))
             .route("/{node_id}", web::get().to(get::<R>))
+            .route("/{node_id}/operations", web::get().to(get_operations::<R>))
             // POST
             .route("", web::post().to(post::<R>))
             // PUT
             .route("", web::put().to(put::<R>))
+            // PATCH
+            .route("/{node_id}", web::patch().to(patch::<R>))
             // DELETE
             .route("/{node_id}", web::delete().to(delete::<R>)),
     );
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct NodePatchDTO {
-    pub id: uuid::Uuid,
-    pub status: NodeStatus,
+fn required_permissions(req: &ServiceRequest) -> &'static [Permission] {
+    match *req.method() {
+        Method::GET if req.path().ends_with("/operations") => &[Permission::OperationsRead],
+        Method::GET => &[Permission::NodesRead],
+        _ => &[Permission::NodesWrite],
+    }
 }
 
-#[instrument(skip(repo))]
-async fn get_all<R: NodeRepository>(
-    filter: Option<web::Query<NodeFilter>>,
-    repo: web::Data<R>,
-) -> HttpResponse {
-    let filter = filter.map(|f| f.into_inner());
+/// Body of `PATCH /v1/nodes/{node_id}`. Only the fields present are changed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NodePatchDTO {
+    pub name: Option<String>,
+    pub cluster_id: Option<Uuid>,
+    pub driver: Option<PowerDriverKind>,
+    #[serde(default, deserialize_with = "merge_patch::nullable")]
+    pub bmc_endpoint: Option<Option<String>>,
+}
 
-    match repo.get_nodes(filter).await {
-        Ok(nodes) => HttpResponse::Ok().json(nodes),
-        Err(_) => HttpResponse::NotFound().body("Not found"),
+impl From<NodePatchDTO> for NodePatch {
+    fn from(dto: NodePatchDTO) -> Self {
+        Self {
+            name: dto.name,
+            cluster_id: dto.cluster_id,
+            driver: dto.driver,
+            bmc_endpoint: dto.bmc_endpoint,
+        }
     }
 }
 
 #[instrument(skip(repo))]
-async fn get<R: NodeRepository>(node_id: web::Path<Uuid>, repo: web::Data<R>) -> HttpResponse {
-    match repo.get_node(&node_id).await {
-        Ok(node) => HttpResponse::Ok().json(node),
-        Err(_) => HttpResponse::NotFound().body("Not found"),
-    }
+async fn get_all<R: NodeRepository>(
+    filter: web::Query<NodeFilter>,
+    page: web::Query<PageQuery>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    match &filter.cluster_id {
+        Some(cluster_id) => rbac_service::check(&grants, Permission::NodesRead, cluster_id)?,
+        None => rbac_service::check_everywhere(&grants, Permission::NodesRead)?,
+    }
+    let nodes = repo.get_nodes(&filter, &page.page_request()?).await?;
+    Ok(HttpResponse::Ok().json(nodes))
 }
 
 #[instrument(skip(repo))]
-async fn post<R: NodeRepository>(node: web::Json<Node>, repo: web::Data<R>) -> HttpResponse {
-    match repo.create_node(&node).await {
-        Ok(node) => HttpResponse::Created().json(node),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Something went wrong: {}", e)),
-    }
+async fn get<R: NodeRepository>(
+    node_id: web::Path<Uuid>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let node = repo.get_node(&node_id).await?;
+    rbac_service::check(&grants, Permission::NodesRead, &node.cluster_id)?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(node.version))
+        .json(node))
 }
 
 #[instrument(skip(repo))]
-async fn put<R: NodeRepository>(node: web::Json<Node>, repo: web::Data<R>) -> HttpResponse {
-    match repo.update_node(&node).await {
-        Ok(node) => HttpResponse::Ok().json(node),
-        Err(e) => HttpResponse::NotFound().body(format!("Something went wrong: {}", e)),
-    }
+async fn get_operations<R: NodeRepository>(
+    node_id: web::Path<Uuid>,
+    filter: web::Query<OperationFilter>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    // an unknown node is a 404, not an empty history
+    let node = repo.get_node(&node_id).await?;
+    rbac_service::check(&grants, Permission::OperationsRead, &node.cluster_id)?;
+
+    let filter = OperationFilter {
+        node_id: Some(node_id.into_inner()),
+        ..filter.into_inner()
+    };
+    let operations = repo.get_operations(&filter).await?;
+    Ok(HttpResponse::Ok().json(operations))
 }
 
-#[instrument(skip(repo))]
-async fn delete<R: NodeRepository>(node_id: web::Path<Uuid>, repo: web::Data<R>) -> HttpResponse {
-    match repo.delete_node(&node_id).await {
-        Ok(id) => HttpResponse::Ok().body(id.to_string()),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Something went wrong: {}", e)),
+#[instrument(skip(audit, repo))]
+async fn post<R: NodeRepository>(
+    node: web::Json<Node>,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        rbac_service::check(&grants, Permission::NodesWrite, &node.cluster_id)?;
+        Ok::<_, ApiError>(repo.create_node(&node).await?)
+    }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(AuditAction::Create, AuditTargetType::Node, Some(node.id))
+                .with_result(&result),
+        )
+        .await;
+    let node = result?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(node.version))
+        .json(node))
+}
+
+#[instrument(skip(audit, repo))]
+async fn put<R: NodeRepository>(
+    node: web::Json<Node>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        // the node can't be moved out of a cluster or into one the caller can't write to
+        rbac_service::check(&grants, Permission::NodesWrite, &node.cluster_id)?;
+        check_node(&grants, Permission::NodesWrite, &node.id, &*repo).await?;
+        Ok::<_, ApiError>(repo.update_node(&node, expected_version.0).await?)
+    }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(AuditAction::Update, AuditTargetType::Node, Some(node.id))
+                .with_change(&result),
+        )
+        .await;
+    let node = result?.after;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(node.version))
+        .json(node))
+}
+
+#[instrument(skip(audit, repo))]
+async fn patch<R: NodeRepository>(
+    node_id: web::Path<Uuid>,
+    patch: web::Json<serde_json::Value>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        let patch: NodePatchDTO = merge_patch::parse(patch.into_inner(), &["bmc_endpoint"])?;
+        if let Some(cluster_id) = &patch.cluster_id {
+            rbac_service::check(&grants, Permission::NodesWrite, cluster_id)?;
+        }
+        check_node(&grants, Permission::NodesWrite, &node_id, &*repo).await?;
+        Ok::<_, ApiError>(
+            repo.patch_node(&node_id, &patch.into(), expected_version.0)
+                .await?,
+        )
     }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(AuditAction::Update, AuditTargetType::Node, Some(*node_id))
+                .with_change(&result),
+        )
+        .await;
+    let node = result?.after;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(node.version))
+        .json(node))
+}
+
+#[instrument(skip(audit, repo))]
+async fn delete<R: NodeRepository>(
+    node_id: web::Path<Uuid>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let result = async {
+        check_node(&grants, Permission::NodesWrite, &node_id, &*repo).await?;
+        Ok::<_, ApiError>(repo.delete_node(&node_id, expected_version.0).await?)
+    }
+    .await;
+    audit
+        .record(
+            AuditEvent::new(AuditAction::Delete, AuditTargetType::Node, Some(*node_id))
+                .with_before(result.as_ref().ok())
+                .with_result(&result.as_ref().map(|_| ())),
+        )
+        .await;
+    let node = result?;
+    Ok(HttpResponse::Ok().body(node.id.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::infrastructure::{audit, rbac};
 
     use super::*;
-    use crate::domain::{models::NodeStatus, repository::node_repository::MockNodeRepository};
+    use crate::domain::{
+        models::{NodeStatus, Operation, OperationType, PowerDriverKind},
+        repository::{
+            node_repository::{MockNodeRepository, NameMatch},
+            Change, Page, RepositoryError,
+        },
+    };
     use actix_http::{Request, StatusCode};
-    use actix_web::{body::MessageBody, dev::ServiceResponse, App};
+    use actix_web::{body::MessageBody, dev::ServiceResponse, App, ResponseError};
     use chrono::Utc;
 
     fn create_test_node(id: uuid::Uuid, name: String) -> Node {
@@ -102,8 +261,11 @@ mod tests {
             name,
             cluster_id: uuid::Uuid::new_v4(),
             status: NodeStatus::PowerOn,
+            driver: PowerDriverKind::Simulated,
+            bmc_endpoint: None,
             created_at: Some(Utc::now()),
             updated_at: None,
+            version: 0,
         }
     }
 
@@ -113,12 +275,13 @@ mod tests {
 
     fn prepare_filter_repo(node: Node) -> MockNodeRepository {
         let mut repo = MockNodeRepository::default();
-        repo.expect_get_nodes()
-            .returning(move |filter| match filter {
-                Some(filter) if node.name.contains(&filter.name) => Ok(vec![node.clone()]),
-                None => Ok(vec![node.clone()]),
-                _ => Ok(vec![]),
-            });
+        repo.expect_get_nodes().returning(move |filter, page| {
+            let nodes = match &filter.name {
+                Some(name) if !node.name.contains(name) => vec![],
+                _ => vec![node.clone()],
+            };
+            Ok(Page::new(nodes, page, None))
+        });
         repo
     }
 
@@ -127,10 +290,20 @@ mod tests {
         let test_node = create_test_node(uuid::Uuid::new_v4(), "NODE_NAME".to_string());
 
         let repo = prepare_filter_repo(test_node.clone());
-        let result = get_all(None, web::Data::new(repo)).await;
+        let result = get_all(
+            web::Query(NodeFilter::default()),
+            web::Query(PageQuery::default()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
-        let nodes = serde_json::from_slice::<'_, Vec<Node>>(&body).ok().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body)
+            .ok()
+            .unwrap()
+            .items;
 
         assert!(nodes.len() == 1);
         assert_eq!(nodes[0], test_node);
@@ -144,15 +317,22 @@ mod tests {
         let repo = prepare_filter_repo(test_node.clone());
 
         let result = get_all(
-            Some(web::Query(NodeFilter {
-                name: "NODE".to_string(),
-            })),
-            web::Data::new(repo),
+            web::Query(NodeFilter {
+                name: Some("NODE".to_string()),
+                ..Default::default()
+            }),
+            web::Query(PageQuery::default()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
         )
-        .await;
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
-        let nodes = serde_json::from_slice::<'_, Vec<Node>>(&body).ok().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body)
+            .ok()
+            .unwrap()
+            .items;
 
         assert!(nodes.len() == 1);
         assert_eq!(nodes[0], test_node);
@@ -166,28 +346,36 @@ mod tests {
         let repo = prepare_filter_repo(test_node.clone());
 
         let result = get_all(
-            Some(web::Query(NodeFilter {
-                name: "other".to_string(),
-            })),
-            web::Data::new(repo),
+            web::Query(NodeFilter {
+                name: Some("other".to_string()),
+                ..Default::default()
+            }),
+            web::Query(PageQuery::default()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
         )
-        .await;
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
-        let nodes = serde_json::from_slice::<'_, Vec<Node>>(&body).ok().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body)
+            .ok()
+            .unwrap()
+            .items;
 
-        assert!(nodes.len() == 0);
+        assert!(nodes.is_empty());
     }
 
     async fn prepare_get_all_response(node: Node, req: Request) -> ServiceResponse {
         let repo = prepare_filter_repo(node);
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
             .configure(configuration::<MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -203,7 +391,10 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
 
         let body = res.into_body().try_into_bytes().unwrap();
-        let nodes = serde_json::from_slice::<'_, Vec<Node>>(&body).ok().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body)
+            .ok()
+            .unwrap()
+            .items;
 
         assert_eq!(nodes, vec![node]);
     }
@@ -229,7 +420,10 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
 
         let body = res.into_body().try_into_bytes().unwrap();
-        let nodes = serde_json::from_slice::<'_, Vec<Node>>(&body).ok().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body)
+            .ok()
+            .unwrap()
+            .items;
 
         assert_eq!(nodes, vec![node]);
     }
@@ -257,7 +451,10 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
 
         let body = res.into_body().try_into_bytes().unwrap();
-        let nodes = serde_json::from_slice::<'_, Vec<Node>>(&body).ok().unwrap();
+        let nodes = serde_json::from_slice::<'_, Page<Node>>(&body)
+            .ok()
+            .unwrap()
+            .items;
 
         assert_eq!(nodes, vec![]);
     }
@@ -272,6 +469,46 @@ mod tests {
         assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
     }
 
+    #[actix_rt::test]
+    async fn get_all_rich_filter_integration_works() {
+        let cluster_id = uuid::Uuid::new_v4();
+        let expected = NodeFilter {
+            name: Some("web".to_string()),
+            name_match: NameMatch::Prefix,
+            case_insensitive: true,
+            status: Some(NodeStatus::PowerOff),
+            cluster_id: Some(cluster_id),
+            created_after: Some("2022-04-01T00:00:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_nodes()
+            .withf(move |filter, page| *filter == expected && page.limit == 5)
+            .returning(|_, page| Ok(Page::new(vec![], page, None)));
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockNodeRepository>);
+        let svc = actix_web::test::init_service(app).await;
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!(
+                "{}?name=web&name_match=prefix&case_insensitive=true&status=poweroff&cluster_id={}&created_after=2022-04-01T00:00:00Z&limit=5",
+                PATH, cluster_id
+            ))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}?status=sleeping", PATH))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
     #[actix_rt::test]
     async fn get_works() {
         let node_id = uuid::Uuid::new_v4();
@@ -283,7 +520,13 @@ mod tests {
             Ok(node)
         });
 
-        let result = get(web::Path::from(node_id), web::Data::new(repo)).await;
+        let result = get(
+            web::Path::from(node_id),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let node = serde_json::from_slice::<'_, Node>(&body).ok().unwrap();
@@ -300,11 +543,12 @@ mod tests {
         });
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
             .configure(configuration::<MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -318,6 +562,7 @@ mod tests {
 
         let res = prepare_get_response(expected.name.clone(), req).await;
         assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("ETag").unwrap(), "\"0\"");
 
         let body = res.into_body().try_into_bytes().unwrap();
         let node = serde_json::from_slice::<'_, Node>(&body).ok().unwrap();
@@ -337,6 +582,55 @@ mod tests {
         assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
     }
 
+    #[actix_rt::test]
+    async fn get_operations_works() {
+        let node_id = uuid::Uuid::new_v4();
+        let operation = Operation::new(node_id, OperationType::Reboot);
+        let expected = operation.clone();
+
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_node()
+            .returning(|id| Ok(create_test_node(*id, "NODE_NAME".to_string())));
+        repo.expect_get_operations()
+            .withf(move |filter| filter.node_id == Some(node_id))
+            .returning(move |_| Ok(vec![operation.clone()]));
+
+        let result = get_operations(
+            web::Path::from(node_id),
+            web::Query(OperationFilter::default()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.status(), StatusCode::OK);
+
+        let body = result.into_body().try_into_bytes().unwrap();
+        let operations = serde_json::from_slice::<'_, Vec<Operation>>(&body)
+            .ok()
+            .unwrap();
+
+        assert_eq!(operations, vec![expected]);
+    }
+
+    #[actix_rt::test]
+    async fn get_operations_returns_not_found_for_unknown_node() {
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_node()
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+        repo.expect_get_operations().never();
+
+        let result = get_operations(
+            web::Path::from(uuid::Uuid::new_v4()),
+            web::Query(OperationFilter::default()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(result.status_code(), StatusCode::NOT_FOUND);
+    }
+
     #[actix_rt::test]
     async fn create_works() {
         let new_node = create_test_node(uuid::Uuid::new_v4(), "NODE_NAME".to_string());
@@ -345,7 +639,14 @@ mod tests {
         repo.expect_create_node()
             .returning(|node| Ok(node.to_owned()));
 
-        let result = post(web::Json(new_node.clone()), web::Data::new(repo)).await;
+        let result = post(
+            web::Json(new_node.clone()),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let node = serde_json::from_slice::<'_, Node>(&body).ok().unwrap();
@@ -359,11 +660,12 @@ mod tests {
             .returning(move |node| Ok(node.to_owned()));
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
             .configure(configuration::<MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -403,10 +705,22 @@ mod tests {
         let new_node = create_test_node(node_id, node_name.to_string());
 
         let mut repo = MockNodeRepository::default();
-        repo.expect_update_node()
-            .returning(|node| Ok(node.to_owned()));
+        repo.expect_update_node().returning(|node, _| {
+            Ok(Change {
+                before: node.to_owned(),
+                after: node.to_owned(),
+            })
+        });
 
-        let result = put(web::Json(new_node), web::Data::new(repo)).await;
+        let result = put(
+            web::Json(new_node),
+            ExpectedVersion::default(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let node = serde_json::from_slice::<'_, Node>(&body).ok().unwrap();
@@ -417,15 +731,20 @@ mod tests {
 
     async fn prepare_update_response(req: Request) -> ServiceResponse {
         let mut repo = MockNodeRepository::default();
-        repo.expect_update_node()
-            .returning(move |node| Ok(node.to_owned()));
+        repo.expect_update_node().returning(move |node, _| {
+            Ok(Change {
+                before: node.to_owned(),
+                after: node.to_owned(),
+            })
+        });
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
             .configure(configuration::<MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
@@ -447,6 +766,49 @@ mod tests {
         assert_eq!(node, new_node);
     }
 
+    #[actix_rt::test]
+    async fn update_integration_checks_if_match() {
+        let mut repo = MockNodeRepository::default();
+        repo.expect_update_node()
+            .withf(|_, expected_version| *expected_version == Some(vec![3]))
+            .returning(|node, _| {
+                Ok(Change {
+                    before: node.to_owned(),
+                    after: Node {
+                        version: 4,
+                        ..node.to_owned()
+                    },
+                })
+            });
+        repo.expect_delete_node()
+            .returning(|_, _| Err(RepositoryError::VersionMismatch));
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockNodeRepository>);
+        let svc = actix_web::test::init_service(app).await;
+
+        let node = create_test_node(uuid::Uuid::new_v4(), "NODE_NAME".to_string());
+        let req = actix_web::test::TestRequest::put()
+            .uri(PATH)
+            .set_json(node.clone())
+            .insert_header(("If-Match", "\"3\""))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get("ETag").unwrap(), "\"4\"");
+
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("{}/{}", PATH, node.id))
+            .insert_header(("If-Match", "\"3\""))
+            .insert_header(valid_bearer())
+            .to_request();
+        let res = actix_web::test::call_service(&svc, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
     #[actix_rt::test]
     async fn update_integration_fails_if_no_authentication() {
         let new_node = create_test_node(uuid::Uuid::new_v4(), "NODE_NAME".to_string());
@@ -459,13 +821,141 @@ mod tests {
     }
 
     #[actix_rt::test]
-    async fn delete_works() {
+    async fn patch_works() {
         let node_id = uuid::Uuid::new_v4();
+        let cluster_id = uuid::Uuid::new_v4();
+        let expected = NodePatch {
+            cluster_id: Some(cluster_id),
+            bmc_endpoint: Some(None),
+            ..Default::default()
+        };
+
+        let mut repo = MockNodeRepository::default();
+        repo.expect_patch_node()
+            .withf(move |_, patch, _| *patch == expected)
+            .returning(|id, patch, _| {
+                let before = create_test_node(*id, "NODE_NAME".to_string());
+                let mut node = before.clone();
+                patch.apply(&mut node);
+                Ok(Change {
+                    before,
+                    after: node,
+                })
+            });
+
+        let result = patch(
+            web::Path::from(node_id),
+            web::Json(serde_json::json!({ "cluster_id": cluster_id, "bmc_endpoint": null })),
+            ExpectedVersion::default(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
+
+        let body = result.into_body().try_into_bytes().unwrap();
+        let node = serde_json::from_slice::<'_, Node>(&body).ok().unwrap();
+
+        assert_eq!(node.id, node_id);
+        assert_eq!(node.name, "NODE_NAME");
+        assert_eq!(node.cluster_id, cluster_id);
+    }
+
+    #[actix_rt::test]
+    async fn patch_rejects_invalid_patches() {
+        for body in [
+            serde_json::json!({ "id": uuid::Uuid::new_v4() }),
+            serde_json::json!({ "created_at": "2022-04-01T00:00:00Z" }),
+            serde_json::json!({ "name": null }),
+            // the status only changes through operations
+            serde_json::json!({ "status": "poweroff" }),
+        ] {
+            let mut repo = MockNodeRepository::default();
+            repo.expect_patch_node().never();
+
+            let result = patch(
+                web::Path::from(uuid::Uuid::new_v4()),
+                web::Json(body),
+                ExpectedVersion::default(),
+                rbac::test_grants(),
+                audit::test_audit(),
+                Scoped::shared(repo),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(result.status_code(), StatusCode::BAD_REQUEST);
+        }
+    }
 
+    async fn prepare_patch_response(req: Request) -> ServiceResponse {
         let mut repo = MockNodeRepository::default();
-        repo.expect_delete_node().returning(|id| Ok(id.to_owned()));
+        repo.expect_patch_node().returning(|id, patch, _| {
+            let before = create_test_node(*id, "NODE_NAME".to_string());
+            let mut node = before.clone();
+            patch.apply(&mut node);
+            Ok(Change {
+                before,
+                after: node,
+            })
+        });
+
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockNodeRepository>);
 
-        let result = delete(web::Path::from(node_id), web::Data::new(repo)).await;
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
+    }
+
+    #[actix_rt::test]
+    async fn patch_integration_works() {
+        let node_id = uuid::Uuid::new_v4();
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", PATH, node_id))
+            .insert_header(("Content-Type", "application/merge-patch+json"))
+            .set_payload(r#"{"driver": "redfish"}"#)
+            .insert_header(valid_bearer())
+            .to_request();
+
+        let res = prepare_patch_response(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let node = serde_json::from_slice::<'_, Node>(&body).ok().unwrap();
+
+        assert_eq!(node.id, node_id);
+        assert_eq!(node.driver, PowerDriverKind::Redfish);
+    }
+
+    #[actix_rt::test]
+    async fn patch_integration_fails_if_no_authentication() {
+        let req = actix_web::test::TestRequest::patch()
+            .uri(&format!("{}/{}", PATH, uuid::Uuid::new_v4()))
+            .set_json(serde_json::json!({ "driver": "redfish" }))
+            .to_request();
+        let res = prepare_patch_response(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn delete_works() {
+        let node_id = uuid::Uuid::new_v4();
+
+        let mut repo = MockNodeRepository::default();
+        repo.expect_delete_node()
+            .returning(|id, _| Ok(create_test_node(*id, "NODE_NAME".to_string())));
+
+        let result = delete(
+            web::Path::from(node_id),
+            ExpectedVersion::default(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
 
         let body = result.into_body().try_into_bytes().unwrap();
         let id = std::str::from_utf8(&body).ok().unwrap();
@@ -475,14 +965,16 @@ mod tests {
 
     async fn prepare_delete_response(req: Request) -> ServiceResponse {
         let mut repo = MockNodeRepository::default();
-        repo.expect_delete_node().returning(|id| Ok(id.to_owned()));
+        repo.expect_delete_node()
+            .returning(|id, _| Ok(create_test_node(*id, "NODE_NAME".to_string())));
 
         let app = App::new()
+            .app_data(auth::test_authenticator())
             .app_data(web::Data::new(repo))
             .configure(configuration::<MockNodeRepository>);
 
-        let mut svc = actix_web::test::init_service(app).await;
-        actix_web::test::call_service(&mut svc, req).await
+        let svc = actix_web::test::init_service(app).await;
+        actix_web::test::call_service(&svc, req).await
     }
 
     #[actix_rt::test]
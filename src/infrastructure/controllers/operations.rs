@@ -1,113 +1,289 @@
 use crate::{
-    application::operation_service::{OperationService, OperationServiceResult},
-    domain::repository::NodeRepository,
-    infrastructure::auth,
+    application::{
+        operation_service::{
+            BatchRequest, BatchTarget, OperationOptions, OperationService, OperationServiceResult,
+        },
+        rbac_service,
+    },
+    domain::{
+        models::{Grants, OperationType, Permission},
+        repository::{node_repository::OperationFilter, NodeRepository},
+    },
+    infrastructure::{audit::Audit, auth, idempotency::Idempotency, rbac::Authorize},
 };
 use actix_web::{
+    dev::ServiceRequest,
+    http::{Method, StatusCode},
     web::{self, PathConfig},
-    HttpResponse,
+    HttpResponse, ResponseError,
 };
 use actix_web_httpauth::middleware::HttpAuthentication;
+use std::collections::HashSet;
 use tracing::instrument;
 use uuid::Uuid;
 use web::ServiceConfig;
 
-use super::path_config_handler;
+use super::{check_node, path_config_handler, ApiError, Scoped};
 
 const PATH: &str = "/v1/operations";
 
 pub fn configuration<R: NodeRepository>(cfg: &mut ServiceConfig) {
     cfg.service(
         web::scope(PATH)
+            .wrap(Idempotency)
+            .wrap(Authorize::new(required_permissions))
             .wrap(HttpAuthentication::bearer(auth::validator))
             .app_data(PathConfig::default().error_handler(path_config_handler))
             // POST
             .route("/poweron", web::post().to(post_poweron::<R>))
             .route("/poweroff", web::post().to(post_poweroff::<R>))
-            .route("/reboot", web::post().to(post_reboot::<R>)),
+            .route("/reboot", web::post().to(post_reboot::<R>))
+            .route("/batches", web::post().to(post_batch::<R>))
+            // GET, after the paths above so the metrics don't take them for an operation id
+            .route("", web::get().to(get_all::<R>))
+            .route("/batches/{batch_id}", web::get().to(get_batch::<R>))
+            .route("/{operation_id}", web::get().to(get::<R>)),
     );
 }
 
-fn to_response(operation_result: OperationServiceResult) -> HttpResponse {
-    match operation_result {
-        Ok(operation) => HttpResponse::Created().json(operation),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Something went wrong: {}", e)),
+fn required_permissions(req: &ServiceRequest) -> &'static [Permission] {
+    if *req.method() == Method::GET {
+        return &[Permission::OperationsRead];
     }
+    match req.path().rsplit('/').next() {
+        Some("poweron") => &[Permission::OperationsPowerOn],
+        Some("poweroff") => &[Permission::OperationsPowerOff],
+        Some("reboot") => &[Permission::OperationsReboot],
+        _ => &[
+            Permission::OperationsPowerOn,
+            Permission::OperationsPowerOff,
+            Permission::OperationsReboot,
+        ],
+    }
+}
+
+fn to_response(operation_result: OperationServiceResult) -> Result<HttpResponse, ApiError> {
+    let operation = operation_result?;
+    Ok(HttpResponse::Accepted().json(operation))
+}
+
+#[instrument(skip(repo))]
+async fn get_all<R: NodeRepository>(
+    filter: web::Query<OperationFilter>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let permission = Permission::OperationsRead;
+    match (&filter.cluster_id, &filter.node_id) {
+        (Some(cluster_id), _) => rbac_service::check(&grants, permission, cluster_id)?,
+        (None, Some(node_id)) => check_node(&grants, permission, node_id, &*repo).await?,
+        (None, None) => rbac_service::check_everywhere(&grants, permission)?,
+    }
+    let operations = repo.get_operations(&filter).await?;
+    Ok(HttpResponse::Ok().json(operations))
 }
 
-#[instrument(skip(svc))]
+#[instrument(skip(repo))]
+async fn get<R: NodeRepository>(
+    operation_id: web::Path<Uuid>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    let operation = repo.get_operation(&operation_id).await?;
+    check_node(
+        &grants,
+        Permission::OperationsRead,
+        &operation.node_id,
+        &*repo,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(operation))
+}
+
+#[instrument(skip(audit, repo, svc))]
 async fn post_poweron<R: NodeRepository>(
     node_id: web::Json<Uuid>,
-    svc: web::Data<OperationService<R>>,
-) -> HttpResponse {
-    to_response(svc.power_on(&node_id).await)
+    options: web::Query<OperationOptions>,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+    svc: Scoped<OperationService<R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_operation(&grants, OperationType::PowerOn, &node_id, &*repo).await?;
+    to_response(
+        audit
+            .scope(svc.power_on(&node_id, options.into_inner()))
+            .await,
+    )
 }
 
-#[instrument(skip(svc))]
+#[instrument(skip(audit, repo, svc))]
 async fn post_poweroff<R: NodeRepository>(
     node_id: web::Json<Uuid>,
-    svc: web::Data<OperationService<R>>,
-) -> HttpResponse {
-    to_response(svc.power_off(&node_id).await)
+    options: web::Query<OperationOptions>,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+    svc: Scoped<OperationService<R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_operation(&grants, OperationType::PowerOff, &node_id, &*repo).await?;
+    to_response(
+        audit
+            .scope(svc.power_off(&node_id, options.into_inner()))
+            .await,
+    )
 }
 
-#[instrument(skip(svc))]
+#[instrument(skip(audit, repo, svc))]
 async fn post_reboot<R: NodeRepository>(
     node_id: web::Json<Uuid>,
-    svc: web::Data<OperationService<R>>,
-) -> HttpResponse {
-    let r = svc.reboot(&node_id).await;
-    // start a new thread to simulate poweron in a few seconds
-    actix_web::rt::spawn(async move {
-        actix_web::rt::time::sleep(std::time::Duration::from_secs(5)).await;
-        if let Err(e) = svc.power_on_without_operation(&node_id).await {
-            tracing::error!("Error powering on after rebooting: {:?}", e);
+    options: web::Query<OperationOptions>,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+    svc: Scoped<OperationService<R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_operation(&grants, OperationType::Reboot, &node_id, &*repo).await?;
+    to_response(
+        audit
+            .scope(svc.reboot(&node_id, options.into_inner()))
+            .await,
+    )
+}
+
+/// A batch can span several clusters, so only the callers that can read the operations
+/// of all of them can see it.
+#[instrument(skip(repo))]
+async fn get_batch<R: NodeRepository>(
+    batch_id: web::Path<Uuid>,
+    grants: Grants,
+    repo: Scoped<R>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check_everywhere(&grants, Permission::OperationsRead)?;
+    let batch = repo.get_batch(&batch_id).await?;
+    Ok(HttpResponse::Ok().json(batch))
+}
+
+#[instrument(skip(audit, repo, svc))]
+async fn post_batch<R: NodeRepository>(
+    request: web::Json<BatchRequest>,
+    options: web::Query<OperationOptions>,
+    grants: Grants,
+    audit: Audit,
+    repo: Scoped<R>,
+    svc: Scoped<OperationService<R>>,
+) -> Result<HttpResponse, ApiError> {
+    let permission = Permission::for_operation(request.operation_type);
+    match &request.target {
+        BatchTarget::ClusterId(cluster_id) => rbac_service::check(&grants, permission, cluster_id)?,
+        BatchTarget::Filter(filter) => match &filter.cluster_id {
+            Some(cluster_id) => rbac_service::check(&grants, permission, cluster_id)?,
+            None => rbac_service::check_everywhere(&grants, permission)?,
+        },
+        // the unknown nodes get through, so the service reports them
+        BatchTarget::NodeIds(_) if grants.allows_everywhere(permission) => {}
+        BatchTarget::NodeIds(node_ids) => {
+            let nodes = repo.get_nodes_by_id(node_ids).await?;
+            let cluster_ids: HashSet<Uuid> = nodes.iter().map(|node| node.cluster_id).collect();
+            for cluster_id in &cluster_ids {
+                rbac_service::check(&grants, permission, cluster_id)?;
+            }
         }
-    });
-    to_response(r)
+    }
+    let result = audit
+        .scope(svc.create_batch(&request, options.into_inner()))
+        .await?;
+    Ok(HttpResponse::Accepted().json(result))
+}
+
+/// Fails unless the operation can be run in the cluster of the node. An unknown node
+/// gets through, so the service reports it as it does for everyone else.
+async fn check_operation<R: NodeRepository>(
+    grants: &Grants,
+    operation_type: OperationType,
+    node_id: &Uuid,
+    repo: &R,
+) -> Result<(), ApiError> {
+    match check_node(
+        grants,
+        Permission::for_operation(operation_type),
+        node_id,
+        repo,
+    )
+    .await
+    {
+        Err(error) if error.status_code() == StatusCode::NOT_FOUND => Ok(()),
+        result => result,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-
-    use std::time::Duration;
-
-    use crate::domain::{
-        models::{Node, NodeStatus, Operation, OperationType},
-        repository::{node_repository::MockNodeRepository, RepositoryError},
+    use crate::infrastructure::{audit, rbac};
+
+    use crate::{
+        application::power_driver::PowerDrivers,
+        domain::{
+            models::{
+                Batch, BatchItem, BatchResult, Node, NodeStatus, Operation, OperationStatus,
+                OperationType, PowerDriverKind,
+            },
+            repository::{node_repository::MockNodeRepository, RepositoryError},
+        },
     };
 
     use super::*;
-    use actix_web::{body::MessageBody, http::StatusCode};
+    use crate::{
+        application::rbac_service::{GrantsResolver, RbacService},
+        domain::{
+            models::{Role, RoleBinding},
+            repository::role_binding_repository::MockRoleBindingRepository,
+        },
+        infrastructure::{
+            controllers::api_error::{Problem, PROBLEM_JSON},
+            request_id::RequestId,
+        },
+    };
+    use actix_web::{
+        body::MessageBody,
+        http::{header, StatusCode},
+        ResponseError,
+    };
     use chrono::Utc;
+    use std::{sync::Arc, time::Duration};
 
-    fn create_test_node(id: uuid::Uuid, name: String) -> Node {
+    fn create_test_node(id: uuid::Uuid, name: String, status: NodeStatus) -> Node {
         Node {
             id,
             name,
             cluster_id: uuid::Uuid::new_v4(),
-            status: NodeStatus::PowerOn,
+            status,
+            driver: PowerDriverKind::Simulated,
+            bmc_endpoint: None,
             created_at: Some(Utc::now()),
             updated_at: None,
+            version: 0,
         }
     }
 
-    fn prepare_operation_svc() -> OperationService<MockNodeRepository> {
+    fn prepare_operation_svc(status: NodeStatus) -> OperationService<MockNodeRepository> {
         let mut node_repo = MockNodeRepository::default();
         node_repo.expect_get_node().returning(move |id| {
-            let node = create_test_node(*id, "my_node".to_string());
+            let node = create_test_node(*id, "my_node".to_string(), status);
             Ok(node)
         });
-
-        node_repo
-            .expect_update_node()
-            .returning(|node| Ok(node.clone()));
+        node_repo.expect_get_nodes_by_id().returning(move |ids| {
+            Ok(ids
+                .iter()
+                .map(|id| create_test_node(*id, "my_node".to_string(), status))
+                .collect())
+        });
 
         node_repo
             .expect_create_operation()
             .returning(|op| Ok(op.clone()));
 
-        OperationService::new(node_repo)
+        OperationService::new(node_repo, PowerDrivers::new())
     }
 
     fn prepare_operation_svc_with_error() -> OperationService<MockNodeRepository> {
@@ -117,14 +293,27 @@ mod tests {
             .once()
             .returning(|_| Err(RepositoryError::DoesNotExist));
 
-        OperationService::new(node_repo)
+        OperationService::new(node_repo, PowerDrivers::new())
+    }
+
+    fn no_options() -> web::Query<OperationOptions> {
+        web::Query(OperationOptions::default())
     }
 
     #[actix_rt::test]
     async fn poweron_works() {
         let node_id = uuid::Uuid::new_v4();
-        let svc = prepare_operation_svc();
-        let res = post_poweron(web::Json(node_id), web::Data::new(svc)).await;
+        let svc = prepare_operation_svc(NodeStatus::PowerOff);
+        let res = post_poweron(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap();
 
         let body = res.into_body().try_into_bytes().unwrap();
         let operation = serde_json::from_slice::<'_, Operation>(&body).ok().unwrap();
@@ -137,15 +326,33 @@ mod tests {
     async fn poweron_errors_properly() {
         let node_id = uuid::Uuid::new_v4();
         let svc = prepare_operation_svc_with_error();
-        let res = post_poweron(web::Json(node_id), web::Data::new(svc)).await;
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let res = post_poweron(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(res.status_code(), StatusCode::NOT_FOUND);
     }
 
     #[actix_rt::test]
     async fn poweroff_works() {
         let node_id = uuid::Uuid::new_v4();
-        let svc = prepare_operation_svc();
-        let res = post_poweroff(web::Json(node_id), web::Data::new(svc)).await;
+        let svc = prepare_operation_svc(NodeStatus::PowerOn);
+        let res = post_poweroff(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap();
 
         let body = res.into_body().try_into_bytes().unwrap();
         let operation = serde_json::from_slice::<'_, Operation>(&body).ok().unwrap();
@@ -158,49 +365,439 @@ mod tests {
     async fn poweroff_errors_properly() {
         let node_id = uuid::Uuid::new_v4();
         let svc = prepare_operation_svc_with_error();
-        let res = post_poweroff(web::Json(node_id), web::Data::new(svc)).await;
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let res = post_poweroff(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(res.status_code(), StatusCode::NOT_FOUND);
     }
 
     #[actix_rt::test]
     async fn reboot_works() {
-        let mut node_repo = MockNodeRepository::default();
+        let node_id = uuid::Uuid::new_v4();
+        let svc = prepare_operation_svc(NodeStatus::PowerOn);
+        let res = post_reboot(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let operation = serde_json::from_slice::<'_, Operation>(&body).ok().unwrap();
+
+        assert_eq!(operation.node_id, node_id);
+        assert_eq!(operation.operation_type, OperationType::Reboot);
+        assert_eq!(operation.status, OperationStatus::Pending);
+    }
 
+    #[actix_rt::test]
+    async fn reboot_errors_properly() {
         let node_id = uuid::Uuid::new_v4();
+        let svc = prepare_operation_svc_with_error();
+        let res = post_reboot(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(res.status_code(), StatusCode::NOT_FOUND);
+    }
 
-        node_repo.expect_get_node().times(2).returning(move |id| {
-            let node = create_test_node(*id, "my_node".to_string());
-            Ok(node)
-        });
+    #[actix_rt::test]
+    async fn poweron_conflicts_if_node_is_already_on() {
+        let node_id = uuid::Uuid::new_v4();
+        let svc = prepare_operation_svc(NodeStatus::PowerOn);
+        let res = post_poweron(
+            web::Json(node_id),
+            no_options(),
+            rbac::test_grants(),
+            audit::test_audit(),
+            Scoped::shared(MockNodeRepository::default()),
+            Scoped::shared(svc),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(res.status_code(), StatusCode::CONFLICT);
+    }
 
-        node_repo
-            .expect_update_node()
-            .once()
-            .returning(|node| Ok(node.clone()));
+    #[actix_rt::test]
+    async fn reboot_integration_can_be_forced() {
+        let svc = prepare_operation_svc(NodeStatus::Rebooting);
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(MockNodeRepository::default()))
+            .app_data(web::Data::new(svc))
+            .configure(configuration::<MockNodeRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/reboot", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(uuid::Uuid::new_v4())
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/reboot?force=true", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(uuid::Uuid::new_v4())
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+    }
 
-        node_repo
+    #[actix_rt::test]
+    async fn post_batch_integration_works() {
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_nodes_by_id().returning(|ids| {
+            Ok(ids
+                .iter()
+                .map(|id| create_test_node(*id, "my_node".to_string(), NodeStatus::PowerOn))
+                .collect())
+        });
+        repo.expect_create_batch()
+            .returning(|batch, operations| Ok((batch.clone(), operations.to_vec())));
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(MockNodeRepository::default()))
+            .app_data(web::Data::new(OperationService::new(
+                repo,
+                PowerDrivers::new(),
+            )))
+            .configure(configuration::<MockNodeRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let node_ids = vec![uuid::Uuid::new_v4(), uuid::Uuid::new_v4()];
+        let body = serde_json::json!({
+            "operation_type": "reboot",
+            "target": { "node_ids": node_ids },
+            "atomic": true
+        });
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/batches", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(&body)
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+        let body = actix_web::test::read_body(res).await;
+        let result = serde_json::from_slice::<'_, BatchResult>(&body).unwrap();
+        assert!(result.batch.atomic);
+        assert_eq!(result.batch.operation_type, OperationType::Reboot);
+        let operations: Vec<_> = result
+            .items
+            .iter()
+            .filter_map(|item| item.operation.as_ref())
+            .map(|operation| operation.node_id)
+            .collect();
+        assert_eq!(operations, node_ids);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/batches", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({
+                "operation_type": "reboot",
+                "target": { "node_ids": [] }
+            }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn post_batch_integration_checks_the_clusters_of_the_nodes() {
+        let (cluster_id, other_cluster_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let (node_id, other_node_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut bindings = MockRoleBindingRepository::default();
+        bindings.expect_get_role_bindings().returning(move |_| {
+            Ok(vec![RoleBinding {
+                id: Uuid::new_v4(),
+                subject: "apikey:jane".to_string(),
+                role: Role::Operator,
+                cluster_id: Some(cluster_id),
+                created_at: None,
+            }])
+        });
+        let resolver: Arc<dyn GrantsResolver> =
+            Arc::new(RbacService::new(bindings, Duration::from_secs(60)));
+        let node = move |id: &Uuid| Node {
+            cluster_id: if *id == node_id {
+                cluster_id
+            } else {
+                other_cluster_id
+            },
+            ..create_test_node(*id, "my_node".to_string(), NodeStatus::PowerOn)
+        };
+        // the nodes are read with a single query
+        let mut repo = MockNodeRepository::default();
+        let known = [node_id, other_node_id];
+        repo.expect_get_nodes_by_id()
+            .times(2)
+            .returning(move |ids| {
+                Ok(ids
+                    .iter()
+                    .filter(|id| known.contains(id))
+                    .map(node)
+                    .collect())
+            });
+        let mut svc_repo = MockNodeRepository::default();
+        svc_repo
+            .expect_get_nodes_by_id()
+            .returning(move |ids| Ok(ids.iter().filter(|id| **id == node_id).map(node).collect()));
+        svc_repo
+            .expect_create_batch()
+            .returning(|batch, operations| Ok((batch.clone(), operations.to_vec())));
+        svc_repo
             .expect_create_operation()
-            .once()
             .returning(|op| Ok(op.clone()));
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator_of("jane", false))
+            .app_data(web::Data::from(resolver))
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(OperationService::new(
+                svc_repo,
+                PowerDrivers::new(),
+            )))
+            .configure(configuration::<MockNodeRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        // the unknown nodes are reported by the service
+        for (node_ids, status) in [
+            (vec![node_id, other_node_id], StatusCode::FORBIDDEN),
+            (vec![node_id, Uuid::new_v4()], StatusCode::ACCEPTED),
+        ] {
+            let req = actix_web::test::TestRequest::post()
+                .uri(&format!("{}/batches", PATH))
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .set_json(serde_json::json!({
+                    "operation_type": "reboot",
+                    "target": { "node_ids": node_ids }
+                }))
+                .to_request();
+            let res = actix_web::test::call_service(&app, req).await;
+            assert_eq!(res.status(), status, "{:?}", node_ids);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn post_batch_integration_reports_the_nodes_that_fail() {
+        let svc = prepare_operation_svc(NodeStatus::PowerOff);
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(MockNodeRepository::default()))
+            .app_data(web::Data::new(svc))
+            .configure(configuration::<MockNodeRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let node_id = uuid::Uuid::new_v4();
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/batches", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({
+                "operation_type": "poweroff",
+                "target": { "node_ids": [node_id] }
+            }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let body = actix_web::test::read_body(res).await;
+        let problem = serde_json::from_slice::<'_, Problem>(&body).unwrap();
+        assert_eq!(problem.code, "batch_rejected");
+        let items: Vec<BatchItem> = serde_json::from_value(problem.items.unwrap()).unwrap();
+        assert_eq!(items[0].node_id, node_id);
+        assert!(items[0].error.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn get_batch_works() {
+        let batch = Batch::new(OperationType::PowerOn, false);
+        let expected = batch.clone();
+
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_batch()
+            .returning(move |_| Ok(batch.clone()));
+
+        let res = get_batch(
+            web::Path::from(expected.id),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let batch = serde_json::from_slice::<'_, Batch>(&body).unwrap();
+        assert_eq!(batch, expected);
+    }
 
-        let svc = OperationService::new(node_repo);
-        let res = post_reboot(web::Json(node_id), web::Data::new(svc)).await;
+    #[actix_rt::test]
+    async fn get_all_works() {
+        let operation = Operation::new(uuid::Uuid::new_v4(), OperationType::Reboot);
+        let expected = operation.clone();
+
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_operations()
+            .withf(|filter| {
+                filter.operation_type == Some(OperationType::Reboot)
+                    && filter.limit() == 10
+                    && filter.offset() == 20
+            })
+            .returning(move |_| Ok(vec![operation.clone()]));
+
+        let filter = OperationFilter {
+            operation_type: Some(OperationType::Reboot),
+            limit: Some(10),
+            offset: Some(20),
+            ..Default::default()
+        };
+        let res = get_all(
+            web::Query(filter),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res.into_body().try_into_bytes().unwrap();
+        let operations = serde_json::from_slice::<'_, Vec<Operation>>(&body)
+            .ok()
+            .unwrap();
+
+        assert_eq!(operations, vec![expected]);
+    }
+
+    #[actix_rt::test]
+    async fn get_works() {
+        let operation = Operation::new(uuid::Uuid::new_v4(), OperationType::PowerOn);
+        let expected = operation.clone();
+
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_operation()
+            .returning(move |_| Ok(operation.clone()));
+
+        let res = get(
+            web::Path::from(expected.id),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
 
         let body = res.into_body().try_into_bytes().unwrap();
         let operation = serde_json::from_slice::<'_, Operation>(&body).ok().unwrap();
 
-        assert_eq!(operation.node_id, node_id);
-        assert_eq!(operation.operation_type, OperationType::Reboot);
+        assert_eq!(operation, expected);
+    }
+
+    #[actix_rt::test]
+    async fn get_returns_not_found() {
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_operation()
+            .returning(|_| Err(RepositoryError::DoesNotExist));
 
-        // waiting >5 secs to check that the node is set to power on
-        actix_rt::time::sleep(Duration::from_secs(6)).await;
+        let res = get(
+            web::Path::from(uuid::Uuid::new_v4()),
+            rbac::test_grants(),
+            Scoped::shared(repo),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(res.status_code(), StatusCode::NOT_FOUND);
     }
 
     #[actix_rt::test]
-    async fn reboot_errors_properly() {
+    async fn get_all_integration_parses_filters() {
         let node_id = uuid::Uuid::new_v4();
-        let svc = prepare_operation_svc_with_error();
-        let res = post_reboot(web::Json(node_id), web::Data::new(svc)).await;
-        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_operations()
+            .withf(move |filter| {
+                filter.node_id == Some(node_id)
+                    && filter.operation_type == Some(OperationType::PowerOff)
+                    && filter.created_after.is_some()
+                    && filter.cluster_id.is_none()
+            })
+            .returning(|_| Ok(vec![]));
+
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockNodeRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!(
+                "{}?node_id={}&operation_type=poweroff&created_after=2022-04-01T00:00:00Z",
+                PATH, node_id
+            ))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn errors_integration_are_problem_json() {
+        let mut repo = MockNodeRepository::default();
+        repo.expect_get_operation()
+            .returning(|_| Err(RepositoryError::DoesNotExist));
+
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .wrap(RequestId)
+            .app_data(web::Data::new(repo))
+            .configure(configuration::<MockNodeRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/{}", PATH, uuid::Uuid::new_v4()))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .insert_header(("X-Request-Id", "request-1"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+
+        let body = actix_web::test::read_body(res).await;
+        let problem = serde_json::from_slice::<'_, Problem>(&body).unwrap();
+        assert_eq!(problem.code, "not_found");
+        assert_eq!(problem.status, 404);
+        assert_eq!(problem.request_id, Some("request-1".to_string()));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/not-an-uuid", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body = actix_web::test::read_body(res).await;
+        let problem = serde_json::from_slice::<'_, Problem>(&body).unwrap();
+        assert_eq!(problem.code, "invalid_path");
+        assert!(problem.request_id.is_some());
     }
 }
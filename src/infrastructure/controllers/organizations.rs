@@ -0,0 +1,203 @@
+use crate::{
+    application::api_key_service::ApiKeyService,
+    domain::{
+        models::{Organization, Permission, Principal},
+        repository::{ApiKeyRepository, OrganizationRepository, RepositoryError},
+    },
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    web::{self, PathConfig},
+    HttpResponse,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use serde::Deserialize;
+use tracing::instrument;
+use uuid::Uuid;
+use web::ServiceConfig;
+
+use super::{path_config_handler, ApiError};
+
+const PATH: &str = "/v1/organizations";
+
+pub fn configuration<O: OrganizationRepository, K: ApiKeyRepository>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope(PATH)
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .app_data(PathConfig::default().error_handler(path_config_handler))
+            // GET
+            .route("", web::get().to(get_all::<O>))
+            .route("/{organization_id}", web::get().to(get::<O>))
+            // POST
+            .route("", web::post().to(post::<O>))
+            // DELETE
+            .route("/{organization_id}", web::delete().to(delete::<O, K>)),
+    );
+}
+
+fn required_permissions(_: &ServiceRequest) -> &'static [Permission] {
+    &[Permission::OrganizationsManage]
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewOrganizationDTO {
+    name: String,
+}
+
+#[instrument(skip(repo))]
+async fn get_all<O: OrganizationRepository>(repo: web::Data<O>) -> Result<HttpResponse, ApiError> {
+    let organizations = repo.get_organizations().await?;
+    Ok(HttpResponse::Ok().json(organizations))
+}
+
+#[instrument(skip(repo))]
+async fn get<O: OrganizationRepository>(
+    organization_id: web::Path<Uuid>,
+    repo: web::Data<O>,
+) -> Result<HttpResponse, ApiError> {
+    let organization = repo.get_organization(&organization_id).await?;
+    Ok(HttpResponse::Ok().json(organization))
+}
+
+#[instrument(skip(repo))]
+async fn post<O: OrganizationRepository>(
+    new_organization: web::Json<NewOrganizationDTO>,
+    caller: Principal,
+    repo: web::Data<O>,
+) -> Result<HttpResponse, ApiError> {
+    if new_organization.name.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            "invalid_organization",
+            "The name of an organization can't be empty",
+        ));
+    }
+    let organization = repo
+        .create_organization(&Organization {
+            id: Uuid::new_v4(),
+            name: new_organization.into_inner().name,
+            created_at: None,
+        })
+        .await?;
+    tracing::info!(
+        "Organization {} created by {}",
+        organization.id,
+        caller.subject
+    );
+    Ok(HttpResponse::Created().json(organization))
+}
+
+/// The clusters of the organization must be deleted first. Its API keys are deleted
+/// with it, and dropped from the cache so they stop working right away.
+#[instrument(skip(repo, api_keys))]
+async fn delete<O: OrganizationRepository, K: ApiKeyRepository>(
+    organization_id: web::Path<Uuid>,
+    caller: Principal,
+    repo: web::Data<O>,
+    api_keys: web::Data<ApiKeyService<K>>,
+) -> Result<HttpResponse, ApiError> {
+    let id = match repo.delete_organization(&organization_id).await {
+        Ok(id) => id,
+        Err(RepositoryError::ForeignKeyViolation) => {
+            return Err(ApiError::conflict(
+                "organization_not_empty",
+                "The organization still has clusters",
+            ))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    api_keys.forget_tenant(&id)?;
+    tracing::info!("Organization {} deleted by {}", id, caller.subject);
+    Ok(HttpResponse::Ok().body(id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repository::{
+        api_key_repository::MockApiKeyRepository,
+        organization_repository::MockOrganizationRepository,
+    };
+    use actix_web::{http::StatusCode, App};
+    use std::time::Duration;
+
+    fn api_key_service() -> web::Data<ApiKeyService<MockApiKeyRepository>> {
+        web::Data::new(ApiKeyService::new(
+            MockApiKeyRepository::default(),
+            Duration::from_secs(60),
+        ))
+    }
+
+    #[actix_rt::test]
+    async fn organizations_are_created_and_deleted() {
+        let mut repo = MockOrganizationRepository::default();
+        repo.expect_create_organization()
+            .withf(|organization| organization.name == "acme")
+            .once()
+            .returning(|organization| Ok(organization.clone()));
+        repo.expect_delete_organization()
+            .once()
+            .returning(|_| Err(RepositoryError::ForeignKeyViolation));
+        repo.expect_delete_organization()
+            .once()
+            .returning(|organization_id| Ok(*organization_id));
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(repo))
+            .app_data(api_key_service())
+            .configure(configuration::<MockOrganizationRepository, MockApiKeyRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        for (name, status) in [
+            ("acme", StatusCode::CREATED),
+            (" ", StatusCode::BAD_REQUEST),
+        ] {
+            let req = actix_web::test::TestRequest::post()
+                .uri(PATH)
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .set_json(serde_json::json!({ "name": name }))
+                .to_request();
+            let res = actix_web::test::call_service(&app, req).await;
+            assert_eq!(res.status(), status, "{:?}", name);
+        }
+
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("{}/{}", PATH, Uuid::new_v4()))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["code"], "organization_not_empty");
+
+        let req = actix_web::test::TestRequest::delete()
+            .uri(&format!("{}/{}", PATH, Uuid::new_v4()))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn tenants_cannot_manage_organizations() {
+        let app = App::new()
+            .app_data(auth::test_tenant_authenticator(
+                "jane",
+                true,
+                Some(Uuid::new_v4()),
+            ))
+            .app_data(web::Data::new(MockOrganizationRepository::default()))
+            .app_data(api_key_service())
+            .configure(configuration::<MockOrganizationRepository, MockApiKeyRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::domain::repository::{
+    pagination::{SortField, SortOrder},
+    PageRequest,
+};
+use serde::{Deserialize, Serialize};
+
+use super::ApiError;
+
+/// Query parameters of the list endpoints.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PageQuery {
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub sort: SortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub with_total: bool,
+}
+
+impl PageQuery {
+    pub fn page_request(&self) -> Result<PageRequest, ApiError> {
+        let page = PageRequest::new(
+            self.limit,
+            self.sort,
+            self.order,
+            self.cursor.as_deref(),
+            self.with_total,
+        )?;
+        Ok(page)
+    }
+}
@@ -0,0 +1,98 @@
+use super::ApiError;
+use crate::domain::repository::RepositoryError;
+use actix_web::{
+    dev::Payload,
+    http::header::{self, EntityTag, Header},
+    FromRequest, HttpRequest,
+};
+use std::future::{ready, Ready};
+
+/// `ETag` of an entity, which is its version.
+pub fn etag(version: i64) -> header::ETag {
+    header::ETag(EntityTag::new_strong(version.to_string()))
+}
+
+/// Versions allowed by the `If-Match` header of a write, which passes if the entity has
+/// any of them.
+///
+/// There's no version to check if the header is missing or it's `*`.
+/// Tags that are weak or aren't versions can never match, so the request fails
+/// with `412` as if the entity had changed if none of the tags is a version.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExpectedVersion(pub Option<Vec<i64>>);
+
+impl ExpectedVersion {
+    fn from_headers(req: &HttpRequest) -> Result<Self, ApiError> {
+        if !req.headers().contains_key(header::IF_MATCH) {
+            return Ok(Self(None));
+        }
+
+        let tags = match header::IfMatch::parse(req) {
+            Ok(header::IfMatch::Any) => return Ok(Self(None)),
+            Ok(header::IfMatch::Items(tags)) => tags,
+            Err(e) => return Err(ApiError::bad_request("invalid_precondition", e.to_string())),
+        };
+        let versions: Vec<i64> = tags
+            .iter()
+            .filter(|tag| !tag.weak)
+            .filter_map(|tag| tag.tag().parse().ok())
+            .collect();
+        if versions.is_empty() {
+            return Err(version_mismatch());
+        }
+        Ok(Self(Some(versions)))
+    }
+}
+
+impl FromRequest for ExpectedVersion {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::from_headers(req))
+    }
+}
+
+fn version_mismatch() -> ApiError {
+    RepositoryError::VersionMismatch.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test::TestRequest, ResponseError};
+
+    fn expected_version(if_match: &str) -> Result<ExpectedVersion, ApiError> {
+        let req = TestRequest::default()
+            .insert_header((header::IF_MATCH, if_match))
+            .to_http_request();
+        ExpectedVersion::from_headers(&req)
+    }
+
+    #[test]
+    fn if_match_is_parsed() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(
+            ExpectedVersion::from_headers(&req),
+            Ok(ExpectedVersion(None))
+        );
+        assert_eq!(expected_version("*"), Ok(ExpectedVersion(None)));
+        assert_eq!(
+            expected_version("\"3\""),
+            Ok(ExpectedVersion(Some(vec![3])))
+        );
+        assert_eq!(
+            expected_version("\"1\", W/\"2\", \"abc\", \"3\""),
+            Ok(ExpectedVersion(Some(vec![1, 3])))
+        );
+    }
+
+    #[test]
+    fn tags_that_are_not_versions_never_match() {
+        // the tags that can't be parsed are left out
+        for if_match in ["W/\"3\"", "\"abc\"", "W/\"1\", \"abc\"", "\"3"] {
+            let error = expected_version(if_match).unwrap_err();
+            assert_eq!(error.status_code(), StatusCode::PRECONDITION_FAILED);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{
+    application::rbac_service::RbacService,
+    domain::{
+        models::{Permission, Principal, Role},
+        repository::RoleBindingRepository,
+    },
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    web::{self, PathConfig, QueryConfig},
+    HttpResponse,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use serde::Deserialize;
+use tracing::instrument;
+use uuid::Uuid;
+use web::ServiceConfig;
+
+use super::{path_config_handler, query_config_handler, ApiError};
+
+const PATH: &str = "/v1/rbac";
+
+pub fn configuration<B: RoleBindingRepository>(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::scope(PATH)
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .app_data(PathConfig::default().error_handler(path_config_handler))
+            .app_data(QueryConfig::default().error_handler(query_config_handler))
+            // GET
+            .route("/role-bindings", web::get().to(get_all::<B>))
+            .route("/role-bindings/{role_binding_id}", web::get().to(get::<B>))
+            // POST
+            .route("/role-bindings", web::post().to(post::<B>))
+            // DELETE
+            .route(
+                "/role-bindings/{role_binding_id}",
+                web::delete().to(delete::<B>),
+            ),
+    );
+}
+
+fn required_permissions(_: &ServiceRequest) -> &'static [Permission] {
+    &[Permission::RbacManage]
+}
+
+#[derive(Debug, Deserialize)]
+struct RoleBindingQuery {
+    subject: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewRoleBindingDTO {
+    subject: String,
+    role: Role,
+    /// Every cluster if there's none.
+    cluster_id: Option<Uuid>,
+}
+
+#[instrument(skip(svc))]
+async fn get_all<B: RoleBindingRepository>(
+    query: web::Query<RoleBindingQuery>,
+    svc: web::Data<RbacService<B>>,
+) -> Result<HttpResponse, ApiError> {
+    let bindings = svc.get_all(query.subject.as_deref()).await?;
+    Ok(HttpResponse::Ok().json(bindings))
+}
+
+#[instrument(skip(svc))]
+async fn get<B: RoleBindingRepository>(
+    role_binding_id: web::Path<Uuid>,
+    svc: web::Data<RbacService<B>>,
+) -> Result<HttpResponse, ApiError> {
+    let binding = svc.get(&role_binding_id).await?;
+    Ok(HttpResponse::Ok().json(binding))
+}
+
+#[instrument(skip(svc))]
+async fn post<B: RoleBindingRepository>(
+    new_binding: web::Json<NewRoleBindingDTO>,
+    caller: Principal,
+    svc: web::Data<RbacService<B>>,
+) -> Result<HttpResponse, ApiError> {
+    let binding = svc
+        .bind(
+            &new_binding.subject,
+            new_binding.role,
+            new_binding.cluster_id,
+        )
+        .await?;
+    tracing::info!(
+        "Role {:?} bound to {} by {}",
+        binding.role,
+        binding.subject,
+        caller.subject
+    );
+    Ok(HttpResponse::Created().json(binding))
+}
+
+#[instrument(skip(svc))]
+async fn delete<B: RoleBindingRepository>(
+    role_binding_id: web::Path<Uuid>,
+    caller: Principal,
+    svc: web::Data<RbacService<B>>,
+) -> Result<HttpResponse, ApiError> {
+    let id = svc.unbind(&role_binding_id).await?;
+    tracing::info!("Role binding {} deleted by {}", id, caller.subject);
+    Ok(HttpResponse::Ok().body(id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::rbac_service::GrantsResolver,
+        domain::{
+            models::{Node, NodeStatus, PowerDriverKind, RoleBinding},
+            repository::{
+                node_repository::MockNodeRepository,
+                role_binding_repository::MockRoleBindingRepository, Page,
+            },
+        },
+        infrastructure::controllers::nodes,
+    };
+    use actix_web::{http::StatusCode, App};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// Mock that keeps the role bindings in `bindings`, so the ones created through the
+    /// API are used right away.
+    fn prepare_repo(bindings: Arc<Mutex<Vec<RoleBinding>>>) -> MockRoleBindingRepository {
+        let mut repo = MockRoleBindingRepository::default();
+        let stored = bindings.clone();
+        repo.expect_get_role_bindings().returning(move |subject| {
+            let bindings = stored.lock().unwrap();
+            Ok(bindings
+                .iter()
+                .filter(|binding| subject.is_none_or(|subject| binding.subject == subject))
+                .cloned()
+                .collect())
+        });
+        repo.expect_create_role_binding().returning(move |binding| {
+            bindings.lock().unwrap().push(binding.clone());
+            Ok(binding.clone())
+        });
+        repo
+    }
+
+    #[actix_rt::test]
+    async fn bindings_are_listed_and_created() {
+        let bindings = Arc::new(Mutex::new(vec![]));
+        let svc = Arc::new(RbacService::new(
+            prepare_repo(bindings.clone()),
+            Duration::from_secs(60),
+        ));
+        let resolver: Arc<dyn GrantsResolver> = svc.clone();
+        let app = App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::from(resolver))
+            .app_data(web::Data::from(svc))
+            .configure(configuration::<MockRoleBindingRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let cluster_id = Uuid::new_v4();
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/role-bindings", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({
+                "subject": "apikey:jane",
+                "role": "operator",
+                "cluster_id": cluster_id,
+            }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        // the subjects say where the callers come from
+        for subject in [" ", "jane", "jwt: "] {
+            let req = actix_web::test::TestRequest::post()
+                .uri(&format!("{}/role-bindings", PATH))
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .set_json(serde_json::json!({ "subject": subject, "role": "viewer" }))
+                .to_request();
+            let res = actix_web::test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{:?}", subject);
+            let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+            assert_eq!(body["code"], "invalid_role_binding");
+        }
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("{}/role-bindings?subject=apikey:jane", PATH))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Vec<RoleBinding> = actix_web::test::read_body_json(res).await;
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].role, Role::Operator);
+        assert_eq!(body[0].cluster_id, Some(cluster_id));
+    }
+
+    #[actix_rt::test]
+    async fn bindings_scope_the_callers_to_their_clusters() {
+        let cluster_id = Uuid::new_v4();
+        let other_cluster_id = Uuid::new_v4();
+        let bindings = Arc::new(Mutex::new(vec![RoleBinding {
+            id: Uuid::new_v4(),
+            subject: "apikey:jane".to_string(),
+            role: Role::Viewer,
+            cluster_id: Some(cluster_id),
+            created_at: None,
+        }]));
+        let resolver: Arc<dyn GrantsResolver> = Arc::new(RbacService::new(
+            prepare_repo(bindings),
+            Duration::from_secs(60),
+        ));
+        let mut node_repo = MockNodeRepository::default();
+        node_repo
+            .expect_get_nodes()
+            .returning(|_, page| Ok(Page::new(vec![], page, None)));
+        node_repo.expect_get_node().returning(move |id| {
+            Ok(Node {
+                id: *id,
+                name: "my_node".to_string(),
+                cluster_id: other_cluster_id,
+                status: NodeStatus::PowerOn,
+                driver: PowerDriverKind::Simulated,
+                bmc_endpoint: None,
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+        });
+        let app = App::new()
+            .app_data(auth::test_authenticator_of("jane", false))
+            .app_data(web::Data::from(resolver))
+            .app_data(web::Data::new(node_repo))
+            .configure(nodes::configuration::<MockNodeRepository>)
+            .configure(configuration::<MockRoleBindingRepository>);
+        let app = actix_web::test::init_service(app).await;
+
+        let cases = [
+            (
+                format!("/v1/nodes?cluster_id={}", cluster_id),
+                StatusCode::OK,
+            ),
+            (
+                format!("/v1/nodes?cluster_id={}", other_cluster_id),
+                StatusCode::FORBIDDEN,
+            ),
+            ("/v1/nodes".to_string(), StatusCode::FORBIDDEN),
+            (
+                format!("/v1/nodes/{}", Uuid::new_v4()),
+                StatusCode::FORBIDDEN,
+            ),
+            (format!("{}/role-bindings", PATH), StatusCode::FORBIDDEN),
+        ];
+        for (uri, status) in cases {
+            let req = actix_web::test::TestRequest::get()
+                .uri(&uri)
+                .insert_header(("Authorization", "Bearer im_a_valid_user"))
+                .to_request();
+            let res = actix_web::test::call_service(&app, req).await;
+            assert_eq!(res.status(), status, "GET {}", uri);
+        }
+    }
+}
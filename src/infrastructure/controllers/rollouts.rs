@@ -0,0 +1,308 @@
+use crate::{
+    application::rollout_service::{RolloutRequest, RolloutService, RolloutServiceResult},
+    domain::{
+        models::{Grants, Permission, Rollout, RolloutProgress},
+        repository::{ClusterRepository, NodeRepository, RolloutRepository},
+    },
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    http::Method,
+    web::{self, PathConfig},
+    HttpResponse, HttpResponseBuilder,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use uuid::Uuid;
+use web::ServiceConfig;
+
+use super::{check_cluster, etag, path_config_handler, ApiError, ExpectedVersion};
+
+const PATH: &str = "/v1/rollouts";
+
+pub fn configuration<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    cfg: &mut ServiceConfig,
+) {
+    cfg.service(
+        web::scope(PATH)
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .app_data(PathConfig::default().error_handler(path_config_handler))
+            // GET
+            .route("/{rollout_id}", web::get().to(get::<C, N, R>))
+            // POST
+            .route("", web::post().to(post::<C, N, R>))
+            .route("/{rollout_id}/pause", web::post().to(pause::<C, N, R>))
+            .route("/{rollout_id}/resume", web::post().to(resume::<C, N, R>))
+            .route("/{rollout_id}/abort", web::post().to(abort::<C, N, R>)),
+    );
+}
+
+fn required_permissions(req: &ServiceRequest) -> &'static [Permission] {
+    match *req.method() {
+        Method::GET => &[Permission::RolloutsRead],
+        _ => &[Permission::RolloutsWrite],
+    }
+}
+
+/// A rollout along with how many of its nodes are in each status.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct RolloutDTO {
+    #[serde(flatten)]
+    rollout: Rollout,
+    progress: RolloutProgress,
+}
+
+/// Fails unless the permission is granted in the cluster of the rollout, which is only
+/// loaded when the permission isn't granted in every cluster or the caller has a tenant.
+async fn check_rollout<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    grants: &Grants,
+    permission: Permission,
+    rollout_id: &Uuid,
+    svc: &RolloutService<N, R>,
+    cluster_repo: &C,
+) -> Result<(), ApiError> {
+    if grants.allows_everywhere(permission) && grants.principal.tenant.is_none() {
+        return Ok(());
+    }
+    let rollout = svc.get(rollout_id).await?;
+    check_cluster(grants, permission, &rollout.cluster_id, cluster_repo).await
+}
+
+fn to_response(
+    mut response: HttpResponseBuilder,
+    rollout_result: RolloutServiceResult,
+) -> Result<HttpResponse, ApiError> {
+    let rollout = rollout_result?;
+    Ok(response
+        .insert_header(etag(rollout.version))
+        .json(RolloutDTO {
+            progress: rollout.progress(),
+            rollout,
+        }))
+}
+
+#[instrument(skip(cluster_repo, svc))]
+async fn get<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    rollout_id: web::Path<Uuid>,
+    grants: Grants,
+    cluster_repo: web::Data<C>,
+    svc: web::Data<RolloutService<N, R>>,
+) -> Result<HttpResponse, ApiError> {
+    let rollout = svc.get(&rollout_id).await?;
+    check_cluster(
+        &grants,
+        Permission::RolloutsRead,
+        &rollout.cluster_id,
+        cluster_repo.get_ref(),
+    )
+    .await?;
+    to_response(HttpResponse::Ok(), Ok(rollout))
+}
+
+#[instrument(skip(cluster_repo, svc))]
+async fn post<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    request: web::Json<RolloutRequest>,
+    grants: Grants,
+    cluster_repo: web::Data<C>,
+    svc: web::Data<RolloutService<N, R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_cluster(
+        &grants,
+        Permission::RolloutsWrite,
+        &request.cluster_id,
+        cluster_repo.get_ref(),
+    )
+    .await?;
+    to_response(HttpResponse::Accepted(), svc.create(&request).await)
+}
+
+#[instrument(skip(cluster_repo, svc))]
+async fn pause<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    rollout_id: web::Path<Uuid>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    cluster_repo: web::Data<C>,
+    svc: web::Data<RolloutService<N, R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_rollout(
+        &grants,
+        Permission::RolloutsWrite,
+        &rollout_id,
+        &svc,
+        cluster_repo.get_ref(),
+    )
+    .await?;
+    let result = svc.pause(&rollout_id, expected_version.0).await;
+    to_response(HttpResponse::Ok(), result)
+}
+
+#[instrument(skip(cluster_repo, svc))]
+async fn resume<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    rollout_id: web::Path<Uuid>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    cluster_repo: web::Data<C>,
+    svc: web::Data<RolloutService<N, R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_rollout(
+        &grants,
+        Permission::RolloutsWrite,
+        &rollout_id,
+        &svc,
+        cluster_repo.get_ref(),
+    )
+    .await?;
+    let result = svc.resume(&rollout_id, expected_version.0).await;
+    to_response(HttpResponse::Ok(), result)
+}
+
+#[instrument(skip(cluster_repo, svc))]
+async fn abort<C: ClusterRepository, N: NodeRepository, R: RolloutRepository>(
+    rollout_id: web::Path<Uuid>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    cluster_repo: web::Data<C>,
+    svc: web::Data<RolloutService<N, R>>,
+) -> Result<HttpResponse, ApiError> {
+    check_rollout(
+        &grants,
+        Permission::RolloutsWrite,
+        &rollout_id,
+        &svc,
+        cluster_repo.get_ref(),
+    )
+    .await?;
+    let result = svc.abort(&rollout_id, expected_version.0).await;
+    to_response(HttpResponse::Ok(), result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::{operation_service::OperationService, power_driver::PowerDrivers},
+        domain::{
+            models::{Node, NodeStatus, PowerDriverKind, RolloutStatus},
+            repository::{
+                cluster_repository::MockClusterRepository, node_repository::MockNodeRepository,
+                rollout_repository::MockRolloutRepository, Page,
+            },
+        },
+    };
+    use actix_web::http::{header, StatusCode};
+
+    type TestService = RolloutService<MockNodeRepository, MockRolloutRepository>;
+
+    fn prepare_app_data(rollout_repo: MockRolloutRepository) -> web::Data<TestService> {
+        let mut node_repo = MockNodeRepository::default();
+        node_repo.expect_get_nodes().returning(|filter, _| {
+            let node = Node {
+                id: Uuid::new_v4(),
+                name: "my_node".to_string(),
+                cluster_id: filter.cluster_id.unwrap(),
+                status: NodeStatus::PowerOn,
+                driver: PowerDriverKind::Simulated,
+                bmc_endpoint: None,
+                created_at: None,
+                updated_at: None,
+                version: 1,
+            };
+            Ok(Page {
+                items: vec![node],
+                next_cursor: None,
+                total: None,
+            })
+        });
+        let operation_service =
+            OperationService::new(MockNodeRepository::default(), PowerDrivers::new());
+        web::Data::new(RolloutService::new(
+            rollout_repo,
+            node_repo,
+            operation_service,
+        ))
+    }
+
+    #[actix_rt::test]
+    async fn post_integration_works() {
+        let mut rollout_repo = MockRolloutRepository::default();
+        rollout_repo.expect_create_rollout().returning(|rollout| {
+            let mut rollout = rollout.clone();
+            rollout.version = 1;
+            Ok(rollout)
+        });
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(MockClusterRepository::default()))
+            .app_data(prepare_app_data(rollout_repo))
+            .configure(
+                configuration::<MockClusterRepository, MockNodeRepository, MockRolloutRepository>,
+            );
+        let app = actix_web::test::init_service(app).await;
+
+        let cluster_id = Uuid::new_v4();
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({ "cluster_id": cluster_id, "max_in_flight": "25%" }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"1\"");
+
+        let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["cluster_id"], cluster_id.to_string());
+        assert_eq!(body["status"], "running");
+        assert_eq!(body["max_in_flight"], 1);
+        assert_eq!(body["progress"]["pending"], 1);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({ "cluster_id": cluster_id, "max_in_flight": "0%" }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn pause_checks_the_version() {
+        let rollout = Rollout {
+            version: 2,
+            ..Rollout::new(Uuid::new_v4(), 1, 0, [Uuid::new_v4()])
+        };
+        let rollout_id = rollout.id;
+        let mut rollout_repo = MockRolloutRepository::default();
+        let stored = rollout.clone();
+        rollout_repo
+            .expect_get_rollout()
+            .returning(move |_| Ok(stored.clone()));
+        rollout_repo
+            .expect_update_rollout()
+            .withf(|_, status, _, version| {
+                *status == RolloutStatus::Paused && *version == Some(vec![1])
+            })
+            .once()
+            .returning(|_, _, _, _| {
+                Err(crate::domain::repository::RepositoryError::VersionMismatch)
+            });
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(MockClusterRepository::default()))
+            .app_data(prepare_app_data(rollout_repo))
+            .configure(
+                configuration::<MockClusterRepository, MockNodeRepository, MockRolloutRepository>,
+            );
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(&format!("{}/{}/pause", PATH, rollout_id))
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .insert_header((header::IF_MATCH, "\"1\""))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{
+    application::{rbac_service, schedule_service::ScheduleService},
+    domain::{
+        models::{
+            Grants, MissedRunPolicy, OperationType, Permission, ScheduleTarget, ScheduledOperation,
+        },
+        repository::{ClusterRepository, NodeRepository, RepositoryError, ScheduleRepository},
+    },
+    infrastructure::{auth, rbac::Authorize},
+};
+use actix_web::{
+    dev::ServiceRequest,
+    http::Method,
+    web::{self, PathConfig},
+    HttpResponse,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::instrument;
+use uuid::Uuid;
+use web::ServiceConfig;
+
+use super::{etag, path_config_handler, ApiError, ExpectedVersion, Scoped};
+
+const PATH: &str = "/v1/schedules";
+
+pub fn configuration<C: ClusterRepository, N: NodeRepository, S: ScheduleRepository>(
+    cfg: &mut ServiceConfig,
+) {
+    cfg.service(
+        web::scope(PATH)
+            .wrap(Authorize::new(required_permissions))
+            .wrap(HttpAuthentication::bearer(auth::validator))
+            .app_data(PathConfig::default().error_handler(path_config_handler))
+            // GET
+            .route("", web::get().to(get_all::<N, S>))
+            .route("/{schedule_id}", web::get().to(get::<C, N, S>))
+            // POST
+            .route("", web::post().to(post::<C, N, S>))
+            // PUT
+            .route("", web::put().to(put::<C, N, S>))
+            // DELETE
+            .route("/{schedule_id}", web::delete().to(delete::<C, N, S>)),
+    );
+}
+
+/// Definition of a schedule. What the scheduler keeps about its runs can't be written, and
+/// is ignored if it's sent back.
+#[derive(Debug, Deserialize)]
+struct ScheduleDTO {
+    id: Uuid,
+    operation_type: OperationType,
+    target: ScheduleTarget,
+    run_at: Option<DateTime<Utc>>,
+    cron: Option<String>,
+    #[serde(default)]
+    missed_run_policy: MissedRunPolicy,
+    enabled: Option<bool>,
+}
+
+impl From<ScheduleDTO> for ScheduledOperation {
+    fn from(dto: ScheduleDTO) -> Self {
+        Self {
+            id: dto.id,
+            operation_type: dto.operation_type,
+            target: dto.target,
+            run_at: dto.run_at,
+            cron: dto.cron,
+            missed_run_policy: dto.missed_run_policy,
+            enabled: dto.enabled.unwrap_or(true),
+            next_run_at: None,
+            last_run_at: None,
+            last_batch_id: None,
+            last_error: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+}
+
+fn required_permissions(req: &ServiceRequest) -> &'static [Permission] {
+    match *req.method() {
+        Method::GET => &[Permission::SchedulesRead],
+        _ => &[Permission::SchedulesWrite],
+    }
+}
+
+/// Fails unless the permissions are granted in the cluster the schedule targets. Writing
+/// a schedule also needs the permission to run its operation there. For the callers with
+/// a tenant, the target must belong to it, so the repositories must be limited to that
+/// tenant.
+///
+/// The target is only loaded when the permissions aren't granted in every cluster or the
+/// caller has a tenant. An unknown node gets through for the callers without one, so the
+/// service reports it.
+async fn check_schedule<C: ClusterRepository, N: NodeRepository>(
+    grants: &Grants,
+    permissions: &[Permission],
+    schedule: &ScheduledOperation,
+    cluster_repo: &C,
+    node_repo: &N,
+) -> Result<(), ApiError> {
+    let tenant = grants.principal.tenant;
+    if tenant.is_none()
+        && permissions
+            .iter()
+            .all(|permission| grants.allows_everywhere(*permission))
+    {
+        return Ok(());
+    }
+    let cluster_id = match schedule.target {
+        ScheduleTarget::ClusterId(cluster_id) => {
+            if tenant.is_some() {
+                cluster_repo.get_cluster(&cluster_id).await?;
+            }
+            cluster_id
+        }
+        ScheduleTarget::NodeId(node_id) => match node_repo.get_node(&node_id).await {
+            Ok(node) => node.cluster_id,
+            Err(RepositoryError::DoesNotExist) if tenant.is_none() => return Ok(()),
+            Err(error) => return Err(error.into()),
+        },
+    };
+    for permission in permissions {
+        rbac_service::check(grants, *permission, &cluster_id)?;
+    }
+    Ok(())
+}
+
+fn write_permissions(schedule: &ScheduledOperation) -> [Permission; 2] {
+    [
+        Permission::SchedulesWrite,
+        Permission::for_operation(schedule.operation_type),
+    ]
+}
+
+/// The callers with a tenant only get the schedules that target it.
+#[instrument(skip(svc))]
+async fn get_all<N: NodeRepository, S: ScheduleRepository>(
+    grants: Grants,
+    svc: web::Data<ScheduleService<N, S>>,
+) -> Result<HttpResponse, ApiError> {
+    rbac_service::check_everywhere(&grants, Permission::SchedulesRead)?;
+    let schedules = svc.get_all(grants.principal.tenant).await?;
+    Ok(HttpResponse::Ok().json(schedules))
+}
+
+#[instrument(skip(cluster_repo, node_repo, svc))]
+async fn get<C: ClusterRepository, N: NodeRepository, S: ScheduleRepository>(
+    schedule_id: web::Path<Uuid>,
+    grants: Grants,
+    cluster_repo: Scoped<C>,
+    node_repo: Scoped<N>,
+    svc: web::Data<ScheduleService<N, S>>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = svc.get(&schedule_id).await?;
+    check_schedule(
+        &grants,
+        &[Permission::SchedulesRead],
+        &schedule,
+        &*cluster_repo,
+        &*node_repo,
+    )
+    .await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(schedule.version))
+        .json(schedule))
+}
+
+#[instrument(skip(cluster_repo, node_repo, svc))]
+async fn post<C: ClusterRepository, N: NodeRepository, S: ScheduleRepository>(
+    schedule: web::Json<ScheduleDTO>,
+    grants: Grants,
+    cluster_repo: Scoped<C>,
+    node_repo: Scoped<N>,
+    svc: web::Data<ScheduleService<N, S>>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = ScheduledOperation::from(schedule.into_inner());
+    check_schedule(
+        &grants,
+        &write_permissions(&schedule),
+        &schedule,
+        &*cluster_repo,
+        &*node_repo,
+    )
+    .await?;
+    let schedule = svc.create(&schedule).await?;
+    Ok(HttpResponse::Created()
+        .insert_header(etag(schedule.version))
+        .json(schedule))
+}
+
+#[instrument(skip(cluster_repo, node_repo, svc))]
+async fn put<C: ClusterRepository, N: NodeRepository, S: ScheduleRepository>(
+    schedule: web::Json<ScheduleDTO>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    cluster_repo: Scoped<C>,
+    node_repo: Scoped<N>,
+    svc: web::Data<ScheduleService<N, S>>,
+) -> Result<HttpResponse, ApiError> {
+    let schedule = ScheduledOperation::from(schedule.into_inner());
+    check_schedule(
+        &grants,
+        &write_permissions(&schedule),
+        &schedule,
+        &*cluster_repo,
+        &*node_repo,
+    )
+    .await?;
+    if !grants.allows_everywhere(Permission::SchedulesWrite) || grants.principal.tenant.is_some() {
+        let existing = svc.get(&schedule.id).await?;
+        let permissions = write_permissions(&existing);
+        check_schedule(
+            &grants,
+            &permissions,
+            &existing,
+            &*cluster_repo,
+            &*node_repo,
+        )
+        .await?;
+    }
+    let schedule = svc.update(&schedule, expected_version.0).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(etag(schedule.version))
+        .json(schedule))
+}
+
+#[instrument(skip(cluster_repo, node_repo, svc))]
+async fn delete<C: ClusterRepository, N: NodeRepository, S: ScheduleRepository>(
+    schedule_id: web::Path<Uuid>,
+    expected_version: ExpectedVersion,
+    grants: Grants,
+    cluster_repo: Scoped<C>,
+    node_repo: Scoped<N>,
+    svc: web::Data<ScheduleService<N, S>>,
+) -> Result<HttpResponse, ApiError> {
+    if !grants.allows_everywhere(Permission::SchedulesWrite) || grants.principal.tenant.is_some() {
+        let schedule = svc.get(&schedule_id).await?;
+        let permissions = [Permission::SchedulesWrite];
+        check_schedule(
+            &grants,
+            &permissions,
+            &schedule,
+            &*cluster_repo,
+            &*node_repo,
+        )
+        .await?;
+    }
+    let id = svc.delete(&schedule_id, expected_version.0).await?;
+    Ok(HttpResponse::Ok().body(id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        application::{operation_service::OperationService, power_driver::PowerDrivers},
+        domain::repository::{
+            cluster_repository::MockClusterRepository, node_repository::MockNodeRepository,
+            schedule_repository::MockScheduleRepository,
+        },
+    };
+    use actix_web::http::{header, StatusCode};
+
+    type TestService = ScheduleService<MockNodeRepository, MockScheduleRepository>;
+
+    fn prepare_app_data(schedule_repo: MockScheduleRepository) -> web::Data<TestService> {
+        let operation_service =
+            OperationService::new(MockNodeRepository::default(), PowerDrivers::new());
+        web::Data::new(ScheduleService::new(schedule_repo, operation_service))
+    }
+
+    #[actix_rt::test]
+    async fn post_integration_works() {
+        let mut schedule_repo = MockScheduleRepository::default();
+        schedule_repo
+            .expect_create_schedule()
+            .returning(|schedule| {
+                let mut schedule = schedule.clone();
+                schedule.version = 1;
+                Ok(schedule)
+            });
+        let app = actix_web::App::new()
+            .app_data(auth::test_authenticator())
+            .app_data(web::Data::new(MockClusterRepository::default()))
+            .app_data(web::Data::new(MockNodeRepository::default()))
+            .app_data(prepare_app_data(schedule_repo))
+            .configure(
+                configuration::<MockClusterRepository, MockNodeRepository, MockScheduleRepository>,
+            );
+        let app = actix_web::test::init_service(app).await;
+
+        let cluster_id = Uuid::new_v4();
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({
+                "id": Uuid::new_v4(),
+                "operation_type": "reboot",
+                "target": { "cluster_id": cluster_id },
+                "cron": "0 3 * * Sun",
+                "last_error": "can't be written",
+            }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(res.headers().get(header::ETAG).unwrap(), "\"1\"");
+
+        let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["target"]["cluster_id"], cluster_id.to_string());
+        assert_eq!(body["missed_run_policy"], "run_once");
+        assert_eq!(body["enabled"], true);
+        assert!(body["next_run_at"].is_string());
+        assert_eq!(body["last_error"], serde_json::Value::Null);
+
+        let req = actix_web::test::TestRequest::post()
+            .uri(PATH)
+            .insert_header(("Authorization", "Bearer im_a_valid_user"))
+            .set_json(serde_json::json!({
+                "id": Uuid::new_v4(),
+                "operation_type": "reboot",
+                "target": { "cluster_id": cluster_id },
+                "run_at": "2020-01-01T00:00:00Z",
+            }))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = actix_web::test::read_body_json(res).await;
+        assert_eq!(body["code"], "invalid_schedule");
+    }
+}
@@ -0,0 +1,52 @@
+use super::{
+    entities::{DbAuditAction, DbAuditOutcome, DbAuditTargetType},
+    query_builder::QueryBuilder,
+};
+use crate::domain::repository::audit_repository::AuditFilter;
+
+pub(super) const AUDIT_EVENT_COLUMNS: &str = "id, actor, tenant_id, action, target_type, target_id, before, after, request_id, source_ip, outcome, reason, created_at";
+
+/// Tenant of the cluster of the target, or of the snapshots and then of the caller if
+/// the target is gone. It's the value of the `tenant_id` of an insert binding the same
+/// parameters as the columns, with the cluster of the event as `$13`.
+pub(super) const TENANT_OF_TARGET: &str = r#"COALESCE(
+                (SELECT tenant_id FROM clusters WHERE id = $13),
+                (SELECT tenant_id FROM nodes WHERE id = $6),
+                (SELECT n.tenant_id FROM operations o JOIN nodes n ON n.id = o.node_id WHERE o.id = $6),
+                $3
+            )"#;
+
+pub(super) fn filter_conditions(filter: &AuditFilter) -> QueryBuilder {
+    let mut builder = QueryBuilder::default();
+    if let Some(actor) = &filter.actor {
+        builder.and_where("actor = ?", vec![actor.clone().into()]);
+    }
+    if let Some(tenant_id) = filter.tenant_id {
+        builder.and_where("tenant_id = ?", vec![tenant_id.into()]);
+    }
+    if let Some(action) = filter.action {
+        builder.and_where("action = ?", vec![DbAuditAction::from(action).into()]);
+    }
+    if let Some(target_type) = filter.target_type {
+        builder.and_where(
+            "target_type = ?",
+            vec![DbAuditTargetType::from(target_type).into()],
+        );
+    }
+    if let Some(target_id) = filter.target_id {
+        builder.and_where("target_id = ?", vec![target_id.into()]);
+    }
+    if let Some(outcome) = filter.outcome {
+        builder.and_where("outcome = ?", vec![DbAuditOutcome::from(outcome).into()]);
+    }
+    if let Some(request_id) = &filter.request_id {
+        builder.and_where("request_id = ?", vec![request_id.clone().into()]);
+    }
+    if let Some(created_after) = filter.created_after {
+        builder.and_where("created_at >= ?", vec![created_after.into()]);
+    }
+    if let Some(created_before) = filter.created_before {
+        builder.and_where("created_at < ?", vec![created_before.into()]);
+    }
+    builder
+}
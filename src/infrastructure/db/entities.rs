@@ -3,7 +3,13 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-use crate::domain::models::{Node, NodeStatus, Operation, OperationType};
+use crate::domain::models::{
+    ApiKey, AuditAction, AuditEvent, AuditOutcome, AuditTargetType, Batch, Cluster, ClusterSummary,
+    IdempotencyRecord, IdempotentResponse, MaintenanceWindow, MissedRunPolicy, Node, NodeStatus,
+    NodeStatusCounts, Operation, OperationStatus, OperationType, Organization, PowerDriverKind,
+    Role, RoleBinding, Rollout, RolloutNode, RolloutNodeStatus, RolloutStatus, ScheduleTarget,
+    ScheduledOperation,
+};
 
 #[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "operation_type", rename_all = "lowercase")]
@@ -36,11 +42,54 @@ impl From<DbOperationType> for OperationType {
     }
 }
 
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "operation_status", rename_all = "lowercase")]
+pub enum DbOperationStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "succeeded")]
+    Succeeded,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+impl From<OperationStatus> for DbOperationStatus {
+    fn from(status: OperationStatus) -> Self {
+        match status {
+            OperationStatus::Pending => DbOperationStatus::Pending,
+            OperationStatus::Running => DbOperationStatus::Running,
+            OperationStatus::Succeeded => DbOperationStatus::Succeeded,
+            OperationStatus::Failed => DbOperationStatus::Failed,
+        }
+    }
+}
+
+impl From<DbOperationStatus> for OperationStatus {
+    fn from(status: DbOperationStatus) -> Self {
+        match status {
+            DbOperationStatus::Pending => OperationStatus::Pending,
+            DbOperationStatus::Running => OperationStatus::Running,
+            DbOperationStatus::Succeeded => OperationStatus::Succeeded,
+            DbOperationStatus::Failed => OperationStatus::Failed,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq, Eq)]
 pub struct DbOperation {
     pub id: Uuid,
     pub node_id: Uuid,
     pub operation_type: DbOperationType,
+    pub status: DbOperationStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub failure_reason: Option<String>,
+    pub attempts: i32,
+    pub batch_id: Option<Uuid>,
+    pub forced: bool,
+    pub overrides_maintenance: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -51,6 +100,14 @@ impl From<Operation> for DbOperation {
             id: op.id,
             node_id: op.node_id,
             operation_type: op.operation_type.into(),
+            status: op.status.into(),
+            started_at: op.started_at,
+            finished_at: op.finished_at,
+            failure_reason: op.failure_reason,
+            attempts: op.attempts,
+            batch_id: op.batch_id,
+            forced: op.forced,
+            overrides_maintenance: op.overrides_maintenance,
             created_at: op.created_at,
             updated_at: op.updated_at,
         }
@@ -63,12 +120,39 @@ impl From<DbOperation> for Operation {
             id: op.id,
             node_id: op.node_id,
             operation_type: op.operation_type.into(),
+            status: op.status.into(),
+            started_at: op.started_at,
+            finished_at: op.finished_at,
+            failure_reason: op.failure_reason,
+            attempts: op.attempts,
+            batch_id: op.batch_id,
+            forced: op.forced,
+            overrides_maintenance: op.overrides_maintenance,
             created_at: op.created_at,
             updated_at: op.updated_at,
         }
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq, Eq)]
+pub struct DbBatch {
+    pub id: Uuid,
+    pub operation_type: DbOperationType,
+    pub atomic: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<DbBatch> for Batch {
+    fn from(batch: DbBatch) -> Self {
+        Self {
+            id: batch.id,
+            operation_type: batch.operation_type.into(),
+            atomic: batch.atomic,
+            created_at: batch.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "node_status", rename_all = "lowercase")]
 pub enum DbNodeStatus {
@@ -100,14 +184,48 @@ impl From<DbNodeStatus> for NodeStatus {
     }
 }
 
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "power_driver", rename_all = "lowercase")]
+pub enum DbPowerDriverKind {
+    #[serde(rename = "simulated")]
+    Simulated,
+    #[serde(rename = "ipmi")]
+    Ipmi,
+    #[serde(rename = "redfish")]
+    Redfish,
+}
+
+impl From<PowerDriverKind> for DbPowerDriverKind {
+    fn from(kind: PowerDriverKind) -> Self {
+        match kind {
+            PowerDriverKind::Simulated => DbPowerDriverKind::Simulated,
+            PowerDriverKind::Ipmi => DbPowerDriverKind::Ipmi,
+            PowerDriverKind::Redfish => DbPowerDriverKind::Redfish,
+        }
+    }
+}
+
+impl From<DbPowerDriverKind> for PowerDriverKind {
+    fn from(kind: DbPowerDriverKind) -> Self {
+        match kind {
+            DbPowerDriverKind::Simulated => PowerDriverKind::Simulated,
+            DbPowerDriverKind::Ipmi => PowerDriverKind::Ipmi,
+            DbPowerDriverKind::Redfish => PowerDriverKind::Redfish,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq, Eq)]
 pub struct DbNode {
     pub id: Uuid,
     pub name: String,
     pub cluster_id: Uuid,
     pub status: DbNodeStatus,
+    pub driver: DbPowerDriverKind,
+    pub bmc_endpoint: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub version: i64,
 }
 
 impl From<Node> for DbNode {
@@ -117,8 +235,11 @@ impl From<Node> for DbNode {
             name: node.name,
             cluster_id: node.cluster_id,
             status: node.status.into(),
+            driver: node.driver.into(),
+            bmc_endpoint: node.bmc_endpoint,
             created_at: node.created_at,
             updated_at: node.updated_at,
+            version: node.version,
         }
     }
 }
@@ -130,8 +251,543 @@ impl From<DbNode> for Node {
             name: node.name,
             cluster_id: node.cluster_id,
             status: node.status.into(),
+            driver: node.driver.into(),
+            bmc_endpoint: node.bmc_endpoint,
             created_at: node.created_at,
             updated_at: node.updated_at,
+            version: node.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct DbCluster {
+    pub id: Uuid,
+    pub name: String,
+    pub tenant_id: Option<Uuid>,
+    pub maintenance_windows: sqlx::types::Json<Vec<MaintenanceWindow>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub version: i64,
+}
+
+impl From<DbCluster> for Cluster {
+    fn from(cluster: DbCluster) -> Self {
+        Self {
+            id: cluster.id,
+            name: cluster.name,
+            tenant_id: cluster.tenant_id,
+            maintenance_windows: cluster.maintenance_windows.0,
+            created_at: cluster.created_at,
+            updated_at: cluster.updated_at,
+            version: cluster.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct DbClusterSummary {
+    pub cluster_id: Uuid,
+    pub node_count: i64,
+    pub poweron_count: i64,
+    pub poweroff_count: i64,
+    pub rebooting_count: i64,
+    pub last_operation_at: Option<DateTime<Utc>>,
+    pub in_flight_operations: i64,
+}
+
+impl From<DbClusterSummary> for ClusterSummary {
+    fn from(summary: DbClusterSummary) -> Self {
+        Self {
+            cluster_id: summary.cluster_id,
+            node_count: summary.node_count,
+            nodes_by_status: NodeStatusCounts {
+                poweron: summary.poweron_count,
+                poweroff: summary.poweroff_count,
+                rebooting: summary.rebooting_count,
+            },
+            last_operation_at: summary.last_operation_at,
+            in_flight_operations: summary.in_flight_operations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct DbNodeStatusCounts {
+    pub cluster_id: Uuid,
+    pub poweron_count: i64,
+    pub poweroff_count: i64,
+    pub rebooting_count: i64,
+}
+
+impl From<DbNodeStatusCounts> for (Uuid, NodeStatusCounts) {
+    fn from(counts: DbNodeStatusCounts) -> Self {
+        (
+            counts.cluster_id,
+            NodeStatusCounts {
+                poweron: counts.poweron_count,
+                poweroff: counts.poweroff_count,
+                rebooting: counts.rebooting_count,
+            },
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "rollout_status", rename_all = "lowercase")]
+pub enum DbRolloutStatus {
+    Running,
+    Paused,
+    Succeeded,
+    Failed,
+    Aborted,
+}
+
+impl From<RolloutStatus> for DbRolloutStatus {
+    fn from(status: RolloutStatus) -> Self {
+        match status {
+            RolloutStatus::Running => DbRolloutStatus::Running,
+            RolloutStatus::Paused => DbRolloutStatus::Paused,
+            RolloutStatus::Succeeded => DbRolloutStatus::Succeeded,
+            RolloutStatus::Failed => DbRolloutStatus::Failed,
+            RolloutStatus::Aborted => DbRolloutStatus::Aborted,
+        }
+    }
+}
+
+impl From<DbRolloutStatus> for RolloutStatus {
+    fn from(status: DbRolloutStatus) -> Self {
+        match status {
+            DbRolloutStatus::Running => RolloutStatus::Running,
+            DbRolloutStatus::Paused => RolloutStatus::Paused,
+            DbRolloutStatus::Succeeded => RolloutStatus::Succeeded,
+            DbRolloutStatus::Failed => RolloutStatus::Failed,
+            DbRolloutStatus::Aborted => RolloutStatus::Aborted,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "rollout_node_status", rename_all = "lowercase")]
+pub enum DbRolloutNodeStatus {
+    Pending,
+    Rebooting,
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+impl From<RolloutNodeStatus> for DbRolloutNodeStatus {
+    fn from(status: RolloutNodeStatus) -> Self {
+        match status {
+            RolloutNodeStatus::Pending => DbRolloutNodeStatus::Pending,
+            RolloutNodeStatus::Rebooting => DbRolloutNodeStatus::Rebooting,
+            RolloutNodeStatus::Succeeded => DbRolloutNodeStatus::Succeeded,
+            RolloutNodeStatus::Failed => DbRolloutNodeStatus::Failed,
+            RolloutNodeStatus::Skipped => DbRolloutNodeStatus::Skipped,
+        }
+    }
+}
+
+impl From<DbRolloutNodeStatus> for RolloutNodeStatus {
+    fn from(status: DbRolloutNodeStatus) -> Self {
+        match status {
+            DbRolloutNodeStatus::Pending => RolloutNodeStatus::Pending,
+            DbRolloutNodeStatus::Rebooting => RolloutNodeStatus::Rebooting,
+            DbRolloutNodeStatus::Succeeded => RolloutNodeStatus::Succeeded,
+            DbRolloutNodeStatus::Failed => RolloutNodeStatus::Failed,
+            DbRolloutNodeStatus::Skipped => RolloutNodeStatus::Skipped,
+        }
+    }
+}
+
+/// Row of the `rollouts` table. Its nodes are in the `rollout_nodes` one.
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct DbRollout {
+    pub id: Uuid,
+    pub cluster_id: Uuid,
+    pub status: DbRolloutStatus,
+    pub max_in_flight: i32,
+    pub max_failures: i32,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub version: i64,
+}
+
+impl DbRollout {
+    pub fn into_rollout(self, nodes: Vec<DbRolloutNode>) -> Rollout {
+        Rollout {
+            id: self.id,
+            cluster_id: self.cluster_id,
+            status: self.status.into(),
+            max_in_flight: self.max_in_flight,
+            max_failures: self.max_failures,
+            nodes: nodes.into_iter().map(|node| node.into()).collect(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            version: self.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct DbRolloutNode {
+    pub node_id: Uuid,
+    pub status: DbRolloutNodeStatus,
+    pub operation_id: Option<Uuid>,
+    pub failure_reason: Option<String>,
+}
+
+impl From<DbRolloutNode> for RolloutNode {
+    fn from(node: DbRolloutNode) -> Self {
+        Self {
+            node_id: node.node_id,
+            status: node.status.into(),
+            operation_id: node.operation_id,
+            failure_reason: node.failure_reason,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "missed_run_policy", rename_all = "snake_case")]
+pub enum DbMissedRunPolicy {
+    RunOnce,
+    Skip,
+}
+
+impl From<MissedRunPolicy> for DbMissedRunPolicy {
+    fn from(policy: MissedRunPolicy) -> Self {
+        match policy {
+            MissedRunPolicy::RunOnce => DbMissedRunPolicy::RunOnce,
+            MissedRunPolicy::Skip => DbMissedRunPolicy::Skip,
+        }
+    }
+}
+
+impl From<DbMissedRunPolicy> for MissedRunPolicy {
+    fn from(policy: DbMissedRunPolicy) -> Self {
+        match policy {
+            DbMissedRunPolicy::RunOnce => MissedRunPolicy::RunOnce,
+            DbMissedRunPolicy::Skip => MissedRunPolicy::Skip,
+        }
+    }
+}
+
+/// Row of the `schedules` table, where the target is split in two columns.
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct DbScheduledOperation {
+    pub id: Uuid,
+    pub operation_type: DbOperationType,
+    pub node_id: Option<Uuid>,
+    pub cluster_id: Option<Uuid>,
+    pub run_at: Option<DateTime<Utc>>,
+    pub cron: Option<String>,
+    pub missed_run_policy: DbMissedRunPolicy,
+    pub enabled: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_batch_id: Option<Uuid>,
+    pub last_error: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub version: i64,
+}
+
+impl TryFrom<DbScheduledOperation> for ScheduledOperation {
+    type Error = sqlx::Error;
+
+    fn try_from(schedule: DbScheduledOperation) -> Result<Self, Self::Error> {
+        // the table only allows one of them
+        let target = match (schedule.node_id, schedule.cluster_id) {
+            (Some(node_id), None) => ScheduleTarget::NodeId(node_id),
+            (None, Some(cluster_id)) => ScheduleTarget::ClusterId(cluster_id),
+            _ => {
+                return Err(sqlx::Error::Decode(
+                    format!("Schedule `{}` must target a node or a cluster", schedule.id).into(),
+                ))
+            }
+        };
+        Ok(Self {
+            id: schedule.id,
+            operation_type: schedule.operation_type.into(),
+            target,
+            run_at: schedule.run_at,
+            cron: schedule.cron,
+            missed_run_policy: schedule.missed_run_policy.into(),
+            enabled: schedule.enabled,
+            next_run_at: schedule.next_run_at,
+            last_run_at: schedule.last_run_at,
+            last_batch_id: schedule.last_batch_id,
+            last_error: schedule.last_error,
+            created_at: schedule.created_at,
+            updated_at: schedule.updated_at,
+            version: schedule.version,
+        })
+    }
+}
+
+/// Columns of the `schedules` table with the target of a schedule.
+pub fn schedule_target_ids(target: &ScheduleTarget) -> (Option<Uuid>, Option<Uuid>) {
+    match *target {
+        ScheduleTarget::NodeId(node_id) => (Some(node_id), None),
+        ScheduleTarget::ClusterId(cluster_id) => (None, Some(cluster_id)),
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct DbIdempotencyRecord {
+    pub owner: String,
+    pub key: String,
+    pub request_hash: String,
+    pub status: Option<i32>,
+    pub headers: Option<sqlx::types::Json<Vec<(String, String)>>>,
+    pub body: Option<Vec<u8>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<DbIdempotencyRecord> for IdempotencyRecord {
+    fn from(record: DbIdempotencyRecord) -> Self {
+        let response = record.status.map(|status| IdempotentResponse {
+            status: status as u16,
+            headers: record.headers.map(|headers| headers.0).unwrap_or_default(),
+            body: record.body.unwrap_or_default(),
+        });
+        Self {
+            owner: record.owner,
+            key: record.key,
+            request_hash: record.request_hash,
+            response,
+            expires_at: record.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct DbApiKey {
+    pub id: Uuid,
+    pub owner: String,
+    pub admin: bool,
+    pub tenant_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<DbApiKey> for ApiKey {
+    fn from(key: DbApiKey) -> Self {
+        Self {
+            id: key.id,
+            owner: key.owner,
+            admin: key.admin,
+            tenant_id: key.tenant_id,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "rbac_role", rename_all = "lowercase")]
+pub enum DbRole {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl From<Role> for DbRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Viewer => DbRole::Viewer,
+            Role::Operator => DbRole::Operator,
+            Role::Admin => DbRole::Admin,
+        }
+    }
+}
+
+impl From<DbRole> for Role {
+    fn from(role: DbRole) -> Self {
+        match role {
+            DbRole::Viewer => Role::Viewer,
+            DbRole::Operator => Role::Operator,
+            DbRole::Admin => Role::Admin,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq, Eq)]
+pub struct DbRoleBinding {
+    pub id: Uuid,
+    pub subject: String,
+    pub role: DbRole,
+    pub cluster_id: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<DbRoleBinding> for RoleBinding {
+    fn from(binding: DbRoleBinding) -> Self {
+        Self {
+            id: binding.id,
+            subject: binding.subject,
+            role: binding.role.into(),
+            cluster_id: binding.cluster_id,
+            created_at: binding.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq, Eq)]
+pub struct DbOrganization {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<DbOrganization> for Organization {
+    fn from(organization: DbOrganization) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name,
+            created_at: organization.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "audit_action", rename_all = "lowercase")]
+pub enum DbAuditAction {
+    Create,
+    Update,
+    Delete,
+    PowerOn,
+    PowerOff,
+    Reboot,
+    Execute,
+    Fail,
+}
+
+impl From<AuditAction> for DbAuditAction {
+    fn from(action: AuditAction) -> Self {
+        match action {
+            AuditAction::Create => DbAuditAction::Create,
+            AuditAction::Update => DbAuditAction::Update,
+            AuditAction::Delete => DbAuditAction::Delete,
+            AuditAction::PowerOn => DbAuditAction::PowerOn,
+            AuditAction::PowerOff => DbAuditAction::PowerOff,
+            AuditAction::Reboot => DbAuditAction::Reboot,
+            AuditAction::Execute => DbAuditAction::Execute,
+            AuditAction::Fail => DbAuditAction::Fail,
+        }
+    }
+}
+
+impl From<DbAuditAction> for AuditAction {
+    fn from(action: DbAuditAction) -> Self {
+        match action {
+            DbAuditAction::Create => AuditAction::Create,
+            DbAuditAction::Update => AuditAction::Update,
+            DbAuditAction::Delete => AuditAction::Delete,
+            DbAuditAction::PowerOn => AuditAction::PowerOn,
+            DbAuditAction::PowerOff => AuditAction::PowerOff,
+            DbAuditAction::Reboot => AuditAction::Reboot,
+            DbAuditAction::Execute => AuditAction::Execute,
+            DbAuditAction::Fail => AuditAction::Fail,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "audit_target_type", rename_all = "lowercase")]
+pub enum DbAuditTargetType {
+    Cluster,
+    Node,
+    Operation,
+    Batch,
+}
+
+impl From<AuditTargetType> for DbAuditTargetType {
+    fn from(target_type: AuditTargetType) -> Self {
+        match target_type {
+            AuditTargetType::Cluster => DbAuditTargetType::Cluster,
+            AuditTargetType::Node => DbAuditTargetType::Node,
+            AuditTargetType::Operation => DbAuditTargetType::Operation,
+            AuditTargetType::Batch => DbAuditTargetType::Batch,
+        }
+    }
+}
+
+impl From<DbAuditTargetType> for AuditTargetType {
+    fn from(target_type: DbAuditTargetType) -> Self {
+        match target_type {
+            DbAuditTargetType::Cluster => AuditTargetType::Cluster,
+            DbAuditTargetType::Node => AuditTargetType::Node,
+            DbAuditTargetType::Operation => AuditTargetType::Operation,
+            DbAuditTargetType::Batch => AuditTargetType::Batch,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "audit_outcome", rename_all = "lowercase")]
+pub enum DbAuditOutcome {
+    Success,
+    Failure,
+}
+
+impl From<AuditOutcome> for DbAuditOutcome {
+    fn from(outcome: AuditOutcome) -> Self {
+        match outcome {
+            AuditOutcome::Success => DbAuditOutcome::Success,
+            AuditOutcome::Failure => DbAuditOutcome::Failure,
+        }
+    }
+}
+
+impl From<DbAuditOutcome> for AuditOutcome {
+    fn from(outcome: DbAuditOutcome) -> Self {
+        match outcome {
+            DbAuditOutcome::Success => AuditOutcome::Success,
+            DbAuditOutcome::Failure => AuditOutcome::Failure,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, FromRow, PartialEq)]
+pub struct DbAuditEvent {
+    pub id: Uuid,
+    pub actor: String,
+    pub tenant_id: Option<Uuid>,
+    pub action: DbAuditAction,
+    pub target_type: DbAuditTargetType,
+    pub target_id: Option<Uuid>,
+    pub before: Option<sqlx::types::Json<serde_json::Value>>,
+    pub after: Option<sqlx::types::Json<serde_json::Value>>,
+    pub request_id: Option<String>,
+    pub source_ip: Option<String>,
+    pub outcome: DbAuditOutcome,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<DbAuditEvent> for AuditEvent {
+    fn from(event: DbAuditEvent) -> Self {
+        Self {
+            id: event.id,
+            actor: event.actor,
+            tenant_id: event.tenant_id,
+            action: event.action.into(),
+            target_type: event.target_type.into(),
+            target_id: event.target_id,
+            before: event.before.map(|before| before.0),
+            after: event.after.map(|after| after.0),
+            request_id: event.request_id,
+            source_ip: event.source_ip,
+            outcome: event.outcome.into(),
+            reason: event.reason,
+            created_at: Some(event.created_at),
         }
     }
 }
@@ -0,0 +1,108 @@
+use super::{in_memory_store::StoredApiKey, InMemoryStore};
+use crate::domain::{
+    models::ApiKey,
+    repository::{ApiKeyRepository, RepositoryError, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryApiKeyRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryApiKeyRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for InMemoryApiKeyRepository {
+    #[instrument(skip(self))]
+    async fn get_api_keys(&self) -> RepositoryResult<Vec<ApiKey>> {
+        let tables = self.store.tables.read()?;
+        let mut keys: Vec<ApiKey> = tables
+            .api_keys
+            .values()
+            .map(|stored| stored.key.clone())
+            .collect();
+        keys.sort_by_key(|key| (key.created_at, key.id));
+        Ok(keys)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_api_key(&self, api_key_id: &Uuid) -> RepositoryResult<ApiKey> {
+        let tables = self.store.tables.read()?;
+        tables
+            .api_keys
+            .get(api_key_id)
+            .map(|stored| stored.key.clone())
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    #[instrument(skip(self, secret_hash))]
+    async fn get_api_key_by_secret_hash(&self, secret_hash: &str) -> RepositoryResult<ApiKey> {
+        let tables = self.store.tables.read()?;
+        tables
+            .api_keys
+            .values()
+            .find(|stored| stored.secret_hash == secret_hash)
+            .map(|stored| stored.key.clone())
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    #[instrument(skip(self, secret_hash))]
+    async fn create_api_key(
+        &self,
+        api_key: &ApiKey,
+        secret_hash: &str,
+    ) -> RepositoryResult<ApiKey> {
+        let mut tables = self.store.tables.write()?;
+        if tables.api_keys.contains_key(&api_key.id)
+            || tables
+                .api_keys
+                .values()
+                .any(|stored| stored.secret_hash == secret_hash)
+        {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        let key = ApiKey {
+            created_at: Some(Utc::now()),
+            last_used_at: None,
+            revoked_at: None,
+            ..api_key.clone()
+        };
+        tables.api_keys.insert(
+            key.id,
+            StoredApiKey {
+                key: key.clone(),
+                secret_hash: secret_hash.to_string(),
+            },
+        );
+        Ok(key)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_api_key(&self, api_key_id: &Uuid) -> RepositoryResult<ApiKey> {
+        let mut tables = self.store.tables.write()?;
+        let stored = tables
+            .api_keys
+            .get_mut(api_key_id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        stored.key.revoked_at.get_or_insert_with(Utc::now);
+        Ok(stored.key.clone())
+    }
+
+    #[instrument(skip(self))]
+    async fn record_use(&self, api_key_id: &Uuid, used_at: DateTime<Utc>) -> RepositoryResult<()> {
+        let mut tables = self.store.tables.write()?;
+        if let Some(stored) = tables.api_keys.get_mut(api_key_id) {
+            stored.key.last_used_at = Some(used_at);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use super::{in_memory_store::Tables, InMemoryStore};
+use crate::domain::{
+    models::AuditEvent,
+    repository::{
+        audit_repository::AuditFilter, AuditRepository, Page, PageRequest, RepositoryError,
+        RepositoryResult,
+    },
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryAuditRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryAuditRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+fn matches(filter: &AuditFilter, event: &AuditEvent) -> bool {
+    filter
+        .actor
+        .as_ref()
+        .is_none_or(|actor| event.actor == *actor)
+        && filter
+            .tenant_id
+            .is_none_or(|id| event.tenant_id == Some(id))
+        && filter.action.is_none_or(|action| event.action == action)
+        && filter
+            .target_type
+            .is_none_or(|target_type| event.target_type == target_type)
+        && filter
+            .target_id
+            .is_none_or(|id| event.target_id == Some(id))
+        && filter
+            .outcome
+            .is_none_or(|outcome| event.outcome == outcome)
+        && filter
+            .request_id
+            .as_ref()
+            .is_none_or(|id| event.request_id.as_ref() == Some(id))
+        && filter
+            .created_after
+            .is_none_or(|after| event.created_at.is_some_and(|at| at >= after))
+        && filter
+            .created_before
+            .is_none_or(|before| event.created_at.is_some_and(|at| at < before))
+}
+
+/// Tenant of the cluster of the target, or of the snapshots and then of the caller if
+/// the target is gone.
+fn tenant_of_target(tables: &Tables, event: &AuditEvent) -> Option<Uuid> {
+    let cluster_id = event
+        .cluster_id()
+        .filter(|id| tables.clusters.contains_key(id))
+        .or_else(|| {
+            let target_id = event.target_id?;
+            let node_id = match tables.operations.get(&target_id) {
+                Some(stored) => stored.operation.node_id,
+                None => target_id,
+            };
+            Some(tables.nodes.get(&node_id)?.cluster_id)
+        });
+    match cluster_id {
+        Some(cluster_id) => tables.clusters.get(&cluster_id)?.tenant_id,
+        None => event.snapshot_tenant_id().or(event.tenant_id),
+    }
+}
+
+#[async_trait]
+impl AuditRepository for InMemoryAuditRepository {
+    #[instrument(skip(self))]
+    async fn get_events(
+        &self,
+        filter: &AuditFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<AuditEvent>> {
+        let tables = self.store.tables.read()?;
+        let events: Vec<AuditEvent> = tables
+            .audit_events
+            .iter()
+            .filter(|event| matches(filter, event))
+            .cloned()
+            .collect();
+        let total = page.with_total.then_some(events.len() as i64);
+        Ok(Page::new(page.apply(events), page, total))
+    }
+
+    #[instrument(skip(self, event), fields(event_id = %event.id))]
+    async fn record_event(&self, event: &AuditEvent) -> RepositoryResult<AuditEvent> {
+        let mut tables = self.store.tables.write()?;
+        if tables
+            .audit_events
+            .iter()
+            .any(|stored| stored.id == event.id)
+        {
+            return Err(RepositoryError::AlreadyExists);
+        }
+        let event = AuditEvent {
+            tenant_id: tenant_of_target(&tables, event),
+            created_at: Some(Utc::now()),
+            ..event.clone()
+        };
+        tables.audit_events.push(event.clone());
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{
+        repository_tests, InMemoryClusterRepository, InMemoryNodeRepository,
+        InMemoryOrganizationRepository,
+    };
+
+    #[actix_rt::test]
+    async fn events_belong_to_the_tenant_of_their_target() {
+        let store = InMemoryStore::default();
+        repository_tests::events_belong_to_the_tenant_of_their_target(
+            InMemoryOrganizationRepository::new(store.clone()),
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store.clone()),
+            InMemoryAuditRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn events_are_filtered_and_paginated() {
+        repository_tests::events_are_filtered_and_paginated(InMemoryAuditRepository::new(
+            InMemoryStore::default(),
+        ))
+        .await;
+    }
+}
@@ -0,0 +1,417 @@
+use super::{in_memory_store::check_version, InMemoryStore};
+use crate::domain::{
+    models::{
+        Cluster, ClusterPatch, ClusterSummary, NodeStatus, NodeStatusCounts, OperationStatus,
+        ScheduleTarget,
+    },
+    repository::{
+        Change, ClusterRepository, Page, PageRequest, RepositoryError, RepositoryResult,
+        TenantScoped,
+    },
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryClusterRepository {
+    store: InMemoryStore,
+    tenant: Option<Uuid>,
+}
+
+impl InMemoryClusterRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self {
+            store,
+            tenant: None,
+        }
+    }
+
+    fn is_visible(&self, cluster: &Cluster) -> bool {
+        self.tenant
+            .is_none_or(|tenant| cluster.tenant_id == Some(tenant))
+    }
+
+    fn get_visible<'a>(
+        &self,
+        clusters: &'a HashMap<Uuid, Cluster>,
+        cluster_id: &Uuid,
+    ) -> RepositoryResult<&'a Cluster> {
+        clusters
+            .get(cluster_id)
+            .filter(|cluster| self.is_visible(cluster))
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+}
+
+/// Names are unique per tenant.
+fn name_taken(clusters: &HashMap<Uuid, Cluster>, cluster: &Cluster) -> bool {
+    clusters
+        .values()
+        .any(|c| c.id != cluster.id && c.tenant_id == cluster.tenant_id && c.name == cluster.name)
+}
+
+impl TenantScoped for InMemoryClusterRepository {
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            store: self.store.clone(),
+            tenant: Some(tenant_id),
+        }
+    }
+}
+
+#[async_trait]
+impl ClusterRepository for InMemoryClusterRepository {
+    #[instrument(skip(self))]
+    async fn get_clusters(&self, page: &PageRequest) -> RepositoryResult<Page<Cluster>> {
+        let tables = self.store.tables.read()?;
+        let clusters: Vec<Cluster> = tables
+            .clusters
+            .values()
+            .filter(|cluster| self.is_visible(cluster))
+            .cloned()
+            .collect();
+        let total = page.with_total.then_some(clusters.len() as i64);
+        Ok(Page::new(page.apply(clusters), page, total))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cluster(&self, cluster_id: &Uuid) -> RepositoryResult<Cluster> {
+        let tables = self.store.tables.read()?;
+        self.get_visible(&tables.clusters, cluster_id).cloned()
+    }
+
+    #[instrument(skip(self))]
+    async fn create_cluster(&self, cluster: &Cluster) -> RepositoryResult<Cluster> {
+        let mut tables = self.store.tables.write()?;
+        let cluster = Cluster {
+            tenant_id: self.tenant.or(cluster.tenant_id),
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            version: 1,
+            ..cluster.clone()
+        };
+        if let Some(tenant_id) = cluster.tenant_id {
+            if !tables.organizations.contains_key(&tenant_id) {
+                return Err(RepositoryError::ForeignKeyViolation);
+            }
+        }
+        if tables.clusters.contains_key(&cluster.id) || name_taken(&tables.clusters, &cluster) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        tables.clusters.insert(cluster.id, cluster.clone());
+        Ok(cluster)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_cluster(
+        &self,
+        cluster: &Cluster,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>> {
+        let mut tables = self.store.tables.write()?;
+        let stored = self.get_visible(&tables.clusters, &cluster.id)?;
+        check_version(stored.version, expected_version.as_deref())?;
+        let renamed = Cluster {
+            tenant_id: stored.tenant_id,
+            ..cluster.clone()
+        };
+        if name_taken(&tables.clusters, &renamed) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        let stored = tables
+            .clusters
+            .get_mut(&cluster.id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        let before = stored.clone();
+        stored.name = cluster.name.clone();
+        stored.maintenance_windows = cluster.maintenance_windows.clone();
+        stored.updated_at = Some(Utc::now());
+        stored.version += 1;
+        Ok(Change {
+            before,
+            after: stored.clone(),
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn patch_cluster(
+        &self,
+        cluster_id: &Uuid,
+        patch: &ClusterPatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>> {
+        let mut tables = self.store.tables.write()?;
+        let before = self.get_visible(&tables.clusters, cluster_id)?.clone();
+        check_version(before.version, expected_version.as_deref())?;
+        let mut cluster = before.clone();
+        patch.apply(&mut cluster);
+        if name_taken(&tables.clusters, &cluster) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        cluster.updated_at = Some(Utc::now());
+        cluster.version += 1;
+        tables.clusters.insert(cluster.id, cluster.clone());
+        Ok(Change {
+            before,
+            after: cluster,
+        })
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_cluster(
+        &self,
+        cluster_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Cluster> {
+        let mut tables = self.store.tables.write()?;
+        let cluster = self.get_visible(&tables.clusters, cluster_id)?;
+        check_version(cluster.version, expected_version.as_deref())?;
+        let cluster = tables
+            .clusters
+            .remove(cluster_id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        tables.cascade_delete_nodes(|node| node.cluster_id == cluster.id);
+        tables
+            .rollouts
+            .retain(|_, stored| stored.rollout.cluster_id != cluster.id);
+        tables
+            .schedules
+            .retain(|_, stored| stored.schedule.target != ScheduleTarget::ClusterId(cluster.id));
+        tables
+            .role_bindings
+            .retain(|_, binding| binding.cluster_id != Some(cluster.id));
+        Ok(cluster)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cluster_summary(&self, cluster_id: &Uuid) -> RepositoryResult<ClusterSummary> {
+        let tables = self.store.tables.read()?;
+        self.get_visible(&tables.clusters, cluster_id)?;
+
+        let mut summary = ClusterSummary {
+            cluster_id: *cluster_id,
+            node_count: 0,
+            nodes_by_status: NodeStatusCounts::default(),
+            last_operation_at: None,
+            in_flight_operations: 0,
+        };
+        for node in tables
+            .nodes
+            .values()
+            .filter(|n| n.cluster_id == *cluster_id)
+        {
+            summary.node_count += 1;
+            match node.status {
+                NodeStatus::PowerOn => summary.nodes_by_status.poweron += 1,
+                NodeStatus::PowerOff => summary.nodes_by_status.poweroff += 1,
+                NodeStatus::Rebooting => summary.nodes_by_status.rebooting += 1,
+            }
+        }
+        for stored in tables.operations.values() {
+            let operation = &stored.operation;
+            if tables
+                .nodes
+                .get(&operation.node_id)
+                .is_none_or(|n| n.cluster_id != *cluster_id)
+            {
+                continue;
+            }
+            summary.last_operation_at = summary.last_operation_at.max(operation.created_at);
+            if matches!(
+                operation.status,
+                OperationStatus::Pending | OperationStatus::Running
+            ) {
+                summary.in_flight_operations += 1;
+            }
+        }
+        Ok(summary)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_node_status_counts(&self) -> RepositoryResult<HashMap<Uuid, NodeStatusCounts>> {
+        let tables = self.store.tables.read()?;
+        let mut counts: HashMap<Uuid, NodeStatusCounts> = tables
+            .clusters
+            .values()
+            .filter(|cluster| self.is_visible(cluster))
+            .map(|cluster| (cluster.id, NodeStatusCounts::default()))
+            .collect();
+        for node in tables.nodes.values() {
+            if let Some(cluster_counts) = counts.get_mut(&node.cluster_id) {
+                match node.status {
+                    NodeStatus::PowerOn => cluster_counts.poweron += 1,
+                    NodeStatus::PowerOff => cluster_counts.poweroff += 1,
+                    NodeStatus::Rebooting => cluster_counts.rebooting += 1,
+                }
+            }
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{Node, NodeStatus, Operation, OperationType},
+            repository::NodeRepository,
+        },
+        infrastructure::db::{repository_tests, InMemoryNodeRepository},
+    };
+
+    fn create_test_cluster(name: &str) -> Cluster {
+        Cluster {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            tenant_id: None,
+            maintenance_windows: vec![],
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn writes_return_what_they_changed() {
+        let store = InMemoryStore::default();
+        repository_tests::writes_return_what_they_changed(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn patches_only_change_the_given_fields() {
+        let store = InMemoryStore::default();
+        repository_tests::patches_only_change_the_given_fields(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn create_rejects_duplicated_names() {
+        let repo = InMemoryClusterRepository::new(InMemoryStore::default());
+        let cluster = repo
+            .create_cluster(&create_test_cluster("CLUSTER"))
+            .await
+            .unwrap();
+        assert!(cluster.created_at.is_some());
+
+        let result = repo.create_cluster(&create_test_cluster("CLUSTER")).await;
+        assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+        assert_eq!(
+            repo.get_clusters(&PageRequest::default())
+                .await
+                .unwrap()
+                .items,
+            vec![cluster]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn update_works() {
+        let repo = InMemoryClusterRepository::new(InMemoryStore::default());
+        let cluster = repo
+            .create_cluster(&create_test_cluster("CLUSTER"))
+            .await
+            .unwrap();
+        repo.create_cluster(&create_test_cluster("OTHER"))
+            .await
+            .unwrap();
+
+        let renamed = Cluster {
+            name: "OTHER".to_string(),
+            ..cluster.clone()
+        };
+        let result = repo.update_cluster(&renamed, None).await;
+        assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+
+        let renamed = Cluster {
+            name: "RENAMED".to_string(),
+            ..cluster.clone()
+        };
+        let updated = repo.update_cluster(&renamed, None).await.unwrap().after;
+        assert_eq!(updated.name, "RENAMED");
+        assert_eq!(updated.created_at, cluster.created_at);
+        assert!(updated.updated_at.is_some());
+
+        let result = repo.update_cluster(&create_test_cluster("NEW"), None).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn delete_cascades_to_nodes_and_operations() {
+        let store = InMemoryStore::default();
+        let cluster_repo = InMemoryClusterRepository::new(store.clone());
+        let node_repo = InMemoryNodeRepository::new(store);
+
+        let cluster = cluster_repo
+            .create_cluster(&create_test_cluster("CLUSTER"))
+            .await
+            .unwrap();
+        let node = node_repo
+            .create_node(&Node {
+                id: uuid::Uuid::new_v4(),
+                name: "NODE".to_string(),
+                cluster_id: cluster.id,
+                status: NodeStatus::PowerOn,
+                driver: Default::default(),
+                bmc_endpoint: None,
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        let operation = node_repo
+            .create_operation(&Operation::new(node.id, OperationType::Reboot))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cluster_repo
+                .delete_cluster(&cluster.id, None)
+                .await
+                .unwrap()
+                .id,
+            cluster.id
+        );
+
+        assert!(cluster_repo.get_cluster(&cluster.id).await.is_err());
+        assert!(node_repo.get_node(&node.id).await.is_err());
+        assert!(node_repo.get_operation(&operation.id).await.is_err());
+
+        let result = cluster_repo.delete_cluster(&cluster.id, None).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn summary_aggregates_nodes_and_operations() {
+        let store = InMemoryStore::default();
+        repository_tests::summary_aggregates_nodes_and_operations(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn writes_check_the_version() {
+        let store = InMemoryStore::default();
+        repository_tests::writes_check_the_version(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+}
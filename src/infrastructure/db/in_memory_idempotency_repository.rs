@@ -0,0 +1,89 @@
+use super::InMemoryStore;
+use crate::domain::{
+    models::{IdempotencyRecord, IdempotentResponse},
+    repository::{IdempotencyRepository, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use tracing::instrument;
+
+#[derive(Clone)]
+pub struct InMemoryIdempotencyRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryIdempotencyRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+fn id(owner: &str, key: &str) -> (String, String) {
+    (owner.to_string(), key.to_string())
+}
+
+#[async_trait]
+impl IdempotencyRepository for InMemoryIdempotencyRepository {
+    #[instrument(skip(self))]
+    async fn claim_key(
+        &self,
+        owner: &str,
+        key: &str,
+        request_hash: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<IdempotencyRecord>> {
+        let mut tables = self.store.tables.write()?;
+        let now = Utc::now();
+        tables
+            .idempotency_keys
+            .retain(|_, record| record.expires_at > now);
+        if let Some(record) = tables.idempotency_keys.get(&id(owner, key)) {
+            return Ok(Some(record.clone()));
+        }
+
+        let record = IdempotencyRecord {
+            owner: owner.to_string(),
+            key: key.to_string(),
+            request_hash: request_hash.to_string(),
+            response: None,
+            expires_at: now + Duration::seconds(lease_secs),
+        };
+        tables.idempotency_keys.insert(id(owner, key), record);
+        Ok(None)
+    }
+
+    #[instrument(skip(self, response))]
+    async fn save_response(
+        &self,
+        owner: &str,
+        key: &str,
+        response: &IdempotentResponse,
+        ttl_secs: i64,
+    ) -> RepositoryResult<()> {
+        let mut tables = self.store.tables.write()?;
+        if let Some(record) = tables.idempotency_keys.get_mut(&id(owner, key)) {
+            record.response = Some(response.clone());
+            record.expires_at = Utc::now() + Duration::seconds(ttl_secs);
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn release_key(&self, owner: &str, key: &str) -> RepositoryResult<()> {
+        let mut tables = self.store.tables.write()?;
+        tables.idempotency_keys.remove(&id(owner, key));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::repository_tests;
+
+    #[actix_rt::test]
+    async fn keys_are_claimed_until_they_expire() {
+        let repo = InMemoryIdempotencyRepository::new(InMemoryStore::default());
+        repository_tests::keys_are_claimed_until_they_expire(repo).await;
+    }
+}
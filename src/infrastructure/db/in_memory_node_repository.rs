@@ -0,0 +1,805 @@
+use super::{
+    in_memory_store::{check_version, StoredOperation, Tables},
+    InMemoryStore,
+};
+use crate::domain::{
+    models::{Batch, Node, NodePatch, NodeStatus, Operation, OperationStatus},
+    repository::{
+        node_repository::{NameMatch, NodeFilter, OperationFilter},
+        Change, NodeRepository, Page, PageRequest, RepositoryError, RepositoryResult, TenantScoped,
+    },
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryNodeRepository {
+    store: InMemoryStore,
+    tenant: Option<Uuid>,
+}
+
+impl InMemoryNodeRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self {
+            store,
+            tenant: None,
+        }
+    }
+
+    fn is_visible(&self, tables: &Tables, node: &Node) -> bool {
+        self.tenant
+            .is_none_or(|tenant| node_tenant(tables, node) == Some(tenant))
+    }
+
+    fn get_visible<'a>(&self, tables: &'a Tables, node_id: &Uuid) -> RepositoryResult<&'a Node> {
+        tables
+            .nodes
+            .get(node_id)
+            .filter(|node| self.is_visible(tables, node))
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    /// Fails if the node would be in a missing cluster, or in the cluster of another
+    /// tenant, or if its name is taken in the tenant of its cluster.
+    fn check_node(&self, tables: &Tables, node: &Node) -> RepositoryResult<()> {
+        let cluster = tables
+            .clusters
+            .get(&node.cluster_id)
+            .filter(|cluster| {
+                self.tenant
+                    .is_none_or(|tenant| cluster.tenant_id == Some(tenant))
+            })
+            .ok_or(RepositoryError::ForeignKeyViolation)?;
+        if tables.nodes.values().any(|n| {
+            n.id != node.id && n.name == node.name && node_tenant(tables, n) == cluster.tenant_id
+        }) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+        Ok(())
+    }
+}
+
+/// Tenant of the cluster of the node.
+fn node_tenant(tables: &Tables, node: &Node) -> Option<Uuid> {
+    tables
+        .clusters
+        .get(&node.cluster_id)
+        .and_then(|cluster| cluster.tenant_id)
+}
+
+fn matches_name(value: &str, name: &str, filter: &NodeFilter) -> bool {
+    let (value, name) = if filter.case_insensitive {
+        (value.to_lowercase(), name.to_lowercase())
+    } else {
+        (value.to_string(), name.to_string())
+    };
+    match filter.name_match {
+        NameMatch::Exact => value == name,
+        NameMatch::Prefix => value.starts_with(&name),
+        NameMatch::Contains => value.contains(&name),
+    }
+}
+
+/// Same filters the databases apply, the name matches the node or cluster name.
+fn matches_filter(node: &Node, cluster_name: Option<&str>, filter: &NodeFilter) -> bool {
+    let name_matches = filter.name.as_ref().is_none_or(|name| {
+        matches_name(&node.name, name, filter)
+            || cluster_name.is_some_and(|cluster_name| matches_name(cluster_name, name, filter))
+    });
+    // nodes without a date never match a date filter, like `NULL` in SQL
+    let after = |date: Option<DateTime<Utc>>, bound: Option<DateTime<Utc>>| {
+        bound.is_none_or(|bound| date.is_some_and(|date| date >= bound))
+    };
+    let before = |date: Option<DateTime<Utc>>, bound: Option<DateTime<Utc>>| {
+        bound.is_none_or(|bound| date.is_some_and(|date| date < bound))
+    };
+
+    name_matches
+        && filter.status.is_none_or(|status| node.status == status)
+        && filter
+            .cluster_id
+            .is_none_or(|cluster_id| node.cluster_id == cluster_id)
+        && after(node.created_at, filter.created_after)
+        && before(node.created_at, filter.created_before)
+        && after(node.updated_at, filter.updated_after)
+        && before(node.updated_at, filter.updated_before)
+}
+
+/// Inserts the operation and increments the version of its node, like the databases do.
+fn insert_operation(tables: &mut Tables, operation: &Operation) -> RepositoryResult<Operation> {
+    if tables.operations.contains_key(&operation.id) {
+        return Err(RepositoryError::AlreadyExists);
+    }
+    if operation
+        .batch_id
+        .is_some_and(|id| !tables.batches.contains_key(&id))
+    {
+        return Err(RepositoryError::ForeignKeyViolation);
+    }
+    let node = tables
+        .nodes
+        .get_mut(&operation.node_id)
+        .ok_or(RepositoryError::ForeignKeyViolation)?;
+    node.version += 1;
+
+    let operation = Operation {
+        started_at: None,
+        finished_at: None,
+        failure_reason: None,
+        attempts: 0,
+        created_at: Some(Utc::now()),
+        updated_at: None,
+        ..operation.clone()
+    };
+    tables.operations.insert(
+        operation.id,
+        StoredOperation {
+            operation: operation.clone(),
+            locked_by: None,
+            locked_until: None,
+        },
+    );
+    Ok(operation)
+}
+
+fn is_claimed_by(stored: &StoredOperation, worker_id: &str) -> bool {
+    stored.operation.status == OperationStatus::Running
+        && stored.locked_by.as_deref() == Some(worker_id)
+}
+
+impl TenantScoped for InMemoryNodeRepository {
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            store: self.store.clone(),
+            tenant: Some(tenant_id),
+        }
+    }
+}
+
+#[async_trait]
+impl NodeRepository for InMemoryNodeRepository {
+    #[instrument(skip(self))]
+    async fn get_nodes(
+        &self,
+        filter: &NodeFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<Node>> {
+        let tables = self.store.tables.read()?;
+        let nodes: Vec<Node> = tables
+            .nodes
+            .values()
+            .filter(|node| self.is_visible(&tables, node))
+            .filter(|node| {
+                let cluster_name = tables
+                    .clusters
+                    .get(&node.cluster_id)
+                    .map(|c| c.name.as_str());
+                matches_filter(node, cluster_name, filter)
+            })
+            .cloned()
+            .collect();
+        let total = page.with_total.then_some(nodes.len() as i64);
+        Ok(Page::new(page.apply(nodes), page, total))
+    }
+
+    #[instrument(skip(self))]
+    async fn get_node(&self, node_id: &Uuid) -> RepositoryResult<Node> {
+        let tables = self.store.tables.read()?;
+        self.get_visible(&tables, node_id).cloned()
+    }
+
+    #[instrument(skip(self))]
+    async fn get_nodes_by_id(&self, node_ids: &[Uuid]) -> RepositoryResult<Vec<Node>> {
+        let tables = self.store.tables.read()?;
+        Ok(node_ids
+            .iter()
+            .filter_map(|node_id| self.get_visible(&tables, node_id).ok())
+            .cloned()
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn create_node(&self, node: &Node) -> RepositoryResult<Node> {
+        let mut tables = self.store.tables.write()?;
+        if tables.nodes.contains_key(&node.id) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+        self.check_node(&tables, node)?;
+
+        let node = Node {
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            version: 1,
+            ..node.clone()
+        };
+        tables.nodes.insert(node.id, node.clone());
+        Ok(node)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_node(
+        &self,
+        node: &Node,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>> {
+        let mut tables = self.store.tables.write()?;
+        let stored = self.get_visible(&tables, &node.id)?;
+        check_version(stored.version, expected_version.as_deref())?;
+        self.check_node(&tables, node)?;
+
+        let stored = tables
+            .nodes
+            .get_mut(&node.id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        let before = stored.clone();
+        *stored = Node {
+            status: stored.status,
+            created_at: stored.created_at,
+            updated_at: Some(Utc::now()),
+            version: stored.version + 1,
+            ..node.clone()
+        };
+        Ok(Change {
+            before,
+            after: stored.clone(),
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn patch_node(
+        &self,
+        node_id: &Uuid,
+        patch: &NodePatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>> {
+        let mut tables = self.store.tables.write()?;
+        let before = self.get_visible(&tables, node_id)?.clone();
+        check_version(before.version, expected_version.as_deref())?;
+        let mut node = before.clone();
+        patch.apply(&mut node);
+        self.check_node(&tables, &node)?;
+
+        node.updated_at = Some(Utc::now());
+        node.version += 1;
+        tables.nodes.insert(node.id, node.clone());
+        Ok(Change {
+            before,
+            after: node,
+        })
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_node(
+        &self,
+        node_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Node> {
+        let mut tables = self.store.tables.write()?;
+        let node = self.get_visible(&tables, node_id)?.clone();
+        check_version(node.version, expected_version.as_deref())?;
+        tables.cascade_delete_nodes(|node| &node.id == node_id);
+        Ok(node)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_node_status(
+        &self,
+        node_id: &Uuid,
+        status: NodeStatus,
+    ) -> RepositoryResult<Node> {
+        let mut tables = self.store.tables.write()?;
+        let stored = tables
+            .nodes
+            .get_mut(node_id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        stored.status = status;
+        stored.updated_at = Some(Utc::now());
+        stored.version += 1;
+        Ok(stored.clone())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_operations(&self, filter: &OperationFilter) -> RepositoryResult<Vec<Operation>> {
+        let tables = self.store.tables.read()?;
+        let mut operations: Vec<Operation> = tables
+            .operations
+            .values()
+            .map(|stored| &stored.operation)
+            .filter(|o| {
+                tables
+                    .nodes
+                    .get(&o.node_id)
+                    .is_some_and(|n| self.is_visible(&tables, n))
+            })
+            .filter(|o| {
+                filter.operation_type.is_none_or(|t| o.operation_type == t)
+                    && filter.node_id.is_none_or(|id| o.node_id == id)
+                    && filter.batch_id.is_none_or(|id| o.batch_id == Some(id))
+                    && filter.cluster_id.is_none_or(|id| {
+                        tables
+                            .nodes
+                            .get(&o.node_id)
+                            .is_some_and(|n| n.cluster_id == id)
+                    })
+                    && filter
+                        .created_after
+                        .is_none_or(|after| o.created_at >= Some(after))
+                    && filter
+                        .created_before
+                        .is_none_or(|before| o.created_at < Some(before))
+            })
+            .cloned()
+            .collect();
+        operations.sort_by_key(|o| std::cmp::Reverse((o.created_at, o.id)));
+        Ok(operations
+            .into_iter()
+            .skip(filter.offset() as usize)
+            .take(filter.limit() as usize)
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_operation(&self, operation_id: &Uuid) -> RepositoryResult<Operation> {
+        let tables = self.store.tables.read()?;
+        let operation = tables
+            .operations
+            .get(operation_id)
+            .map(|stored| stored.operation.clone())
+            .ok_or(RepositoryError::DoesNotExist)?;
+        self.get_visible(&tables, &operation.node_id)?;
+        Ok(operation)
+    }
+
+    #[instrument(skip(self))]
+    async fn create_operation(&self, operation: &Operation) -> RepositoryResult<Operation> {
+        let mut tables = self.store.tables.write()?;
+        self.get_visible(&tables, &operation.node_id)
+            .map_err(|_| RepositoryError::ForeignKeyViolation)?;
+        insert_operation(&mut tables, operation)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_operation(
+        &self,
+        operation: &Operation,
+        worker_id: &str,
+    ) -> RepositoryResult<Operation> {
+        let mut tables = self.store.tables.write()?;
+        // unless the lease expired and the operation belongs to another worker by now
+        let stored = tables
+            .operations
+            .get_mut(&operation.id)
+            .filter(|stored| is_claimed_by(stored, worker_id))
+            .ok_or(RepositoryError::DoesNotExist)?;
+        stored.operation.status = operation.status;
+        stored.operation.started_at = operation.started_at;
+        stored.operation.finished_at = operation.finished_at;
+        stored.operation.failure_reason = operation.failure_reason.clone();
+        stored.operation.updated_at = Some(Utc::now());
+        // updating an operation releases the lease the worker had on it
+        stored.locked_by = None;
+        stored.locked_until = None;
+        Ok(stored.operation.clone())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_batch(&self, batch_id: &Uuid) -> RepositoryResult<Batch> {
+        let tables = self.store.tables.read()?;
+        let batch = tables
+            .batches
+            .get(batch_id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        // a batch belongs to the tenant of the nodes of its operations
+        let is_visible = self.tenant.is_none()
+            || tables.operations.values().any(|stored| {
+                stored.operation.batch_id == Some(batch.id)
+                    && tables
+                        .nodes
+                        .get(&stored.operation.node_id)
+                        .is_some_and(|node| self.is_visible(&tables, node))
+            });
+        if !is_visible {
+            return Err(RepositoryError::DoesNotExist);
+        }
+        Ok(batch.clone())
+    }
+
+    #[instrument(skip(self, operations))]
+    async fn create_batch(
+        &self,
+        batch: &Batch,
+        operations: &[Operation],
+    ) -> RepositoryResult<(Batch, Vec<Operation>)> {
+        let mut tables = self.store.tables.write()?;
+        if tables.batches.contains_key(&batch.id) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+        // nothing is written unless all the operations can be created
+        for operation in operations {
+            if tables.operations.contains_key(&operation.id) {
+                return Err(RepositoryError::AlreadyExists);
+            }
+            self.get_visible(&tables, &operation.node_id)
+                .map_err(|_| RepositoryError::ForeignKeyViolation)?;
+        }
+
+        let batch = Batch {
+            created_at: Some(Utc::now()),
+            ..batch.clone()
+        };
+        tables.batches.insert(batch.id, batch.clone());
+        let mut created = Vec::with_capacity(operations.len());
+        for operation in operations {
+            created.push(insert_operation(&mut tables, operation)?);
+        }
+        Ok((batch, created))
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_operation(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Operation>> {
+        let mut tables = self.store.tables.write()?;
+        let now = Utc::now();
+        let is_leased = |stored: &StoredOperation| {
+            stored.operation.status == OperationStatus::Running
+                && stored.locked_until.is_some_and(|until| until >= now)
+        };
+        let is_unfinished = |stored: &StoredOperation| {
+            matches!(
+                stored.operation.status,
+                OperationStatus::Pending | OperationStatus::Running
+            )
+        };
+        let order = |stored: &StoredOperation| (stored.operation.created_at, stored.operation.id);
+        // the operations of a node run one at a time, in the order they were requested
+        let is_blocked = |stored: &StoredOperation| {
+            tables.operations.values().any(|other| {
+                other.operation.node_id == stored.operation.node_id
+                    && other.operation.id != stored.operation.id
+                    && (is_leased(other) || (is_unfinished(other) && order(other) < order(stored)))
+            })
+        };
+        let next_id = tables
+            .operations
+            .values()
+            .filter(|stored| match stored.operation.status {
+                OperationStatus::Pending => true,
+                OperationStatus::Running => stored.locked_until.is_some_and(|until| until < now),
+                _ => false,
+            })
+            .filter(|stored| !is_blocked(stored))
+            .min_by_key(|stored| stored.operation.created_at)
+            .map(|stored| stored.operation.id);
+        let next = next_id.and_then(|id| tables.operations.get_mut(&id));
+
+        Ok(next.map(|stored| {
+            stored.operation.status = OperationStatus::Running;
+            stored.operation.started_at.get_or_insert(now);
+            stored.operation.attempts += 1;
+            stored.operation.updated_at = Some(now);
+            stored.locked_by = Some(worker_id.to_string());
+            stored.locked_until = Some(now + Duration::seconds(lease_secs));
+            stored.operation.clone()
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn renew_operation(
+        &self,
+        operation_id: &Uuid,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<()> {
+        let mut tables = self.store.tables.write()?;
+        let now = Utc::now();
+        // an expired lease can't be renewed, since another worker may have claimed it already
+        let stored = tables
+            .operations
+            .get_mut(operation_id)
+            .filter(|stored| is_claimed_by(stored, worker_id))
+            .filter(|stored| stored.locked_until.is_some_and(|until| until >= now))
+            .ok_or(RepositoryError::DoesNotExist)?;
+        stored.locked_until = Some(now + Duration::seconds(lease_secs));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{Cluster, OperationType},
+            repository::{
+                pagination::{Cursor, SortField, SortOrder},
+                ClusterRepository,
+            },
+        },
+        infrastructure::db::{repository_tests, InMemoryClusterRepository},
+    };
+
+    async fn prepare_repo(cluster_name: &str) -> (InMemoryNodeRepository, Uuid) {
+        let store = InMemoryStore::default();
+        let cluster = InMemoryClusterRepository::new(store.clone())
+            .create_cluster(&Cluster {
+                id: uuid::Uuid::new_v4(),
+                name: cluster_name.to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        (InMemoryNodeRepository::new(store), cluster.id)
+    }
+
+    fn create_test_node(name: &str, cluster_id: Uuid) -> Node {
+        Node {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            cluster_id,
+            status: NodeStatus::PowerOn,
+            driver: Default::default(),
+            bmc_endpoint: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_checks_names_and_clusters() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        repo.create_node(&create_test_node("NODE", cluster_id))
+            .await
+            .unwrap();
+
+        let result = repo
+            .create_node(&create_test_node("NODE", cluster_id))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+
+        let result = repo
+            .create_node(&create_test_node("OTHER", uuid::Uuid::new_v4()))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_filters_by_node_and_cluster_name() {
+        let (repo, cluster_id) = prepare_repo("RACK_A").await;
+        let node_1 = repo
+            .create_node(&create_test_node("NODE_1", cluster_id))
+            .await
+            .unwrap();
+        let node_2 = repo
+            .create_node(&create_test_node("NODE_2", cluster_id))
+            .await
+            .unwrap();
+
+        let filter = |name: &str| NodeFilter {
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let page = PageRequest::default();
+        assert_eq!(
+            repo.get_nodes(&filter("_2"), &page).await.unwrap().items,
+            vec![node_2.clone()]
+        );
+        assert_eq!(
+            repo.get_nodes(&filter("RACK"), &page).await.unwrap().items,
+            vec![node_1.clone(), node_2.clone()]
+        );
+        assert!(repo
+            .get_nodes(&filter("nope"), &page)
+            .await
+            .unwrap()
+            .items
+            .is_empty());
+        assert_eq!(
+            repo.get_nodes(&NodeFilter::default(), &page)
+                .await
+                .unwrap()
+                .items,
+            vec![node_1, node_2]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_combines_filters() {
+        let store = InMemoryStore::default();
+        repository_tests::get_nodes_combines_filters(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_paginates() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        for name in ["NODE_B", "NODE_C", "NODE_A"] {
+            repo.create_node(&create_test_node(name, cluster_id))
+                .await
+                .unwrap();
+        }
+
+        let mut page =
+            PageRequest::new(Some(2), SortField::Name, SortOrder::Desc, None, true).unwrap();
+        let first = repo.get_nodes(&NodeFilter::default(), &page).await.unwrap();
+        assert_eq!(first.total, Some(3));
+        assert_eq!(
+            first
+                .items
+                .iter()
+                .map(|n| n.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["NODE_C", "NODE_B"]
+        );
+
+        page.after = Some(Cursor::decode(&first.next_cursor.unwrap()).unwrap());
+        let second = repo.get_nodes(&NodeFilter::default(), &page).await.unwrap();
+        assert_eq!(second.items[0].name, "NODE_A");
+        assert_eq!(second.next_cursor, None);
+    }
+
+    #[actix_rt::test]
+    async fn delete_cascades_to_operations() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        let node = repo
+            .create_node(&create_test_node("NODE", cluster_id))
+            .await
+            .unwrap();
+        let operation = repo
+            .create_operation(&Operation::new(node.id, OperationType::PowerOff))
+            .await
+            .unwrap();
+
+        assert_eq!(repo.delete_node(&node.id, None).await.unwrap().id, node.id);
+        assert!(repo.get_operation(&operation.id).await.is_err());
+
+        let result = repo.delete_node(&node.id, None).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn claim_operation_leases_oldest_pending_operation() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        let node = repo
+            .create_node(&create_test_node("NODE", cluster_id))
+            .await
+            .unwrap();
+        let other_node = repo
+            .create_node(&create_test_node("OTHER_NODE", cluster_id))
+            .await
+            .unwrap();
+        let first = repo
+            .create_operation(&Operation::new(node.id, OperationType::PowerOff))
+            .await
+            .unwrap();
+        let second = repo
+            .create_operation(&Operation::new(other_node.id, OperationType::PowerOn))
+            .await
+            .unwrap();
+
+        let claimed = repo.claim_operation("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert_eq!(claimed.status, OperationStatus::Running);
+        assert_eq!(claimed.attempts, 1);
+        assert!(claimed.started_at.is_some());
+
+        let claimed = repo.claim_operation("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, second.id);
+        assert!(repo.claim_operation("worker", 60).await.unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn claim_operation_reclaims_expired_leases() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        let node = repo
+            .create_node(&create_test_node("NODE", cluster_id))
+            .await
+            .unwrap();
+        let operation = repo
+            .create_operation(&Operation::new(node.id, OperationType::Reboot))
+            .await
+            .unwrap();
+
+        let claimed = repo.claim_operation("worker", -1).await.unwrap().unwrap();
+        let reclaimed = repo.claim_operation("other", 60).await.unwrap().unwrap();
+        assert_eq!(reclaimed.id, operation.id);
+        assert_eq!(reclaimed.attempts, 2);
+        assert_eq!(reclaimed.started_at, claimed.started_at);
+
+        let mut finished = reclaimed;
+        finished.status = OperationStatus::Succeeded;
+        finished.finished_at = Some(Utc::now());
+        repo.update_operation(&finished, "other").await.unwrap();
+        assert!(repo.claim_operation("worker", -1).await.unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn get_operations_filters_by_cluster() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        let node = repo
+            .create_node(&create_test_node("NODE", cluster_id))
+            .await
+            .unwrap();
+        let first = repo
+            .create_operation(&Operation::new(node.id, OperationType::PowerOff))
+            .await
+            .unwrap();
+        let second = repo
+            .create_operation(&Operation::new(node.id, OperationType::PowerOn))
+            .await
+            .unwrap();
+
+        let filter = OperationFilter {
+            cluster_id: Some(cluster_id),
+            ..Default::default()
+        };
+        assert_eq!(
+            repo.get_operations(&filter).await.unwrap(),
+            vec![second, first.clone()]
+        );
+
+        let filter = OperationFilter {
+            operation_type: Some(OperationType::PowerOff),
+            ..Default::default()
+        };
+        assert_eq!(repo.get_operations(&filter).await.unwrap(), vec![first]);
+
+        let filter = OperationFilter {
+            cluster_id: Some(uuid::Uuid::new_v4()),
+            ..Default::default()
+        };
+        assert!(repo.get_operations(&filter).await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn create_batch_is_all_or_nothing() {
+        let store = InMemoryStore::default();
+        repository_tests::create_batch_is_all_or_nothing(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_only_finished_by_the_worker_holding_them() {
+        let store = InMemoryStore::default();
+        repository_tests::operations_are_only_finished_by_the_worker_holding_them(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn operations_of_a_node_are_claimed_one_at_a_time() {
+        let store = InMemoryStore::default();
+        repository_tests::operations_of_a_node_are_claimed_one_at_a_time(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_listed_newest_first_by_pages() {
+        let store = InMemoryStore::default();
+        repository_tests::operations_are_listed_newest_first_by_pages(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+}
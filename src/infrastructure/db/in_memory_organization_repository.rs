@@ -0,0 +1,106 @@
+use super::InMemoryStore;
+use crate::domain::{
+    models::Organization,
+    repository::{OrganizationRepository, RepositoryError, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryOrganizationRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryOrganizationRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for InMemoryOrganizationRepository {
+    #[instrument(skip(self))]
+    async fn get_organizations(&self) -> RepositoryResult<Vec<Organization>> {
+        let tables = self.store.tables.read()?;
+        let mut organizations: Vec<Organization> = tables.organizations.values().cloned().collect();
+        organizations.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(organizations)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_organization(&self, organization_id: &Uuid) -> RepositoryResult<Organization> {
+        let tables = self.store.tables.read()?;
+        tables
+            .organizations
+            .get(organization_id)
+            .cloned()
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    #[instrument(skip(self))]
+    async fn create_organization(
+        &self,
+        organization: &Organization,
+    ) -> RepositoryResult<Organization> {
+        let mut tables = self.store.tables.write()?;
+        if tables.organizations.contains_key(&organization.id)
+            || tables
+                .organizations
+                .values()
+                .any(|o| o.name == organization.name)
+        {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        let organization = Organization {
+            created_at: Some(Utc::now()),
+            ..organization.clone()
+        };
+        tables
+            .organizations
+            .insert(organization.id, organization.clone());
+        Ok(organization)
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_organization(&self, organization_id: &Uuid) -> RepositoryResult<Uuid> {
+        let mut tables = self.store.tables.write()?;
+        if !tables.organizations.contains_key(organization_id) {
+            return Err(RepositoryError::DoesNotExist);
+        }
+        if tables
+            .clusters
+            .values()
+            .any(|cluster| cluster.tenant_id == Some(*organization_id))
+        {
+            return Err(RepositoryError::ForeignKeyViolation);
+        }
+
+        tables
+            .api_keys
+            .retain(|_, stored| stored.key.tenant_id != Some(*organization_id));
+        tables.organizations.remove(organization_id);
+        Ok(*organization_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{
+        repository_tests, InMemoryClusterRepository, InMemoryNodeRepository,
+    };
+
+    #[actix_rt::test]
+    async fn tenants_only_see_their_own_data() {
+        let store = InMemoryStore::default();
+        repository_tests::tenants_only_see_their_own_data(
+            InMemoryOrganizationRepository::new(store.clone()),
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,88 @@
+use super::InMemoryStore;
+use crate::domain::{
+    models::RoleBinding,
+    repository::{RepositoryError, RepositoryResult, RoleBindingRepository},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryRoleBindingRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryRoleBindingRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl RoleBindingRepository for InMemoryRoleBindingRepository {
+    #[instrument(skip(self))]
+    async fn get_role_bindings<'a>(
+        &self,
+        subject: Option<&'a str>,
+    ) -> RepositoryResult<Vec<RoleBinding>> {
+        let tables = self.store.tables.read()?;
+        let mut bindings: Vec<RoleBinding> = tables
+            .role_bindings
+            .values()
+            .filter(|binding| subject.is_none_or(|subject| binding.subject == subject))
+            .cloned()
+            .collect();
+        bindings.sort_by_key(|binding| (binding.created_at, binding.id));
+        Ok(bindings)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<RoleBinding> {
+        let tables = self.store.tables.read()?;
+        tables
+            .role_bindings
+            .get(role_binding_id)
+            .cloned()
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    #[instrument(skip(self))]
+    async fn create_role_binding(
+        &self,
+        role_binding: &RoleBinding,
+    ) -> RepositoryResult<RoleBinding> {
+        let mut tables = self.store.tables.write()?;
+        if tables.role_bindings.contains_key(&role_binding.id)
+            || tables.role_bindings.values().any(|binding| {
+                binding.subject == role_binding.subject
+                    && binding.role == role_binding.role
+                    && binding.cluster_id == role_binding.cluster_id
+            })
+        {
+            return Err(RepositoryError::AlreadyExists);
+        }
+        if let Some(cluster_id) = &role_binding.cluster_id {
+            if !tables.clusters.contains_key(cluster_id) {
+                return Err(RepositoryError::ForeignKeyViolation);
+            }
+        }
+
+        let binding = RoleBinding {
+            created_at: Some(Utc::now()),
+            ..role_binding.clone()
+        };
+        tables.role_bindings.insert(binding.id, binding.clone());
+        Ok(binding)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<Uuid> {
+        let mut tables = self.store.tables.write()?;
+        tables
+            .role_bindings
+            .remove(role_binding_id)
+            .map(|binding| binding.id)
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+}
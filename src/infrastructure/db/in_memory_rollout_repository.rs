@@ -0,0 +1,236 @@
+use super::{
+    in_memory_store::{check_version, StoredRollout},
+    InMemoryStore,
+};
+use crate::domain::{
+    models::{Rollout, RolloutNode, RolloutStatus},
+    repository::{RepositoryError, RepositoryResult, RolloutRepository},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryRolloutRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryRolloutRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl RolloutRepository for InMemoryRolloutRepository {
+    #[instrument(skip(self))]
+    async fn get_rollout(&self, rollout_id: &Uuid) -> RepositoryResult<Rollout> {
+        let tables = self.store.tables.read()?;
+        tables
+            .rollouts
+            .get(rollout_id)
+            .map(|stored| stored.rollout.clone())
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    #[instrument(skip(self, rollout), fields(rollout_id = %rollout.id))]
+    async fn create_rollout(&self, rollout: &Rollout) -> RepositoryResult<Rollout> {
+        let mut tables = self.store.tables.write()?;
+        if !tables.clusters.contains_key(&rollout.cluster_id) {
+            return Err(RepositoryError::ForeignKeyViolation);
+        }
+        if tables.rollouts.contains_key(&rollout.id) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        let rollout = Rollout {
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            version: 1,
+            ..rollout.clone()
+        };
+        tables.rollouts.insert(
+            rollout.id,
+            StoredRollout {
+                rollout: rollout.clone(),
+                locked_by: None,
+                locked_until: None,
+            },
+        );
+        Ok(rollout)
+    }
+
+    #[instrument(skip(self, nodes))]
+    async fn update_rollout(
+        &self,
+        rollout_id: &Uuid,
+        status: RolloutStatus,
+        nodes: &[RolloutNode],
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Rollout> {
+        let mut tables = self.store.tables.write()?;
+        let stored = tables
+            .rollouts
+            .get_mut(rollout_id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        check_version(stored.rollout.version, expected_version.as_deref())?;
+
+        for node in nodes {
+            if let Some(stored_node) = stored
+                .rollout
+                .nodes
+                .iter_mut()
+                .find(|n| n.node_id == node.node_id)
+            {
+                *stored_node = node.clone();
+            }
+        }
+        stored.rollout.status = status;
+        stored.rollout.updated_at = Some(Utc::now());
+        stored.rollout.version += 1;
+        Ok(stored.rollout.clone())
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_rollout(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Rollout>> {
+        let mut tables = self.store.tables.write()?;
+        let now = Utc::now();
+        let next = tables
+            .rollouts
+            .values_mut()
+            .filter(|stored| {
+                matches!(
+                    stored.rollout.status,
+                    RolloutStatus::Running | RolloutStatus::Paused
+                ) && stored.locked_until.is_none_or(|until| until < now)
+            })
+            // `None` sorts first, like `NULLS FIRST`
+            .min_by_key(|stored| stored.locked_until);
+
+        Ok(next.map(|stored| {
+            stored.locked_by = Some(worker_id.to_string());
+            stored.locked_until = Some(now + Duration::seconds(lease_secs));
+            stored.rollout.clone()
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn release_rollout(
+        &self,
+        rollout_id: &Uuid,
+        worker_id: &str,
+        delay_secs: i64,
+    ) -> RepositoryResult<()> {
+        let mut tables = self.store.tables.write()?;
+        // if the lease expired, the rollout may belong to another worker by now
+        if let Some(stored) = tables
+            .rollouts
+            .get_mut(rollout_id)
+            .filter(|stored| stored.locked_by.as_deref() == Some(worker_id))
+        {
+            stored.locked_by = None;
+            stored.locked_until = Some(Utc::now() + Duration::seconds(delay_secs));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{models::Cluster, repository::ClusterRepository},
+        infrastructure::db::{repository_tests, InMemoryClusterRepository},
+    };
+
+    async fn prepare_repo() -> (InMemoryRolloutRepository, InMemoryClusterRepository, Uuid) {
+        let store = InMemoryStore::default();
+        let cluster_repo = InMemoryClusterRepository::new(store.clone());
+        let cluster = cluster_repo
+            .create_cluster(&Cluster {
+                id: Uuid::new_v4(),
+                name: "CLUSTER".to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        (
+            InMemoryRolloutRepository::new(store),
+            cluster_repo,
+            cluster.id,
+        )
+    }
+
+    #[actix_rt::test]
+    async fn create_checks_the_cluster_and_deletion_cascades() {
+        let (repo, cluster_repo, cluster_id) = prepare_repo().await;
+        let result = repo
+            .create_rollout(&Rollout::new(Uuid::new_v4(), 1, 0, [Uuid::new_v4()]))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+
+        let rollout = repo
+            .create_rollout(&Rollout::new(cluster_id, 1, 0, [Uuid::new_v4()]))
+            .await
+            .unwrap();
+        assert_eq!(rollout.version, 1);
+        assert!(rollout.created_at.is_some());
+
+        cluster_repo
+            .delete_cluster(&cluster_id, None)
+            .await
+            .unwrap();
+        let result = repo.get_rollout(&rollout.id).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn rollout_updates_check_the_version() {
+        let store = InMemoryStore::default();
+        repository_tests::rollout_updates_check_the_version(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryRolloutRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn claim_rollout_leases_unfinished_rollouts() {
+        let (repo, _, cluster_id) = prepare_repo().await;
+        let rollout = repo
+            .create_rollout(&Rollout::new(cluster_id, 1, 0, [Uuid::new_v4()]))
+            .await
+            .unwrap();
+
+        let claimed = repo.claim_rollout("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, rollout.id);
+        assert!(repo.claim_rollout("other", 60).await.unwrap().is_none());
+
+        // only the worker holding the lease can release it
+        repo.release_rollout(&rollout.id, "other", -1)
+            .await
+            .unwrap();
+        assert!(repo.claim_rollout("other", 60).await.unwrap().is_none());
+        repo.release_rollout(&rollout.id, "worker", -1)
+            .await
+            .unwrap();
+        assert!(repo.claim_rollout("other", 60).await.unwrap().is_some());
+
+        repo.update_rollout(&rollout.id, RolloutStatus::Succeeded, &[], None)
+            .await
+            .unwrap();
+        repo.release_rollout(&rollout.id, "other", -1)
+            .await
+            .unwrap();
+        assert!(repo.claim_rollout("worker", 60).await.unwrap().is_none());
+    }
+}
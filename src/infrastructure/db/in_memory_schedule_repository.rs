@@ -0,0 +1,408 @@
+use super::{
+    in_memory_store::{check_version, StoredSchedule, Tables},
+    InMemoryStore,
+};
+use crate::domain::{
+    models::{ScheduleRun, ScheduleTarget, ScheduledOperation},
+    repository::{RepositoryError, RepositoryResult, ScheduleRepository},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InMemoryScheduleRepository {
+    store: InMemoryStore,
+}
+
+impl InMemoryScheduleRepository {
+    pub fn new(store: InMemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+/// Fails like the foreign keys of the target of a schedule.
+fn check_target(tables: &Tables, target: &ScheduleTarget) -> RepositoryResult<()> {
+    let exists = match target {
+        ScheduleTarget::NodeId(node_id) => tables.nodes.contains_key(node_id),
+        ScheduleTarget::ClusterId(cluster_id) => tables.clusters.contains_key(cluster_id),
+    };
+    if exists {
+        Ok(())
+    } else {
+        Err(RepositoryError::ForeignKeyViolation)
+    }
+}
+
+#[async_trait]
+impl ScheduleRepository for InMemoryScheduleRepository {
+    #[instrument(skip(self))]
+    async fn get_schedules(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> RepositoryResult<Vec<ScheduledOperation>> {
+        let tables = self.store.tables.read()?;
+        let tenant_of = |cluster_id: &Uuid| {
+            tables
+                .clusters
+                .get(cluster_id)
+                .and_then(|cluster| cluster.tenant_id)
+        };
+        let mut schedules: Vec<ScheduledOperation> = tables
+            .schedules
+            .values()
+            .filter(|stored| {
+                tenant_id.is_none()
+                    || match stored.schedule.target {
+                        ScheduleTarget::ClusterId(cluster_id) => {
+                            tenant_of(&cluster_id) == tenant_id
+                        }
+                        ScheduleTarget::NodeId(node_id) => {
+                            tables
+                                .nodes
+                                .get(&node_id)
+                                .and_then(|node| tenant_of(&node.cluster_id))
+                                == tenant_id
+                        }
+                    }
+            })
+            .map(|stored| stored.schedule.clone())
+            .collect();
+        schedules.sort_by_key(|schedule| (schedule.created_at, schedule.id));
+        Ok(schedules)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_schedule(&self, schedule_id: &Uuid) -> RepositoryResult<ScheduledOperation> {
+        let tables = self.store.tables.read()?;
+        tables
+            .schedules
+            .get(schedule_id)
+            .map(|stored| stored.schedule.clone())
+            .ok_or(RepositoryError::DoesNotExist)
+    }
+
+    #[instrument(skip(self))]
+    async fn create_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+    ) -> RepositoryResult<ScheduledOperation> {
+        let mut tables = self.store.tables.write()?;
+        check_target(&tables, &schedule.target)?;
+        if tables.schedules.contains_key(&schedule.id) {
+            return Err(RepositoryError::AlreadyExists);
+        }
+
+        let schedule = ScheduledOperation {
+            last_run_at: None,
+            last_batch_id: None,
+            last_error: None,
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            version: 1,
+            ..schedule.clone()
+        };
+        tables.schedules.insert(
+            schedule.id,
+            StoredSchedule {
+                schedule: schedule.clone(),
+                locked_by: None,
+                locked_until: None,
+                run_batch_id: None,
+            },
+        );
+        Ok(schedule)
+    }
+
+    #[instrument(skip(self))]
+    async fn update_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<ScheduledOperation> {
+        let mut tables = self.store.tables.write()?;
+        let stored = tables
+            .schedules
+            .get(&schedule.id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        check_version(stored.schedule.version, expected_version.as_deref())?;
+        check_target(&tables, &schedule.target)?;
+
+        let stored = tables
+            .schedules
+            .get_mut(&schedule.id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        stored.schedule = ScheduledOperation {
+            last_run_at: stored.schedule.last_run_at,
+            last_batch_id: stored.schedule.last_batch_id,
+            last_error: stored.schedule.last_error.clone(),
+            created_at: stored.schedule.created_at,
+            updated_at: Some(Utc::now()),
+            version: stored.schedule.version + 1,
+            ..schedule.clone()
+        };
+        Ok(stored.schedule.clone())
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_schedule(
+        &self,
+        schedule_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Uuid> {
+        let mut tables = self.store.tables.write()?;
+        let stored = tables
+            .schedules
+            .get(schedule_id)
+            .ok_or(RepositoryError::DoesNotExist)?;
+        check_version(stored.schedule.version, expected_version.as_deref())?;
+        tables.schedules.remove(schedule_id);
+        Ok(*schedule_id)
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_schedule(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<ScheduledOperation>> {
+        let mut tables = self.store.tables.write()?;
+        let now = Utc::now();
+        let next = tables
+            .schedules
+            .values_mut()
+            .filter(|stored| {
+                stored.schedule.enabled
+                    && stored.schedule.next_run_at.is_some_and(|next| next <= now)
+                    && stored.locked_until.is_none_or(|until| until < now)
+            })
+            .min_by_key(|stored| stored.schedule.next_run_at);
+
+        Ok(next.map(|stored| {
+            stored.locked_by = Some(worker_id.to_string());
+            stored.locked_until = Some(now + Duration::seconds(lease_secs));
+            stored.schedule.clone()
+        }))
+    }
+
+    #[instrument(skip(self))]
+    async fn reserve_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        batch_id: &Uuid,
+    ) -> RepositoryResult<Uuid> {
+        let mut tables = self.store.tables.write()?;
+        let stored = tables
+            .schedules
+            .get_mut(schedule_id)
+            .filter(|stored| stored.locked_by.as_deref() == Some(worker_id))
+            .ok_or(RepositoryError::DoesNotExist)?;
+        Ok(*stored.run_batch_id.get_or_insert(*batch_id))
+    }
+
+    #[instrument(skip(self))]
+    async fn record_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        version: i64,
+        run: &ScheduleRun,
+    ) -> RepositoryResult<()> {
+        let mut tables = self.store.tables.write()?;
+        // if the lease expired, the schedule may belong to another worker by now
+        if let Some(stored) = tables
+            .schedules
+            .get_mut(schedule_id)
+            .filter(|stored| stored.locked_by.as_deref() == Some(worker_id))
+        {
+            stored.schedule.last_run_at = Some(run.ran_at);
+            stored.schedule.last_batch_id = run.batch_id;
+            stored.schedule.last_error = run.error.clone();
+            if stored.schedule.version == version {
+                stored.schedule.next_run_at = run.next_run_at;
+            }
+            stored.locked_by = None;
+            stored.locked_until = None;
+            stored.run_batch_id = None;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{Cluster, MissedRunPolicy, OperationType},
+            repository::ClusterRepository,
+        },
+        infrastructure::db::{
+            repository_tests, InMemoryClusterRepository, InMemoryNodeRepository,
+            InMemoryOrganizationRepository,
+        },
+    };
+
+    async fn prepare_repo() -> (InMemoryScheduleRepository, InMemoryClusterRepository, Uuid) {
+        let store = InMemoryStore::default();
+        let cluster_repo = InMemoryClusterRepository::new(store.clone());
+        let cluster = cluster_repo
+            .create_cluster(&Cluster {
+                id: Uuid::new_v4(),
+                name: "CLUSTER".to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        (
+            InMemoryScheduleRepository::new(store),
+            cluster_repo,
+            cluster.id,
+        )
+    }
+
+    fn create_test_schedule(cluster_id: Uuid, next_run_in_secs: i64) -> ScheduledOperation {
+        ScheduledOperation {
+            id: Uuid::new_v4(),
+            operation_type: OperationType::Reboot,
+            target: ScheduleTarget::ClusterId(cluster_id),
+            run_at: None,
+            cron: Some("0 3 * * Sun".to_string()),
+            missed_run_policy: MissedRunPolicy::RunOnce,
+            enabled: true,
+            next_run_at: Some(Utc::now() + Duration::seconds(next_run_in_secs)),
+            last_run_at: None,
+            last_batch_id: None,
+            last_error: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_checks_the_target_and_deletion_cascades() {
+        let (repo, cluster_repo, cluster_id) = prepare_repo().await;
+        let result = repo
+            .create_schedule(&create_test_schedule(Uuid::new_v4(), 60))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+
+        let schedule = repo
+            .create_schedule(&create_test_schedule(cluster_id, 60))
+            .await
+            .unwrap();
+        assert_eq!(schedule.version, 1);
+        assert_eq!(
+            repo.get_schedules(None).await.unwrap(),
+            vec![schedule.clone()]
+        );
+
+        cluster_repo
+            .delete_cluster(&cluster_id, None)
+            .await
+            .unwrap();
+        let result = repo.get_schedule(&schedule.id).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn claim_schedule_takes_due_schedules() {
+        let (repo, _, cluster_id) = prepare_repo().await;
+        repo.create_schedule(&create_test_schedule(cluster_id, 60))
+            .await
+            .unwrap();
+        let mut disabled = create_test_schedule(cluster_id, -60);
+        disabled.enabled = false;
+        repo.create_schedule(&disabled).await.unwrap();
+        let due = repo
+            .create_schedule(&create_test_schedule(cluster_id, -10))
+            .await
+            .unwrap();
+
+        let claimed = repo.claim_schedule("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, due.id);
+        assert!(repo.claim_schedule("other", 60).await.unwrap().is_none());
+
+        let next_run_at = Some(Utc::now() + Duration::days(7));
+        let run = ScheduleRun {
+            ran_at: Utc::now(),
+            batch_id: None,
+            error: Some("boom".to_string()),
+            next_run_at,
+        };
+        // only the worker holding the lease can record the run
+        repo.record_run(&due.id, "other", 1, &run).await.unwrap();
+        assert!(repo
+            .get_schedule(&due.id)
+            .await
+            .unwrap()
+            .last_run_at
+            .is_none());
+        repo.record_run(&due.id, "worker", 1, &run).await.unwrap();
+        let ran = repo.get_schedule(&due.id).await.unwrap();
+        assert_eq!(ran.last_run_at, Some(run.ran_at));
+        assert_eq!(ran.last_error, run.error);
+        assert_eq!(ran.next_run_at, next_run_at);
+        assert_eq!(ran.version, 1);
+        assert!(repo.claim_schedule("worker", 60).await.unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn runs_reserve_a_single_batch() {
+        let store = InMemoryStore::default();
+        repository_tests::runs_reserve_a_single_batch(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryScheduleRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn schedules_are_filtered_by_tenant() {
+        let store = InMemoryStore::default();
+        repository_tests::schedules_are_filtered_by_tenant(
+            InMemoryOrganizationRepository::new(store.clone()),
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store.clone()),
+            InMemoryScheduleRepository::new(store),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn runs_keep_the_next_run_of_a_newer_definition() {
+        let (repo, _, cluster_id) = prepare_repo().await;
+        let schedule = repo
+            .create_schedule(&create_test_schedule(cluster_id, -10))
+            .await
+            .unwrap();
+        repo.claim_schedule("worker", 60).await.unwrap().unwrap();
+
+        let mut updated = schedule.clone();
+        updated.next_run_at = Some(Utc::now() + Duration::hours(1));
+        let updated = repo.update_schedule(&updated, Some(vec![1])).await.unwrap();
+        assert_eq!(updated.version, 2);
+        let result = repo.update_schedule(&updated, Some(vec![1])).await;
+        assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+
+        let run = ScheduleRun {
+            ran_at: Utc::now(),
+            batch_id: None,
+            error: None,
+            next_run_at: Some(Utc::now() + Duration::days(7)),
+        };
+        repo.record_run(&schedule.id, "worker", 1, &run)
+            .await
+            .unwrap();
+        let ran = repo.get_schedule(&schedule.id).await.unwrap();
+        assert_eq!(ran.last_run_at, Some(run.ran_at));
+        assert_eq!(ran.next_run_at, updated.next_run_at);
+    }
+}
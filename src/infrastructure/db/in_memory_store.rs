@@ -0,0 +1,107 @@
+use crate::domain::{
+    models::{
+        ApiKey, AuditEvent, Batch, Cluster, IdempotencyRecord, Node, Operation, Organization,
+        RoleBinding, Rollout, ScheduleTarget, ScheduledOperation,
+    },
+    repository::{RepositoryError, RepositoryResult},
+};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use uuid::Uuid;
+
+/// Operation plus the lease information that Postgres keeps in the
+/// `locked_by` and `locked_until` columns.
+#[derive(Debug, Clone)]
+pub(super) struct StoredOperation {
+    pub operation: Operation,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Rollout plus the lease of the worker that is advancing it.
+#[derive(Debug, Clone)]
+pub(super) struct StoredRollout {
+    pub rollout: Rollout,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+/// Schedule plus the lease of the scheduler that is running it, and the batch reserved
+/// for the run.
+#[derive(Debug, Clone)]
+pub(super) struct StoredSchedule {
+    pub schedule: ScheduledOperation,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub run_batch_id: Option<Uuid>,
+}
+
+/// API key plus the hash of its secret.
+#[derive(Debug, Clone)]
+pub(super) struct StoredApiKey {
+    pub key: ApiKey,
+    pub secret_hash: String,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Tables {
+    pub organizations: HashMap<Uuid, Organization>,
+    pub clusters: HashMap<Uuid, Cluster>,
+    pub nodes: HashMap<Uuid, Node>,
+    pub operations: HashMap<Uuid, StoredOperation>,
+    pub batches: HashMap<Uuid, Batch>,
+    pub rollouts: HashMap<Uuid, StoredRollout>,
+    pub schedules: HashMap<Uuid, StoredSchedule>,
+    /// By owner and key.
+    pub idempotency_keys: HashMap<(String, String), IdempotencyRecord>,
+    pub api_keys: HashMap<Uuid, StoredApiKey>,
+    pub role_bindings: HashMap<Uuid, RoleBinding>,
+    /// Only ever appended to.
+    pub audit_events: Vec<AuditEvent>,
+}
+
+impl Tables {
+    /// Removes the matching nodes along with their operations and schedules, the same
+    /// way the `ON DELETE CASCADE` constraints do.
+    pub fn cascade_delete_nodes(&mut self, predicate: impl Fn(&Node) -> bool) {
+        let node_ids: Vec<Uuid> = self
+            .nodes
+            .values()
+            .filter(|node| predicate(node))
+            .map(|node| node.id)
+            .collect();
+        for node_id in &node_ids {
+            self.nodes.remove(node_id);
+        }
+        self.operations
+            .retain(|_, stored| !node_ids.contains(&stored.operation.node_id));
+        self.schedules
+            .retain(|_, stored| match stored.schedule.target {
+                ScheduleTarget::NodeId(node_id) => !node_ids.contains(&node_id),
+                ScheduleTarget::ClusterId(_) => true,
+            });
+    }
+}
+
+/// Fails like the conditional writes of the databases when the entity has another version.
+pub(super) fn check_version(
+    version: i64,
+    expected_version: Option<&[i64]>,
+) -> RepositoryResult<()> {
+    match expected_version {
+        Some(expected) if !expected.contains(&version) => Err(RepositoryError::VersionMismatch),
+        _ => Ok(()),
+    }
+}
+
+/// Shared state of the in-memory repositories.
+///
+/// Both repositories must be created from clones of the same store so
+/// that nodes can see their clusters and cluster deletions cascade to them.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    pub(super) tables: Arc<RwLock<Tables>>,
+}
@@ -1,14 +1,311 @@
+mod audit_queries;
 mod entities;
+mod in_memory_api_key_repository;
+mod in_memory_audit_repository;
+mod in_memory_cluster_repository;
+mod in_memory_idempotency_repository;
+mod in_memory_node_repository;
+mod in_memory_organization_repository;
+mod in_memory_role_binding_repository;
+mod in_memory_rollout_repository;
+mod in_memory_schedule_repository;
+mod in_memory_store;
+mod node_queries;
+mod pagination;
+mod postgres_api_key_repository;
+mod postgres_audit_repository;
 mod postgres_cluster_repository;
+mod postgres_idempotency_repository;
 mod postgres_node_repository;
+mod postgres_organization_repository;
+mod postgres_pool_collector;
+mod postgres_role_binding_repository;
+mod postgres_rollout_repository;
+mod postgres_schedule_repository;
+mod query_builder;
+/// Tests that the repositories must pass whatever their storage. The tests of every
+/// storage run them with their own repositories.
+#[cfg(test)]
+mod repository_tests;
+#[cfg(feature = "sqlite")]
+mod sqlite_api_key_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_audit_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_cluster_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_idempotency_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_node_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_organization_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_role_binding_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_rollout_repository;
+#[cfg(feature = "sqlite")]
+mod sqlite_schedule_repository;
 
+pub use in_memory_api_key_repository::InMemoryApiKeyRepository;
+pub use in_memory_audit_repository::InMemoryAuditRepository;
+pub use in_memory_cluster_repository::InMemoryClusterRepository;
+pub use in_memory_idempotency_repository::InMemoryIdempotencyRepository;
+pub use in_memory_node_repository::InMemoryNodeRepository;
+pub use in_memory_organization_repository::InMemoryOrganizationRepository;
+pub use in_memory_role_binding_repository::InMemoryRoleBindingRepository;
+pub use in_memory_rollout_repository::InMemoryRolloutRepository;
+pub use in_memory_schedule_repository::InMemoryScheduleRepository;
+pub use in_memory_store::InMemoryStore;
+pub use postgres_api_key_repository::PostgresApiKeyRepository;
+pub use postgres_audit_repository::PostgresAuditRepository;
 pub use postgres_cluster_repository::PostgresClusterRepository;
+pub use postgres_idempotency_repository::PostgresIdempotencyRepository;
 pub use postgres_node_repository::PostgresNodeRepository;
+pub use postgres_organization_repository::PostgresOrganizationRepository;
+pub use postgres_pool_collector::PostgresPoolCollector;
+pub use postgres_role_binding_repository::PostgresRoleBindingRepository;
+pub use postgres_rollout_repository::PostgresRolloutRepository;
+pub use postgres_schedule_repository::PostgresScheduleRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_api_key_repository::SqliteApiKeyRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_audit_repository::SqliteAuditRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_cluster_repository::SqliteClusterRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_idempotency_repository::SqliteIdempotencyRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_node_repository::SqliteNodeRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_organization_repository::SqliteOrganizationRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_role_binding_repository::SqliteRoleBindingRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_rollout_repository::SqliteRolloutRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite_schedule_repository::SqliteScheduleRepository;
 
-use crate::domain::repository::RepositoryError;
+use crate::domain::repository::{Change, RepositoryError, RepositoryResult};
+
+// Postgres SQLSTATE codes and SQLite extended result codes.
+const UNIQUE_VIOLATION_CODES: &[&str] = &["23505", "1555", "2067"];
+const FOREIGN_KEY_VIOLATION_CODES: &[&str] = &["23503", "787"];
+const INVALID_TEXT_REPRESENTATION_CODE: &str = "22P02";
+// SQLite sometimes reports constraint errors with the generic `SQLITE_ERROR`
+// code (e.g. when they're raised while stepping through a `RETURNING` clause),
+// so the message is the only reliable thing to look at.
+const SQLITE_UNIQUE_VIOLATION_MESSAGE: &str = "UNIQUE constraint failed";
+const SQLITE_FOREIGN_KEY_VIOLATION_MESSAGE: &str = "FOREIGN KEY constraint failed";
+
+fn classify_database_error(error: &dyn sqlx::error::DatabaseError) -> Option<RepositoryError> {
+    let code = error.code();
+    let code = code.as_deref().unwrap_or_default();
+    let message = error.message();
+
+    if UNIQUE_VIOLATION_CODES.contains(&code)
+        || message.starts_with(SQLITE_UNIQUE_VIOLATION_MESSAGE)
+    {
+        Some(RepositoryError::AlreadyExists)
+    } else if FOREIGN_KEY_VIOLATION_CODES.contains(&code)
+        || message.starts_with(SQLITE_FOREIGN_KEY_VIOLATION_MESSAGE)
+    {
+        Some(RepositoryError::ForeignKeyViolation)
+    } else if code == INVALID_TEXT_REPRESENTATION_CODE {
+        Some(RepositoryError::InvalidId)
+    } else {
+        None
+    }
+}
 
 impl From<sqlx::Error> for RepositoryError {
     fn from(error: sqlx::Error) -> Self {
-        RepositoryError::Generic(Box::new(error))
+        match &error {
+            sqlx::Error::RowNotFound => RepositoryError::DoesNotExist,
+            sqlx::Error::Database(db_error) => classify_database_error(db_error.as_ref())
+                .unwrap_or_else(|| RepositoryError::Generic(Box::new(error))),
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => RepositoryError::Unavailable(error.to_string()),
+            _ => RepositoryError::Generic(Box::new(error)),
+        }
+    }
+}
+
+/// Change made by a conditional write, from the rows it returned: the row as it was
+/// before, if it exists, then the row as it is after, if it had the expected version.
+fn change_of<D: Into<T>, T>(rows: Vec<D>) -> RepositoryResult<Change<T>> {
+    let mut rows = rows.into_iter().map(Into::into);
+    match (rows.next(), rows.next()) {
+        (Some(before), Some(after)) => Ok(Change { before, after }),
+        (Some(_), None) => Err(RepositoryError::VersionMismatch),
+        (None, _) => Err(RepositoryError::DoesNotExist),
+    }
+}
+
+/// Rows for [`change_of`] of a conditional write in SQLite, which can only return the row
+/// after it: the row read by `previous`, then the one returned by `write` if it ran. Both
+/// run in the same transaction.
+#[cfg(feature = "sqlite")]
+async fn sqlite_change_rows<'q, D>(
+    pool: &sqlx::SqlitePool,
+    previous: sqlx::query::QueryAs<'q, sqlx::Sqlite, D, sqlx::sqlite::SqliteArguments<'q>>,
+    write: sqlx::query::QueryAs<'q, sqlx::Sqlite, D, sqlx::sqlite::SqliteArguments<'q>>,
+) -> Result<Vec<D>, sqlx::Error>
+where
+    D: Send + Unpin + for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow>,
+{
+    let mut tx = pool.begin().await?;
+    let mut rows: Vec<D> = previous
+        .fetch_optional(&mut tx)
+        .await?
+        .into_iter()
+        .collect();
+    if !rows.is_empty() {
+        rows.extend(write.fetch_optional(&mut tx).await?);
+    }
+    tx.commit().await?;
+    Ok(rows)
+}
+
+/// Opens a SQLite database, creating the file if needed, and runs the
+/// migrations in `migrations/sqlite` so the single binary can start on its own.
+#[cfg(feature = "sqlite")]
+pub async fn sqlite_pool(conn_str: &str) -> Result<sqlx::SqlitePool, sqlx::Error> {
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use std::str::FromStr;
+
+    let options = SqliteConnectOptions::from_str(conn_str)?.create_if_missing(true);
+    let pool_options = if conn_str.contains(":memory:") {
+        // every connection gets its own in-memory database, so we must stick to one
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        SqlitePoolOptions::new()
+    };
+    let pool = pool_options.connect_with(options).await?;
+    sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+    Ok(pool)
+}
+
+/// Pool to the Postgres database of `TEST_DATABASE_URL`, or `None` if it isn't set so the
+/// tests that need it can be skipped.
+///
+/// Every pool gets its own database with all the migrations, so the tests can run at the
+/// same time without seeing each other's data. The databases are left behind, so the
+/// server should only be used for the tests.
+#[cfg(test)]
+pub async fn postgres_test_pool() -> Option<sqlx::PgPool> {
+    use sqlx::{postgres::PgConnectOptions, Connection, Executor, PgConnection};
+
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    // not a schema, since sqlx looks the custom types up by name whatever their schema
+    let database = format!("test_{}", uuid::Uuid::new_v4().to_simple());
+    let mut conn = PgConnection::connect(&url)
+        .await
+        .expect("Can't connect to TEST_DATABASE_URL");
+    conn.execute(format!("CREATE DATABASE {}", database).as_str())
+        .await
+        .unwrap();
+    conn.close().await.unwrap();
+
+    let options = url.parse::<PgConnectOptions>().unwrap().database(&database);
+    let pool = sqlx::PgPool::connect_with(options).await.unwrap();
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("Can't run the migrations in the test database");
+    Some(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{borrow::Cow, error::Error, fmt};
+
+    #[derive(Debug)]
+    struct TestDatabaseError {
+        code: &'static str,
+        message: &'static str,
+    }
+
+    impl fmt::Display for TestDatabaseError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}: {}", self.code, self.message)
+        }
+    }
+
+    impl Error for TestDatabaseError {}
+
+    impl sqlx::error::DatabaseError for TestDatabaseError {
+        fn message(&self) -> &str {
+            self.message
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.code))
+        }
+
+        fn as_error(&self) -> &(dyn Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn Error + Send + Sync + 'static> {
+            self
+        }
+    }
+
+    fn database_error(code: &'static str, message: &'static str) -> RepositoryError {
+        sqlx::Error::Database(Box::new(TestDatabaseError { code, message })).into()
+    }
+
+    #[test]
+    fn sqlx_errors_are_classified() {
+        assert!(matches!(
+            sqlx::Error::RowNotFound.into(),
+            RepositoryError::DoesNotExist
+        ));
+        assert!(matches!(
+            database_error("23505", "duplicate key value violates unique constraint"),
+            RepositoryError::AlreadyExists
+        ));
+        assert!(matches!(
+            database_error("1", "UNIQUE constraint failed: nodes.name"),
+            RepositoryError::AlreadyExists
+        ));
+        assert!(matches!(
+            database_error(
+                "23503",
+                "insert or update on table violates foreign key constraint"
+            ),
+            RepositoryError::ForeignKeyViolation
+        ));
+        assert!(matches!(
+            database_error("1", "FOREIGN KEY constraint failed"),
+            RepositoryError::ForeignKeyViolation
+        ));
+        assert!(matches!(
+            database_error("22P02", "invalid input syntax for type uuid"),
+            RepositoryError::InvalidId
+        ));
+        assert!(matches!(
+            database_error("42P01", "relation does not exist"),
+            RepositoryError::Generic(_)
+        ));
+        assert!(matches!(
+            sqlx::Error::PoolTimedOut.into(),
+            RepositoryError::Unavailable(_)
+        ));
+        assert!(matches!(
+            sqlx::Error::Io(std::io::ErrorKind::ConnectionRefused.into()).into(),
+            RepositoryError::Unavailable(_)
+        ));
     }
 }
@@ -0,0 +1,114 @@
+use super::{
+    entities::{DbNodeStatus, DbOperation, DbOperationStatus, DbOperationType},
+    query_builder::{like_pattern, QueryBuilder},
+};
+use crate::domain::{
+    models::Operation,
+    repository::node_repository::{NameMatch, NodeFilter},
+};
+use chrono::{DateTime, Utc};
+use sqlx::{database::HasArguments, Database, Encode, Executor, FromRow, IntoArguments, Type};
+use uuid::Uuid;
+
+pub(super) const NODES_FROM: &str = "FROM nodes n JOIN clusters c ON n.cluster_id = c.id";
+
+/// Conditions of the filter of nodes, given the condition of each storage matching
+/// the name of a node or its cluster.
+///
+/// The `LIKE` conditions are given an escaped pattern, the others the name itself.
+pub(super) fn filter_nodes(
+    filter: &NodeFilter,
+    name_condition: fn(&str, NameMatch, bool) -> String,
+) -> QueryBuilder {
+    let mut builder = QueryBuilder::default();
+    if let Some(name) = &filter.name {
+        let condition = format!(
+            "({} OR {})",
+            name_condition("n.name", filter.name_match, filter.case_insensitive),
+            name_condition("c.name", filter.name_match, filter.case_insensitive)
+        );
+        let value = match condition.contains("LIKE") {
+            true => like_pattern(name, filter.name_match),
+            false => name.clone(),
+        };
+        let values = vec![value.into(); condition.matches('?').count()];
+        builder.and_where(condition, values);
+    }
+    if let Some(status) = filter.status {
+        let status: DbNodeStatus = status.into();
+        builder.and_where("n.status = ?", vec![status.into()]);
+    }
+    if let Some(cluster_id) = filter.cluster_id {
+        builder.and_where("n.cluster_id = ?", vec![cluster_id.into()]);
+    }
+    if let Some(created_after) = filter.created_after {
+        builder.and_where("n.created_at >= ?", vec![created_after.into()]);
+    }
+    if let Some(created_before) = filter.created_before {
+        builder.and_where("n.created_at < ?", vec![created_before.into()]);
+    }
+    if let Some(updated_after) = filter.updated_after {
+        builder.and_where("n.updated_at >= ?", vec![updated_after.into()]);
+    }
+    if let Some(updated_before) = filter.updated_before {
+        builder.and_where("n.updated_at < ?", vec![updated_before.into()]);
+    }
+    builder
+}
+
+/// Conditions matching the nodes with the given ids, which can't be empty.
+pub(super) fn filter_node_ids(node_ids: &[Uuid], tenant: Option<Uuid>) -> QueryBuilder {
+    let mut builder = QueryBuilder::default();
+    let placeholders = vec!["?"; node_ids.len()].join(", ");
+    let values = node_ids.iter().map(|id| (*id).into()).collect();
+    builder.and_where(format!("id IN ({})", placeholders), values);
+    if let Some(tenant) = tenant {
+        builder.and_where("tenant_id = ?", vec![tenant.into()]);
+    }
+    builder
+}
+
+/// Inserts the operation and increments the version of its node, since requesting
+/// an operation changes the node and concurrent updates of it must fail.
+pub(super) async fn insert_operation<DB>(
+    conn: &mut DB::Connection,
+    operation: &Operation,
+) -> Result<DbOperation, sqlx::Error>
+where
+    DB: Database,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'r> DbOperation: FromRow<'r, DB::Row>,
+    for<'q> Uuid: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<Uuid>: Encode<'q, DB> + Type<DB>,
+    for<'q> DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+    for<'q> bool: Encode<'q, DB> + Type<DB>,
+    for<'q> DbOperationType: Encode<'q, DB> + Type<DB>,
+    for<'q> DbOperationStatus: Encode<'q, DB> + Type<DB>,
+{
+    let db_opt_type: DbOperationType = operation.operation_type.into();
+    let db_opt_status: DbOperationStatus = operation.status.into();
+
+    let operation = sqlx::query_as::<DB, DbOperation>(
+        r#"
+        INSERT INTO operations (id, operation_type, node_id, status, created_at, batch_id, forced, overrides_maintenance)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, operation_type, node_id, status, started_at, finished_at, failure_reason, attempts, batch_id, forced, overrides_maintenance, created_at, updated_at
+        "#,
+    )
+    .bind(operation.id)
+    .bind(db_opt_type)
+    .bind(operation.node_id)
+    .bind(db_opt_status)
+    .bind(Utc::now())
+    .bind(operation.batch_id)
+    .bind(operation.forced)
+    .bind(operation.overrides_maintenance)
+    .fetch_one(&mut *conn)
+    .await?;
+    sqlx::query::<DB>("UPDATE nodes SET version = version + 1 WHERE id = $1")
+        .bind(operation.node_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(operation)
+}
@@ -0,0 +1,81 @@
+use super::query_builder::QueryBuilder;
+use crate::domain::repository::pagination::{PageRequest, SortOrder, SortValue};
+
+fn direction(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    }
+}
+
+/// Adds the condition that skips the rows up to the cursor of the page, if any.
+pub(super) fn and_after_cursor(
+    builder: &mut QueryBuilder,
+    page: &PageRequest,
+    sort_expression: &str,
+    id_column: &str,
+) {
+    if let Some(cursor) = &page.after {
+        let operator = match page.order {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        };
+        let value = match &cursor.value {
+            SortValue::Text(value) => value.clone().into(),
+            SortValue::Timestamp(value) => (*value).into(),
+        };
+        builder.and_where(
+            format!("({}, {}) {} (?, ?)", sort_expression, id_column, operator),
+            vec![value, cursor.id.into()],
+        );
+    }
+}
+
+/// `ORDER BY` and `LIMIT` of the page. The id breaks the ties so the order is stable.
+pub(super) fn order_by(page: &PageRequest, sort_expression: &str, id_column: &str) -> String {
+    let direction = direction(page.order);
+    format!(
+        "ORDER BY {} {}, {} {} LIMIT {}",
+        sort_expression,
+        direction,
+        id_column,
+        direction,
+        page.fetch_limit()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::repository::pagination::{Cursor, SortField};
+    use uuid::Uuid;
+
+    #[test]
+    fn clauses_are_built_from_the_page() {
+        let mut page = PageRequest {
+            limit: 10,
+            order: SortOrder::Desc,
+            ..Default::default()
+        };
+        let mut builder = QueryBuilder::default();
+        and_after_cursor(&mut builder, &page, "n.name", "n.id");
+        assert_eq!(builder.where_clause(), "");
+        assert_eq!(
+            order_by(&page, "n.name", "n.id"),
+            "ORDER BY n.name DESC, n.id DESC LIMIT 11"
+        );
+
+        page.after = Some(Cursor {
+            sort: SortField::Name,
+            order: SortOrder::Desc,
+            value: SortValue::Text("NODE".to_string()),
+            id: Uuid::new_v4(),
+        });
+        builder.and_where("n.status = ?", vec!["poweron".to_string().into()]);
+        and_after_cursor(&mut builder, &page, "n.name", "n.id");
+        assert_eq!(
+            builder.where_clause(),
+            "WHERE n.status = $1 AND (n.name, n.id) < ($2, $3)"
+        );
+    }
+}
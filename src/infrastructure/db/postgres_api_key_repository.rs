@@ -0,0 +1,141 @@
+use crate::domain::{
+    models::ApiKey,
+    repository::{ApiKeyRepository, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::DbApiKey;
+
+const API_KEY_COLUMNS: &str =
+    "id, owner, admin, tenant_id, created_at, expires_at, last_used_at, revoked_at";
+
+#[derive(Clone)]
+pub struct PostgresApiKeyRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresApiKeyRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for PostgresApiKeyRepository {
+    #[instrument(skip(self))]
+    async fn get_api_keys(&self) -> RepositoryResult<Vec<ApiKey>> {
+        let result = sqlx::query_as::<_, DbApiKey>(&format!(
+            "SELECT {} FROM api_keys ORDER BY created_at, id",
+            API_KEY_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|keys| keys.into_iter().map(|k| k.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_api_key(&self, api_key_id: &Uuid) -> RepositoryResult<ApiKey> {
+        let result = sqlx::query_as::<_, DbApiKey>(&format!(
+            "SELECT {} FROM api_keys WHERE id = $1",
+            API_KEY_COLUMNS
+        ))
+        .bind(api_key_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|k| k.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, secret_hash))]
+    async fn get_api_key_by_secret_hash(&self, secret_hash: &str) -> RepositoryResult<ApiKey> {
+        let result = sqlx::query_as::<_, DbApiKey>(&format!(
+            "SELECT {} FROM api_keys WHERE secret_hash = $1",
+            API_KEY_COLUMNS
+        ))
+        .bind(secret_hash)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|k| k.into()).map_err(|e| {
+            tracing::debug!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, secret_hash))]
+    async fn create_api_key(
+        &self,
+        api_key: &ApiKey,
+        secret_hash: &str,
+    ) -> RepositoryResult<ApiKey> {
+        let result = sqlx::query_as::<_, DbApiKey>(&format!(
+            r#"
+            INSERT INTO api_keys (id, owner, admin, tenant_id, secret_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING {}
+            "#,
+            API_KEY_COLUMNS
+        ))
+        .bind(api_key.id)
+        .bind(&api_key.owner)
+        .bind(api_key.admin)
+        .bind(api_key.tenant_id)
+        .bind(secret_hash)
+        .bind(api_key.expires_at)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|k| k.into()).map_err(|e| {
+            tracing::error!("Error creating API key: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_api_key(&self, api_key_id: &Uuid) -> RepositoryResult<ApiKey> {
+        let result = sqlx::query_as::<_, DbApiKey>(&format!(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = COALESCE(revoked_at, $1)
+            WHERE id = $2
+            RETURNING {}
+            "#,
+            API_KEY_COLUMNS
+        ))
+        .bind(Utc::now())
+        .bind(api_key_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|k| k.into()).map_err(|e| {
+            tracing::error!("Error revoking API key: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn record_use(&self, api_key_id: &Uuid, used_at: DateTime<Utc>) -> RepositoryResult<()> {
+        let result = sqlx::query("UPDATE api_keys SET last_used_at = $1 WHERE id = $2")
+            .bind(used_at)
+            .bind(api_key_id)
+            .execute(&self.pool)
+            .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error recording the use of an API key: {:?}", e);
+            e.into()
+        })
+    }
+}
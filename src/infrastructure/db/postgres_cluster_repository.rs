@@ -1,19 +1,32 @@
 use crate::domain::{
-    models::Cluster,
-    repository::{ClusterRepository, RepositoryError, RepositoryResult},
+    models::{Cluster, ClusterPatch, ClusterSummary, NodeStatusCounts},
+    repository::{
+        pagination::SortField, Change, ClusterRepository, Page, PageRequest, RepositoryResult,
+        TenantScoped,
+    },
 };
 use async_trait::async_trait;
 use chrono::Utc;
+use sqlx::types::Json;
+use std::collections::HashMap;
 use tracing::instrument;
 use uuid::Uuid;
 
+use super::{
+    change_of,
+    entities::{DbCluster, DbClusterSummary, DbNodeStatusCounts},
+    pagination::{and_after_cursor, order_by},
+    query_builder::QueryBuilder,
+};
+
 pub struct PostgresClusterRepository {
     pool: sqlx::PgPool,
+    tenant: Option<Uuid>,
 }
 
 impl PostgresClusterRepository {
     pub fn new(pool: sqlx::PgPool) -> Self {
-        Self { pool }
+        Self { pool, tenant: None }
     }
 }
 
@@ -21,6 +34,26 @@ impl Clone for PostgresClusterRepository {
     fn clone(&self) -> Self {
         Self {
             pool: self.pool.clone(),
+            tenant: self.tenant,
+        }
+    }
+}
+
+/// Expression the clusters are sorted by. Clusters have no status.
+fn sort_expression(sort: SortField) -> &'static str {
+    match sort {
+        SortField::Name => "name",
+        SortField::CreatedAt => "COALESCE(created_at, 'epoch')",
+        SortField::UpdatedAt => "COALESCE(updated_at, created_at, 'epoch')",
+        SortField::Status => "''::text",
+    }
+}
+
+impl TenantScoped for PostgresClusterRepository {
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            tenant: Some(tenant_id),
         }
     }
 }
@@ -28,28 +61,60 @@ impl Clone for PostgresClusterRepository {
 #[async_trait]
 impl ClusterRepository for PostgresClusterRepository {
     #[instrument(skip(self))]
-    async fn get_clusters(&self) -> RepositoryResult<Vec<Cluster>> {
-        let result =
-            sqlx::query_as::<_, Cluster>("SELECT id, name, created_at, updated_at FROM clusters")
-                .fetch_all(&self.pool)
-                .await;
+    async fn get_clusters(&self, page: &PageRequest) -> RepositoryResult<Page<Cluster>> {
+        let sort = sort_expression(page.sort);
+        let mut builder = QueryBuilder::default();
+        if let Some(tenant) = self.tenant {
+            builder.and_where("tenant_id = ?", vec![tenant.into()]);
+        }
+        // the total ignores the cursor
+        let count_sql = format!("SELECT COUNT(*) FROM clusters {}", builder.where_clause());
+        let count_query = builder.bind(sqlx::query_as::<_, (i64,)>(&count_sql));
+
+        and_after_cursor(&mut builder, page, sort, "id");
+        let sql = format!(
+            r#"
+            SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+            FROM clusters {} {}
+            "#,
+            builder.where_clause(),
+            order_by(page, sort, "id")
+        );
+        let query = builder.bind(sqlx::query_as::<_, DbCluster>(&sql));
+
+        let result = async {
+            let clusters = query.fetch_all(&self.pool).await?;
+            let total = if page.with_total {
+                Some(count_query.fetch_one(&self.pool).await?.0)
+            } else {
+                None
+            };
+            let clusters = clusters.into_iter().map(|c| c.into()).collect();
+            Ok::<_, sqlx::Error>(Page::new(clusters, page, total))
+        }
+        .await;
 
         result.map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::Generic(Box::new(e))
+            e.into()
         })
     }
 
     #[instrument(skip(self))]
     async fn get_cluster(&self, cluster_id: &uuid::Uuid) -> RepositoryResult<Cluster> {
-        let result = sqlx::query_as::<_, Cluster>(
-            "SELECT id, name, created_at, updated_at FROM clusters WHERE id = $1",
+        let result = sqlx::query_as::<_, DbCluster>(
+            r#"
+            SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+            FROM clusters
+            WHERE id = $1 AND ($2::uuid IS NULL OR tenant_id = $2)
+            "#,
         )
         .bind(cluster_id)
+        .bind(self.tenant)
         .fetch_one(&self.pool)
         .await;
 
-        result.map_err(|e| {
+        result.map(|c| c.into()).map_err(|e| {
             tracing::error!("{:?}", e);
             e.into()
         })
@@ -57,62 +122,276 @@ impl ClusterRepository for PostgresClusterRepository {
 
     #[instrument(skip(self))]
     async fn create_cluster(&self, cluster: &Cluster) -> RepositoryResult<Cluster> {
-        let result = sqlx::query_as::<_, Cluster>(
+        let result = sqlx::query_as::<_, DbCluster>(
             r#"
-        INSERT INTO clusters (id, name)
-        VALUES ($1, $2)
-        RETURNING id, name, created_at, updated_at
+        INSERT INTO clusters (id, name, tenant_id, maintenance_windows)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, name, tenant_id, maintenance_windows, created_at, updated_at, version
         "#,
         )
-        .bind(&cluster.id)
+        .bind(cluster.id)
         .bind(&cluster.name)
+        .bind(self.tenant.or(cluster.tenant_id))
+        .bind(Json(&cluster.maintenance_windows))
         .fetch_one(&self.pool)
         .await;
 
-        result.map_err(|e| {
+        result.map(|c| c.into()).map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::AlreadyExists
+            e.into()
         })
     }
 
     #[instrument(skip(self))]
-    async fn update_cluster(&self, cluster: &Cluster) -> RepositoryResult<Cluster> {
-        let result = sqlx::query_as::<_, Cluster>(
+    async fn update_cluster(
+        &self,
+        cluster: &Cluster,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>> {
+        let result = sqlx::query_as::<_, DbCluster>(
             r#"
-            UPDATE clusters
-            SET name = $1, updated_at = $2
-            WHERE id = $3
-            RETURNING id, name, created_at, updated_at
+            WITH previous AS (
+                SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+                FROM clusters
+                WHERE id = $4 AND ($6::uuid IS NULL OR tenant_id = $6)
+                FOR UPDATE
+            ), changed AS (
+                UPDATE clusters c
+                SET name = $1, maintenance_windows = $2, updated_at = $3, version = c.version + 1
+                FROM previous
+                WHERE c.id = previous.id AND ($5::bigint[] IS NULL OR previous.version = ANY($5))
+                RETURNING c.id, c.name, c.tenant_id, c.maintenance_windows, c.created_at,
+                    c.updated_at, c.version
+            )
+            SELECT 0 AS step, * FROM previous UNION ALL SELECT 1 AS step, * FROM changed
+            ORDER BY step
         "#,
         )
         .bind(&cluster.name)
+        .bind(Json(&cluster.maintenance_windows))
         .bind(Utc::now())
-        .bind(&cluster.id)
-        .fetch_one(&self.pool)
+        .bind(cluster.id)
+        .bind(expected_version)
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
         .await;
 
-        result.map_err(|e| {
-            tracing::error!("{:?}", e);
-            RepositoryError::DoesNotExist
-        })
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn patch_cluster(
+        &self,
+        cluster_id: &Uuid,
+        patch: &ClusterPatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>> {
+        let result = sqlx::query_as::<_, DbCluster>(
+            r#"
+            WITH previous AS (
+                SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+                FROM clusters
+                WHERE id = $4 AND ($6::uuid IS NULL OR tenant_id = $6)
+                FOR UPDATE
+            ), changed AS (
+                UPDATE clusters c
+                SET name = COALESCE($1, c.name),
+                    maintenance_windows = COALESCE($2, c.maintenance_windows),
+                    updated_at = $3, version = c.version + 1
+                FROM previous
+                WHERE c.id = previous.id AND ($5::bigint[] IS NULL OR previous.version = ANY($5))
+                RETURNING c.id, c.name, c.tenant_id, c.maintenance_windows, c.created_at,
+                    c.updated_at, c.version
+            )
+            SELECT 0 AS step, * FROM previous UNION ALL SELECT 1 AS step, * FROM changed
+            ORDER BY step
+        "#,
+        )
+        .bind(&patch.name)
+        .bind(patch.maintenance_windows.as_ref().map(Json))
+        .bind(Utc::now())
+        .bind(cluster_id)
+        .bind(expected_version)
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
     }
 
     #[instrument(skip(self), err)]
-    async fn delete_cluster(&self, cluster_id: &Uuid) -> RepositoryResult<Uuid> {
-        let result = sqlx::query_as::<_, Cluster>(
+    async fn delete_cluster(
+        &self,
+        cluster_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Cluster> {
+        let result = sqlx::query_as::<_, DbCluster>(
+            r#"
+            WITH previous AS (
+                SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+                FROM clusters
+                WHERE id = $1 AND ($3::uuid IS NULL OR tenant_id = $3)
+                FOR UPDATE
+            ), deleted AS (
+                DELETE FROM clusters c
+                USING previous
+                WHERE c.id = previous.id AND ($2::bigint[] IS NULL OR previous.version = ANY($2))
+                RETURNING c.id
+            )
+            SELECT 0 AS step, * FROM previous
+            UNION ALL SELECT 1 AS step, previous.* FROM previous JOIN deleted USING (id)
+            ORDER BY step
+        "#,
+        )
+        .bind(cluster_id)
+        .bind(expected_version)
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        match result {
+            Ok(rows) => change_of(rows).map(|change: Change<Cluster>| change.before),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cluster_summary(&self, cluster_id: &Uuid) -> RepositoryResult<ClusterSummary> {
+        let result = sqlx::query_as::<_, DbClusterSummary>(
             r#"
-            DELETE FROM clusters
-            WHERE id = $1
-            RETURNING id, name, created_at, updated_at
+            SELECT
+                c.id AS cluster_id,
+                COALESCE(ns.node_count, 0) AS node_count,
+                COALESCE(ns.poweron_count, 0) AS poweron_count,
+                COALESCE(ns.poweroff_count, 0) AS poweroff_count,
+                COALESCE(ns.rebooting_count, 0) AS rebooting_count,
+                os.last_operation_at,
+                COALESCE(os.in_flight_operations, 0) AS in_flight_operations
+            FROM clusters c
+            LEFT JOIN (
+                SELECT
+                    n.cluster_id,
+                    COUNT(*) AS node_count,
+                    COUNT(*) FILTER (WHERE n.status = 'poweron') AS poweron_count,
+                    COUNT(*) FILTER (WHERE n.status = 'poweroff') AS poweroff_count,
+                    COUNT(*) FILTER (WHERE n.status = 'rebooting') AS rebooting_count
+                FROM nodes n
+                WHERE n.cluster_id = $1
+                GROUP BY n.cluster_id
+            ) ns ON ns.cluster_id = c.id
+            LEFT JOIN (
+                SELECT
+                    n.cluster_id,
+                    MAX(o.created_at) AS last_operation_at,
+                    COUNT(*) FILTER (WHERE o.status IN ('pending', 'running')) AS in_flight_operations
+                FROM operations o
+                JOIN nodes n ON o.node_id = n.id
+                WHERE n.cluster_id = $1
+                GROUP BY n.cluster_id
+            ) os ON os.cluster_id = c.id
+            WHERE c.id = $1 AND ($2::uuid IS NULL OR c.tenant_id = $2)
         "#,
         )
         .bind(cluster_id)
+        .bind(self.tenant)
         .fetch_one(&self.pool)
         .await;
 
-        result.map(|u| u.id).map_err(|e| {
+        result.map(|x| x.into()).map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::DoesNotExist
+            e.into()
         })
     }
+
+    #[instrument(skip(self))]
+    async fn get_node_status_counts(&self) -> RepositoryResult<HashMap<Uuid, NodeStatusCounts>> {
+        let result = sqlx::query_as::<_, DbNodeStatusCounts>(
+            r#"
+            SELECT
+                c.id AS cluster_id,
+                COUNT(n.id) FILTER (WHERE n.status = 'poweron') AS poweron_count,
+                COUNT(n.id) FILTER (WHERE n.status = 'poweroff') AS poweroff_count,
+                COUNT(n.id) FILTER (WHERE n.status = 'rebooting') AS rebooting_count
+            FROM clusters c
+            LEFT JOIN nodes n ON n.cluster_id = c.id
+            WHERE $1::uuid IS NULL OR c.tenant_id = $1
+            GROUP BY c.id
+        "#,
+        )
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|counts| counts.into_iter().map(|x| x.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{postgres_test_pool, repository_tests, PostgresNodeRepository};
+
+    #[actix_rt::test]
+    async fn writes_return_what_they_changed() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::writes_return_what_they_changed(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn writes_check_the_version() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::writes_check_the_version(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn patches_only_change_the_given_fields() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::patches_only_change_the_given_fields(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn summary_aggregates_nodes_and_operations() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::summary_aggregates_nodes_and_operations(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
 }
@@ -0,0 +1,149 @@
+use crate::domain::{
+    models::{IdempotencyRecord, IdempotentResponse},
+    repository::{IdempotencyRepository, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::types::Json;
+use tracing::instrument;
+
+use super::entities::DbIdempotencyRecord;
+
+#[derive(Clone)]
+pub struct PostgresIdempotencyRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresIdempotencyRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for PostgresIdempotencyRepository {
+    #[instrument(skip(self))]
+    async fn claim_key(
+        &self,
+        owner: &str,
+        key: &str,
+        request_hash: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<IdempotencyRecord>> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting expired idempotency keys: {:?}", e);
+                e
+            })?;
+
+        let claimed = sqlx::query_scalar::<_, String>(
+            r#"
+            INSERT INTO idempotency_keys (owner, key, request_hash, expires_at)
+            VALUES ($1, $2, $3, now() + make_interval(secs => $4))
+            ON CONFLICT (owner, key) DO NOTHING
+            RETURNING key
+            "#,
+        )
+        .bind(owner)
+        .bind(key)
+        .bind(request_hash)
+        .bind(lease_secs as f64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error claiming idempotency key: {:?}", e);
+            e
+        })?;
+        if claimed.is_some() {
+            return Ok(None);
+        }
+
+        let record = sqlx::query_as::<_, DbIdempotencyRecord>(
+            r#"
+            SELECT owner, key, request_hash, status, headers, body, expires_at
+            FROM idempotency_keys
+            WHERE owner = $1 AND key = $2
+            "#,
+        )
+        .bind(owner)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting idempotency key: {:?}", e);
+            e
+        })?;
+        Ok(Some(record.map(|r| r.into()).unwrap_or_else(|| {
+            // the request that had the key was just released, so it's still in progress
+            // as far as this one knows
+            IdempotencyRecord {
+                owner: owner.to_string(),
+                key: key.to_string(),
+                request_hash: request_hash.to_string(),
+                response: None,
+                expires_at: Utc::now(),
+            }
+        })))
+    }
+
+    #[instrument(skip(self, response))]
+    async fn save_response(
+        &self,
+        owner: &str,
+        key: &str,
+        response: &IdempotentResponse,
+        ttl_secs: i64,
+    ) -> RepositoryResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = $1, headers = $2, body = $3,
+                expires_at = now() + make_interval(secs => $4)
+            WHERE owner = $5 AND key = $6
+            "#,
+        )
+        .bind(response.status as i32)
+        .bind(Json(&response.headers))
+        .bind(&response.body)
+        .bind(ttl_secs as f64)
+        .bind(owner)
+        .bind(key)
+        .execute(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error saving idempotent response: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn release_key(&self, owner: &str, key: &str) -> RepositoryResult<()> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE owner = $1 AND key = $2")
+            .bind(owner)
+            .bind(key)
+            .execute(&self.pool)
+            .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error releasing idempotency key: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{postgres_test_pool, repository_tests};
+
+    #[actix_rt::test]
+    async fn keys_are_claimed_until_they_expire() {
+        if let Some(pool) = postgres_test_pool().await {
+            let repo = PostgresIdempotencyRepository::new(pool);
+            repository_tests::keys_are_claimed_until_they_expire(repo).await;
+        }
+    }
+}
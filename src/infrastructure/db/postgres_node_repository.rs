@@ -1,8 +1,11 @@
 use crate::{
     domain::{
-        models::{Node, Operation, OperationType},
+        models::{Batch, Node, NodePatch, NodeStatus, Operation},
         repository::{
-            node_repository::NodeFilter, NodeRepository, RepositoryError, RepositoryResult,
+            node_repository::{NameMatch, NodeFilter, OperationFilter},
+            pagination::SortField,
+            Change, NodeRepository, Page, PageRequest, RepositoryError, RepositoryResult,
+            TenantScoped,
         },
     },
     infrastructure::db::entities::DbNode,
@@ -10,18 +13,76 @@ use crate::{
 };
 use async_trait::async_trait;
 use chrono::Utc;
+use sqlx::Postgres;
 use tracing::instrument;
 use uuid::Uuid;
 
-use super::entities::{DbNodeStatus, DbOperationType};
+use super::{
+    change_of,
+    entities::{DbBatch, DbNodeStatus, DbOperationStatus, DbOperationType, DbPowerDriverKind},
+    node_queries::{filter_node_ids, filter_nodes, insert_operation, NODES_FROM},
+    pagination::{and_after_cursor, order_by},
+};
 
 pub struct PostgresNodeRepository {
     pool: sqlx::PgPool,
+    tenant: Option<Uuid>,
 }
 
 impl PostgresNodeRepository {
     pub fn new(pool: sqlx::PgPool) -> Self {
-        Self { pool }
+        Self { pool, tenant: None }
+    }
+
+    /// Fails like the foreign key if the cluster belongs to another tenant.
+    async fn check_cluster(&self, cluster_id: &Uuid) -> RepositoryResult<()> {
+        let tenant = match self.tenant {
+            Some(tenant) => tenant,
+            None => return Ok(()),
+        };
+        let result = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM clusters WHERE id = $1 AND tenant_id = $2)",
+        )
+        .bind(cluster_id)
+        .bind(tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(RepositoryError::ForeignKeyViolation),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Fails like the foreign key if any of the nodes belongs to another tenant.
+    async fn check_nodes(&self, node_ids: &[Uuid]) -> RepositoryResult<()> {
+        let tenant = match self.tenant {
+            Some(tenant) => tenant,
+            None => return Ok(()),
+        };
+        let mut node_ids = node_ids.to_vec();
+        node_ids.sort();
+        node_ids.dedup();
+        let result = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM nodes WHERE id = ANY($1) AND tenant_id = $2",
+        )
+        .bind(&node_ids)
+        .bind(tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(count) if count == node_ids.len() as i64 => Ok(()),
+            Ok(_) => Err(RepositoryError::ForeignKeyViolation),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
     }
 }
 
@@ -29,6 +90,36 @@ impl Clone for PostgresNodeRepository {
     fn clone(&self) -> Self {
         Self {
             pool: self.pool.clone(),
+            tenant: self.tenant,
+        }
+    }
+}
+
+/// Expression the nodes are sorted by. Nodes without a date or status are sorted first.
+fn sort_expression(sort: SortField) -> &'static str {
+    match sort {
+        SortField::Name => "n.name",
+        SortField::CreatedAt => "COALESCE(n.created_at, 'epoch')",
+        SortField::UpdatedAt => "COALESCE(n.updated_at, n.created_at, 'epoch')",
+        SortField::Status => "COALESCE(n.status::text, '')",
+    }
+}
+
+/// Condition matching the name of a node or its cluster.
+fn name_condition(column: &str, name_match: NameMatch, case_insensitive: bool) -> String {
+    match (name_match, case_insensitive) {
+        (NameMatch::Exact, false) => format!("{} = ?", column),
+        (NameMatch::Exact, true) => format!("LOWER({}) = LOWER(?)", column),
+        (_, false) => format!("{} LIKE ? ESCAPE '\\'", column),
+        (_, true) => format!("{} ILIKE ? ESCAPE '\\'", column),
+    }
+}
+
+impl TenantScoped for PostgresNodeRepository {
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            tenant: Some(tenant_id),
         }
     }
 }
@@ -36,165 +127,600 @@ impl Clone for PostgresNodeRepository {
 #[async_trait]
 impl NodeRepository for PostgresNodeRepository {
     #[instrument(skip(self))]
-    async fn get_nodes(&self, filter: Option<NodeFilter>) -> RepositoryResult<Vec<Node>> {
-        let query = if let Some(filter) = filter {
-            sqlx::query_as::<_, DbNode>(
-                r"
-                SELECT n.id, n.name, n.status, n.cluster_id, n.created_at, n.updated_at
-                FROM nodes n
-                JOIN clusters c on n.cluster_id = c.id
-                where n.name like $1 or c.name like $1;
-                ",
-            )
-            .bind(format!("%{}%", filter.name))
-        } else {
-            sqlx::query_as::<_, DbNode>(
-                "SELECT id, name, status, cluster_id, created_at, updated_at FROM nodes",
-            )
-        };
+    async fn get_nodes(
+        &self,
+        filter: &NodeFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<Node>> {
+        let sort = sort_expression(page.sort);
+        let mut builder = filter_nodes(filter, name_condition);
+        if let Some(tenant) = self.tenant {
+            builder.and_where("n.tenant_id = ?", vec![tenant.into()]);
+        }
+        // the total ignores the cursor
+        let count_sql = format!("SELECT COUNT(*) {} {}", NODES_FROM, builder.where_clause());
+        let count_query = builder.bind(sqlx::query_as::<_, (i64,)>(&count_sql));
 
-        let result = query.fetch_all(&self.pool).await;
+        and_after_cursor(&mut builder, page, sort, "n.id");
+        let sql = format!(
+            "SELECT n.id, n.name, n.status, n.cluster_id, n.driver, n.bmc_endpoint, n.created_at, n.updated_at, n.version {} {} {}",
+            NODES_FROM,
+            builder.where_clause(),
+            order_by(page, sort, "n.id")
+        );
+        let query = builder.bind(sqlx::query_as::<_, DbNode>(&sql));
 
-        result
-            .map(|x| x.into_iter().map(|x| x.into()).collect())
-            .map_err(|e| {
-                tracing::error!("{:?}", e);
-                e.into()
-            })
+        let result = async {
+            let nodes = query.fetch_all(&self.pool).await?;
+            let total = if page.with_total {
+                Some(count_query.fetch_one(&self.pool).await?.0)
+            } else {
+                None
+            };
+            let nodes = nodes.into_iter().map(|x| x.into()).collect();
+            Ok::<_, sqlx::Error>(Page::new(nodes, page, total))
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
     }
 
     #[instrument(skip(self))]
     async fn get_node(&self, node_id: &uuid::Uuid) -> RepositoryResult<Node> {
         let result = sqlx::query_as::<_, DbNode>(
-            "SELECT id, name, status, cluster_id, created_at, updated_at FROM nodes WHERE id = $1",
+            r#"
+            SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+            FROM nodes
+            WHERE id = $1 AND ($2::uuid IS NULL OR tenant_id = $2)
+        "#,
         )
         .bind(node_id)
+        .bind(self.tenant)
         .fetch_one(&self.pool)
         .await;
 
         result.map(|x| x.into()).map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::InvalidId
+            e.into()
         })
     }
 
+    #[instrument(skip(self))]
+    async fn get_nodes_by_id(&self, node_ids: &[Uuid]) -> RepositoryResult<Vec<Node>> {
+        if node_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let builder = filter_node_ids(node_ids, self.tenant);
+        let sql = format!(
+            "SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version FROM nodes {}",
+            builder.where_clause()
+        );
+        let result = builder
+            .bind(sqlx::query_as::<_, DbNode>(&sql))
+            .fetch_all(&self.pool)
+            .await;
+
+        result
+            .map(|nodes| nodes.into_iter().map(|x| x.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
     #[instrument(skip(self))]
     async fn create_node(&self, node: &Node) -> RepositoryResult<Node> {
+        self.check_cluster(&node.cluster_id).await?;
         let db_status: DbNodeStatus = node.status.into();
+        let db_driver: DbPowerDriverKind = node.driver.into();
+        // the tenant is copied from the cluster, so the name is unique in it
         let result = sqlx::query_as::<_, DbNode>(
             r#"
-        INSERT INTO nodes (id, name, status, cluster_id)
-        VALUES ($1, $2, $3, $4)
-        RETURNING id, name, status, cluster_id, created_at, updated_at
+        INSERT INTO nodes (id, name, status, cluster_id, driver, bmc_endpoint, tenant_id)
+        VALUES ($1, $2, $3, $4, $5, $6, (SELECT tenant_id FROM clusters WHERE id = $4))
+        RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
         "#,
         )
-        .bind(&node.id)
+        .bind(node.id)
         .bind(&node.name)
         .bind(db_status)
-        .bind(&node.cluster_id)
+        .bind(node.cluster_id)
+        .bind(db_driver)
+        .bind(&node.bmc_endpoint)
         .fetch_one(&self.pool)
         .await;
 
         result.map(|x| x.into()).map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::AlreadyExists
+            e.into()
         })
     }
 
     #[instrument(skip(self))]
-    async fn update_node(&self, node: &Node) -> RepositoryResult<Node> {
-        let db_status: DbNodeStatus = node.status.into();
+    async fn update_node(
+        &self,
+        node: &Node,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>> {
+        self.check_cluster(&node.cluster_id).await?;
+        let db_driver: DbPowerDriverKind = node.driver.into();
         let result = sqlx::query_as::<_, DbNode>(
             r#"
-            UPDATE nodes
-            SET name = $1, status = $2, cluster_id = $3, updated_at = $4
-            WHERE id = $5
-            RETURNING id, name, status, cluster_id, created_at, updated_at
+            WITH previous AS (
+                SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+                FROM nodes
+                WHERE id = $6 AND ($8::uuid IS NULL OR tenant_id = $8)
+                FOR UPDATE
+            ), changed AS (
+                UPDATE nodes n
+                SET name = $1, cluster_id = $2, driver = $3, bmc_endpoint = $4, updated_at = $5,
+                    version = n.version + 1, tenant_id = (SELECT tenant_id FROM clusters WHERE id = $2)
+                FROM previous
+                WHERE n.id = previous.id AND ($7::bigint[] IS NULL OR previous.version = ANY($7))
+                RETURNING n.id, n.name, n.status, n.cluster_id, n.driver, n.bmc_endpoint, n.created_at,
+                    n.updated_at, n.version
+            )
+            SELECT 0 AS step, * FROM previous UNION ALL SELECT 1 AS step, * FROM changed
+            ORDER BY step
         "#,
         )
         .bind(&node.name)
+        .bind(node.cluster_id)
+        .bind(db_driver)
+        .bind(&node.bmc_endpoint)
+        .bind(Utc::now())
+        .bind(node.id)
+        .bind(expected_version)
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn patch_node(
+        &self,
+        node_id: &Uuid,
+        patch: &NodePatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>> {
+        if let Some(cluster_id) = &patch.cluster_id {
+            self.check_cluster(cluster_id).await?;
+        }
+        let db_driver: Option<DbPowerDriverKind> = patch.driver.map(Into::into);
+        let result = sqlx::query_as::<_, DbNode>(
+            r#"
+            WITH previous AS (
+                SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+                FROM nodes
+                WHERE id = $7 AND ($9::uuid IS NULL OR tenant_id = $9)
+                FOR UPDATE
+            ), changed AS (
+                UPDATE nodes n
+                SET name = COALESCE($1, n.name),
+                    cluster_id = COALESCE($2, n.cluster_id),
+                    driver = COALESCE($3, n.driver),
+                    bmc_endpoint = CASE WHEN $4 THEN $5 ELSE n.bmc_endpoint END,
+                    updated_at = $6,
+                    version = n.version + 1,
+                    tenant_id = (SELECT tenant_id FROM clusters WHERE id = COALESCE($2, n.cluster_id))
+                FROM previous
+                WHERE n.id = previous.id AND ($8::bigint[] IS NULL OR previous.version = ANY($8))
+                RETURNING n.id, n.name, n.status, n.cluster_id, n.driver, n.bmc_endpoint, n.created_at,
+                    n.updated_at, n.version
+            )
+            SELECT 0 AS step, * FROM previous UNION ALL SELECT 1 AS step, * FROM changed
+            ORDER BY step
+        "#,
+        )
+        .bind(&patch.name)
+        .bind(patch.cluster_id)
+        .bind(db_driver)
+        .bind(patch.bmc_endpoint.is_some())
+        .bind(patch.bmc_endpoint.clone().flatten())
+        .bind(Utc::now())
+        .bind(node_id)
+        .bind(expected_version)
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_node(
+        &self,
+        node_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Node> {
+        let result = sqlx::query_as::<_, DbNode>(
+            r#"
+            WITH previous AS (
+                SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+                FROM nodes
+                WHERE id = $1 AND ($3::uuid IS NULL OR tenant_id = $3)
+                FOR UPDATE
+            ), deleted AS (
+                DELETE FROM nodes n
+                USING previous
+                WHERE n.id = previous.id AND ($2::bigint[] IS NULL OR previous.version = ANY($2))
+                RETURNING n.id
+            )
+            SELECT 0 AS step, * FROM previous
+            UNION ALL SELECT 1 AS step, previous.* FROM previous JOIN deleted USING (id)
+            ORDER BY step
+        "#,
+        )
+        .bind(node_id)
+        .bind(expected_version)
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        match result {
+            Ok(rows) => change_of(rows).map(|change: Change<Node>| change.before),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn update_node_status(
+        &self,
+        node_id: &Uuid,
+        status: NodeStatus,
+    ) -> RepositoryResult<Node> {
+        let db_status: DbNodeStatus = status.into();
+        let result = sqlx::query_as::<_, DbNode>(
+            r#"
+            UPDATE nodes
+            SET status = $1, updated_at = $2, version = version + 1
+            WHERE id = $3
+            RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+        "#,
+        )
         .bind(db_status)
-        .bind(&node.cluster_id)
         .bind(Utc::now())
-        .bind(&node.id)
+        .bind(node_id)
         .fetch_one(&self.pool)
         .await;
 
         result.map(|x| x.into()).map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::DoesNotExist
+            e.into()
         })
     }
 
-    #[instrument(skip(self), err)]
-    async fn delete_node(&self, node_id: &Uuid) -> RepositoryResult<Uuid> {
-        let result = sqlx::query_as::<_, DbNode>(
+    #[instrument(skip(self))]
+    async fn get_operations(&self, filter: &OperationFilter) -> RepositoryResult<Vec<Operation>> {
+        let db_opt_type: Option<DbOperationType> = filter.operation_type.map(|x| x.into());
+        let result = sqlx::query_as::<_, DbOperation>(
             r#"
-            DELETE FROM nodes
-            WHERE id = $1
-            RETURNING id, name, status, cluster_id, created_at, updated_at
+            SELECT o.id, o.operation_type, o.node_id, o.status, o.started_at, o.finished_at, o.failure_reason, o.attempts, o.batch_id, o.forced, o.overrides_maintenance, o.created_at, o.updated_at
+            FROM operations o
+            JOIN nodes n on o.node_id = n.id
+            WHERE ($1::operation_type IS NULL OR o.operation_type = $1)
+            AND ($2::uuid IS NULL OR o.node_id = $2)
+            AND ($3::uuid IS NULL OR n.cluster_id = $3)
+            AND ($4::timestamptz IS NULL OR o.created_at >= $4)
+            AND ($5::timestamptz IS NULL OR o.created_at < $5)
+            AND ($6::uuid IS NULL OR o.batch_id = $6)
+            AND ($7::uuid IS NULL OR n.tenant_id = $7)
+            ORDER BY o.created_at DESC, o.id DESC
+            LIMIT $8 OFFSET $9
         "#,
         )
-        .bind(node_id)
+        .bind(db_opt_type)
+        .bind(filter.node_id)
+        .bind(filter.cluster_id)
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .bind(filter.batch_id)
+        .bind(self.tenant)
+        .bind(i64::from(filter.limit()))
+        .bind(i64::from(filter.offset()))
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|x| x.into_iter().map(|x| x.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_operation(&self, operation_id: &Uuid) -> RepositoryResult<Operation> {
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            SELECT o.id, o.operation_type, o.node_id, o.status, o.started_at, o.finished_at, o.failure_reason, o.attempts, o.batch_id, o.forced, o.overrides_maintenance, o.created_at, o.updated_at
+            FROM operations o
+            JOIN nodes n ON o.node_id = n.id
+            WHERE o.id = $1 AND ($2::uuid IS NULL OR n.tenant_id = $2)
+        "#,
+        )
+        .bind(operation_id)
+        .bind(self.tenant)
         .fetch_one(&self.pool)
         .await;
 
-        result.map(|u| u.id).map_err(|e| {
+        result.map(|x| x.into()).map_err(|e| {
             tracing::error!("{:?}", e);
-            RepositoryError::DoesNotExist
+            e.into()
         })
     }
 
     #[instrument(skip(self))]
     async fn create_operation(&self, operation: &Operation) -> RepositoryResult<Operation> {
-        let node_status = match operation.operation_type {
-            OperationType::PowerOn => DbNodeStatus::PowerOn,
-            OperationType::PowerOff => DbNodeStatus::PowerOff,
-            OperationType::Reboot => DbNodeStatus::Rebooting,
-        };
+        self.check_nodes(&[operation.node_id]).await?;
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            let operation = insert_operation::<Postgres>(&mut tx, operation).await?;
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(operation)
+        }
+        .await;
 
-        let db_opt_type: DbOperationType = operation.operation_type.into();
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("Error creating operation: {:?}", e);
+            e.into()
+        })
+    }
 
-        let mut tx = self.pool.begin().await?;
+    #[instrument(skip(self))]
+    async fn update_operation(
+        &self,
+        operation: &Operation,
+        worker_id: &str,
+    ) -> RepositoryResult<Operation> {
+        let db_opt_status: DbOperationStatus = operation.status.into();
+        // updating an operation releases the lease the worker had on it, unless the lease
+        // expired and the operation belongs to another worker by now
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            UPDATE operations
+            SET status = $1, started_at = $2, finished_at = $3, failure_reason = $4, updated_at = $5,
+                locked_by = NULL, locked_until = NULL
+            WHERE id = $6 AND status = 'running' AND locked_by = $7
+            RETURNING id, operation_type, node_id, status, started_at, finished_at, failure_reason, attempts, batch_id, forced, overrides_maintenance, created_at, updated_at
+        "#,
+        )
+        .bind(db_opt_status)
+        .bind(operation.started_at)
+        .bind(operation.finished_at)
+        .bind(&operation.failure_reason)
+        .bind(Utc::now())
+        .bind(operation.id)
+        .bind(worker_id)
+        .fetch_one(&self.pool)
+        .await;
 
-        let insert_op = sqlx::query_as::<_, DbOperation>(
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_batch(&self, batch_id: &Uuid) -> RepositoryResult<Batch> {
+        let result = sqlx::query_as::<_, DbBatch>(
             r#"
-        INSERT INTO operations (id, operation_type, node_id)
-        VALUES ($1, $2, $3)
-        RETURNING id, operation_type, node_id, created_at, updated_at
+            SELECT id, operation_type, atomic, created_at
+            FROM batches b
+            WHERE id = $1 AND ($2::uuid IS NULL OR EXISTS (
+                SELECT 1
+                FROM operations o
+                JOIN nodes n ON o.node_id = n.id
+                WHERE o.batch_id = b.id AND n.tenant_id = $2
+            ))
         "#,
         )
-        .bind(&operation.id)
-        .bind(db_opt_type)
-        .bind(&operation.node_id)
-        .fetch_one(&mut tx)
-        .await;
-
-        match insert_op {
-            Ok(o) => {
-                if let Err(e) = sqlx::query(
-                    r#"
-                    UPDATE nodes
-                    SET  status = $1, updated_at = $2
-                    WHERE id = $3
-                "#,
-                )
-                .bind(node_status)
-                .bind(Utc::now())
-                .bind(&operation.node_id)
-                .execute(&mut tx)
-                .await
-                {
-                    tracing::error!("Error updating node while creating operation: {:?}", e);
-                    return Err(e.into());
-                }
-                tx.commit().await?;
-                Ok(o.into())
-            }
-            Err(e) => {
-                tracing::error!("Error creating operation: {:?}", e);
-                Err(e.into())
+        .bind(batch_id)
+        .bind(self.tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, operations))]
+    async fn create_batch(
+        &self,
+        batch: &Batch,
+        operations: &[Operation],
+    ) -> RepositoryResult<(Batch, Vec<Operation>)> {
+        let node_ids: Vec<Uuid> = operations.iter().map(|o| o.node_id).collect();
+        self.check_nodes(&node_ids).await?;
+        let db_opt_type: DbOperationType = batch.operation_type.into();
+
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            let batch = sqlx::query_as::<_, DbBatch>(
+                r#"
+            INSERT INTO batches (id, operation_type, atomic)
+            VALUES ($1, $2, $3)
+            RETURNING id, operation_type, atomic, created_at
+            "#,
+            )
+            .bind(batch.id)
+            .bind(db_opt_type)
+            .bind(batch.atomic)
+            .fetch_one(&mut tx)
+            .await?;
+            let mut created = Vec::with_capacity(operations.len());
+            for operation in operations {
+                created.push(
+                    insert_operation::<Postgres>(&mut tx, operation)
+                        .await?
+                        .into(),
+                );
             }
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>((batch.into(), created))
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("Error creating batch: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_operation(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Operation>> {
+        // SKIP LOCKED lets several replicas poll the queue without blocking each other
+        // or picking up the same operation. The operations of a node run one at a time, in
+        // the order they were requested, so its BMC never gets two commands at once.
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            UPDATE operations
+            SET status = 'running', started_at = COALESCE(started_at, now()), attempts = attempts + 1,
+                locked_by = $1, locked_until = now() + make_interval(secs => $2), updated_at = now()
+            WHERE id = (
+                SELECT o.id
+                FROM operations o
+                WHERE (o.status = 'pending' OR (o.status = 'running' AND o.locked_until < now()))
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM operations r
+                    WHERE r.node_id = o.node_id AND r.id <> o.id
+                    AND ((r.status = 'running' AND r.locked_until >= now())
+                        OR (r.status IN ('pending', 'running')
+                            AND (r.created_at, r.id) < (o.created_at, o.id)))
+                )
+                ORDER BY o.created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, operation_type, node_id, status, started_at, finished_at, failure_reason, attempts, batch_id, forced, overrides_maintenance, created_at, updated_at
+        "#,
+        )
+        .bind(worker_id)
+        .bind(lease_secs as f64)
+        .fetch_optional(&self.pool)
+        .await;
+
+        result.map(|x| x.map(|x| x.into())).map_err(|e| {
+            tracing::error!("Error claiming operation: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn renew_operation(
+        &self,
+        operation_id: &Uuid,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<()> {
+        // an expired lease can't be renewed, since another worker may have claimed it already
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE operations
+            SET locked_until = now() + make_interval(secs => $1)
+            WHERE id = $2 AND status = 'running' AND locked_by = $3 AND locked_until >= now()
+            RETURNING id
+        "#,
+        )
+        .bind(lease_secs as f64)
+        .bind(operation_id)
+        .bind(worker_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error renewing operation: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{
+        postgres_test_pool, repository_tests, PostgresClusterRepository,
+    };
+
+    #[actix_rt::test]
+    async fn get_nodes_combines_filters() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::get_nodes_combines_filters(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_batch_is_all_or_nothing() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::create_batch_is_all_or_nothing(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_only_finished_by_the_worker_holding_them() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::operations_are_only_finished_by_the_worker_holding_them(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn operations_of_a_node_are_claimed_one_at_a_time() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::operations_of_a_node_are_claimed_one_at_a_time(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_listed_newest_first_by_pages() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::operations_are_listed_newest_first_by_pages(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool),
+            )
+            .await;
         }
     }
 }
@@ -0,0 +1,40 @@
+use crate::application::metrics::{Collector, MetricFamily, MetricKind};
+use async_trait::async_trait;
+
+/// Connections of the pool to the Postgres database.
+pub struct PostgresPoolCollector {
+    pool: sqlx::PgPool,
+    // the pool doesn't tell its options
+    max_connections: u32,
+}
+
+impl PostgresPoolCollector {
+    pub fn new(pool: sqlx::PgPool, max_connections: u32) -> Self {
+        Self {
+            pool,
+            max_connections,
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for PostgresPoolCollector {
+    async fn collect(&self) -> Vec<MetricFamily> {
+        let idle = self.pool.num_idle() as u32;
+        let in_use = self.pool.size().saturating_sub(idle);
+        let mut connections = MetricFamily::new(
+            "db_pool_connections",
+            "Open connections of the database pool by state.",
+            MetricKind::Gauge,
+        );
+        connections.add(vec![("state", "in_use".to_string())], in_use as f64);
+        connections.add(vec![("state", "idle".to_string())], idle as f64);
+        let mut max_connections = MetricFamily::new(
+            "db_pool_max_connections",
+            "Maximum number of connections of the database pool.",
+            MetricKind::Gauge,
+        );
+        max_connections.add(vec![], self.max_connections as f64);
+        vec![connections, max_connections]
+    }
+}
@@ -0,0 +1,102 @@
+use crate::domain::{
+    models::RoleBinding,
+    repository::{RepositoryResult, RoleBindingRepository},
+};
+use async_trait::async_trait;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::{DbRole, DbRoleBinding};
+
+const ROLE_BINDING_COLUMNS: &str = "id, subject, role, cluster_id, created_at";
+
+#[derive(Clone)]
+pub struct PostgresRoleBindingRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresRoleBindingRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RoleBindingRepository for PostgresRoleBindingRepository {
+    #[instrument(skip(self))]
+    async fn get_role_bindings<'a>(
+        &self,
+        subject: Option<&'a str>,
+    ) -> RepositoryResult<Vec<RoleBinding>> {
+        let result = sqlx::query_as::<_, DbRoleBinding>(&format!(
+            "SELECT {} FROM role_bindings WHERE $1::text IS NULL OR subject = $1 ORDER BY created_at, id",
+            ROLE_BINDING_COLUMNS
+        ))
+        .bind(subject)
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|bindings| bindings.into_iter().map(|b| b.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<RoleBinding> {
+        let result = sqlx::query_as::<_, DbRoleBinding>(&format!(
+            "SELECT {} FROM role_bindings WHERE id = $1",
+            ROLE_BINDING_COLUMNS
+        ))
+        .bind(role_binding_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|b| b.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_role_binding(
+        &self,
+        role_binding: &RoleBinding,
+    ) -> RepositoryResult<RoleBinding> {
+        let result = sqlx::query_as::<_, DbRoleBinding>(&format!(
+            r#"
+            INSERT INTO role_bindings (id, subject, role, cluster_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+            "#,
+            ROLE_BINDING_COLUMNS
+        ))
+        .bind(role_binding.id)
+        .bind(&role_binding.subject)
+        .bind(DbRole::from(role_binding.role))
+        .bind(role_binding.cluster_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|b| b.into()).map_err(|e| {
+            tracing::error!("Error creating role binding: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<Uuid> {
+        let result =
+            sqlx::query_as::<_, (Uuid,)>("DELETE FROM role_bindings WHERE id = $1 RETURNING id")
+                .bind(role_binding_id)
+                .fetch_one(&self.pool)
+                .await;
+
+        result.map(|(id,)| id).map_err(|e| {
+            tracing::error!("Error deleting role binding: {:?}", e);
+            e.into()
+        })
+    }
+}
@@ -0,0 +1,319 @@
+use crate::domain::{
+    models::{ScheduleRun, ScheduledOperation},
+    repository::{RepositoryError, RepositoryResult, ScheduleRepository},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::{
+    schedule_target_ids, DbMissedRunPolicy, DbOperationType, DbScheduledOperation,
+};
+
+const SCHEDULE_COLUMNS: &str = r#"
+    id, operation_type, node_id, cluster_id, run_at, cron, missed_run_policy, enabled,
+    next_run_at, last_run_at, last_batch_id, last_error, created_at, updated_at, version
+"#;
+
+#[derive(Clone)]
+pub struct PostgresScheduleRepository {
+    pool: sqlx::PgPool,
+}
+
+impl PostgresScheduleRepository {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Error of a conditional write that matched no row, which happens when the schedule
+    /// doesn't exist but also when it doesn't have the expected version.
+    async fn write_error(&self, schedule_id: &Uuid, error: sqlx::Error) -> RepositoryError {
+        tracing::error!("{:?}", error);
+        match error {
+            sqlx::Error::RowNotFound => match self.get_schedule(schedule_id).await {
+                Ok(_) => RepositoryError::VersionMismatch,
+                Err(e) => e,
+            },
+            e => e.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScheduleRepository for PostgresScheduleRepository {
+    #[instrument(skip(self))]
+    async fn get_schedules(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> RepositoryResult<Vec<ScheduledOperation>> {
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            SELECT {}
+            FROM schedules
+            WHERE $1::uuid IS NULL
+            OR cluster_id IN (SELECT id FROM clusters WHERE tenant_id = $1)
+            OR node_id IN (SELECT id FROM nodes WHERE tenant_id = $1)
+            ORDER BY created_at, id
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .and_then(|schedules| schedules.into_iter().map(TryInto::try_into).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_schedule(&self, schedule_id: &Uuid) -> RepositoryResult<ScheduledOperation> {
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            "SELECT {} FROM schedules WHERE id = $1",
+            SCHEDULE_COLUMNS
+        ))
+        .bind(schedule_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.and_then(TryInto::try_into).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+    ) -> RepositoryResult<ScheduledOperation> {
+        let (node_id, cluster_id) = schedule_target_ids(&schedule.target);
+        let operation_type: DbOperationType = schedule.operation_type.into();
+        let policy: DbMissedRunPolicy = schedule.missed_run_policy.into();
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            INSERT INTO schedules (id, operation_type, node_id, cluster_id, run_at, cron,
+                missed_run_policy, enabled, next_run_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING {}
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(schedule.id)
+        .bind(operation_type)
+        .bind(node_id)
+        .bind(cluster_id)
+        .bind(schedule.run_at)
+        .bind(&schedule.cron)
+        .bind(policy)
+        .bind(schedule.enabled)
+        .bind(schedule.next_run_at)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.and_then(TryInto::try_into).map_err(|e| {
+            tracing::error!("Error creating schedule: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<ScheduledOperation> {
+        let (node_id, cluster_id) = schedule_target_ids(&schedule.target);
+        let operation_type: DbOperationType = schedule.operation_type.into();
+        let policy: DbMissedRunPolicy = schedule.missed_run_policy.into();
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            UPDATE schedules
+            SET operation_type = $1, node_id = $2, cluster_id = $3, run_at = $4, cron = $5,
+                missed_run_policy = $6, enabled = $7, next_run_at = $8, updated_at = $9,
+                version = version + 1
+            WHERE id = $10 AND ($11::bigint[] IS NULL OR version = ANY($11))
+            RETURNING {}
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(operation_type)
+        .bind(node_id)
+        .bind(cluster_id)
+        .bind(schedule.run_at)
+        .bind(&schedule.cron)
+        .bind(policy)
+        .bind(schedule.enabled)
+        .bind(schedule.next_run_at)
+        .bind(Utc::now())
+        .bind(schedule.id)
+        .bind(expected_version)
+        .fetch_one(&self.pool)
+        .await;
+
+        match result.and_then(TryInto::try_into) {
+            Ok(schedule) => Ok(schedule),
+            Err(e) => Err(self.write_error(&schedule.id, e).await),
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_schedule(
+        &self,
+        schedule_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Uuid> {
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            DELETE FROM schedules
+            WHERE id = $1 AND ($2::bigint[] IS NULL OR version = ANY($2))
+            RETURNING id
+            "#,
+        )
+        .bind(schedule_id)
+        .bind(expected_version)
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(id) => Ok(id),
+            Err(e) => Err(self.write_error(schedule_id, e).await),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_schedule(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<ScheduledOperation>> {
+        // SKIP LOCKED lets several replicas look for due schedules without blocking each
+        // other or running the same schedule twice.
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            UPDATE schedules
+            SET locked_by = $1, locked_until = now() + make_interval(secs => $2)
+            WHERE id = (
+                SELECT id
+                FROM schedules
+                WHERE enabled AND next_run_at <= now()
+                AND (locked_until IS NULL OR locked_until < now())
+                ORDER BY next_run_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {}
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(worker_id)
+        .bind(lease_secs as f64)
+        .fetch_optional(&self.pool)
+        .await;
+
+        result
+            .and_then(|s| s.map(TryInto::try_into).transpose())
+            .map_err(|e| {
+                tracing::error!("Error claiming schedule: {:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn reserve_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        batch_id: &Uuid,
+    ) -> RepositoryResult<Uuid> {
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE schedules
+            SET run_batch_id = COALESCE(run_batch_id, $1)
+            WHERE id = $2 AND locked_by = $3
+            RETURNING run_batch_id
+            "#,
+        )
+        .bind(batch_id)
+        .bind(schedule_id)
+        .bind(worker_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("Error reserving schedule run: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn record_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        version: i64,
+        run: &ScheduleRun,
+    ) -> RepositoryResult<()> {
+        // if the lease expired, the schedule may belong to another worker by now
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules
+            SET last_run_at = $1, last_batch_id = $2, last_error = $3,
+                next_run_at = CASE WHEN version = $4 THEN $5 ELSE next_run_at END,
+                locked_by = NULL, locked_until = NULL, run_batch_id = NULL
+            WHERE id = $6 AND locked_by = $7
+            "#,
+        )
+        .bind(run.ran_at)
+        .bind(run.batch_id)
+        .bind(&run.error)
+        .bind(version)
+        .bind(run.next_run_at)
+        .bind(schedule_id)
+        .bind(worker_id)
+        .execute(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error recording schedule run: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{
+        postgres_test_pool, repository_tests, PostgresClusterRepository, PostgresNodeRepository,
+        PostgresOrganizationRepository,
+    };
+
+    #[actix_rt::test]
+    async fn runs_reserve_a_single_batch() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::runs_reserve_a_single_batch(
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresScheduleRepository::new(pool),
+            )
+            .await;
+        }
+    }
+
+    #[actix_rt::test]
+    async fn schedules_are_filtered_by_tenant() {
+        if let Some(pool) = postgres_test_pool().await {
+            repository_tests::schedules_are_filtered_by_tenant(
+                PostgresOrganizationRepository::new(pool.clone()),
+                PostgresClusterRepository::new(pool.clone()),
+                PostgresNodeRepository::new(pool.clone()),
+                PostgresScheduleRepository::new(pool),
+            )
+            .await;
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use super::entities::{DbAuditAction, DbAuditOutcome, DbAuditTargetType, DbNodeStatus};
+use crate::domain::repository::node_repository::NameMatch;
+use chrono::{DateTime, Utc};
+use sqlx::{database::HasArguments, query::QueryAs, Database, Encode, Type};
+use uuid::Uuid;
+
+type Query<'q, DB, O> = QueryAs<'q, DB, O, <DB as HasArguments<'q>>::Arguments>;
+
+/// Value of a parameter of a [`QueryBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum SqlValue {
+    Text(String),
+    Uuid(Uuid),
+    Timestamp(DateTime<Utc>),
+    NodeStatus(DbNodeStatus),
+    AuditAction(DbAuditAction),
+    AuditTargetType(DbAuditTargetType),
+    AuditOutcome(DbAuditOutcome),
+}
+
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        SqlValue::Text(value)
+    }
+}
+
+impl From<Uuid> for SqlValue {
+    fn from(value: Uuid) -> Self {
+        SqlValue::Uuid(value)
+    }
+}
+
+impl From<DateTime<Utc>> for SqlValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        SqlValue::Timestamp(value)
+    }
+}
+
+impl From<DbNodeStatus> for SqlValue {
+    fn from(value: DbNodeStatus) -> Self {
+        SqlValue::NodeStatus(value)
+    }
+}
+
+impl From<DbAuditAction> for SqlValue {
+    fn from(value: DbAuditAction) -> Self {
+        SqlValue::AuditAction(value)
+    }
+}
+
+impl From<DbAuditTargetType> for SqlValue {
+    fn from(value: DbAuditTargetType) -> Self {
+        SqlValue::AuditTargetType(value)
+    }
+}
+
+impl From<DbAuditOutcome> for SqlValue {
+    fn from(value: DbAuditOutcome) -> Self {
+        SqlValue::AuditOutcome(value)
+    }
+}
+
+/// Composes the `WHERE` clause of a query out of several conditions.
+///
+/// The conditions use `?` as placeholder of their values, which are always bound
+/// as parameters (`$1`, `$2`...) and never written into the SQL.
+#[derive(Debug, Clone, Default)]
+pub(super) struct QueryBuilder {
+    conditions: Vec<String>,
+    values: Vec<SqlValue>,
+}
+
+impl QueryBuilder {
+    /// Adds a condition with one value per `?` placeholder.
+    pub fn and_where(&mut self, condition: impl Into<String>, values: Vec<SqlValue>) -> &mut Self {
+        let condition = condition.into();
+        assert_eq!(
+            condition.matches('?').count(),
+            values.len(),
+            "every placeholder of `{}` needs a value",
+            condition
+        );
+        self.conditions.push(condition);
+        self.values.extend(values);
+        self
+    }
+
+    pub fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            return String::new();
+        }
+
+        let mut clause = String::from("WHERE ");
+        let mut param = 0;
+        for (i, condition) in self.conditions.iter().enumerate() {
+            if i > 0 {
+                clause.push_str(" AND ");
+            }
+            for (j, part) in condition.split('?').enumerate() {
+                if j > 0 {
+                    param += 1;
+                    clause.push_str(&format!("${}", param));
+                }
+                clause.push_str(part);
+            }
+        }
+        clause
+    }
+
+    /// Binds the values of the conditions, in order, to a query built with [`QueryBuilder::where_clause`].
+    pub fn bind<'q, DB, O>(&self, mut query: Query<'q, DB, O>) -> Query<'q, DB, O>
+    where
+        DB: Database,
+        String: Encode<'q, DB> + Type<DB>,
+        Uuid: Encode<'q, DB> + Type<DB>,
+        DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+        DbNodeStatus: Encode<'q, DB> + Type<DB>,
+        DbAuditAction: Encode<'q, DB> + Type<DB>,
+        DbAuditTargetType: Encode<'q, DB> + Type<DB>,
+        DbAuditOutcome: Encode<'q, DB> + Type<DB>,
+    {
+        for value in &self.values {
+            query = match value {
+                SqlValue::Text(value) => query.bind(value.clone()),
+                SqlValue::Uuid(value) => query.bind(*value),
+                SqlValue::Timestamp(value) => query.bind(*value),
+                SqlValue::NodeStatus(value) => query.bind(*value),
+                SqlValue::AuditAction(value) => query.bind(*value),
+                SqlValue::AuditTargetType(value) => query.bind(*value),
+                SqlValue::AuditOutcome(value) => query.bind(*value),
+            };
+        }
+        query
+    }
+}
+
+/// `LIKE` pattern matching the start or any part of a string, with its wildcards escaped.
+///
+/// The queries using it must set `\` as the `ESCAPE` character.
+pub(super) fn like_pattern(value: &str, name_match: NameMatch) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    match name_match {
+        NameMatch::Exact => escaped,
+        NameMatch::Prefix => format!("{}%", escaped),
+        NameMatch::Contains => format!("%{}%", escaped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn where_clause_numbers_the_parameters() {
+        let mut builder = QueryBuilder::default();
+        assert_eq!(builder.where_clause(), "");
+
+        builder
+            .and_where(
+                "(n.name = ? OR c.name = ?)",
+                vec!["NODE".to_string().into(), "NODE".to_string().into()],
+            )
+            .and_where("n.cluster_id = ?", vec![Uuid::new_v4().into()]);
+        assert_eq!(
+            builder.where_clause(),
+            "WHERE (n.name = $1 OR c.name = $2) AND n.cluster_id = $3"
+        );
+    }
+
+    #[test]
+    fn like_patterns_are_escaped() {
+        assert_eq!(like_pattern("NODE", NameMatch::Prefix), "NODE%");
+        assert_eq!(like_pattern("100%_a", NameMatch::Contains), "%100\\%\\_a%");
+        assert_eq!(like_pattern("a\\b", NameMatch::Exact), "a\\\\b");
+    }
+
+    #[test]
+    #[should_panic]
+    fn conditions_need_a_value_per_placeholder() {
+        QueryBuilder::default().and_where("n.name = ?", vec![]);
+    }
+}
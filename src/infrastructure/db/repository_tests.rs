@@ -0,0 +1,1157 @@
+use crate::domain::{
+    models::{
+        AuditAction, AuditEvent, AuditOutcome, AuditTargetType, Batch, Cluster, ClusterPatch,
+        IdempotentResponse, MissedRunPolicy, Node, NodePatch, NodeStatus, NodeStatusCounts,
+        Operation, OperationStatus, OperationType, Organization, PowerDriverKind, Rollout,
+        RolloutNodeStatus, RolloutStatus, ScheduleRun, ScheduleTarget, ScheduledOperation,
+    },
+    repository::{
+        audit_repository::AuditFilter,
+        node_repository::{NameMatch, NodeFilter, OperationFilter},
+        pagination::{Cursor, SortOrder},
+        AuditRepository, ClusterRepository, IdempotencyRepository, NodeRepository,
+        OrganizationRepository, PageRequest, RepositoryError, RolloutRepository,
+        ScheduleRepository,
+    },
+};
+use chrono::{Duration, Utc};
+use sqlx::{database::HasArguments, IntoArguments};
+use uuid::Uuid;
+
+fn test_cluster() -> Cluster {
+    Cluster {
+        id: Uuid::new_v4(),
+        name: Uuid::new_v4().to_string(),
+        tenant_id: None,
+        maintenance_windows: vec![],
+        created_at: None,
+        updated_at: None,
+        version: 0,
+    }
+}
+
+fn test_node(cluster_id: Uuid) -> Node {
+    Node {
+        id: Uuid::new_v4(),
+        name: Uuid::new_v4().to_string(),
+        cluster_id,
+        status: NodeStatus::PowerOn,
+        driver: Default::default(),
+        bmc_endpoint: None,
+        created_at: None,
+        updated_at: None,
+        version: 0,
+    }
+}
+
+fn test_schedule(target: ScheduleTarget) -> ScheduledOperation {
+    ScheduledOperation {
+        id: Uuid::new_v4(),
+        operation_type: OperationType::Reboot,
+        target,
+        run_at: None,
+        cron: Some("0 3 * * Sun".to_string()),
+        missed_run_policy: MissedRunPolicy::RunOnce,
+        enabled: true,
+        next_run_at: Some(Utc::now() - Duration::seconds(10)),
+        last_run_at: None,
+        last_batch_id: None,
+        last_error: None,
+        created_at: None,
+        updated_at: None,
+        version: 0,
+    }
+}
+
+pub async fn writes_return_what_they_changed(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let patch = ClusterPatch {
+        name: Some(Uuid::new_v4().to_string()),
+        ..Default::default()
+    };
+    let renamed = clusters
+        .patch_cluster(&cluster.id, &patch, Some(vec![cluster.version]))
+        .await
+        .unwrap();
+    assert_eq!(renamed.before, cluster);
+    assert_eq!(Some(&renamed.after.name), patch.name.as_ref());
+    assert_eq!(renamed.after.version, cluster.version + 1);
+
+    let result = clusters
+        .update_cluster(&cluster, Some(vec![cluster.version]))
+        .await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+    let result = clusters.update_cluster(&test_cluster(), None).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    let other_tenant = clusters.for_tenant(Uuid::new_v4());
+    let result = other_tenant.patch_cluster(&cluster.id, &patch, None).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    let result = other_tenant.delete_cluster(&cluster.id, None).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    // the status only changes through operations
+    let replaced = Node {
+        name: Uuid::new_v4().to_string(),
+        status: NodeStatus::PowerOff,
+        ..node.clone()
+    };
+    let change = nodes
+        .update_node(&replaced, Some(vec![node.version]))
+        .await
+        .unwrap();
+    assert_eq!(change.before, node);
+    assert_eq!(change.after.name, replaced.name);
+    assert_eq!(change.after.status, node.status);
+    let patch = NodePatch {
+        name: Some(Uuid::new_v4().to_string()),
+        ..Default::default()
+    };
+    let patched = nodes.patch_node(&node.id, &patch, None).await.unwrap();
+    assert_eq!(patched.before, change.after);
+
+    let result = nodes.delete_node(&node.id, Some(vec![node.version])).await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+    let deleted = nodes.delete_node(&node.id, None).await.unwrap();
+    assert_eq!(deleted, patched.after);
+    let result = nodes.delete_node(&node.id, None).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+    let deleted = clusters.delete_cluster(&cluster.id, None).await.unwrap();
+    assert_eq!(deleted, renamed.after);
+}
+
+pub async fn summary_aggregates_nodes_and_operations(
+    cluster_repo: impl ClusterRepository,
+    node_repo: impl NodeRepository,
+) {
+    let cluster = cluster_repo.create_cluster(&test_cluster()).await.unwrap();
+    let other = cluster_repo.create_cluster(&test_cluster()).await.unwrap();
+    let empty = cluster_repo.get_cluster_summary(&cluster.id).await.unwrap();
+    assert_eq!(empty.node_count, 0);
+    assert_eq!(empty.last_operation_at, None);
+
+    let mut nodes = vec![];
+    for (cluster_id, status) in [
+        (cluster.id, NodeStatus::PowerOn),
+        (cluster.id, NodeStatus::PowerOff),
+        (cluster.id, NodeStatus::PowerOn),
+        (other.id, NodeStatus::Rebooting),
+    ] {
+        let node = node_repo
+            .create_node(&Node {
+                status,
+                ..test_node(cluster_id)
+            })
+            .await
+            .unwrap();
+        nodes.push(node);
+    }
+    node_repo
+        .create_operation(&Operation::new(nodes[0].id, OperationType::Reboot))
+        .await
+        .unwrap();
+    let finished = node_repo
+        .claim_operation("worker", 60)
+        .await
+        .unwrap()
+        .unwrap();
+    node_repo
+        .update_operation(
+            &Operation {
+                status: OperationStatus::Succeeded,
+                ..finished
+            },
+            "worker",
+        )
+        .await
+        .unwrap();
+    let pending = node_repo
+        .create_operation(&Operation::new(nodes[1].id, OperationType::PowerOn))
+        .await
+        .unwrap();
+    node_repo
+        .create_operation(&Operation::new(nodes[3].id, OperationType::PowerOn))
+        .await
+        .unwrap();
+
+    let summary = cluster_repo.get_cluster_summary(&cluster.id).await.unwrap();
+    assert_eq!(summary.cluster_id, cluster.id);
+    assert_eq!(summary.node_count, 3);
+    assert_eq!(
+        summary.nodes_by_status,
+        NodeStatusCounts {
+            poweron: 2,
+            poweroff: 1,
+            rebooting: 0,
+        }
+    );
+    assert_eq!(summary.in_flight_operations, 1);
+    assert_eq!(summary.last_operation_at, pending.created_at);
+
+    let counts = cluster_repo.get_node_status_counts().await.unwrap();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[&cluster.id], summary.nodes_by_status);
+    assert_eq!(counts[&other.id].rebooting, 1);
+
+    let result = cluster_repo.get_cluster_summary(&Uuid::new_v4()).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+}
+
+pub async fn get_nodes_combines_filters(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters
+        .create_cluster(&Cluster {
+            name: "RACK_A".to_string(),
+            ..test_cluster()
+        })
+        .await
+        .unwrap();
+    let node = |name: &str| Node {
+        name: name.to_string(),
+        ..test_node(cluster.id)
+    };
+    let node_1 = nodes.create_node(&node("web-1")).await.unwrap();
+    let node_2 = nodes.create_node(&node("WEB-10")).await.unwrap();
+    let node_2 = nodes
+        .update_node_status(&node_2.id, NodeStatus::PowerOff)
+        .await
+        .unwrap();
+
+    let names = |filter: NodeFilter| {
+        let nodes = &nodes;
+        async move {
+            let page = PageRequest::default();
+            let found = nodes.get_nodes(&filter, &page).await.unwrap().items;
+            found.into_iter().map(|n| n.name).collect::<Vec<_>>()
+        }
+    };
+    let name = |name: &str, name_match: NameMatch, case_insensitive: bool| NodeFilter {
+        name: Some(name.to_string()),
+        name_match,
+        case_insensitive,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        names(name("web-1", NameMatch::Exact, false)).await,
+        vec!["web-1"]
+    );
+    assert_eq!(
+        names(name("web-1", NameMatch::Exact, true)).await,
+        vec!["web-1"]
+    );
+    assert_eq!(
+        names(name("web", NameMatch::Prefix, true)).await,
+        vec!["web-1", "WEB-10"]
+    );
+    assert_eq!(
+        names(name("eb", NameMatch::Prefix, true)).await,
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        names(name("EB", NameMatch::Contains, false)).await,
+        vec!["WEB-10"]
+    );
+    // the names of the clusters match too
+    assert_eq!(
+        names(name("rack", NameMatch::Prefix, true)).await,
+        vec!["web-1", "WEB-10"]
+    );
+
+    let filter = NodeFilter {
+        status: Some(NodeStatus::PowerOff),
+        cluster_id: Some(cluster.id),
+        created_after: node_1.created_at,
+        updated_after: node_2.updated_at,
+        ..Default::default()
+    };
+    assert_eq!(names(filter).await, vec!["WEB-10"]);
+    let filter = NodeFilter {
+        created_before: node_2.created_at,
+        ..Default::default()
+    };
+    assert_eq!(names(filter).await, vec!["web-1"]);
+    let filter = NodeFilter {
+        cluster_id: Some(Uuid::new_v4()),
+        ..Default::default()
+    };
+    assert!(names(filter).await.is_empty());
+}
+
+pub async fn patches_only_change_the_given_fields(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let other = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let patch = ClusterPatch {
+        name: Some(other.name),
+        ..Default::default()
+    };
+    let result = clusters.patch_cluster(&cluster.id, &patch, None).await;
+    assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+    let patched = clusters
+        .patch_cluster(&cluster.id, &ClusterPatch::default(), None)
+        .await
+        .unwrap()
+        .after;
+    assert_eq!(patched.name, cluster.name);
+    assert!(patched.updated_at.is_some());
+    let result = clusters
+        .patch_cluster(&Uuid::new_v4(), &ClusterPatch::default(), None)
+        .await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+    let node = nodes
+        .create_node(&Node {
+            bmc_endpoint: Some("https://bmc.example.com".to_string()),
+            ..test_node(cluster.id)
+        })
+        .await
+        .unwrap();
+    let patch = NodePatch {
+        driver: Some(PowerDriverKind::Redfish),
+        bmc_endpoint: Some(None),
+        ..Default::default()
+    };
+    let patched = nodes
+        .patch_node(&node.id, &patch, None)
+        .await
+        .unwrap()
+        .after;
+    assert_eq!(patched.name, node.name);
+    assert_eq!(patched.cluster_id, cluster.id);
+    assert_eq!(patched.status, node.status);
+    assert_eq!(patched.driver, PowerDriverKind::Redfish);
+    assert_eq!(patched.bmc_endpoint, None);
+    assert_eq!(patched.created_at, node.created_at);
+    assert!(patched.updated_at.is_some());
+    let patch = NodePatch {
+        cluster_id: Some(Uuid::new_v4()),
+        ..Default::default()
+    };
+    let result = nodes.patch_node(&node.id, &patch, None).await;
+    assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+    let result = nodes
+        .patch_node(&Uuid::new_v4(), &NodePatch::default(), None)
+        .await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+}
+
+pub async fn writes_check_the_version(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    assert_eq!(cluster.version, 1);
+    let patch = ClusterPatch {
+        name: Some(Uuid::new_v4().to_string()),
+        ..Default::default()
+    };
+    let patched = clusters
+        .patch_cluster(&cluster.id, &patch, Some(vec![1]))
+        .await
+        .unwrap()
+        .after;
+    assert_eq!(patched.version, 2);
+    let result = clusters.update_cluster(&cluster, Some(vec![1])).await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+    let result = clusters.delete_cluster(&cluster.id, Some(vec![1])).await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    assert_eq!(node.version, 1);
+    let renamed = Node {
+        name: Uuid::new_v4().to_string(),
+        ..node.clone()
+    };
+    let updated = nodes
+        .update_node(&renamed, Some(vec![1]))
+        .await
+        .unwrap()
+        .after;
+    assert_eq!(updated.version, 2);
+    let result = nodes.update_node(&renamed, Some(vec![1])).await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+
+    // requesting an operation changes the node too
+    nodes
+        .create_operation(&Operation::new(node.id, OperationType::Reboot))
+        .await
+        .unwrap();
+    let result = nodes
+        .patch_node(&node.id, &NodePatch::default(), Some(vec![2]))
+        .await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+    // any of the versions can match
+    let result = nodes.delete_node(&node.id, Some(vec![1, 2])).await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+    assert_eq!(
+        nodes
+            .delete_node(&node.id, Some(vec![2, 3]))
+            .await
+            .unwrap()
+            .id,
+        node.id
+    );
+    let result = nodes.delete_node(&node.id, Some(vec![3])).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+    assert_eq!(
+        clusters
+            .delete_cluster(&cluster.id, Some(vec![1, 2]))
+            .await
+            .unwrap()
+            .id,
+        cluster.id
+    );
+}
+
+pub async fn create_batch_is_all_or_nothing(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let batch = Batch::new(OperationType::Reboot, true);
+    let operation = |node_id| Operation {
+        batch_id: Some(batch.id),
+        ..Operation::new(node_id, OperationType::Reboot)
+    };
+
+    let result = nodes
+        .create_batch(&batch, &[operation(node.id), operation(Uuid::new_v4())])
+        .await;
+    assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+    assert!(matches!(
+        nodes.get_batch(&batch.id).await,
+        Err(RepositoryError::DoesNotExist)
+    ));
+    assert_eq!(nodes.get_node(&node.id).await.unwrap().version, 1);
+
+    let (created, operations) = nodes
+        .create_batch(&batch, &[operation(node.id)])
+        .await
+        .unwrap();
+    assert!(created.created_at.is_some());
+    assert_eq!(nodes.get_batch(&batch.id).await.unwrap(), created);
+    assert_eq!(nodes.get_node(&node.id).await.unwrap().version, 2);
+
+    let filter = OperationFilter {
+        batch_id: Some(batch.id),
+        ..Default::default()
+    };
+    assert_eq!(nodes.get_operations(&filter).await.unwrap(), operations);
+}
+
+pub async fn operations_are_only_finished_by_the_worker_holding_them(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let operation = nodes
+        .create_operation(&Operation::new(node.id, OperationType::Reboot))
+        .await
+        .unwrap();
+    let finished = |operation: &Operation| Operation {
+        status: OperationStatus::Succeeded,
+        finished_at: Some(Utc::now()),
+        ..operation.clone()
+    };
+    let result = nodes
+        .update_operation(&finished(&operation), "worker")
+        .await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+    // the lease runs out while the worker is still running the operation
+    let claimed = nodes.claim_operation("worker", -1).await.unwrap().unwrap();
+    let result = nodes.renew_operation(&operation.id, "worker", 60).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    let reclaimed = nodes.claim_operation("other", 60).await.unwrap().unwrap();
+    assert_eq!(reclaimed.id, operation.id);
+    nodes
+        .renew_operation(&operation.id, "other", 60)
+        .await
+        .unwrap();
+
+    let result = nodes.update_operation(&finished(&claimed), "worker").await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    let updated = nodes
+        .update_operation(&finished(&reclaimed), "other")
+        .await
+        .unwrap();
+    assert_eq!(updated.status, OperationStatus::Succeeded);
+    assert_eq!(nodes.get_operation(&operation.id).await.unwrap(), updated);
+
+    // finishing an operation releases it
+    let result = nodes.renew_operation(&operation.id, "other", 60).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    let result = nodes.update_operation(&finished(&reclaimed), "other").await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    assert!(nodes.claim_operation("worker", 60).await.unwrap().is_none());
+}
+
+pub async fn operations_of_a_node_are_claimed_one_at_a_time(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let other_node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let mut operations = vec![];
+    for (node_id, operation_type) in [
+        (node.id, OperationType::PowerOff),
+        (node.id, OperationType::PowerOn),
+        (other_node.id, OperationType::Reboot),
+    ] {
+        let operation = nodes
+            .create_operation(&Operation::new(node_id, operation_type))
+            .await
+            .unwrap();
+        operations.push(operation);
+    }
+
+    let claimed = nodes.claim_operation("worker", -1).await.unwrap().unwrap();
+    assert_eq!(claimed.id, operations[0].id);
+    // the second operation of the node waits for the first one, even if its lease expired
+    let reclaimed = nodes.claim_operation("other", 60).await.unwrap().unwrap();
+    assert_eq!(reclaimed.id, operations[0].id);
+    let claimed = nodes.claim_operation("other", 60).await.unwrap().unwrap();
+    assert_eq!(claimed.id, operations[2].id);
+    assert!(nodes.claim_operation("other", 60).await.unwrap().is_none());
+
+    nodes
+        .update_operation(
+            &Operation {
+                status: OperationStatus::Failed,
+                ..reclaimed
+            },
+            "other",
+        )
+        .await
+        .unwrap();
+    let claimed = nodes.claim_operation("other", 60).await.unwrap().unwrap();
+    assert_eq!(claimed.id, operations[1].id);
+}
+
+pub async fn operations_are_listed_newest_first_by_pages(
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let mut operations = vec![];
+    for operation_type in [
+        OperationType::PowerOff,
+        OperationType::PowerOn,
+        OperationType::Reboot,
+    ] {
+        let operation = nodes
+            .create_operation(&Operation::new(node.id, operation_type))
+            .await
+            .unwrap();
+        operations.push(operation);
+    }
+    operations.sort_by_key(|o| std::cmp::Reverse((o.created_at, o.id)));
+
+    let page = |limit, offset| OperationFilter {
+        node_id: Some(node.id),
+        limit,
+        offset,
+        ..Default::default()
+    };
+    assert_eq!(
+        nodes.get_operations(&page(None, None)).await.unwrap(),
+        operations
+    );
+    assert_eq!(
+        nodes.get_operations(&page(Some(2), None)).await.unwrap(),
+        operations[..2]
+    );
+    assert_eq!(
+        nodes.get_operations(&page(Some(2), Some(2))).await.unwrap(),
+        operations[2..]
+    );
+    assert_eq!(
+        nodes.get_operations(&page(Some(0), Some(1))).await.unwrap(),
+        operations[1..2]
+    );
+}
+
+pub async fn rollout_updates_check_the_version(
+    clusters: impl ClusterRepository,
+    rollouts: impl RolloutRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let rollout = rollouts
+        .create_rollout(&Rollout::new(cluster.id, 1, 0, [Uuid::new_v4()]))
+        .await
+        .unwrap();
+
+    let mut node = rollout.nodes[0].clone();
+    node.status = RolloutNodeStatus::Rebooting;
+    node.operation_id = Some(Uuid::new_v4());
+    let updated = rollouts
+        .update_rollout(
+            &rollout.id,
+            RolloutStatus::Running,
+            &[node.clone()],
+            Some(vec![rollout.version]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.nodes, vec![node.clone()]);
+    assert_eq!(updated.version, rollout.version + 1);
+    assert_eq!(rollouts.get_rollout(&rollout.id).await.unwrap(), updated);
+
+    // the progress of a stale read is rejected, along with its status
+    let mut failed = node.clone();
+    failed.status = RolloutNodeStatus::Failed;
+    let result = rollouts
+        .update_rollout(
+            &rollout.id,
+            RolloutStatus::Failed,
+            &[failed],
+            Some(vec![rollout.version]),
+        )
+        .await;
+    assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+    assert_eq!(rollouts.get_rollout(&rollout.id).await.unwrap(), updated);
+
+    let paused = rollouts
+        .update_rollout(
+            &rollout.id,
+            RolloutStatus::Paused,
+            &[],
+            Some(vec![updated.version]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(paused.status, RolloutStatus::Paused);
+    assert_eq!(paused.nodes, vec![node]);
+    assert_eq!(paused.version, updated.version + 1);
+
+    let result = rollouts
+        .update_rollout(&Uuid::new_v4(), RolloutStatus::Paused, &[], None)
+        .await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+}
+
+pub async fn runs_reserve_a_single_batch(
+    clusters: impl ClusterRepository,
+    schedules: impl ScheduleRepository,
+) {
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    let schedule = schedules
+        .create_schedule(&test_schedule(ScheduleTarget::ClusterId(cluster.id)))
+        .await
+        .unwrap();
+    let result = schedules
+        .reserve_run(&schedule.id, "worker", &Uuid::new_v4())
+        .await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+    // the lease expires right away
+    schedules
+        .claim_schedule("worker", -1)
+        .await
+        .unwrap()
+        .unwrap();
+    let batch_id = Uuid::new_v4();
+    let reserved = schedules
+        .reserve_run(&schedule.id, "worker", &batch_id)
+        .await
+        .unwrap();
+    assert_eq!(reserved, batch_id);
+    // the run is tried again by another worker
+    schedules
+        .claim_schedule("other", 60)
+        .await
+        .unwrap()
+        .unwrap();
+    let reserved = schedules
+        .reserve_run(&schedule.id, "other", &Uuid::new_v4())
+        .await
+        .unwrap();
+    assert_eq!(reserved, batch_id);
+
+    let run = ScheduleRun {
+        ran_at: Utc::now(),
+        batch_id: None,
+        error: None,
+        next_run_at: Some(Utc::now() - Duration::seconds(1)),
+    };
+    schedules
+        .record_run(&schedule.id, "other", schedule.version, &run)
+        .await
+        .unwrap();
+    schedules
+        .claim_schedule("worker", 60)
+        .await
+        .unwrap()
+        .unwrap();
+    let next_batch_id = Uuid::new_v4();
+    let reserved = schedules
+        .reserve_run(&schedule.id, "worker", &next_batch_id)
+        .await
+        .unwrap();
+    assert_eq!(reserved, next_batch_id);
+}
+
+pub async fn schedules_are_filtered_by_tenant(
+    organizations: impl OrganizationRepository,
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+    schedules: impl ScheduleRepository,
+) {
+    let mut tenants = vec![];
+    for _ in 0..2 {
+        let organization = Organization {
+            id: Uuid::new_v4(),
+            name: Uuid::new_v4().to_string(),
+            created_at: None,
+        };
+        tenants.push(
+            organizations
+                .create_organization(&organization)
+                .await
+                .unwrap()
+                .id,
+        );
+    }
+    let mut created = vec![];
+    for tenant in &tenants {
+        let cluster = clusters
+            .for_tenant(*tenant)
+            .create_cluster(&test_cluster())
+            .await
+            .unwrap();
+        let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+        for target in [
+            ScheduleTarget::ClusterId(cluster.id),
+            ScheduleTarget::NodeId(node.id),
+        ] {
+            let schedule = schedules
+                .create_schedule(&test_schedule(target))
+                .await
+                .unwrap();
+            created.push(schedule.id);
+        }
+    }
+
+    let ids = |schedules: Vec<ScheduledOperation>| {
+        schedules
+            .into_iter()
+            .map(|schedule| schedule.id)
+            .filter(|id| created.contains(id))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(schedules.get_schedules(None).await.unwrap()), created);
+    assert_eq!(
+        ids(schedules.get_schedules(Some(tenants[0])).await.unwrap()),
+        created[..2]
+    );
+    assert_eq!(
+        ids(schedules.get_schedules(Some(tenants[1])).await.unwrap()),
+        created[2..]
+    );
+    let result = schedules.get_schedules(Some(Uuid::new_v4())).await;
+    assert!(result.unwrap().is_empty());
+}
+
+pub async fn keys_are_claimed_until_they_expire(repo: impl IdempotencyRepository) {
+    assert!(repo
+        .claim_key("jane", "key", "hash", 60)
+        .await
+        .unwrap()
+        .is_none());
+    let record = repo
+        .claim_key("jane", "key", "other", 60)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.request_hash, "hash");
+    assert!(record.response.is_none());
+    // the key is only taken for its owner
+    assert!(repo
+        .claim_key("john", "key", "other", 60)
+        .await
+        .unwrap()
+        .is_none());
+
+    let response = IdempotentResponse {
+        status: 202,
+        headers: vec![("content-type".to_string(), "application/json".to_string())],
+        body: b"{}".to_vec(),
+    };
+    repo.save_response("jane", "key", &response, 60)
+        .await
+        .unwrap();
+    let record = repo
+        .claim_key("jane", "key", "hash", 60)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.response, Some(response));
+    let record = repo
+        .claim_key("john", "key", "hash", 60)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.request_hash, "other");
+    assert!(record.response.is_none());
+
+    repo.release_key("jane", "key").await.unwrap();
+    // claims that are never answered are only kept for their lease
+    assert!(repo
+        .claim_key("jane", "key", "hash", 0)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(repo
+        .claim_key("jane", "key", "other", 60)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+pub async fn tenants_only_see_their_own_data(
+    organizations: impl OrganizationRepository,
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+) {
+    let mut tenants = vec![];
+    for _ in 0..2 {
+        let organization = Organization {
+            id: Uuid::new_v4(),
+            name: Uuid::new_v4().to_string(),
+            created_at: None,
+        };
+        tenants.push(
+            organizations
+                .create_organization(&organization)
+                .await
+                .unwrap()
+                .id,
+        );
+    }
+    let (acme, globex) = (tenants[0], tenants[1]);
+
+    // the names only have to be unique in each tenant
+    let mut created = vec![];
+    for tenant in [acme, globex] {
+        let cluster = clusters
+            .for_tenant(tenant)
+            .create_cluster(&Cluster {
+                name: "CLUSTER".to_string(),
+                ..test_cluster()
+            })
+            .await
+            .unwrap();
+        assert_eq!(cluster.tenant_id, Some(tenant));
+        let tenant_nodes = nodes.for_tenant(tenant);
+        let node = tenant_nodes
+            .create_node(&Node {
+                name: "NODE".to_string(),
+                ..test_node(cluster.id)
+            })
+            .await
+            .unwrap();
+        let operation = tenant_nodes
+            .create_operation(&Operation::new(node.id, OperationType::Reboot))
+            .await
+            .unwrap();
+        created.push((cluster, node, operation));
+    }
+    let (acme_cluster, acme_node, acme_operation) = &created[0];
+    let (globex_cluster, globex_node, globex_operation) = &created[1];
+
+    let acme_clusters = clusters.for_tenant(acme);
+    let visible = acme_clusters
+        .get_clusters(&PageRequest::default())
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].id, acme_cluster.id);
+    assert!(matches!(
+        acme_clusters.get_cluster(&globex_cluster.id).await,
+        Err(RepositoryError::DoesNotExist)
+    ));
+    assert!(matches!(
+        acme_clusters.delete_cluster(&globex_cluster.id, None).await,
+        Err(RepositoryError::DoesNotExist)
+    ));
+
+    let acme_nodes = nodes.for_tenant(acme);
+    let visible = acme_nodes
+        .get_nodes(&Default::default(), &PageRequest::default())
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].id, acme_node.id);
+    assert!(matches!(
+        acme_nodes.get_node(&globex_node.id).await,
+        Err(RepositoryError::DoesNotExist)
+    ));
+    assert!(matches!(
+        acme_nodes.delete_node(&globex_node.id, None).await,
+        Err(RepositoryError::DoesNotExist)
+    ));
+    let node_ids = [acme_node.id, globex_node.id, Uuid::new_v4()];
+    let ids = |nodes: Vec<Node>| {
+        let mut ids: Vec<Uuid> = nodes.into_iter().map(|node| node.id).collect();
+        ids.sort_by_key(|id| *id != acme_node.id);
+        ids
+    };
+    let visible = acme_nodes.get_nodes_by_id(&node_ids).await.unwrap();
+    assert_eq!(ids(visible), node_ids[..1]);
+    let visible = nodes.get_nodes_by_id(&node_ids).await.unwrap();
+    assert_eq!(ids(visible), node_ids[..2]);
+    let moved = Node {
+        cluster_id: globex_cluster.id,
+        ..acme_node.clone()
+    };
+    assert!(matches!(
+        acme_nodes.update_node(&moved, None).await,
+        Err(RepositoryError::ForeignKeyViolation)
+    ));
+
+    let visible = acme_nodes
+        .get_operations(&Default::default())
+        .await
+        .unwrap();
+    assert_eq!(visible, vec![acme_operation.clone()]);
+    assert!(matches!(
+        acme_nodes.get_operation(&globex_operation.id).await,
+        Err(RepositoryError::DoesNotExist)
+    ));
+    assert!(matches!(
+        acme_nodes
+            .create_operation(&Operation::new(globex_node.id, OperationType::PowerOff))
+            .await,
+        Err(RepositoryError::ForeignKeyViolation)
+    ));
+
+    // the repositories without a tenant see everything
+    assert_eq!(
+        nodes.get_operation(&globex_operation.id).await.unwrap(),
+        *globex_operation
+    );
+    let result = organizations.delete_organization(&globex).await;
+    assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+    clusters
+        .delete_cluster(&globex_cluster.id, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        organizations.delete_organization(&globex).await.unwrap(),
+        globex
+    );
+    let result = organizations.get_organization(&globex).await;
+    assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+}
+
+pub async fn nodes_keep_the_tenant_of_their_cluster<DB: sqlx::Database>(
+    organizations: impl OrganizationRepository,
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+    pool: &sqlx::Pool<DB>,
+) where
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> Uuid: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    let organization = Organization {
+        id: Uuid::new_v4(),
+        name: Uuid::new_v4().to_string(),
+        created_at: None,
+    };
+    organizations
+        .create_organization(&organization)
+        .await
+        .unwrap();
+    let cluster = clusters.create_cluster(&test_cluster()).await.unwrap();
+    nodes.create_node(&test_node(cluster.id)).await.unwrap();
+
+    // the writes that skip the repositories
+    for sql in [
+        "UPDATE nodes SET tenant_id = $1",
+        "UPDATE clusters SET tenant_id = $1",
+    ] {
+        let result = sqlx::query(sql).bind(organization.id).execute(pool).await;
+        assert!(result.is_err(), "{}", sql);
+    }
+}
+
+pub async fn events_are_filtered_and_paginated(repo: impl AuditRepository) {
+    let node_id = Uuid::new_v4();
+    for action in [
+        AuditAction::Create,
+        AuditAction::PowerOff,
+        AuditAction::Reboot,
+    ] {
+        let event = AuditEvent {
+            actor: "jane".to_string(),
+            after: Some(serde_json::json!({ "id": node_id })),
+            ..AuditEvent::new(action, AuditTargetType::Node, Some(node_id))
+        };
+        let recorded = repo.record_event(&event).await.unwrap();
+        assert!(recorded.created_at.is_some());
+        assert_eq!(recorded.after, event.after);
+    }
+    let failed = AuditEvent {
+        actor: "john".to_string(),
+        outcome: AuditOutcome::Failure,
+        ..AuditEvent::new(AuditAction::Delete, AuditTargetType::Cluster, None)
+    };
+    repo.record_event(&failed).await.unwrap();
+    assert!(matches!(
+        repo.record_event(&failed).await,
+        Err(RepositoryError::AlreadyExists)
+    ));
+
+    let filter = AuditFilter {
+        actor: Some("jane".to_string()),
+        target_type: Some(AuditTargetType::Node),
+        target_id: Some(node_id),
+        ..Default::default()
+    };
+    let mut page = PageRequest {
+        limit: 2,
+        order: SortOrder::Desc,
+        with_total: true,
+        ..Default::default()
+    };
+    let first = repo.get_events(&filter, &page).await.unwrap();
+    assert_eq!(first.total, Some(3));
+    let actions: Vec<AuditAction> = first.items.iter().map(|event| event.action).collect();
+    assert_eq!(actions, vec![AuditAction::Reboot, AuditAction::PowerOff]);
+
+    page.after = Some(Cursor::decode(&first.next_cursor.unwrap()).unwrap());
+    let second = repo.get_events(&filter, &page).await.unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert_eq!(second.items[0].action, AuditAction::Create);
+    assert_eq!(second.next_cursor, None);
+
+    let filter = AuditFilter {
+        action: Some(AuditAction::Delete),
+        outcome: Some(AuditOutcome::Failure),
+        ..Default::default()
+    };
+    let events = repo
+        .get_events(&filter, &PageRequest::default())
+        .await
+        .unwrap()
+        .items;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor, "john");
+}
+
+/// Checks that the events can't be changed even with SQL, through the pool of `repo`.
+pub async fn events_are_never_changed<DB: sqlx::Database>(
+    repo: impl AuditRepository,
+    pool: &sqlx::Pool<DB>,
+) where
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+{
+    let event = AuditEvent::new(AuditAction::Create, AuditTargetType::Node, None);
+    repo.record_event(&event).await.unwrap();
+
+    for sql in [
+        "UPDATE audit_events SET actor = 'nobody'",
+        "DELETE FROM audit_events",
+    ] {
+        assert!(sqlx::query(sql).execute(pool).await.is_err(), "{}", sql);
+    }
+}
+
+pub async fn events_belong_to_the_tenant_of_their_target(
+    organizations: impl OrganizationRepository,
+    clusters: impl ClusterRepository,
+    nodes: impl NodeRepository,
+    audit: impl AuditRepository,
+) {
+    let tenant = organizations
+        .create_organization(&Organization {
+            id: Uuid::new_v4(),
+            name: "acme".to_string(),
+            created_at: None,
+        })
+        .await
+        .unwrap()
+        .id;
+    let cluster = clusters
+        .for_tenant(tenant)
+        .create_cluster(&test_cluster())
+        .await
+        .unwrap();
+    let node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let deleted_node = nodes.create_node(&test_node(cluster.id)).await.unwrap();
+    let operation = nodes
+        .create_operation(&Operation::new(node.id, OperationType::Reboot))
+        .await
+        .unwrap();
+    nodes.delete_node(&deleted_node.id, None).await.unwrap();
+
+    // events of the workers and of the callers without a tenant
+    let events = [
+        AuditEvent::new(
+            AuditAction::Update,
+            AuditTargetType::Cluster,
+            Some(cluster.id),
+        ),
+        AuditEvent::new(AuditAction::Reboot, AuditTargetType::Node, Some(node.id)),
+        AuditEvent::new(
+            AuditAction::Execute,
+            AuditTargetType::Operation,
+            Some(operation.id),
+        ),
+        AuditEvent::new(
+            AuditAction::Delete,
+            AuditTargetType::Node,
+            Some(deleted_node.id),
+        )
+        .with_before(Some(&deleted_node)),
+    ];
+    for event in events {
+        let event = audit.record_event(&event).await.unwrap();
+        assert_eq!(event.tenant_id, Some(tenant), "{:?}", event.target_type);
+    }
+
+    let deleted_cluster = clusters
+        .for_tenant(tenant)
+        .create_cluster(&test_cluster())
+        .await
+        .unwrap();
+    clusters
+        .delete_cluster(&deleted_cluster.id, None)
+        .await
+        .unwrap();
+    let event = AuditEvent::new(
+        AuditAction::Delete,
+        AuditTargetType::Cluster,
+        Some(deleted_cluster.id),
+    )
+    .with_before(Some(&deleted_cluster));
+    let event = audit.record_event(&event).await.unwrap();
+    assert_eq!(event.tenant_id, Some(tenant));
+
+    // the caller's, if nothing is known about the target
+    let other_tenant = Some(Uuid::new_v4());
+    let event = AuditEvent {
+        tenant_id: other_tenant,
+        ..AuditEvent::new(AuditAction::Create, AuditTargetType::Node, None)
+    };
+    let event = audit.record_event(&event).await.unwrap();
+    assert_eq!(event.tenant_id, other_tenant);
+}
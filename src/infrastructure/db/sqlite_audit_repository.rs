@@ -0,0 +1,138 @@
+use crate::domain::{
+    models::AuditEvent,
+    repository::{
+        audit_repository::AuditFilter, AuditRepository, Page, PageRequest, RepositoryResult,
+    },
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::types::Json;
+use tracing::instrument;
+
+use super::{
+    audit_queries::{filter_conditions, AUDIT_EVENT_COLUMNS, TENANT_OF_TARGET},
+    entities::{DbAuditAction, DbAuditEvent, DbAuditOutcome, DbAuditTargetType},
+    pagination::{and_after_cursor, order_by},
+};
+
+#[derive(Clone)]
+pub struct SqliteAuditRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteAuditRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AuditRepository for SqliteAuditRepository {
+    #[instrument(skip(self))]
+    async fn get_events(
+        &self,
+        filter: &AuditFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<AuditEvent>> {
+        let mut builder = filter_conditions(filter);
+        // the total ignores the cursor
+        let count_sql = format!(
+            "SELECT COUNT(*) FROM audit_events {}",
+            builder.where_clause()
+        );
+        let count_query = builder.bind(sqlx::query_as::<_, (i64,)>(&count_sql));
+
+        and_after_cursor(&mut builder, page, "created_at", "id");
+        let sql = format!(
+            "SELECT {} FROM audit_events {} {}",
+            AUDIT_EVENT_COLUMNS,
+            builder.where_clause(),
+            order_by(page, "created_at", "id")
+        );
+        let query = builder.bind(sqlx::query_as::<_, DbAuditEvent>(&sql));
+
+        let result = async {
+            let events = query.fetch_all(&self.pool).await?;
+            let total = if page.with_total {
+                Some(count_query.fetch_one(&self.pool).await?.0)
+            } else {
+                None
+            };
+            let events = events.into_iter().map(|e| e.into()).collect();
+            Ok::<_, sqlx::Error>(Page::new(events, page, total))
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, event), fields(event_id = %event.id))]
+    async fn record_event(&self, event: &AuditEvent) -> RepositoryResult<AuditEvent> {
+        let result = sqlx::query_as::<_, DbAuditEvent>(&format!(
+            r#"
+            INSERT INTO audit_events (id, actor, tenant_id, action, target_type, target_id, before, after, request_id, source_ip, outcome, reason, created_at)
+            VALUES ($1, $2, {}, $4, $5, $6, $7, $8, $9, $10, $11, $12, $14)
+            RETURNING {}
+            "#,
+            TENANT_OF_TARGET, AUDIT_EVENT_COLUMNS
+        ))
+        .bind(event.id)
+        .bind(&event.actor)
+        .bind(event.snapshot_tenant_id().or(event.tenant_id))
+        .bind(DbAuditAction::from(event.action))
+        .bind(DbAuditTargetType::from(event.target_type))
+        .bind(event.target_id)
+        .bind(event.before.as_ref().map(Json))
+        .bind(event.after.as_ref().map(Json))
+        .bind(&event.request_id)
+        .bind(&event.source_ip)
+        .bind(DbAuditOutcome::from(event.outcome))
+        .bind(&event.reason)
+        .bind(event.cluster_id())
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|e| e.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{
+        repository_tests, sqlite_pool, SqliteClusterRepository, SqliteNodeRepository,
+        SqliteOrganizationRepository,
+    };
+
+    #[actix_rt::test]
+    async fn events_belong_to_the_tenant_of_their_target() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::events_belong_to_the_tenant_of_their_target(
+            SqliteOrganizationRepository::new(pool.clone()),
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool.clone()),
+            SqliteAuditRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn events_are_filtered_and_paginated() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::events_are_filtered_and_paginated(SqliteAuditRepository::new(pool)).await;
+    }
+
+    #[actix_rt::test]
+    async fn events_are_never_changed() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::events_are_never_changed(SqliteAuditRepository::new(pool.clone()), &pool)
+            .await;
+    }
+}
@@ -0,0 +1,493 @@
+use crate::domain::{
+    models::{Cluster, ClusterPatch, ClusterSummary, NodeStatusCounts},
+    repository::{
+        pagination::SortField, Change, ClusterRepository, Page, PageRequest, RepositoryResult,
+        TenantScoped,
+    },
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{query::QueryAs, sqlite::SqliteArguments, types::Json, Sqlite};
+use std::collections::HashMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{
+    change_of,
+    entities::{DbCluster, DbClusterSummary, DbNodeStatusCounts},
+    pagination::{and_after_cursor, order_by},
+    query_builder::QueryBuilder,
+    sqlite_change_rows,
+};
+
+#[derive(Clone)]
+pub struct SqliteClusterRepository {
+    pool: sqlx::SqlitePool,
+    tenant: Option<Uuid>,
+}
+
+impl SqliteClusterRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool, tenant: None }
+    }
+
+    /// Query of the cluster as it is before a write.
+    fn previous<'q>(
+        &self,
+        cluster_id: &Uuid,
+    ) -> QueryAs<'q, Sqlite, DbCluster, SqliteArguments<'q>> {
+        sqlx::query_as::<_, DbCluster>(
+            r#"
+            SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+            FROM clusters
+            WHERE id = $1 AND ($2 IS NULL OR tenant_id = $2)
+            "#,
+        )
+        .bind(*cluster_id)
+        .bind(self.tenant)
+    }
+}
+
+/// Expression the clusters are sorted by. Clusters have no status.
+fn sort_expression(sort: SortField) -> &'static str {
+    match sort {
+        SortField::Name => "name",
+        SortField::CreatedAt => "COALESCE(created_at, '1970-01-01 00:00:00')",
+        SortField::UpdatedAt => "COALESCE(updated_at, created_at, '1970-01-01 00:00:00')",
+        SortField::Status => "''",
+    }
+}
+
+impl TenantScoped for SqliteClusterRepository {
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            tenant: Some(tenant_id),
+        }
+    }
+}
+
+#[async_trait]
+impl ClusterRepository for SqliteClusterRepository {
+    #[instrument(skip(self))]
+    async fn get_clusters(&self, page: &PageRequest) -> RepositoryResult<Page<Cluster>> {
+        let sort = sort_expression(page.sort);
+        let mut builder = QueryBuilder::default();
+        if let Some(tenant) = self.tenant {
+            builder.and_where("tenant_id = ?", vec![tenant.into()]);
+        }
+        // the total ignores the cursor
+        let count_sql = format!("SELECT COUNT(*) FROM clusters {}", builder.where_clause());
+        let count_query = builder.bind(sqlx::query_as::<_, (i64,)>(&count_sql));
+
+        and_after_cursor(&mut builder, page, sort, "id");
+        let sql = format!(
+            r#"
+            SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+            FROM clusters {} {}
+            "#,
+            builder.where_clause(),
+            order_by(page, sort, "id")
+        );
+        let query = builder.bind(sqlx::query_as::<_, DbCluster>(&sql));
+
+        let result = async {
+            let clusters = query.fetch_all(&self.pool).await?;
+            let total = if page.with_total {
+                Some(count_query.fetch_one(&self.pool).await?.0)
+            } else {
+                None
+            };
+            let clusters = clusters.into_iter().map(|c| c.into()).collect();
+            Ok::<_, sqlx::Error>(Page::new(clusters, page, total))
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cluster(&self, cluster_id: &uuid::Uuid) -> RepositoryResult<Cluster> {
+        let result = sqlx::query_as::<_, DbCluster>(
+            r#"
+            SELECT id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+            FROM clusters
+            WHERE id = $1 AND ($2 IS NULL OR tenant_id = $2)
+            "#,
+        )
+        .bind(cluster_id)
+        .bind(self.tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|c| c.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_cluster(&self, cluster: &Cluster) -> RepositoryResult<Cluster> {
+        let result = sqlx::query_as::<_, DbCluster>(
+            r#"
+        INSERT INTO clusters (id, name, tenant_id, maintenance_windows, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+        "#,
+        )
+        .bind(cluster.id)
+        .bind(&cluster.name)
+        .bind(self.tenant.or(cluster.tenant_id))
+        .bind(Json(&cluster.maintenance_windows))
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|c| c.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_cluster(
+        &self,
+        cluster: &Cluster,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>> {
+        let write = sqlx::query_as::<_, DbCluster>(
+            r#"
+            UPDATE clusters
+            SET name = $1, maintenance_windows = $2, updated_at = $3, version = version + 1
+            WHERE id = $4 AND ($5 IS NULL OR version IN (SELECT value FROM json_each($5)))
+            RETURNING id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+        "#,
+        )
+        .bind(&cluster.name)
+        .bind(Json(&cluster.maintenance_windows))
+        .bind(Utc::now())
+        .bind(cluster.id)
+        .bind(expected_version.map(Json));
+        let result = sqlite_change_rows(&self.pool, self.previous(&cluster.id), write).await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn patch_cluster(
+        &self,
+        cluster_id: &Uuid,
+        patch: &ClusterPatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Cluster>> {
+        let write = sqlx::query_as::<_, DbCluster>(
+            r#"
+            UPDATE clusters
+            SET name = COALESCE($1, name),
+                maintenance_windows = COALESCE($2, maintenance_windows),
+                updated_at = $3, version = version + 1
+            WHERE id = $4 AND ($5 IS NULL OR version IN (SELECT value FROM json_each($5)))
+            RETURNING id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+        "#,
+        )
+        .bind(&patch.name)
+        .bind(patch.maintenance_windows.as_ref().map(Json))
+        .bind(Utc::now())
+        .bind(cluster_id)
+        .bind(expected_version.map(Json));
+        let result = sqlite_change_rows(&self.pool, self.previous(cluster_id), write).await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_cluster(
+        &self,
+        cluster_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Cluster> {
+        let write = sqlx::query_as::<_, DbCluster>(
+            r#"
+            DELETE FROM clusters
+            WHERE id = $1 AND ($2 IS NULL OR version IN (SELECT value FROM json_each($2)))
+            RETURNING id, name, tenant_id, maintenance_windows, created_at, updated_at, version
+        "#,
+        )
+        .bind(cluster_id)
+        .bind(expected_version.map(Json));
+        let result = sqlite_change_rows(&self.pool, self.previous(cluster_id), write).await;
+
+        match result {
+            Ok(rows) => change_of(rows).map(|change: Change<Cluster>| change.before),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn get_cluster_summary(&self, cluster_id: &Uuid) -> RepositoryResult<ClusterSummary> {
+        let result = sqlx::query_as::<_, DbClusterSummary>(
+            r#"
+            SELECT
+                c.id AS cluster_id,
+                COALESCE(ns.node_count, 0) AS node_count,
+                COALESCE(ns.poweron_count, 0) AS poweron_count,
+                COALESCE(ns.poweroff_count, 0) AS poweroff_count,
+                COALESCE(ns.rebooting_count, 0) AS rebooting_count,
+                os.last_operation_at,
+                COALESCE(os.in_flight_operations, 0) AS in_flight_operations
+            FROM clusters c
+            LEFT JOIN (
+                SELECT
+                    n.cluster_id,
+                    COUNT(*) AS node_count,
+                    COUNT(*) FILTER (WHERE n.status = 'poweron') AS poweron_count,
+                    COUNT(*) FILTER (WHERE n.status = 'poweroff') AS poweroff_count,
+                    COUNT(*) FILTER (WHERE n.status = 'rebooting') AS rebooting_count
+                FROM nodes n
+                WHERE n.cluster_id = $1
+                GROUP BY n.cluster_id
+            ) ns ON ns.cluster_id = c.id
+            LEFT JOIN (
+                SELECT
+                    n.cluster_id,
+                    MAX(o.created_at) AS last_operation_at,
+                    COUNT(*) FILTER (WHERE o.status IN ('pending', 'running')) AS in_flight_operations
+                FROM operations o
+                JOIN nodes n ON o.node_id = n.id
+                WHERE n.cluster_id = $1
+                GROUP BY n.cluster_id
+            ) os ON os.cluster_id = c.id
+            WHERE c.id = $1 AND ($2 IS NULL OR c.tenant_id = $2)
+        "#,
+        )
+        .bind(cluster_id)
+        .bind(self.tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_node_status_counts(&self) -> RepositoryResult<HashMap<Uuid, NodeStatusCounts>> {
+        let result = sqlx::query_as::<_, DbNodeStatusCounts>(
+            r#"
+            SELECT
+                c.id AS cluster_id,
+                COUNT(n.id) FILTER (WHERE n.status = 'poweron') AS poweron_count,
+                COUNT(n.id) FILTER (WHERE n.status = 'poweroff') AS poweroff_count,
+                COUNT(n.id) FILTER (WHERE n.status = 'rebooting') AS rebooting_count
+            FROM clusters c
+            LEFT JOIN nodes n ON n.cluster_id = c.id
+            WHERE $1 IS NULL OR c.tenant_id = $1
+            GROUP BY c.id
+        "#,
+        )
+        .bind(self.tenant)
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|counts| counts.into_iter().map(|x| x.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{MaintenanceWindow, Node, NodeStatus},
+            repository::{NodeRepository, RepositoryError},
+        },
+        infrastructure::db::{repository_tests, sqlite_pool, SqliteNodeRepository},
+    };
+
+    fn create_test_cluster(name: &str) -> Cluster {
+        Cluster {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            tenant_id: None,
+            maintenance_windows: vec![],
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn writes_return_what_they_changed() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::writes_return_what_they_changed(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn patches_only_change_the_given_fields() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::patches_only_change_the_given_fields(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn crud_works() {
+        let repo = SqliteClusterRepository::new(sqlite_pool("sqlite::memory:").await.unwrap());
+        let cluster = repo
+            .create_cluster(&create_test_cluster("CLUSTER"))
+            .await
+            .unwrap();
+        assert!(cluster.created_at.is_some());
+        assert_eq!(repo.get_cluster(&cluster.id).await.unwrap(), cluster);
+
+        let result = repo.create_cluster(&create_test_cluster("CLUSTER")).await;
+        assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+
+        let renamed = Cluster {
+            name: "RENAMED".to_string(),
+            ..cluster.clone()
+        };
+        let updated = repo.update_cluster(&renamed, None).await.unwrap().after;
+        assert_eq!(updated.name, "RENAMED");
+        assert!(updated.updated_at.is_some());
+        let page = PageRequest {
+            with_total: true,
+            ..Default::default()
+        };
+        let clusters = repo.get_clusters(&page).await.unwrap();
+        assert_eq!(clusters.items, vec![updated]);
+        assert_eq!(clusters.total, Some(1));
+    }
+
+    #[actix_rt::test]
+    async fn delete_cascades_to_nodes() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        let cluster_repo = SqliteClusterRepository::new(pool.clone());
+        let node_repo = SqliteNodeRepository::new(pool);
+
+        let cluster = cluster_repo
+            .create_cluster(&create_test_cluster("CLUSTER"))
+            .await
+            .unwrap();
+        let node = node_repo
+            .create_node(&Node {
+                id: uuid::Uuid::new_v4(),
+                name: "NODE".to_string(),
+                cluster_id: cluster.id,
+                status: NodeStatus::PowerOn,
+                driver: Default::default(),
+                bmc_endpoint: None,
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cluster_repo
+                .delete_cluster(&cluster.id, None)
+                .await
+                .unwrap()
+                .id,
+            cluster.id
+        );
+        assert!(node_repo.get_node(&node.id).await.is_err());
+
+        let result = cluster_repo.delete_cluster(&cluster.id, None).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn summary_aggregates_nodes_and_operations() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::summary_aggregates_nodes_and_operations(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn maintenance_windows_are_saved() {
+        let repo = SqliteClusterRepository::new(sqlite_pool("sqlite::memory:").await.unwrap());
+        let window = MaintenanceWindow {
+            days: vec![chrono::Weekday::Sat, chrono::Weekday::Sun],
+            start: chrono::NaiveTime::from_hms(2, 0, 0),
+            duration_minutes: 180,
+            timezone: "Europe/Madrid".to_string(),
+        };
+        let cluster = repo
+            .create_cluster(&Cluster {
+                maintenance_windows: vec![window.clone()],
+                ..create_test_cluster("CLUSTER")
+            })
+            .await
+            .unwrap();
+        assert_eq!(cluster.maintenance_windows, vec![window.clone()]);
+        assert_eq!(repo.get_cluster(&cluster.id).await.unwrap(), cluster);
+
+        // the windows are left untouched unless they're in the patch
+        let patch = ClusterPatch {
+            name: Some("RENAMED".to_string()),
+            ..Default::default()
+        };
+        let patched = repo
+            .patch_cluster(&cluster.id, &patch, None)
+            .await
+            .unwrap()
+            .after;
+        assert_eq!(patched.maintenance_windows, vec![window]);
+
+        let patch = ClusterPatch {
+            maintenance_windows: Some(vec![]),
+            ..Default::default()
+        };
+        let patched = repo
+            .patch_cluster(&cluster.id, &patch, None)
+            .await
+            .unwrap()
+            .after;
+        assert!(patched.maintenance_windows.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn writes_check_the_version() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::writes_check_the_version(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+}
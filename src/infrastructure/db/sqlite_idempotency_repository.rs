@@ -0,0 +1,149 @@
+use crate::domain::{
+    models::{IdempotencyRecord, IdempotentResponse},
+    repository::{IdempotencyRepository, RepositoryResult},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::types::Json;
+use tracing::instrument;
+
+use super::entities::DbIdempotencyRecord;
+
+#[derive(Clone)]
+pub struct SqliteIdempotencyRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteIdempotencyRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for SqliteIdempotencyRepository {
+    #[instrument(skip(self))]
+    async fn claim_key(
+        &self,
+        owner: &str,
+        key: &str,
+        request_hash: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<IdempotencyRecord>> {
+        let now = Utc::now();
+        sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting expired idempotency keys: {:?}", e);
+                e
+            })?;
+
+        let claimed = sqlx::query_scalar::<_, String>(
+            r#"
+            INSERT INTO idempotency_keys (owner, key, request_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (owner, key) DO NOTHING
+            RETURNING key
+            "#,
+        )
+        .bind(owner)
+        .bind(key)
+        .bind(request_hash)
+        .bind(now + Duration::seconds(lease_secs))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error claiming idempotency key: {:?}", e);
+            e
+        })?;
+        if claimed.is_some() {
+            return Ok(None);
+        }
+
+        let record = sqlx::query_as::<_, DbIdempotencyRecord>(
+            r#"
+            SELECT owner, key, request_hash, status, headers, body, expires_at
+            FROM idempotency_keys
+            WHERE owner = $1 AND key = $2
+            "#,
+        )
+        .bind(owner)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting idempotency key: {:?}", e);
+            e
+        })?;
+        Ok(Some(record.map(|r| r.into()).unwrap_or_else(|| {
+            // the request that had the key was just released, so it's still in progress
+            // as far as this one knows
+            IdempotencyRecord {
+                owner: owner.to_string(),
+                key: key.to_string(),
+                request_hash: request_hash.to_string(),
+                response: None,
+                expires_at: now,
+            }
+        })))
+    }
+
+    #[instrument(skip(self, response))]
+    async fn save_response(
+        &self,
+        owner: &str,
+        key: &str,
+        response: &IdempotentResponse,
+        ttl_secs: i64,
+    ) -> RepositoryResult<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = $1, headers = $2, body = $3, expires_at = $4
+            WHERE owner = $5 AND key = $6
+            "#,
+        )
+        .bind(response.status as i32)
+        .bind(Json(&response.headers))
+        .bind(&response.body)
+        .bind(Utc::now() + Duration::seconds(ttl_secs))
+        .bind(owner)
+        .bind(key)
+        .execute(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error saving idempotent response: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn release_key(&self, owner: &str, key: &str) -> RepositoryResult<()> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE owner = $1 AND key = $2")
+            .bind(owner)
+            .bind(key)
+            .execute(&self.pool)
+            .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error releasing idempotency key: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{repository_tests, sqlite_pool};
+
+    #[actix_rt::test]
+    async fn keys_are_claimed_until_they_expire() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        let repo = SqliteIdempotencyRepository::new(pool);
+        repository_tests::keys_are_claimed_until_they_expire(repo).await;
+    }
+}
@@ -0,0 +1,849 @@
+use crate::{
+    domain::{
+        models::{Batch, Node, NodePatch, NodeStatus, Operation},
+        repository::{
+            node_repository::{NameMatch, NodeFilter, OperationFilter},
+            pagination::SortField,
+            Change, NodeRepository, Page, PageRequest, RepositoryError, RepositoryResult,
+            TenantScoped,
+        },
+    },
+    infrastructure::db::entities::DbNode,
+    infrastructure::db::entities::DbOperation,
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::{query::QueryAs, sqlite::SqliteArguments, types::Json, Sqlite};
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{
+    change_of,
+    entities::{DbBatch, DbNodeStatus, DbOperationStatus, DbOperationType, DbPowerDriverKind},
+    node_queries::{filter_node_ids, filter_nodes, insert_operation, NODES_FROM},
+    pagination::{and_after_cursor, order_by},
+    sqlite_change_rows,
+};
+
+#[derive(Clone)]
+pub struct SqliteNodeRepository {
+    pool: sqlx::SqlitePool,
+    tenant: Option<Uuid>,
+}
+
+impl SqliteNodeRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool, tenant: None }
+    }
+
+    /// Fails like the foreign key if the row of `table` belongs to another tenant.
+    async fn check_tenant(&self, table: &str, id: &Uuid) -> RepositoryResult<()> {
+        let tenant = match self.tenant {
+            Some(tenant) => tenant,
+            None => return Ok(()),
+        };
+        let sql = format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1 AND tenant_id = $2)",
+            table
+        );
+        let result = sqlx::query_scalar::<_, bool>(&sql)
+            .bind(id)
+            .bind(tenant)
+            .fetch_one(&self.pool)
+            .await;
+
+        match result {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(RepositoryError::ForeignKeyViolation),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Query of the node as it is before a write.
+    fn previous<'q>(&self, node_id: &Uuid) -> QueryAs<'q, Sqlite, DbNode, SqliteArguments<'q>> {
+        sqlx::query_as::<_, DbNode>(
+            r#"
+            SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+            FROM nodes
+            WHERE id = $1 AND ($2 IS NULL OR tenant_id = $2)
+            "#,
+        )
+        .bind(*node_id)
+        .bind(self.tenant)
+    }
+}
+
+/// Expression the nodes are sorted by. Nodes without a date or status are sorted first.
+fn sort_expression(sort: SortField) -> &'static str {
+    match sort {
+        SortField::Name => "n.name",
+        SortField::CreatedAt => "COALESCE(n.created_at, '1970-01-01 00:00:00')",
+        SortField::UpdatedAt => "COALESCE(n.updated_at, n.created_at, '1970-01-01 00:00:00')",
+        SortField::Status => "COALESCE(n.status, '')",
+    }
+}
+
+/// Condition matching the name of a node or its cluster.
+///
+/// `LIKE` is always case insensitive in SQLite (for ASCII characters), so the
+/// case sensitive matches are done with string functions.
+fn name_condition(column: &str, name_match: NameMatch, case_insensitive: bool) -> String {
+    match (name_match, case_insensitive) {
+        (NameMatch::Exact, false) => format!("{} = ?", column),
+        (NameMatch::Exact, true) => format!("LOWER({}) = LOWER(?)", column),
+        (NameMatch::Prefix, false) => format!("substr({}, 1, length(?)) = ?", column),
+        (NameMatch::Contains, false) => format!("instr({}, ?) > 0", column),
+        (_, true) => format!("{} LIKE ? ESCAPE '\\'", column),
+    }
+}
+
+impl TenantScoped for SqliteNodeRepository {
+    fn for_tenant(&self, tenant_id: Uuid) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            tenant: Some(tenant_id),
+        }
+    }
+}
+
+#[async_trait]
+impl NodeRepository for SqliteNodeRepository {
+    #[instrument(skip(self))]
+    async fn get_nodes(
+        &self,
+        filter: &NodeFilter,
+        page: &PageRequest,
+    ) -> RepositoryResult<Page<Node>> {
+        let sort = sort_expression(page.sort);
+        let mut builder = filter_nodes(filter, name_condition);
+        if let Some(tenant) = self.tenant {
+            builder.and_where("n.tenant_id = ?", vec![tenant.into()]);
+        }
+        // the total ignores the cursor
+        let count_sql = format!("SELECT COUNT(*) {} {}", NODES_FROM, builder.where_clause());
+        let count_query = builder.bind(sqlx::query_as::<_, (i64,)>(&count_sql));
+
+        and_after_cursor(&mut builder, page, sort, "n.id");
+        let sql = format!(
+            "SELECT n.id, n.name, n.status, n.cluster_id, n.driver, n.bmc_endpoint, n.created_at, n.updated_at, n.version {} {} {}",
+            NODES_FROM,
+            builder.where_clause(),
+            order_by(page, sort, "n.id")
+        );
+        let query = builder.bind(sqlx::query_as::<_, DbNode>(&sql));
+
+        let result = async {
+            let nodes = query.fetch_all(&self.pool).await?;
+            let total = if page.with_total {
+                Some(count_query.fetch_one(&self.pool).await?.0)
+            } else {
+                None
+            };
+            let nodes = nodes.into_iter().map(|x| x.into()).collect();
+            Ok::<_, sqlx::Error>(Page::new(nodes, page, total))
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_node(&self, node_id: &uuid::Uuid) -> RepositoryResult<Node> {
+        let result = sqlx::query_as::<_, DbNode>(
+            r#"
+            SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+            FROM nodes
+            WHERE id = $1 AND ($2 IS NULL OR tenant_id = $2)
+        "#,
+        )
+        .bind(node_id)
+        .bind(self.tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_nodes_by_id(&self, node_ids: &[Uuid]) -> RepositoryResult<Vec<Node>> {
+        if node_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let builder = filter_node_ids(node_ids, self.tenant);
+        let sql = format!(
+            "SELECT id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version FROM nodes {}",
+            builder.where_clause()
+        );
+        let result = builder
+            .bind(sqlx::query_as::<_, DbNode>(&sql))
+            .fetch_all(&self.pool)
+            .await;
+
+        result
+            .map(|nodes| nodes.into_iter().map(|x| x.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_node(&self, node: &Node) -> RepositoryResult<Node> {
+        self.check_tenant("clusters", &node.cluster_id).await?;
+        let db_status: DbNodeStatus = node.status.into();
+        let db_driver: DbPowerDriverKind = node.driver.into();
+        // the tenant is copied from the cluster, so the name is unique in it
+        let result = sqlx::query_as::<_, DbNode>(
+            r#"
+        INSERT INTO nodes (id, name, status, cluster_id, driver, bmc_endpoint, created_at, tenant_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, (SELECT tenant_id FROM clusters WHERE id = $4))
+        RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+        "#,
+        )
+        .bind(node.id)
+        .bind(&node.name)
+        .bind(db_status)
+        .bind(node.cluster_id)
+        .bind(db_driver)
+        .bind(&node.bmc_endpoint)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_node(
+        &self,
+        node: &Node,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>> {
+        self.check_tenant("clusters", &node.cluster_id).await?;
+        let db_driver: DbPowerDriverKind = node.driver.into();
+        let write = sqlx::query_as::<_, DbNode>(
+            r#"
+            UPDATE nodes
+            SET name = $1, cluster_id = $2, driver = $3, bmc_endpoint = $4, updated_at = $5,
+                version = version + 1, tenant_id = (SELECT tenant_id FROM clusters WHERE id = $2)
+            WHERE id = $6 AND ($7 IS NULL OR version IN (SELECT value FROM json_each($7)))
+            RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+        "#,
+        )
+        .bind(&node.name)
+        .bind(node.cluster_id)
+        .bind(db_driver)
+        .bind(&node.bmc_endpoint)
+        .bind(Utc::now())
+        .bind(node.id)
+        .bind(expected_version.map(Json));
+        let result = sqlite_change_rows(&self.pool, self.previous(&node.id), write).await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn patch_node(
+        &self,
+        node_id: &Uuid,
+        patch: &NodePatch,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Change<Node>> {
+        if let Some(cluster_id) = &patch.cluster_id {
+            self.check_tenant("clusters", cluster_id).await?;
+        }
+        let db_driver: Option<DbPowerDriverKind> = patch.driver.map(Into::into);
+        let write = sqlx::query_as::<_, DbNode>(
+            r#"
+            UPDATE nodes
+            SET name = COALESCE($1, name),
+                cluster_id = COALESCE($2, cluster_id),
+                driver = COALESCE($3, driver),
+                bmc_endpoint = CASE WHEN $4 THEN $5 ELSE bmc_endpoint END,
+                updated_at = $6,
+                version = version + 1,
+                tenant_id = (SELECT tenant_id FROM clusters WHERE id = COALESCE($2, nodes.cluster_id))
+            WHERE id = $7 AND ($8 IS NULL OR version IN (SELECT value FROM json_each($8)))
+            RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+        "#,
+        )
+        .bind(&patch.name)
+        .bind(patch.cluster_id)
+        .bind(db_driver)
+        .bind(patch.bmc_endpoint.is_some())
+        .bind(patch.bmc_endpoint.clone().flatten())
+        .bind(Utc::now())
+        .bind(node_id)
+        .bind(expected_version.map(Json));
+        let result = sqlite_change_rows(&self.pool, self.previous(node_id), write).await;
+
+        match result {
+            Ok(rows) => change_of(rows),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_node(
+        &self,
+        node_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Node> {
+        let write = sqlx::query_as::<_, DbNode>(
+            r#"
+            DELETE FROM nodes
+            WHERE id = $1 AND ($2 IS NULL OR version IN (SELECT value FROM json_each($2)))
+            RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+        "#,
+        )
+        .bind(node_id)
+        .bind(expected_version.map(Json));
+        let result = sqlite_change_rows(&self.pool, self.previous(node_id), write).await;
+
+        match result {
+            Ok(rows) => change_of(rows).map(|change: Change<Node>| change.before),
+            Err(e) => {
+                tracing::error!("{:?}", e);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn update_node_status(
+        &self,
+        node_id: &Uuid,
+        status: NodeStatus,
+    ) -> RepositoryResult<Node> {
+        let db_status: DbNodeStatus = status.into();
+        let result = sqlx::query_as::<_, DbNode>(
+            r#"
+            UPDATE nodes
+            SET status = $1, updated_at = $2, version = version + 1
+            WHERE id = $3
+            RETURNING id, name, status, cluster_id, driver, bmc_endpoint, created_at, updated_at, version
+        "#,
+        )
+        .bind(db_status)
+        .bind(Utc::now())
+        .bind(node_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_operations(&self, filter: &OperationFilter) -> RepositoryResult<Vec<Operation>> {
+        let db_opt_type: Option<DbOperationType> = filter.operation_type.map(|x| x.into());
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            SELECT o.id, o.operation_type, o.node_id, o.status, o.started_at, o.finished_at, o.failure_reason, o.attempts, o.batch_id, o.forced, o.overrides_maintenance, o.created_at, o.updated_at
+            FROM operations o
+            JOIN nodes n on o.node_id = n.id
+            WHERE ($1 IS NULL OR o.operation_type = $1)
+            AND ($2 IS NULL OR o.node_id = $2)
+            AND ($3 IS NULL OR n.cluster_id = $3)
+            AND ($4 IS NULL OR o.created_at >= $4)
+            AND ($5 IS NULL OR o.created_at < $5)
+            AND ($6 IS NULL OR o.batch_id = $6)
+            AND ($7 IS NULL OR n.tenant_id = $7)
+            ORDER BY o.created_at DESC, o.id DESC
+            LIMIT $8 OFFSET $9
+        "#,
+        )
+        .bind(db_opt_type)
+        .bind(filter.node_id)
+        .bind(filter.cluster_id)
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .bind(filter.batch_id)
+        .bind(self.tenant)
+        .bind(i64::from(filter.limit()))
+        .bind(i64::from(filter.offset()))
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|x| x.into_iter().map(|x| x.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_operation(&self, operation_id: &Uuid) -> RepositoryResult<Operation> {
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            SELECT o.id, o.operation_type, o.node_id, o.status, o.started_at, o.finished_at, o.failure_reason, o.attempts, o.batch_id, o.forced, o.overrides_maintenance, o.created_at, o.updated_at
+            FROM operations o
+            JOIN nodes n ON o.node_id = n.id
+            WHERE o.id = $1 AND ($2 IS NULL OR n.tenant_id = $2)
+        "#,
+        )
+        .bind(operation_id)
+        .bind(self.tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_operation(&self, operation: &Operation) -> RepositoryResult<Operation> {
+        self.check_tenant("nodes", &operation.node_id).await?;
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            let operation = insert_operation::<Sqlite>(&mut tx, operation).await?;
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(operation)
+        }
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("Error creating operation: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_operation(
+        &self,
+        operation: &Operation,
+        worker_id: &str,
+    ) -> RepositoryResult<Operation> {
+        let db_opt_status: DbOperationStatus = operation.status.into();
+        // updating an operation releases the lease the worker had on it, unless the lease
+        // expired and the operation belongs to another worker by now
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            UPDATE operations
+            SET status = $1, started_at = $2, finished_at = $3, failure_reason = $4, updated_at = $5,
+                locked_by = NULL, locked_until = NULL
+            WHERE id = $6 AND status = 'running' AND locked_by = $7
+            RETURNING id, operation_type, node_id, status, started_at, finished_at, failure_reason, attempts, batch_id, forced, overrides_maintenance, created_at, updated_at
+        "#,
+        )
+        .bind(db_opt_status)
+        .bind(operation.started_at)
+        .bind(operation.finished_at)
+        .bind(&operation.failure_reason)
+        .bind(Utc::now())
+        .bind(operation.id)
+        .bind(worker_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_batch(&self, batch_id: &Uuid) -> RepositoryResult<Batch> {
+        let result = sqlx::query_as::<_, DbBatch>(
+            r#"
+            SELECT id, operation_type, atomic, created_at
+            FROM batches b
+            WHERE id = $1 AND ($2 IS NULL OR EXISTS (
+                SELECT 1
+                FROM operations o
+                JOIN nodes n ON o.node_id = n.id
+                WHERE o.batch_id = b.id AND n.tenant_id = $2
+            ))
+        "#,
+        )
+        .bind(batch_id)
+        .bind(self.tenant)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|x| x.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, operations))]
+    async fn create_batch(
+        &self,
+        batch: &Batch,
+        operations: &[Operation],
+    ) -> RepositoryResult<(Batch, Vec<Operation>)> {
+        for operation in operations {
+            self.check_tenant("nodes", &operation.node_id).await?;
+        }
+        let db_opt_type: DbOperationType = batch.operation_type.into();
+
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            let batch = sqlx::query_as::<_, DbBatch>(
+                r#"
+            INSERT INTO batches (id, operation_type, atomic, created_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, operation_type, atomic, created_at
+            "#,
+            )
+            .bind(batch.id)
+            .bind(db_opt_type)
+            .bind(batch.atomic)
+            .bind(Utc::now())
+            .fetch_one(&mut tx)
+            .await?;
+            let mut created = Vec::with_capacity(operations.len());
+            for operation in operations {
+                created.push(insert_operation::<Sqlite>(&mut tx, operation).await?.into());
+            }
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>((batch.into(), created))
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("Error creating batch: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_operation(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Operation>> {
+        // SQLite only allows one writer at a time, so the select and the update
+        // can't race with another worker and there's no need for SKIP LOCKED. The
+        // operations of a node run one at a time, in the order they were requested.
+        let now = Utc::now();
+        let result = sqlx::query_as::<_, DbOperation>(
+            r#"
+            UPDATE operations
+            SET status = 'running', started_at = COALESCE(started_at, $3), attempts = attempts + 1,
+                locked_by = $1, locked_until = $2, updated_at = $3
+            WHERE id = (
+                SELECT o.id
+                FROM operations o
+                WHERE (o.status = 'pending' OR (o.status = 'running' AND o.locked_until < $3))
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM operations r
+                    WHERE r.node_id = o.node_id AND r.id <> o.id
+                    AND ((r.status = 'running' AND r.locked_until >= $3)
+                        OR (r.status IN ('pending', 'running')
+                            AND (r.created_at, r.id) < (o.created_at, o.id)))
+                )
+                ORDER BY o.created_at
+                LIMIT 1
+            )
+            RETURNING id, operation_type, node_id, status, started_at, finished_at, failure_reason, attempts, batch_id, forced, overrides_maintenance, created_at, updated_at
+        "#,
+        )
+        .bind(worker_id)
+        .bind(now + Duration::seconds(lease_secs))
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await;
+
+        result.map(|x| x.map(|x| x.into())).map_err(|e| {
+            tracing::error!("Error claiming operation: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn renew_operation(
+        &self,
+        operation_id: &Uuid,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<()> {
+        let now = Utc::now();
+        // an expired lease can't be renewed, since another worker may have claimed it already
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE operations
+            SET locked_until = $1
+            WHERE id = $2 AND status = 'running' AND locked_by = $3 AND locked_until >= $4
+            RETURNING id
+        "#,
+        )
+        .bind(now + Duration::seconds(lease_secs))
+        .bind(operation_id)
+        .bind(worker_id)
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error renewing operation: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{Cluster, OperationStatus, OperationType},
+            repository::{
+                pagination::{self, Cursor, SortOrder},
+                ClusterRepository, RepositoryError,
+            },
+        },
+        infrastructure::db::{repository_tests, sqlite_pool, SqliteClusterRepository},
+    };
+
+    async fn prepare_repo(cluster_name: &str) -> (SqliteNodeRepository, Uuid) {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        let cluster = SqliteClusterRepository::new(pool.clone())
+            .create_cluster(&Cluster {
+                id: uuid::Uuid::new_v4(),
+                name: cluster_name.to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        (SqliteNodeRepository::new(pool), cluster.id)
+    }
+
+    fn filter(name: &str) -> NodeFilter {
+        NodeFilter {
+            name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn create_test_node(name: &str, cluster_id: Uuid) -> Node {
+        Node {
+            id: uuid::Uuid::new_v4(),
+            name: name.to_string(),
+            cluster_id,
+            status: NodeStatus::PowerOn,
+            driver: Default::default(),
+            bmc_endpoint: Some("https://10.0.0.5".to_string()),
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn crud_works() {
+        let (repo, cluster_id) = prepare_repo("RACK_A").await;
+        let node = repo
+            .create_node(&create_test_node("NODE_1", cluster_id))
+            .await
+            .unwrap();
+        assert_eq!(repo.get_node(&node.id).await.unwrap(), node);
+
+        let result = repo
+            .create_node(&create_test_node("NODE_1", cluster_id))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+        let result = repo
+            .create_node(&create_test_node("NODE_2", uuid::Uuid::new_v4()))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+        let result = repo.get_node(&uuid::Uuid::new_v4()).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+
+        let node = repo
+            .update_node_status(&node.id, NodeStatus::PowerOff)
+            .await
+            .unwrap();
+        assert_eq!(node.status, NodeStatus::PowerOff);
+
+        let page = PageRequest::default();
+        assert_eq!(
+            repo.get_nodes(&filter("RACK"), &page).await.unwrap().items,
+            vec![node.clone()]
+        );
+        assert!(repo
+            .get_nodes(&filter("nope"), &page)
+            .await
+            .unwrap()
+            .items
+            .is_empty());
+
+        assert_eq!(repo.delete_node(&node.id, None).await.unwrap().id, node.id);
+        assert!(repo
+            .get_nodes(&NodeFilter::default(), &page)
+            .await
+            .unwrap()
+            .items
+            .is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_combines_filters() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::get_nodes_combines_filters(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn get_nodes_paginates() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        let mut expected = vec![];
+        for (name, status) in [
+            ("NODE_1", NodeStatus::PowerOn),
+            ("NODE_2", NodeStatus::PowerOff),
+            ("NODE_3", NodeStatus::PowerOn),
+        ] {
+            let node = repo
+                .create_node(&Node {
+                    status,
+                    ..create_test_node(name, cluster_id)
+                })
+                .await
+                .unwrap();
+            expected.push(node);
+        }
+        // poweroff < poweron, ties broken by id
+        expected.sort_by_key(|n| (pagination::status_text(n.status), n.id));
+
+        for sort in [SortField::Status, SortField::UpdatedAt] {
+            let mut page = PageRequest::new(Some(1), sort, SortOrder::Asc, None, true).unwrap();
+            let mut nodes = vec![];
+            loop {
+                let result = repo.get_nodes(&filter("CLUSTER"), &page).await.unwrap();
+                assert_eq!(result.total, Some(3));
+                nodes.extend(result.items);
+                match result.next_cursor {
+                    Some(cursor) => page.after = Some(Cursor::decode(&cursor).unwrap()),
+                    None => break,
+                }
+            }
+            if sort == SortField::Status {
+                assert_eq!(nodes, expected);
+            } else {
+                assert_eq!(nodes.len(), 3);
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn operations_queue_works() {
+        let (repo, cluster_id) = prepare_repo("CLUSTER").await;
+        let node = repo
+            .create_node(&create_test_node("NODE", cluster_id))
+            .await
+            .unwrap();
+        let first = repo
+            .create_operation(&Operation::new(node.id, OperationType::PowerOff))
+            .await
+            .unwrap();
+        let second = repo
+            .create_operation(&Operation::new(node.id, OperationType::PowerOn))
+            .await
+            .unwrap();
+
+        let filter = OperationFilter {
+            cluster_id: Some(cluster_id),
+            ..Default::default()
+        };
+        assert_eq!(
+            repo.get_operations(&filter).await.unwrap(),
+            vec![second.clone(), first.clone()]
+        );
+
+        // expired lease, so it can be claimed again
+        let claimed = repo.claim_operation("worker", -1).await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert_eq!(claimed.status, OperationStatus::Running);
+        let reclaimed = repo.claim_operation("other", 60).await.unwrap().unwrap();
+        assert_eq!(reclaimed.id, first.id);
+        assert_eq!(reclaimed.attempts, 2);
+        assert_eq!(reclaimed.started_at, claimed.started_at);
+
+        // the node is still running the first one
+        assert!(repo.claim_operation("worker", 60).await.unwrap().is_none());
+        let mut finished = reclaimed;
+        finished.status = OperationStatus::Succeeded;
+        finished.finished_at = Some(Utc::now());
+        let updated = repo.update_operation(&finished, "other").await.unwrap();
+        assert_eq!(repo.get_operation(&first.id).await.unwrap(), updated);
+
+        let claimed = repo.claim_operation("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, second.id);
+        assert!(repo.claim_operation("worker", 60).await.unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn create_batch_is_all_or_nothing() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::create_batch_is_all_or_nothing(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_only_finished_by_the_worker_holding_them() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::operations_are_only_finished_by_the_worker_holding_them(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn operations_of_a_node_are_claimed_one_at_a_time() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::operations_of_a_node_are_claimed_one_at_a_time(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn operations_are_listed_newest_first_by_pages() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::operations_are_listed_newest_first_by_pages(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+}
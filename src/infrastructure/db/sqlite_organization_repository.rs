@@ -0,0 +1,122 @@
+use crate::domain::{
+    models::Organization,
+    repository::{OrganizationRepository, RepositoryResult},
+};
+use async_trait::async_trait;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::DbOrganization;
+
+#[derive(Clone)]
+pub struct SqliteOrganizationRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteOrganizationRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for SqliteOrganizationRepository {
+    #[instrument(skip(self))]
+    async fn get_organizations(&self) -> RepositoryResult<Vec<Organization>> {
+        let result = sqlx::query_as::<_, DbOrganization>(
+            "SELECT id, name, created_at FROM organizations ORDER BY name",
+        )
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|organizations| organizations.into_iter().map(|o| o.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_organization(&self, organization_id: &Uuid) -> RepositoryResult<Organization> {
+        let result = sqlx::query_as::<_, DbOrganization>(
+            "SELECT id, name, created_at FROM organizations WHERE id = $1",
+        )
+        .bind(organization_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|o| o.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_organization(
+        &self,
+        organization: &Organization,
+    ) -> RepositoryResult<Organization> {
+        let result = sqlx::query_as::<_, DbOrganization>(
+            r#"
+            INSERT INTO organizations (id, name)
+            VALUES ($1, $2)
+            RETURNING id, name, created_at
+            "#,
+        )
+        .bind(organization.id)
+        .bind(&organization.name)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|o| o.into()).map_err(|e| {
+            tracing::error!("Error creating organization: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_organization(&self, organization_id: &Uuid) -> RepositoryResult<Uuid> {
+        let result =
+            sqlx::query_as::<_, (Uuid,)>("DELETE FROM organizations WHERE id = $1 RETURNING id")
+                .bind(organization_id)
+                .fetch_one(&self.pool)
+                .await;
+
+        result.map(|(id,)| id).map_err(|e| {
+            tracing::error!("Error deleting organization: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{
+        repository_tests, sqlite_pool, SqliteClusterRepository, SqliteNodeRepository,
+    };
+
+    #[actix_rt::test]
+    async fn tenants_only_see_their_own_data() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::tenants_only_see_their_own_data(
+            SqliteOrganizationRepository::new(pool.clone()),
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn nodes_keep_the_tenant_of_their_cluster() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::nodes_keep_the_tenant_of_their_cluster(
+            SqliteOrganizationRepository::new(pool.clone()),
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool.clone()),
+            &pool,
+        )
+        .await;
+    }
+}
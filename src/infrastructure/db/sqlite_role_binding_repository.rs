@@ -0,0 +1,193 @@
+use crate::domain::{
+    models::RoleBinding,
+    repository::{RepositoryResult, RoleBindingRepository},
+};
+use async_trait::async_trait;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::{DbRole, DbRoleBinding};
+
+const ROLE_BINDING_COLUMNS: &str = "id, subject, role, cluster_id, created_at";
+
+#[derive(Clone)]
+pub struct SqliteRoleBindingRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteRoleBindingRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RoleBindingRepository for SqliteRoleBindingRepository {
+    #[instrument(skip(self))]
+    async fn get_role_bindings<'a>(
+        &self,
+        subject: Option<&'a str>,
+    ) -> RepositoryResult<Vec<RoleBinding>> {
+        let result = sqlx::query_as::<_, DbRoleBinding>(&format!(
+            "SELECT {} FROM role_bindings WHERE $1 IS NULL OR subject = $1 ORDER BY created_at, id",
+            ROLE_BINDING_COLUMNS
+        ))
+        .bind(subject)
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .map(|bindings| bindings.into_iter().map(|b| b.into()).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<RoleBinding> {
+        let result = sqlx::query_as::<_, DbRoleBinding>(&format!(
+            "SELECT {} FROM role_bindings WHERE id = $1",
+            ROLE_BINDING_COLUMNS
+        ))
+        .bind(role_binding_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|b| b.into()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_role_binding(
+        &self,
+        role_binding: &RoleBinding,
+    ) -> RepositoryResult<RoleBinding> {
+        let result = sqlx::query_as::<_, DbRoleBinding>(&format!(
+            r#"
+            INSERT INTO role_bindings (id, subject, role, cluster_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+            "#,
+            ROLE_BINDING_COLUMNS
+        ))
+        .bind(role_binding.id)
+        .bind(&role_binding.subject)
+        .bind(DbRole::from(role_binding.role))
+        .bind(role_binding.cluster_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map(|b| b.into()).map_err(|e| {
+            tracing::error!("Error creating role binding: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_role_binding(&self, role_binding_id: &Uuid) -> RepositoryResult<Uuid> {
+        let result =
+            sqlx::query_as::<_, (Uuid,)>("DELETE FROM role_bindings WHERE id = $1 RETURNING id")
+                .bind(role_binding_id)
+                .fetch_one(&self.pool)
+                .await;
+
+        result.map(|(id,)| id).map_err(|e| {
+            tracing::error!("Error deleting role binding: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{Cluster, Role},
+            repository::{ClusterRepository, RepositoryError},
+        },
+        infrastructure::db::{sqlite_pool, SqliteClusterRepository},
+    };
+
+    #[actix_rt::test]
+    async fn role_bindings_are_deleted_with_their_cluster() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        let cluster_repo = SqliteClusterRepository::new(pool.clone());
+        let repo = SqliteRoleBindingRepository::new(pool);
+        let cluster = cluster_repo
+            .create_cluster(&Cluster {
+                id: Uuid::new_v4(),
+                name: "CLUSTER".to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+
+        let binding = RoleBinding {
+            id: Uuid::new_v4(),
+            subject: "jane".to_string(),
+            role: Role::Operator,
+            cluster_id: Some(cluster.id),
+            created_at: None,
+        };
+        let created = repo.create_role_binding(&binding).await.unwrap();
+        assert!(created.created_at.is_some());
+        let global = RoleBinding {
+            id: Uuid::new_v4(),
+            cluster_id: None,
+            ..binding.clone()
+        };
+        repo.create_role_binding(&global).await.unwrap();
+        for duplicate in [&binding, &global] {
+            let result = repo
+                .create_role_binding(&RoleBinding {
+                    id: Uuid::new_v4(),
+                    ..duplicate.clone()
+                })
+                .await;
+            assert!(matches!(result, Err(RepositoryError::AlreadyExists)));
+        }
+        let result = repo
+            .create_role_binding(&RoleBinding {
+                id: Uuid::new_v4(),
+                cluster_id: Some(Uuid::new_v4()),
+                ..binding.clone()
+            })
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+
+        assert_eq!(repo.get_role_bindings(Some("jane")).await.unwrap().len(), 2);
+        assert!(repo
+            .get_role_bindings(Some("john"))
+            .await
+            .unwrap()
+            .is_empty());
+        cluster_repo
+            .delete_cluster(&cluster.id, None)
+            .await
+            .unwrap();
+        let remaining: Vec<Uuid> = repo
+            .get_role_bindings(None)
+            .await
+            .unwrap()
+            .iter()
+            .map(|binding| binding.id)
+            .collect();
+        assert_eq!(remaining, vec![global.id]);
+        assert_eq!(
+            repo.delete_role_binding(&global.id).await.unwrap(),
+            global.id
+        );
+        assert!(matches!(
+            repo.delete_role_binding(&global.id).await,
+            Err(RepositoryError::DoesNotExist)
+        ));
+    }
+}
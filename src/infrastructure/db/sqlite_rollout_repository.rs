@@ -0,0 +1,343 @@
+use crate::domain::{
+    models::{Rollout, RolloutNode, RolloutStatus},
+    repository::{RepositoryError, RepositoryResult, RolloutRepository},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::types::Json;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::{DbRollout, DbRolloutNode, DbRolloutNodeStatus, DbRolloutStatus};
+
+const ROLLOUT_COLUMNS: &str =
+    "id, cluster_id, status, max_in_flight, max_failures, created_at, updated_at, version";
+
+#[derive(Clone)]
+pub struct SqliteRolloutRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteRolloutRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds the nodes to the row of a rollout.
+    async fn with_nodes(&self, rollout: DbRollout) -> Result<Rollout, sqlx::Error> {
+        let nodes = sqlx::query_as::<_, DbRolloutNode>(
+            r#"
+            SELECT node_id, status, operation_id, failure_reason
+            FROM rollout_nodes
+            WHERE rollout_id = $1
+            ORDER BY position
+        "#,
+        )
+        .bind(rollout.id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rollout.into_rollout(nodes))
+    }
+}
+
+#[async_trait]
+impl RolloutRepository for SqliteRolloutRepository {
+    #[instrument(skip(self))]
+    async fn get_rollout(&self, rollout_id: &Uuid) -> RepositoryResult<Rollout> {
+        let result = async {
+            let rollout = sqlx::query_as::<_, DbRollout>(&format!(
+                "SELECT {} FROM rollouts WHERE id = $1",
+                ROLLOUT_COLUMNS
+            ))
+            .bind(rollout_id)
+            .fetch_one(&self.pool)
+            .await?;
+            self.with_nodes(rollout).await
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, rollout), fields(rollout_id = %rollout.id))]
+    async fn create_rollout(&self, rollout: &Rollout) -> RepositoryResult<Rollout> {
+        let db_status: DbRolloutStatus = rollout.status.into();
+
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            let created = sqlx::query_as::<_, DbRollout>(&format!(
+                r#"
+                INSERT INTO rollouts (id, cluster_id, status, max_in_flight, max_failures, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING {}
+                "#,
+                ROLLOUT_COLUMNS
+            ))
+            .bind(rollout.id)
+            .bind(rollout.cluster_id)
+            .bind(db_status)
+            .bind(rollout.max_in_flight)
+            .bind(rollout.max_failures)
+            .bind(Utc::now())
+            .fetch_one(&mut tx)
+            .await?;
+            for (position, node) in rollout.nodes.iter().enumerate() {
+                let db_node_status: DbRolloutNodeStatus = node.status.into();
+                sqlx::query(
+                    r#"
+                    INSERT INTO rollout_nodes (rollout_id, node_id, position, status, operation_id, failure_reason)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    "#,
+                )
+                .bind(rollout.id)
+                .bind(node.node_id)
+                .bind(position as i32)
+                .bind(db_node_status)
+                .bind(node.operation_id)
+                .bind(&node.failure_reason)
+                .execute(&mut tx)
+                .await?;
+            }
+            tx.commit().await?;
+            self.with_nodes(created).await
+        }
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("Error creating rollout: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self, nodes))]
+    async fn update_rollout(
+        &self,
+        rollout_id: &Uuid,
+        status: RolloutStatus,
+        nodes: &[RolloutNode],
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Rollout> {
+        let db_status: DbRolloutStatus = status.into();
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            let updated = sqlx::query_as::<_, DbRollout>(&format!(
+                r#"
+                UPDATE rollouts
+                SET status = $1, updated_at = $4, version = version + 1
+                WHERE id = $2 AND ($3 IS NULL OR version IN (SELECT value FROM json_each($3)))
+                RETURNING {}
+                "#,
+                ROLLOUT_COLUMNS
+            ))
+            .bind(db_status)
+            .bind(rollout_id)
+            .bind(expected_version.map(Json))
+            .bind(Utc::now())
+            .fetch_optional(&mut tx)
+            .await?;
+            let updated = match updated {
+                Some(updated) => updated,
+                None => {
+                    let exists = sqlx::query_scalar::<_, bool>(
+                        "SELECT EXISTS (SELECT 1 FROM rollouts WHERE id = $1)",
+                    )
+                    .bind(rollout_id)
+                    .fetch_one(&mut tx)
+                    .await?;
+                    return Ok(Err(match exists {
+                        true => RepositoryError::VersionMismatch,
+                        false => RepositoryError::DoesNotExist,
+                    }));
+                }
+            };
+            for node in nodes {
+                let db_node_status: DbRolloutNodeStatus = node.status.into();
+                sqlx::query(
+                    r#"
+                    UPDATE rollout_nodes
+                    SET status = $1, operation_id = $2, failure_reason = $3
+                    WHERE rollout_id = $4 AND node_id = $5
+                    "#,
+                )
+                .bind(db_node_status)
+                .bind(node.operation_id)
+                .bind(&node.failure_reason)
+                .bind(rollout_id)
+                .bind(node.node_id)
+                .execute(&mut tx)
+                .await?;
+            }
+            tx.commit().await?;
+            self.with_nodes(updated).await.map(Ok)
+        }
+        .await;
+
+        result.unwrap_or_else(|e| {
+            tracing::error!("Error updating rollout: {:?}", e);
+            Err(e.into())
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_rollout(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<Rollout>> {
+        // SQLite only allows one writer at a time, so the select and the update
+        // can't race with another worker and there's no need for SKIP LOCKED.
+        let now = Utc::now();
+        let result = sqlx::query_as::<_, DbRollout>(&format!(
+            r#"
+            UPDATE rollouts
+            SET locked_by = $1, locked_until = $2
+            WHERE id = (
+                SELECT id
+                FROM rollouts
+                WHERE status IN ('running', 'paused')
+                AND (locked_until IS NULL OR locked_until < $3)
+                ORDER BY locked_until NULLS FIRST
+                LIMIT 1
+            )
+            RETURNING {}
+            "#,
+            ROLLOUT_COLUMNS
+        ))
+        .bind(worker_id)
+        .bind(now + Duration::seconds(lease_secs))
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await;
+
+        let result = match result {
+            Ok(Some(rollout)) => self.with_nodes(rollout).await.map(Some),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| {
+            tracing::error!("Error claiming rollout: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn release_rollout(
+        &self,
+        rollout_id: &Uuid,
+        worker_id: &str,
+        delay_secs: i64,
+    ) -> RepositoryResult<()> {
+        // if the lease expired, the rollout may belong to another worker by now
+        let result = sqlx::query(
+            r#"
+            UPDATE rollouts
+            SET locked_by = NULL, locked_until = $1
+            WHERE id = $2 AND locked_by = $3
+            "#,
+        )
+        .bind(Utc::now() + Duration::seconds(delay_secs))
+        .bind(rollout_id)
+        .bind(worker_id)
+        .execute(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error releasing rollout: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{models::Cluster, repository::ClusterRepository},
+        infrastructure::db::{repository_tests, sqlite_pool, SqliteClusterRepository},
+    };
+
+    async fn prepare_repo() -> (SqliteRolloutRepository, SqliteClusterRepository, Uuid) {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        let cluster_repo = SqliteClusterRepository::new(pool.clone());
+        let cluster = cluster_repo
+            .create_cluster(&Cluster {
+                id: Uuid::new_v4(),
+                name: "CLUSTER".to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        (SqliteRolloutRepository::new(pool), cluster_repo, cluster.id)
+    }
+
+    #[actix_rt::test]
+    async fn create_keeps_the_order_of_the_nodes() {
+        let (repo, cluster_repo, cluster_id) = prepare_repo().await;
+        let result = repo
+            .create_rollout(&Rollout::new(Uuid::new_v4(), 1, 0, [Uuid::new_v4()]))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+
+        let rollout = Rollout::new(cluster_id, 2, 1, (0..5).map(|_| Uuid::new_v4()));
+        let created = repo.create_rollout(&rollout).await.unwrap();
+        assert_eq!(created.nodes, rollout.nodes);
+        assert_eq!(created.version, 1);
+        assert_eq!(repo.get_rollout(&rollout.id).await.unwrap(), created);
+
+        cluster_repo
+            .delete_cluster(&cluster_id, None)
+            .await
+            .unwrap();
+        let result = repo.get_rollout(&rollout.id).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn rollout_updates_check_the_version() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::rollout_updates_check_the_version(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteRolloutRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn claim_rollout_leases_unfinished_rollouts() {
+        let (repo, _, cluster_id) = prepare_repo().await;
+        let rollout = repo
+            .create_rollout(&Rollout::new(cluster_id, 1, 0, [Uuid::new_v4()]))
+            .await
+            .unwrap();
+
+        let claimed = repo.claim_rollout("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, rollout.id);
+        assert_eq!(claimed.nodes.len(), 1);
+        assert!(repo.claim_rollout("other", 60).await.unwrap().is_none());
+
+        // only the worker holding the lease can release it
+        repo.release_rollout(&rollout.id, "other", -1)
+            .await
+            .unwrap();
+        assert!(repo.claim_rollout("other", 60).await.unwrap().is_none());
+        repo.release_rollout(&rollout.id, "worker", -1)
+            .await
+            .unwrap();
+        assert!(repo.claim_rollout("other", 60).await.unwrap().is_some());
+
+        repo.update_rollout(&rollout.id, RolloutStatus::Aborted, &[], None)
+            .await
+            .unwrap();
+        repo.release_rollout(&rollout.id, "other", -1)
+            .await
+            .unwrap();
+        assert!(repo.claim_rollout("worker", 60).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,426 @@
+use crate::domain::{
+    models::{ScheduleRun, ScheduledOperation},
+    repository::{RepositoryError, RepositoryResult, ScheduleRepository},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::types::Json;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::entities::{
+    schedule_target_ids, DbMissedRunPolicy, DbOperationType, DbScheduledOperation,
+};
+
+const SCHEDULE_COLUMNS: &str = r#"
+    id, operation_type, node_id, cluster_id, run_at, cron, missed_run_policy, enabled,
+    next_run_at, last_run_at, last_batch_id, last_error, created_at, updated_at, version
+"#;
+
+#[derive(Clone)]
+pub struct SqliteScheduleRepository {
+    pool: sqlx::SqlitePool,
+}
+
+impl SqliteScheduleRepository {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Error of a conditional write that matched no row, which happens when the schedule
+    /// doesn't exist but also when it doesn't have the expected version.
+    async fn write_error(&self, schedule_id: &Uuid, error: sqlx::Error) -> RepositoryError {
+        tracing::error!("{:?}", error);
+        match error {
+            sqlx::Error::RowNotFound => match self.get_schedule(schedule_id).await {
+                Ok(_) => RepositoryError::VersionMismatch,
+                Err(e) => e,
+            },
+            e => e.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl ScheduleRepository for SqliteScheduleRepository {
+    #[instrument(skip(self))]
+    async fn get_schedules(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> RepositoryResult<Vec<ScheduledOperation>> {
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            SELECT {}
+            FROM schedules
+            WHERE $1 IS NULL
+            OR cluster_id IN (SELECT id FROM clusters WHERE tenant_id = $1)
+            OR node_id IN (SELECT id FROM nodes WHERE tenant_id = $1)
+            ORDER BY created_at, id
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await;
+
+        result
+            .and_then(|schedules| schedules.into_iter().map(TryInto::try_into).collect())
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn get_schedule(&self, schedule_id: &Uuid) -> RepositoryResult<ScheduledOperation> {
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            "SELECT {} FROM schedules WHERE id = $1",
+            SCHEDULE_COLUMNS
+        ))
+        .bind(schedule_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.and_then(TryInto::try_into).map_err(|e| {
+            tracing::error!("{:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn create_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+    ) -> RepositoryResult<ScheduledOperation> {
+        let (node_id, cluster_id) = schedule_target_ids(&schedule.target);
+        let operation_type: DbOperationType = schedule.operation_type.into();
+        let policy: DbMissedRunPolicy = schedule.missed_run_policy.into();
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            INSERT INTO schedules (id, operation_type, node_id, cluster_id, run_at, cron,
+                missed_run_policy, enabled, next_run_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING {}
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(schedule.id)
+        .bind(operation_type)
+        .bind(node_id)
+        .bind(cluster_id)
+        .bind(schedule.run_at)
+        .bind(&schedule.cron)
+        .bind(policy)
+        .bind(schedule.enabled)
+        .bind(schedule.next_run_at)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await;
+
+        result.and_then(TryInto::try_into).map_err(|e| {
+            tracing::error!("Error creating schedule: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_schedule(
+        &self,
+        schedule: &ScheduledOperation,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<ScheduledOperation> {
+        let (node_id, cluster_id) = schedule_target_ids(&schedule.target);
+        let operation_type: DbOperationType = schedule.operation_type.into();
+        let policy: DbMissedRunPolicy = schedule.missed_run_policy.into();
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            UPDATE schedules
+            SET operation_type = $1, node_id = $2, cluster_id = $3, run_at = $4, cron = $5,
+                missed_run_policy = $6, enabled = $7, next_run_at = $8, updated_at = $9,
+                version = version + 1
+            WHERE id = $10 AND ($11 IS NULL OR version IN (SELECT value FROM json_each($11)))
+            RETURNING {}
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(operation_type)
+        .bind(node_id)
+        .bind(cluster_id)
+        .bind(schedule.run_at)
+        .bind(&schedule.cron)
+        .bind(policy)
+        .bind(schedule.enabled)
+        .bind(schedule.next_run_at)
+        .bind(Utc::now())
+        .bind(schedule.id)
+        .bind(expected_version.map(Json))
+        .fetch_one(&self.pool)
+        .await;
+
+        match result.and_then(TryInto::try_into) {
+            Ok(schedule) => Ok(schedule),
+            Err(e) => Err(self.write_error(&schedule.id, e).await),
+        }
+    }
+
+    #[instrument(skip(self), err)]
+    async fn delete_schedule(
+        &self,
+        schedule_id: &Uuid,
+        expected_version: Option<Vec<i64>>,
+    ) -> RepositoryResult<Uuid> {
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            DELETE FROM schedules
+            WHERE id = $1 AND ($2 IS NULL OR version IN (SELECT value FROM json_each($2)))
+            RETURNING id
+            "#,
+        )
+        .bind(schedule_id)
+        .bind(expected_version.map(Json))
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(id) => Ok(id),
+            Err(e) => Err(self.write_error(schedule_id, e).await),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn claim_schedule(
+        &self,
+        worker_id: &str,
+        lease_secs: i64,
+    ) -> RepositoryResult<Option<ScheduledOperation>> {
+        // SQLite only allows one writer at a time, so the select and the update
+        // can't race with another worker and there's no need for SKIP LOCKED.
+        let now = Utc::now();
+        let result = sqlx::query_as::<_, DbScheduledOperation>(&format!(
+            r#"
+            UPDATE schedules
+            SET locked_by = $1, locked_until = $2
+            WHERE id = (
+                SELECT id
+                FROM schedules
+                WHERE enabled AND next_run_at <= $3
+                AND (locked_until IS NULL OR locked_until < $3)
+                ORDER BY next_run_at
+                LIMIT 1
+            )
+            RETURNING {}
+            "#,
+            SCHEDULE_COLUMNS
+        ))
+        .bind(worker_id)
+        .bind(now + Duration::seconds(lease_secs))
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await;
+
+        result
+            .and_then(|s| s.map(TryInto::try_into).transpose())
+            .map_err(|e| {
+                tracing::error!("Error claiming schedule: {:?}", e);
+                e.into()
+            })
+    }
+
+    #[instrument(skip(self))]
+    async fn reserve_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        batch_id: &Uuid,
+    ) -> RepositoryResult<Uuid> {
+        let result = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE schedules
+            SET run_batch_id = COALESCE(run_batch_id, $1)
+            WHERE id = $2 AND locked_by = $3
+            RETURNING run_batch_id
+            "#,
+        )
+        .bind(batch_id)
+        .bind(schedule_id)
+        .bind(worker_id)
+        .fetch_one(&self.pool)
+        .await;
+
+        result.map_err(|e| {
+            tracing::error!("Error reserving schedule run: {:?}", e);
+            e.into()
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn record_run(
+        &self,
+        schedule_id: &Uuid,
+        worker_id: &str,
+        version: i64,
+        run: &ScheduleRun,
+    ) -> RepositoryResult<()> {
+        // if the lease expired, the schedule may belong to another worker by now
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules
+            SET last_run_at = $1, last_batch_id = $2, last_error = $3,
+                next_run_at = CASE WHEN version = $4 THEN $5 ELSE next_run_at END,
+                locked_by = NULL, locked_until = NULL, run_batch_id = NULL
+            WHERE id = $6 AND locked_by = $7
+            "#,
+        )
+        .bind(run.ran_at)
+        .bind(run.batch_id)
+        .bind(&run.error)
+        .bind(version)
+        .bind(run.next_run_at)
+        .bind(schedule_id)
+        .bind(worker_id)
+        .execute(&self.pool)
+        .await;
+
+        result.map(|_| ()).map_err(|e| {
+            tracing::error!("Error recording schedule run: {:?}", e);
+            e.into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            models::{Cluster, MissedRunPolicy, OperationType, ScheduleTarget},
+            repository::ClusterRepository,
+        },
+        infrastructure::db::{
+            repository_tests, sqlite_pool, SqliteClusterRepository, SqliteNodeRepository,
+            SqliteOrganizationRepository,
+        },
+    };
+
+    async fn prepare_repo() -> (SqliteScheduleRepository, SqliteClusterRepository, Uuid) {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        let cluster_repo = SqliteClusterRepository::new(pool.clone());
+        let cluster = cluster_repo
+            .create_cluster(&Cluster {
+                id: Uuid::new_v4(),
+                name: "CLUSTER".to_string(),
+                tenant_id: None,
+                maintenance_windows: vec![],
+                created_at: None,
+                updated_at: None,
+                version: 0,
+            })
+            .await
+            .unwrap();
+        (
+            SqliteScheduleRepository::new(pool),
+            cluster_repo,
+            cluster.id,
+        )
+    }
+
+    fn create_test_schedule(cluster_id: Uuid, next_run_in_secs: i64) -> ScheduledOperation {
+        ScheduledOperation {
+            id: Uuid::new_v4(),
+            operation_type: OperationType::Reboot,
+            target: ScheduleTarget::ClusterId(cluster_id),
+            run_at: None,
+            cron: Some("0 3 * * Sun".to_string()),
+            missed_run_policy: MissedRunPolicy::Skip,
+            enabled: true,
+            next_run_at: Some(Utc::now() + Duration::seconds(next_run_in_secs)),
+            last_run_at: None,
+            last_batch_id: None,
+            last_error: None,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_checks_the_target_and_deletion_cascades() {
+        let (repo, cluster_repo, cluster_id) = prepare_repo().await;
+        let result = repo
+            .create_schedule(&create_test_schedule(Uuid::new_v4(), 60))
+            .await;
+        assert!(matches!(result, Err(RepositoryError::ForeignKeyViolation)));
+
+        let schedule = create_test_schedule(cluster_id, 60);
+        let created = repo.create_schedule(&schedule).await.unwrap();
+        assert_eq!(created.target, schedule.target);
+        assert_eq!(created.missed_run_policy, MissedRunPolicy::Skip);
+        assert_eq!(created.version, 1);
+        assert_eq!(repo.get_schedules(None).await.unwrap(), vec![created]);
+
+        cluster_repo
+            .delete_cluster(&cluster_id, None)
+            .await
+            .unwrap();
+        let result = repo.get_schedule(&schedule.id).await;
+        assert!(matches!(result, Err(RepositoryError::DoesNotExist)));
+    }
+
+    #[actix_rt::test]
+    async fn claim_schedule_takes_due_schedules() {
+        let (repo, _, cluster_id) = prepare_repo().await;
+        repo.create_schedule(&create_test_schedule(cluster_id, 60))
+            .await
+            .unwrap();
+        let due = repo
+            .create_schedule(&create_test_schedule(cluster_id, -10))
+            .await
+            .unwrap();
+
+        let claimed = repo.claim_schedule("worker", 60).await.unwrap().unwrap();
+        assert_eq!(claimed.id, due.id);
+        assert!(repo.claim_schedule("other", 60).await.unwrap().is_none());
+
+        // a newer definition keeps its own next run
+        let mut updated = due.clone();
+        updated.next_run_at = Some(Utc::now() + Duration::hours(1));
+        let updated = repo.update_schedule(&updated, Some(vec![1])).await.unwrap();
+        let result = repo.update_schedule(&updated, Some(vec![1])).await;
+        assert!(matches!(result, Err(RepositoryError::VersionMismatch)));
+
+        let run = ScheduleRun {
+            ran_at: Utc::now(),
+            batch_id: None,
+            error: Some("boom".to_string()),
+            next_run_at: Some(Utc::now() + Duration::days(7)),
+        };
+        repo.record_run(&due.id, "worker", 1, &run).await.unwrap();
+        let ran = repo.get_schedule(&due.id).await.unwrap();
+        assert_eq!(ran.last_run_at, Some(run.ran_at));
+        assert_eq!(ran.last_error, run.error);
+        assert_eq!(ran.next_run_at, updated.next_run_at);
+        assert!(repo.claim_schedule("worker", 60).await.unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn runs_reserve_a_single_batch() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::runs_reserve_a_single_batch(
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteScheduleRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[actix_rt::test]
+    async fn schedules_are_filtered_by_tenant() {
+        let pool = sqlite_pool("sqlite::memory:").await.unwrap();
+        repository_tests::schedules_are_filtered_by_tenant(
+            SqliteOrganizationRepository::new(pool.clone()),
+            SqliteClusterRepository::new(pool.clone()),
+            SqliteNodeRepository::new(pool.clone()),
+            SqliteScheduleRepository::new(pool),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    domain::{
+        models::{IdempotentResponse, Principal},
+        repository::{IdempotencyRepository, RepositoryResult},
+    },
+    infrastructure::controllers::ApiError,
+};
+use actix_web::{
+    body::{self, BoxBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::PayloadError,
+    http::{
+        header::{HeaderName, HeaderValue},
+        Method, StatusCode,
+    },
+    web, Error, FromRequest, HttpMessage, HttpResponse, ResponseError,
+};
+use futures::{
+    future::{ready, LocalBoxFuture, Ready},
+    stream, Stream,
+};
+use sha2::{Digest, Sha256};
+use std::{pin::Pin, rc::Rc, sync::Arc, time::Duration};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Header added to the responses that are replayed.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Where the [`Idempotency`] middleware keeps the keys and for how long.
+///
+/// It's shared as app data. Without it, the middleware lets every request through.
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    repository: Arc<dyn IdempotencyRepository>,
+    /// How long the responses are kept.
+    ttl: Duration,
+    /// How long a key is kept while its request is being processed, so it's freed if the
+    /// request never ends (e.g. the API stopped while processing it).
+    lease: Duration,
+}
+
+impl IdempotencyKeys {
+    pub fn new(repository: impl IdempotencyRepository, ttl: Duration, lease: Duration) -> Self {
+        Self {
+            repository: Arc::new(repository),
+            ttl,
+            lease,
+        }
+    }
+}
+
+/// Whose the key is. Every caller has its own keys in each tenant, so a caller can't get
+/// the responses of another one by reusing its key.
+fn owner(principal: &Principal) -> String {
+    // the tenant is a UUID, so it can't run into the subject
+    let tenant = principal
+        .tenant
+        .map(|tenant| tenant.to_string())
+        .unwrap_or_default();
+    format!("{}/{}", tenant, principal.subject)
+}
+
+/// Middleware that makes the `POST` requests with an `Idempotency-Key` header safe to retry.
+///
+/// The first request with a key is processed and its response is stored. Retries with the
+/// same key and the same request get that response again, with an `Idempotent-Replayed`
+/// header, instead of being processed. Reusing the key for another request is rejected
+/// with `422`, and so is retrying while the first request is still being processed, with
+/// `409`. Server errors aren't stored, so those requests can be retried for real.
+///
+/// It must wrap the scopes before the authentication does, so it only sees the requests
+/// of authenticated users. The keys are scoped to the [`Principal`] of the request.
+pub struct Idempotency;
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = IdempotencyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: body::MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let keys = req.app_data::<web::Data<IdempotencyKeys>>().cloned();
+        let key = req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .map(|value| value.to_str().unwrap_or_default().to_string());
+        let owner = req.extensions().get::<Principal>().map(owner);
+
+        Box::pin(async move {
+            let (keys, key, owner) = match (keys, key, owner) {
+                (Some(keys), Some(key), Some(owner)) if req.method() == Method::POST => {
+                    (keys, key, owner)
+                }
+                _ => return Ok(service.call(req).await?.map_into_boxed_body()),
+            };
+            if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+                let error = ApiError::bad_request(
+                    "invalid_idempotency_key",
+                    format!(
+                        "The `Idempotency-Key` header must have between 1 and {} visible ASCII characters",
+                        MAX_IDEMPOTENCY_KEY_LEN
+                    ),
+                );
+                return Ok(req.error_response(error));
+            }
+            process(service, keys, owner, key, req).await
+        })
+    }
+}
+
+async fn process<S, B>(
+    service: Rc<S>,
+    keys: web::Data<IdempotencyKeys>,
+    owner: String,
+    key: String,
+    mut req: ServiceRequest,
+) -> Result<ServiceResponse<BoxBody>, Error>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: body::MessageBody + 'static,
+{
+    let body = {
+        let (http_req, payload) = req.parts_mut();
+        web::Bytes::from_request(http_req, payload).await
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => return Ok(req.error_response(e)),
+    };
+    let request_hash = request_hash(&req, &body);
+    // the handler reads the body again
+    let payload: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> =
+        Box::pin(stream::once(ready(Ok(body))));
+    req.set_payload(Payload::from(payload));
+
+    let claimed = keys
+        .repository
+        .claim_key(&owner, &key, &request_hash, keys.lease.as_secs() as i64)
+        .await;
+    match claimed {
+        Err(e) => return Ok(req.error_response(ApiError::from(e))),
+        Ok(Some(record)) if record.request_hash != request_hash => {
+            let error = ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "idempotency_key_reused",
+                "The `Idempotency-Key` was already used for another request",
+            );
+            return Ok(req.error_response(error));
+        }
+        Ok(Some(record)) => {
+            return Ok(match record.response {
+                Some(response) => req.into_response(replay(response)),
+                None => req.error_response(ApiError::conflict(
+                    "idempotency_key_in_use",
+                    "A request with this `Idempotency-Key` is still being processed",
+                )),
+            });
+        }
+        Ok(None) => {}
+    }
+
+    let res = match service.call(req).await {
+        Ok(res) => res,
+        Err(e) => {
+            log_error(keys.repository.release_key(&owner, &key).await);
+            return Err(e);
+        }
+    };
+    let status = res.status();
+    let headers: Vec<(String, String)> = res
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let body = match body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            log_error(keys.repository.release_key(&owner, &key).await);
+            let error: Box<dyn std::error::Error> = e.into();
+            return Ok(ServiceResponse::new(
+                req,
+                ApiError::internal(error).error_response(),
+            ));
+        }
+    };
+
+    if status.is_server_error() {
+        log_error(keys.repository.release_key(&owner, &key).await);
+    } else {
+        let response = IdempotentResponse {
+            status: status.as_u16(),
+            headers,
+            body: body.to_vec(),
+        };
+        let ttl_secs = keys.ttl.as_secs() as i64;
+        log_error(
+            keys.repository
+                .save_response(&owner, &key, &response, ttl_secs)
+                .await,
+        );
+    }
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+}
+
+/// Hash of everything that makes a request different from another one.
+fn request_hash(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        req.method().as_str().as_bytes(),
+        req.path().as_bytes(),
+        req.query_string().as_bytes(),
+        body,
+    ] {
+        // the length keeps the parts from running into each other
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn replay(response: IdempotentResponse) -> HttpResponse {
+    let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in response.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            builder.append_header((name, value));
+        }
+    }
+    builder
+        .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+        .body(response.body)
+}
+
+/// The response is sent anyway, the key only expires earlier or later than it should.
+fn log_error(result: RepositoryResult<()>) {
+    if let Err(e) = result {
+        tracing::error!("Error storing the idempotency key: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::db::{InMemoryIdempotencyRepository, InMemoryStore};
+    use actix_web::{
+        test::{self, TestRequest},
+        App,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Header with the subject of the caller, which stands in for the authentication.
+    const CALLER_HEADER: &str = "x-caller";
+
+    /// Counts the requests it processes. The first `failures` ones fail.
+    struct Counter {
+        calls: AtomicUsize,
+        failures: usize,
+    }
+
+    async fn count(body: web::Bytes, counter: web::Data<Counter>) -> HttpResponse {
+        let calls = counter.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if calls <= counter.failures {
+            return HttpResponse::ServiceUnavailable().finish();
+        }
+        HttpResponse::Accepted()
+            .insert_header(("etag", "\"1\""))
+            .json(serde_json::json!({ "calls": calls, "body": body.len() }))
+    }
+
+    async fn prepare_app(
+        failures: usize,
+        repo: InMemoryIdempotencyRepository,
+    ) -> (
+        impl Service<actix_http::Request, Response = ServiceResponse, Error = Error>,
+        web::Data<Counter>,
+    ) {
+        let counter = web::Data::new(Counter {
+            calls: AtomicUsize::new(0),
+            failures,
+        });
+        let keys = IdempotencyKeys::new(repo, Duration::from_secs(60), Duration::from_secs(60));
+        let app = App::new()
+            .app_data(web::Data::new(keys))
+            .app_data(counter.clone())
+            .service(
+                web::scope("/v1")
+                    .wrap(Idempotency)
+                    .wrap_fn(|req, srv| {
+                        let subject = req.headers().get(CALLER_HEADER).unwrap();
+                        let principal = Principal {
+                            subject: subject.to_str().unwrap().to_string(),
+                            roles: vec![],
+                            tenant: None,
+                        };
+                        req.extensions_mut().insert(principal);
+                        srv.call(req)
+                    })
+                    .route("/things", web::post().to(count)),
+            );
+        (test::init_service(app).await, counter)
+    }
+
+    fn in_memory_repo() -> InMemoryIdempotencyRepository {
+        InMemoryIdempotencyRepository::new(InMemoryStore::default())
+    }
+
+    fn post(key: Option<&str>, body: &'static str) -> actix_http::Request {
+        post_as("jane", key, body)
+    }
+
+    fn post_as(caller: &str, key: Option<&str>, body: &'static str) -> actix_http::Request {
+        let req = TestRequest::post()
+            .uri("/v1/things")
+            .insert_header((CALLER_HEADER, caller))
+            .set_payload(body);
+        match key {
+            Some(key) => req.insert_header((IDEMPOTENCY_KEY_HEADER, key)),
+            None => req,
+        }
+        .to_request()
+    }
+
+    #[actix_rt::test]
+    async fn retries_get_the_stored_response() {
+        let (app, counter) = prepare_app(0, in_memory_repo()).await;
+
+        let res = test::call_service(&app, post(Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(res.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        let first = test::read_body(res).await;
+
+        let res = test::call_service(&app, post(Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            res.headers().get(IDEMPOTENT_REPLAYED_HEADER).unwrap(),
+            "true"
+        );
+        assert_eq!(res.headers().get("etag").unwrap(), "\"1\"");
+        assert_eq!(test::read_body(res).await, first);
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 1);
+
+        let res = test::call_service(&app, post(Some("key"), "{\"other\":1}")).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(body["code"], "idempotency_key_reused");
+
+        // requests without a key are always processed
+        test::call_service(&app, post(None, "{}")).await;
+        test::call_service(&app, post(None, "{}")).await;
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 3);
+
+        let res = test::call_service(&app, post(Some(""), "{}")).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn server_errors_are_not_stored() {
+        let (app, counter) = prepare_app(1, in_memory_repo()).await;
+
+        let res = test::call_service(&app, post(Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let res = test::call_service(&app, post(Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(res.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_rt::test]
+    async fn callers_have_their_own_keys() {
+        let (app, counter) = prepare_app(0, in_memory_repo()).await;
+
+        let res = test::call_service(&app, post_as("jane", Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        let res = test::call_service(&app, post_as("john", Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert!(res.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_rt::test]
+    async fn keys_of_requests_that_never_ended_are_freed() {
+        let repo = in_memory_repo();
+        let (app, counter) = prepare_app(0, repo.clone()).await;
+
+        // the same request, still being processed or dropped
+        let hash = request_hash(
+            &TestRequest::post().uri("/v1/things").to_srv_request(),
+            b"{}",
+        );
+        repo.claim_key("/jane", "key", &hash, 60).await.unwrap();
+        let res = test::call_service(&app, post(Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        repo.release_key("/jane", "key").await.unwrap();
+        repo.claim_key("/jane", "key", &hash, 0).await.unwrap();
+        let res = test::call_service(&app, post(Some("key"), "{}")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(counter.calls.load(Ordering::SeqCst), 1);
+    }
+}
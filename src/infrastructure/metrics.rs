@@ -0,0 +1,111 @@
+use crate::application::metrics::Metrics;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::time::Instant;
+
+/// Route of the requests to paths without a resource, which are all counted together so
+/// that random paths don't make up new metrics.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Middleware that counts the requests and how long they take to be answered, by method,
+/// route and status.
+///
+/// It must be wrapped before `NormalizePath`, so it sees the paths after they're
+/// normalized. The route is the first pattern matching the path whatever the method, so
+/// literal paths must be registered before the patterns that would also match them.
+pub struct HttpMetrics {
+    metrics: Metrics,
+}
+
+impl HttpMetrics {
+    pub fn new(metrics: Metrics) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HttpMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = HttpMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpMetricsMiddleware {
+            service,
+            metrics: self.metrics.clone(),
+        }))
+    }
+}
+
+pub struct HttpMetricsMiddleware<S> {
+    service: S,
+    metrics: Metrics,
+}
+
+impl<S, B> Service<ServiceRequest> for HttpMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+        let metrics = self.metrics.clone();
+
+        let fut = self.service.call(req);
+        Box::pin(async move {
+            let res = fut.await;
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            metrics.observe_request(&method, &route, status.as_u16(), start.elapsed());
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse};
+
+    #[actix_rt::test]
+    async fn requests_are_counted_by_route() {
+        let metrics = Metrics::default();
+        let app = App::new().wrap(HttpMetrics::new(metrics.clone())).service(
+            web::scope("/v1/things").route("/{thing_id}", web::get().to(HttpResponse::NoContent)),
+        );
+        let app = actix_web::test::init_service(app).await;
+
+        for uri in ["/v1/things/1", "/v1/things/2", "/nowhere"] {
+            let req = actix_web::test::TestRequest::get().uri(uri).to_request();
+            actix_web::test::call_service(&app, req).await;
+        }
+
+        let text = metrics.render().await;
+        for line in [
+            r#"http_requests_total{method="GET",route="/v1/things/{thing_id}",status="204"} 2"#,
+            r#"http_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+        ] {
+            assert!(text.lines().any(|l| l == line), "{} not in\n{}", line, text);
+        }
+    }
+}
@@ -1,3 +1,10 @@
+mod audit;
 mod auth;
 pub mod controllers;
 pub mod db;
+pub mod idempotency;
+pub mod jwt;
+pub mod metrics;
+pub mod power;
+pub mod rbac;
+pub mod request_id;
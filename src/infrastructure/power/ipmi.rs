@@ -0,0 +1,204 @@
+use crate::{
+    application::power_driver::{
+        bmc_endpoint, PowerDriver, PowerDriverError, PowerDriverResult, PowerState,
+    },
+    domain::models::Node,
+};
+use async_trait::async_trait;
+use tokio::process::Command;
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct IpmiConfig {
+    /// Path to the `ipmitool` binary.
+    pub program: String,
+    pub interface: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Default for IpmiConfig {
+    fn default() -> Self {
+        Self {
+            program: "ipmitool".to_string(),
+            interface: "lanplus".to_string(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+/// Manages nodes through IPMI by shelling out to `ipmitool`.
+#[derive(Debug, Clone)]
+pub struct IpmiPowerDriver {
+    config: IpmiConfig,
+}
+
+impl IpmiPowerDriver {
+    pub fn new(config: IpmiConfig) -> Self {
+        Self { config }
+    }
+
+    /// Builds the `ipmitool` arguments for a `chassis power` command.
+    fn args(&self, node: &Node, action: &str) -> PowerDriverResult<Vec<String>> {
+        let endpoint = bmc_endpoint(node)?;
+        let mut args = vec!["-I".to_string(), self.config.interface.clone()];
+        match split_endpoint(endpoint) {
+            (host, Some(port)) => args.extend(["-H", host, "-p", port].map(String::from)),
+            (host, None) => args.extend(["-H", host].map(String::from)),
+        }
+        if let Some(username) = &self.config.username {
+            args.extend(["-U".to_string(), username.clone()]);
+        }
+        if self.config.password.is_some() {
+            // read the password from the IPMI_PASSWORD env var so it doesn't show up in `ps`
+            args.push("-E".to_string());
+        }
+        args.extend(["chassis", "power", action].map(String::from));
+        Ok(args)
+    }
+
+    #[instrument(skip(self, node), fields(node_id = %node.id))]
+    async fn run(&self, node: &Node, action: &str) -> PowerDriverResult<String> {
+        let mut command = Command::new(&self.config.program);
+        command.args(self.args(node, action)?);
+        if let Some(password) = &self.config.password {
+            command.env("IPMI_PASSWORD", password);
+        }
+        let output = command
+            .output()
+            .await
+            .map_err(|e| PowerDriverError::Request(e.to_string()))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(PowerDriverError::UnexpectedResponse(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+}
+
+/// Splits the BMC endpoint into its host and port. It can be `host`, `host:port`, an IPv6
+/// address or an IPv6 address with a port in brackets (`[fd00::5]:623`).
+fn split_endpoint(endpoint: &str) -> (&str, Option<&str>) {
+    if let Some(bracketed) = endpoint.strip_prefix('[') {
+        return match bracketed.split_once("]:") {
+            Some((host, port)) => (host, Some(port)),
+            None => (bracketed.trim_end_matches(']'), None),
+        };
+    }
+    match endpoint.rsplit_once(':') {
+        // a bare IPv6 address has more than one colon and no port
+        Some((host, port)) if !host.contains(':') => (host, Some(port)),
+        _ => (endpoint, None),
+    }
+}
+
+fn parse_power_state(output: &str) -> PowerDriverResult<PowerState> {
+    match output.trim().to_lowercase().as_str() {
+        "chassis power is on" => Ok(PowerState::On),
+        "chassis power is off" => Ok(PowerState::Off),
+        other => Err(PowerDriverError::UnexpectedResponse(other.to_string())),
+    }
+}
+
+#[async_trait]
+impl PowerDriver for IpmiPowerDriver {
+    async fn power_on(&self, node: &Node) -> PowerDriverResult<()> {
+        self.run(node, "on").await.map(|_| ())
+    }
+
+    async fn power_off(&self, node: &Node) -> PowerDriverResult<()> {
+        self.run(node, "off").await.map(|_| ())
+    }
+
+    async fn reboot(&self, node: &Node) -> PowerDriverResult<()> {
+        self.run(node, "cycle").await.map(|_| ())
+    }
+
+    async fn query_power_state(&self, node: &Node) -> PowerDriverResult<PowerState> {
+        let output = self.run(node, "status").await?;
+        parse_power_state(&output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::{NodeStatus, PowerDriverKind};
+    use uuid::Uuid;
+
+    fn create_test_node(bmc_endpoint: &str) -> Node {
+        Node {
+            id: Uuid::new_v4(),
+            name: "my_node".to_string(),
+            cluster_id: Uuid::new_v4(),
+            status: NodeStatus::PowerOn,
+            driver: PowerDriverKind::Ipmi,
+            bmc_endpoint: Some(bmc_endpoint.to_string()),
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn args_include_host_port_and_credentials() {
+        let driver = IpmiPowerDriver::new(IpmiConfig {
+            username: Some("admin".to_string()),
+            password: Some("secret".to_string()),
+            ..Default::default()
+        });
+        let args = driver
+            .args(&create_test_node("10.0.0.5:623"), "status")
+            .unwrap();
+
+        assert_eq!(
+            args,
+            vec![
+                "-I", "lanplus", "-H", "10.0.0.5", "-p", "623", "-U", "admin", "-E", "chassis",
+                "power", "status"
+            ]
+        );
+        assert!(!args.contains(&"secret".to_string()));
+    }
+
+    #[test]
+    fn split_endpoint_accepts_ipv6_addresses() {
+        assert_eq!(split_endpoint("bmc.local"), ("bmc.local", None));
+        assert_eq!(split_endpoint("10.0.0.5:623"), ("10.0.0.5", Some("623")));
+        assert_eq!(split_endpoint("fd00::5"), ("fd00::5", None));
+        assert_eq!(split_endpoint("[fd00::5]"), ("fd00::5", None));
+        assert_eq!(split_endpoint("[fd00::5]:623"), ("fd00::5", Some("623")));
+    }
+
+    #[test]
+    fn parse_power_state_works() {
+        assert_eq!(
+            parse_power_state("Chassis Power is on\n").unwrap(),
+            PowerState::On
+        );
+        assert_eq!(
+            parse_power_state("Chassis Power is off").unwrap(),
+            PowerState::Off
+        );
+        assert!(parse_power_state("Error: Unable to establish IPMI session").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn query_power_state_runs_the_program() {
+        // `echo` stands in for ipmitool so we can check the arguments it receives
+        let driver = IpmiPowerDriver::new(IpmiConfig {
+            program: "echo".to_string(),
+            ..Default::default()
+        });
+        let output = driver
+            .run(&create_test_node("10.0.0.5"), "on")
+            .await
+            .unwrap();
+
+        assert_eq!(output.trim(), "-I lanplus -H 10.0.0.5 chassis power on");
+    }
+}
@@ -0,0 +1,5 @@
+mod ipmi;
+mod redfish;
+
+pub use ipmi::{IpmiConfig, IpmiPowerDriver};
+pub use redfish::{RedfishConfig, RedfishPowerDriver};
@@ -0,0 +1,270 @@
+use crate::{
+    application::power_driver::{
+        bmc_endpoint, PowerDriver, PowerDriverError, PowerDriverResult, PowerState,
+    },
+    domain::models::Node,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::instrument;
+
+#[derive(Debug, Clone)]
+pub struct RedfishConfig {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Path of the computer system resource, relative to the BMC endpoint.
+    pub system_path: String,
+    /// BMCs usually come with self-signed certificates.
+    pub accept_invalid_certs: bool,
+    pub timeout: Duration,
+}
+
+impl Default for RedfishConfig {
+    fn default() -> Self {
+        Self {
+            username: None,
+            password: None,
+            system_path: "/redfish/v1/Systems/1".to_string(),
+            accept_invalid_certs: false,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ComputerSystem {
+    #[serde(rename = "PowerState")]
+    power_state: String,
+}
+
+/// Manages nodes through the Redfish REST API exposed by their BMC.
+#[derive(Debug, Clone)]
+pub struct RedfishPowerDriver {
+    client: reqwest::Client,
+    config: RedfishConfig,
+}
+
+impl RedfishPowerDriver {
+    pub fn new(config: RedfishConfig) -> PowerDriverResult<Self> {
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(config.accept_invalid_certs)
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| PowerDriverError::Request(e.to_string()))?;
+        Ok(Self { client, config })
+    }
+
+    fn system_url(&self, node: &Node) -> PowerDriverResult<String> {
+        let endpoint = bmc_endpoint(node)?;
+        Ok(format!(
+            "{}{}",
+            endpoint.trim_end_matches('/'),
+            self.config.system_path
+        ))
+    }
+
+    fn authenticate(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.username {
+            Some(username) => request.basic_auth(username, self.config.password.as_ref()),
+            None => request,
+        }
+    }
+
+    #[instrument(skip(self, node), fields(node_id = %node.id))]
+    async fn reset(&self, node: &Node, reset_type: &str) -> PowerDriverResult<()> {
+        let url = format!("{}/Actions/ComputerSystem.Reset", self.system_url(node)?);
+        let request = self
+            .client
+            .post(&url)
+            .json(&json!({ "ResetType": reset_type }));
+        let response = self
+            .authenticate(request)
+            .send()
+            .await
+            .map_err(|e| PowerDriverError::Request(e.to_string()))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(PowerDriverError::UnexpectedResponse(format!(
+                "{} returned {}",
+                url,
+                response.status()
+            )))
+        }
+    }
+}
+
+#[async_trait]
+impl PowerDriver for RedfishPowerDriver {
+    async fn power_on(&self, node: &Node) -> PowerDriverResult<()> {
+        self.reset(node, "On").await
+    }
+
+    async fn power_off(&self, node: &Node) -> PowerDriverResult<()> {
+        self.reset(node, "ForceOff").await
+    }
+
+    async fn reboot(&self, node: &Node) -> PowerDriverResult<()> {
+        self.reset(node, "ForceRestart").await
+    }
+
+    #[instrument(skip(self, node), fields(node_id = %node.id))]
+    async fn query_power_state(&self, node: &Node) -> PowerDriverResult<PowerState> {
+        let url = self.system_url(node)?;
+        let response = self
+            .authenticate(self.client.get(&url))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| PowerDriverError::Request(e.to_string()))?;
+        let system = response
+            .json::<ComputerSystem>()
+            .await
+            .map_err(|e| PowerDriverError::UnexpectedResponse(e.to_string()))?;
+        parse_power_state(&system.power_state)
+    }
+}
+
+fn parse_power_state(power_state: &str) -> PowerDriverResult<PowerState> {
+    match power_state {
+        "On" => Ok(PowerState::On),
+        "Off" => Ok(PowerState::Off),
+        "PoweringOn" => Ok(PowerState::PoweringOn),
+        "PoweringOff" => Ok(PowerState::PoweringOff),
+        other => Err(PowerDriverError::UnexpectedResponse(format!(
+            "Unknown power state `{}`",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::models::{NodeStatus, PowerDriverKind};
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    const SYSTEM_PATH: &str = "/redfish/v1/Systems/1";
+
+    struct MockBmc {
+        power_state: Mutex<String>,
+    }
+
+    async fn get_system(bmc: web::Data<MockBmc>) -> HttpResponse {
+        let power_state = bmc.power_state.lock().unwrap().clone();
+        HttpResponse::Ok().json(json!({ "Id": "1", "PowerState": power_state }))
+    }
+
+    async fn reset_system(
+        body: web::Json<serde_json::Value>,
+        bmc: web::Data<MockBmc>,
+    ) -> HttpResponse {
+        let power_state = match body["ResetType"].as_str() {
+            Some("On") | Some("ForceRestart") => "On",
+            Some("ForceOff") => "Off",
+            _ => return HttpResponse::BadRequest().finish(),
+        };
+        *bmc.power_state.lock().unwrap() = power_state.to_string();
+        HttpResponse::NoContent().finish()
+    }
+
+    /// Starts a fake BMC in a random port and returns its endpoint.
+    fn start_mock_bmc() -> String {
+        let bmc = web::Data::new(MockBmc {
+            power_state: Mutex::new("On".to_string()),
+        });
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(bmc.clone())
+                .route(SYSTEM_PATH, web::get().to(get_system))
+                .route(
+                    &format!("{}/Actions/ComputerSystem.Reset", SYSTEM_PATH),
+                    web::post().to(reset_system),
+                )
+        })
+        .workers(1)
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let address = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{}", address)
+    }
+
+    fn create_test_node(bmc_endpoint: Option<String>) -> Node {
+        Node {
+            id: Uuid::new_v4(),
+            name: "my_node".to_string(),
+            cluster_id: Uuid::new_v4(),
+            status: NodeStatus::PowerOn,
+            driver: PowerDriverKind::Redfish,
+            bmc_endpoint,
+            created_at: None,
+            updated_at: None,
+            version: 0,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn redfish_driver_works_against_mock_bmc() {
+        let node = create_test_node(Some(start_mock_bmc()));
+        let driver = RedfishPowerDriver::new(RedfishConfig::default()).unwrap();
+
+        assert_eq!(
+            driver.query_power_state(&node).await.unwrap(),
+            PowerState::On
+        );
+        driver.power_off(&node).await.unwrap();
+        assert_eq!(
+            driver.query_power_state(&node).await.unwrap(),
+            PowerState::Off
+        );
+        driver.reboot(&node).await.unwrap();
+        assert_eq!(
+            driver.query_power_state(&node).await.unwrap(),
+            PowerState::On
+        );
+    }
+
+    #[test]
+    fn parse_power_state_keeps_the_transitions() {
+        assert_eq!(parse_power_state("On").unwrap(), PowerState::On);
+        assert_eq!(
+            parse_power_state("PoweringOn").unwrap(),
+            PowerState::PoweringOn
+        );
+        assert_eq!(
+            NodeStatus::from(parse_power_state("PoweringOn").unwrap()),
+            NodeStatus::PowerOn
+        );
+        assert!(parse_power_state("Paused").is_err());
+    }
+
+    #[actix_rt::test]
+    async fn redfish_driver_fails_if_system_does_not_exist() {
+        let node = create_test_node(Some(start_mock_bmc()));
+        let config = RedfishConfig {
+            system_path: "/redfish/v1/Systems/2".to_string(),
+            ..Default::default()
+        };
+        let driver = RedfishPowerDriver::new(config).unwrap();
+
+        assert!(driver.power_on(&node).await.is_err());
+        assert!(driver.query_power_state(&node).await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn redfish_driver_needs_an_endpoint() {
+        let node = create_test_node(None);
+        let driver = RedfishPowerDriver::new(RedfishConfig::default()).unwrap();
+
+        assert!(matches!(
+            driver.power_on(&node).await,
+            Err(PowerDriverError::MissingEndpoint(_))
+        ));
+    }
+}
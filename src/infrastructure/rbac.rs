@@ -0,0 +1,200 @@
+use crate::{
+    application::rbac_service::GrantsResolver,
+    domain::models::{AuditAction, AuditEvent, AuditTargetType, Grants, Permission, Principal},
+    infrastructure::{audit::Audit, controllers::ApiError},
+};
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{Method, StatusCode},
+    web, Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+/// Permissions needed for a request, any of which is enough to get past the [`Authorize`]
+/// middleware.
+pub type RequiredPermissions = fn(&ServiceRequest) -> &'static [Permission];
+
+/// Middleware that rejects with `403` the requests of the callers that don't have the
+/// permissions they need in any cluster, leaving their [`Grants`] in the extensions of
+/// the request.
+///
+/// The handlers check the grants again for the cluster they work on, since the cluster
+/// isn't known until the resources are loaded.
+///
+/// The grants come from the [`GrantsResolver`] of the app, or only from the roles of the
+/// token if there's none. It must wrap the scopes after the authentication does, so the
+/// [`Principal`] is already known.
+pub struct Authorize {
+    required: RequiredPermissions,
+    audited: Option<AuditTargetType>,
+}
+
+impl Authorize {
+    pub fn new(required: RequiredPermissions) -> Self {
+        Self {
+            required,
+            audited: None,
+        }
+    }
+
+    /// Records the rejected requests that would have changed the targets as failed
+    /// attempts, with the id at the end of their path as the target.
+    pub fn audited(mut self, target_type: AuditTargetType) -> Self {
+        self.audited = Some(target_type);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Authorize
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = AuthorizeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthorizeMiddleware {
+            service: Rc::new(service),
+            required: self.required,
+            audited: self.audited,
+        }))
+    }
+}
+
+pub struct AuthorizeMiddleware<S> {
+    service: Rc<S>,
+    required: RequiredPermissions,
+    audited: Option<AuditTargetType>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthorizeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let required = (self.required)(&req);
+        let audited = self.audited;
+
+        Box::pin(async move {
+            let principal = req.extensions().get::<Principal>().cloned();
+            let principal = match principal {
+                Some(principal) => principal,
+                None => return Ok(req.error_response(unauthorized()).map_into_right_body()),
+            };
+            let grants = match req.app_data::<web::Data<dyn GrantsResolver>>() {
+                Some(resolver) => match resolver.grants(&principal).await {
+                    Ok(grants) => grants,
+                    Err(e) => {
+                        let error = ApiError::from(e);
+                        return Ok(req.error_response(error).map_into_right_body());
+                    }
+                },
+                None => Grants::new(principal, &[]),
+            };
+            if !required
+                .iter()
+                .any(|permission| grants.allows_anywhere(*permission))
+            {
+                tracing::warn!(
+                    "{} is missing {:?} for {} {}",
+                    grants.principal.subject,
+                    required,
+                    req.method(),
+                    req.path()
+                );
+                let error = match audited {
+                    Some(target_type) if req.method() != Method::GET => {
+                        let error = forbidden(required);
+                        let event = rejected_event(&req, target_type, &error);
+                        match Audit::extract(&req.parts_mut().0.clone()).await {
+                            Ok(audit) => {
+                                audit.record(event).await;
+                                error
+                            }
+                            Err(e) => e,
+                        }
+                    }
+                    _ => forbidden(required),
+                };
+                return Ok(req.error_response(error).map_into_right_body());
+            }
+            req.extensions_mut().insert(grants);
+            let res = service.call(req).await?;
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// Failed attempt to change the target of the request.
+fn rejected_event(
+    req: &ServiceRequest,
+    target_type: AuditTargetType,
+    error: &ApiError,
+) -> AuditEvent {
+    let action = match *req.method() {
+        Method::POST => AuditAction::Create,
+        Method::DELETE => AuditAction::Delete,
+        _ => AuditAction::Update,
+    };
+    let target_id = req
+        .path()
+        .rsplit('/')
+        .next()
+        .and_then(|segment| segment.parse().ok());
+    AuditEvent::new(action, target_type, target_id).with_result(&Err::<(), _>(error))
+}
+
+fn forbidden(required: &[Permission]) -> ApiError {
+    let permissions: Vec<String> = required
+        .iter()
+        .map(|permission| format!("`{}`", permission))
+        .collect();
+    ApiError::new(
+        StatusCode::FORBIDDEN,
+        "forbidden",
+        format!("Missing permission {}", permissions.join(" or ")),
+    )
+}
+
+fn unauthorized() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "unauthorized",
+        "The request isn't authenticated",
+    )
+}
+
+/// Grants of the caller of the handlers wrapped with [`Authorize`].
+impl FromRequest for Grants {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<Grants>()
+                .cloned()
+                .ok_or_else(unauthorized),
+        )
+    }
+}
+
+/// Grants of an admin for the tests of the handlers.
+#[cfg(test)]
+pub fn test_grants() -> Grants {
+    Grants::new(super::auth::test_principal(), &[])
+}
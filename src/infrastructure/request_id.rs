@@ -0,0 +1,129 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+// longer ids coming from the clients are replaced by a new one
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Returns the id of the request being processed, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Middleware that gives every request an id, reusing the `X-Request-Id`
+/// header sent by the client when it's a valid one.
+///
+/// The id is sent back in the `X-Request-Id` response header and can be
+/// retrieved with [`current`] while the request is being processed.
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware { service }))
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let header_value = HeaderValue::from_str(&request_id);
+
+        let fut = REQUEST_ID.scope(request_id, self.service.call(req));
+        Box::pin(async move {
+            let mut res = fut.await?;
+            if let Ok(value) = header_value {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpResponse};
+
+    async fn echo_request_id() -> HttpResponse {
+        HttpResponse::Ok().body(current().unwrap_or_default())
+    }
+
+    #[actix_rt::test]
+    async fn request_id_is_generated_and_returned() {
+        let app = App::new()
+            .wrap(RequestId)
+            .route("/", web::get().to(echo_request_id));
+        let app = actix_web::test::init_service(app).await;
+
+        let res = actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get().uri("/").to_request(),
+        )
+        .await;
+        let header = res.headers().get(REQUEST_ID_HEADER).unwrap().clone();
+        let body = actix_web::test::read_body(res).await;
+
+        assert!(uuid::Uuid::parse_str(header.to_str().unwrap()).is_ok());
+        assert_eq!(body, header.as_bytes());
+    }
+
+    #[actix_rt::test]
+    async fn request_id_from_the_client_is_reused() {
+        let app = App::new()
+            .wrap(RequestId)
+            .route("/", web::get().to(echo_request_id));
+        let app = actix_web::test::init_service(app).await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/")
+            .insert_header((REQUEST_ID_HEADER, "my-request"))
+            .to_request();
+        let res = actix_web::test::call_service(&app, req).await;
+
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "my-request");
+    }
+
+    #[test]
+    fn current_is_empty_outside_requests() {
+        assert_eq!(current(), None);
+    }
+}
@@ -3,16 +3,90 @@ mod domain;
 mod infrastructure;
 
 use crate::{
-    application::operation_service::OperationService,
+    application::{
+        api_key_service::{ApiKeyService, Authenticator},
+        audit_log::{AuditLog, AuditTrail},
+        maintenance_policy::MaintenanceWindowPolicy,
+        metrics::{Metrics, NodeStatusCollector},
+        operation_service::OperationService,
+        operation_worker::{OperationWorker, OperationWorkerConfig},
+        power_driver::{PowerDrivers, SimulatedPowerDriver},
+        rbac_service::{GrantsResolver, RbacService},
+        rollout_service::RolloutService,
+        rollout_worker::{RolloutWorker, RolloutWorkerConfig},
+        schedule_service::ScheduleService,
+        scheduler::{Scheduler, SchedulerConfig},
+    },
+    domain::{
+        models::PowerDriverKind,
+        repository::{
+            ApiKeyRepository, AuditRepository, ClusterRepository, IdempotencyRepository,
+            NodeRepository, OrganizationRepository, RoleBindingRepository, RolloutRepository,
+            ScheduleRepository,
+        },
+    },
     infrastructure::{
         controllers,
-        db::{PostgresClusterRepository, PostgresNodeRepository},
+        db::{
+            InMemoryApiKeyRepository, InMemoryAuditRepository, InMemoryClusterRepository,
+            InMemoryIdempotencyRepository, InMemoryNodeRepository, InMemoryOrganizationRepository,
+            InMemoryRoleBindingRepository, InMemoryRolloutRepository, InMemoryScheduleRepository,
+            InMemoryStore, PostgresApiKeyRepository, PostgresAuditRepository,
+            PostgresClusterRepository, PostgresIdempotencyRepository, PostgresNodeRepository,
+            PostgresOrganizationRepository, PostgresPoolCollector, PostgresRoleBindingRepository,
+            PostgresRolloutRepository, PostgresScheduleRepository,
+        },
+        idempotency::IdempotencyKeys,
+        jwt::{JwtConfig, JwtValidator},
+        metrics::HttpMetrics,
+        power::{IpmiConfig, IpmiPowerDriver, RedfishConfig, RedfishPowerDriver},
+        request_id::RequestId,
     },
 };
 use actix_cors::Cors;
 use actix_web::{middleware, web, App, HttpServer};
+use sqlx::postgres::PgPoolOptions;
+use std::{
+    io::Write,
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 use tracing_subscriber::EnvFilter;
 
+fn env_list(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Writes a secret to a file only its owner can read, replacing the file if it exists.
+fn write_secret(path: &str, secret: &str) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // the mode is only applied to the new files
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(secret.as_bytes())
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // init env vars
@@ -28,22 +102,244 @@ async fn main() -> std::io::Result<()> {
         tracing.json().init();
     }
 
+    // instantiate repos
     let conn_str = std::env::var("DATABASE_URL").expect("No DATABASE_URL env var found");
-    let pool = sqlx::PgPool::connect(&conn_str)
+    if conn_str.starts_with("memory:") {
+        tracing::warn!("Using in-memory storage. Data will be lost when the API stops");
+        let store = InMemoryStore::default();
+        run(
+            InMemoryClusterRepository::new(store.clone()),
+            InMemoryNodeRepository::new(store.clone()),
+            InMemoryRolloutRepository::new(store.clone()),
+            InMemoryScheduleRepository::new(store.clone()),
+            InMemoryIdempotencyRepository::new(store.clone()),
+            InMemoryApiKeyRepository::new(store.clone()),
+            InMemoryRoleBindingRepository::new(store.clone()),
+            InMemoryOrganizationRepository::new(store.clone()),
+            InMemoryAuditRepository::new(store),
+            Metrics::default(),
+        )
+        .await
+    } else if conn_str.starts_with("sqlite:") {
+        run_sqlite(&conn_str).await
+    } else {
+        let max_connections = env_or("DATABASE_MAX_CONNECTIONS", 10);
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(&conn_str)
+            .await
+            .expect("Can't connect to database");
+        let metrics = Metrics::default();
+        metrics.register(PostgresPoolCollector::new(pool.clone(), max_connections));
+        // pool uses arc internally so it can be cloned without any impact
+        run(
+            PostgresClusterRepository::new(pool.clone()),
+            PostgresNodeRepository::new(pool.clone()),
+            PostgresRolloutRepository::new(pool.clone()),
+            PostgresScheduleRepository::new(pool.clone()),
+            PostgresIdempotencyRepository::new(pool.clone()),
+            PostgresApiKeyRepository::new(pool.clone()),
+            PostgresRoleBindingRepository::new(pool.clone()),
+            PostgresOrganizationRepository::new(pool.clone()),
+            PostgresAuditRepository::new(pool),
+            metrics,
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "sqlite")]
+async fn run_sqlite(conn_str: &str) -> std::io::Result<()> {
+    use infrastructure::db::{
+        sqlite_pool, SqliteApiKeyRepository, SqliteAuditRepository, SqliteClusterRepository,
+        SqliteIdempotencyRepository, SqliteNodeRepository, SqliteOrganizationRepository,
+        SqliteRoleBindingRepository, SqliteRolloutRepository, SqliteScheduleRepository,
+    };
+
+    let pool = sqlite_pool(conn_str)
         .await
         .expect("Can't connect to database");
+    run(
+        SqliteClusterRepository::new(pool.clone()),
+        SqliteNodeRepository::new(pool.clone()),
+        SqliteRolloutRepository::new(pool.clone()),
+        SqliteScheduleRepository::new(pool.clone()),
+        SqliteIdempotencyRepository::new(pool.clone()),
+        SqliteApiKeyRepository::new(pool.clone()),
+        SqliteRoleBindingRepository::new(pool.clone()),
+        SqliteOrganizationRepository::new(pool.clone()),
+        SqliteAuditRepository::new(pool),
+        Metrics::default(),
+    )
+    .await
+}
 
-    // instantiate repos
-    // pool uses arc internally so it can be cloned without any impact
-    let cluster_repo = PostgresClusterRepository::new(pool.clone());
-    let node_repo = PostgresNodeRepository::new(pool.clone());
+#[cfg(not(feature = "sqlite"))]
+async fn run_sqlite(_conn_str: &str) -> std::io::Result<()> {
+    panic!("SQLite support is not enabled. Build the API with `--features sqlite`");
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run<C, N, R, S, I, K, B, O, A>(
+    cluster_repo: C,
+    node_repo: N,
+    rollout_repo: R,
+    schedule_repo: S,
+    idempotency_repo: I,
+    api_key_repo: K,
+    role_binding_repo: B,
+    organization_repo: O,
+    audit_repo: A,
+    metrics: Metrics,
+) -> std::io::Result<()>
+where
+    C: ClusterRepository + Clone,
+    N: NodeRepository + Clone,
+    R: RolloutRepository + Clone,
+    S: ScheduleRepository + Clone,
+    I: IdempotencyRepository,
+    K: ApiKeyRepository + Clone,
+    B: RoleBindingRepository + Clone,
+    O: OrganizationRepository,
+    A: AuditRepository + Clone,
+{
+    // power drivers
+    let reboot_delay = Duration::from_secs(env_or("REBOOT_DELAY_SECS", 5));
+    let ipmi_config = IpmiConfig {
+        program: env_or("IPMITOOL_PATH", IpmiConfig::default().program),
+        username: std::env::var("IPMI_USERNAME").ok(),
+        password: std::env::var("IPMI_PASSWORD").ok(),
+        ..Default::default()
+    };
+    let redfish_config = RedfishConfig {
+        username: std::env::var("REDFISH_USERNAME").ok(),
+        password: std::env::var("REDFISH_PASSWORD").ok(),
+        accept_invalid_certs: env_or("REDFISH_ACCEPT_INVALID_CERTS", false),
+        ..Default::default()
+    };
+    let redfish_driver =
+        RedfishPowerDriver::new(redfish_config).expect("Can't create the Redfish driver");
+    let drivers = PowerDrivers::new()
+        .with(
+            PowerDriverKind::Simulated,
+            SimulatedPowerDriver::new(reboot_delay),
+        )
+        .with(PowerDriverKind::Ipmi, IpmiPowerDriver::new(ipmi_config))
+        .with(PowerDriverKind::Redfish, redfish_driver);
 
     // application services
-    let ops_svc = OperationService::new(node_repo.clone());
+    let audit_log: Arc<dyn AuditLog> =
+        Arc::new(AuditTrail::new(audit_repo.clone()).with_metrics(metrics.clone()));
+    metrics.register(NodeStatusCollector::new(cluster_repo.clone()));
+    let ops_svc = OperationService::new(node_repo.clone(), drivers)
+        .with_maintenance_policy(MaintenanceWindowPolicy::new(cluster_repo.clone()))
+        .with_audit_log(audit_log.clone())
+        .with_metrics(metrics.clone());
+    let rollout_svc = RolloutService::new(rollout_repo, node_repo.clone(), ops_svc.clone());
+    let schedule_svc = ScheduleService::new(schedule_repo, ops_svc.clone());
+    let api_key_svc = ApiKeyService::new(
+        api_key_repo,
+        Duration::from_secs(env_or("API_KEY_CACHE_TTL_SECS", 60)),
+    );
+    if let Ok(secret) = std::env::var("BOOTSTRAP_API_KEY") {
+        let key = api_key_svc
+            .bootstrap(&secret)
+            .await
+            .expect("Can't store the bootstrap API key");
+        tracing::info!("Bootstrap API key {} is ready", key.id);
+    } else if let Ok(path) = std::env::var("BOOTSTRAP_API_KEY_FILE") {
+        if let Some(minted) = api_key_svc
+            .bootstrap_if_empty()
+            .await
+            .expect("Can't mint the bootstrap API key")
+        {
+            write_secret(&path, &minted.secret).expect("Can't write the bootstrap API key");
+            tracing::warn!(
+                "There were no API keys, so the admin key {} was minted and its secret written to {}",
+                minted.key.id,
+                path
+            );
+        }
+    } else if !api_key_svc
+        .has_keys()
+        .await
+        .expect("Can't read the API keys")
+    {
+        panic!("There are no API keys: set BOOTSTRAP_API_KEY or BOOTSTRAP_API_KEY_FILE");
+    }
+    let rbac_svc = RbacService::new(
+        role_binding_repo,
+        Duration::from_secs(env_or("ROLE_BINDING_CACHE_TTL_SECS", 60)),
+    );
+    let jwt_validator = std::env::var("JWT_KEYS_PATH").ok().map(|keys_path| {
+        let defaults = JwtConfig::new(keys_path);
+        let config = JwtConfig {
+            audience: env_list("JWT_AUDIENCE"),
+            issuer: env_list("JWT_ISSUER"),
+            leeway: Duration::from_secs(env_or("JWT_LEEWAY_SECS", defaults.leeway.as_secs())),
+            reload_interval: Duration::from_secs(env_or(
+                "JWT_KEYS_RELOAD_SECS",
+                defaults.reload_interval.as_secs(),
+            )),
+            ..defaults
+        };
+        let validator = JwtValidator::new(config).expect("Can't load the JWT keys");
+        validator.clone().watch();
+        web::Data::new(validator)
+    });
+
+    // background workers
+    let defaults = OperationWorkerConfig::default();
+    let worker_config = OperationWorkerConfig {
+        poll_interval: Duration::from_millis(env_or(
+            "WORKER_POLL_INTERVAL_MS",
+            defaults.poll_interval.as_millis() as u64,
+        )),
+        lease: Duration::from_secs(env_or("WORKER_LEASE_SECS", defaults.lease.as_secs())),
+        max_attempts: env_or("WORKER_MAX_ATTEMPTS", defaults.max_attempts),
+        concurrency: env_or("WORKER_CONCURRENCY", defaults.concurrency),
+    };
+    let rollout_worker_config = RolloutWorkerConfig {
+        poll_interval: worker_config.poll_interval,
+        lease: worker_config.lease,
+        check_interval: Duration::from_secs(env_or(
+            "ROLLOUT_CHECK_INTERVAL_SECS",
+            RolloutWorkerConfig::default().check_interval.as_secs(),
+        )),
+    };
+    let scheduler_config = SchedulerConfig {
+        poll_interval: worker_config.poll_interval,
+        lease: worker_config.lease,
+        missed_run_grace: Duration::from_secs(env_or(
+            "SCHEDULER_MISSED_RUN_GRACE_SECS",
+            SchedulerConfig::default().missed_run_grace.as_secs(),
+        )),
+    };
+    OperationWorker::new(ops_svc.clone(), worker_config).start();
+    RolloutWorker::new(rollout_svc.clone(), rollout_worker_config).start();
+    Scheduler::new(schedule_svc.clone(), scheduler_config).start();
 
+    let idempotency_keys = web::Data::new(IdempotencyKeys::new(
+        idempotency_repo,
+        Duration::from_secs(env_or("IDEMPOTENCY_KEY_TTL_SECS", 24 * 60 * 60)),
+        Duration::from_secs(env_or("IDEMPOTENCY_KEY_LEASE_SECS", 60)),
+    ));
     let cluster_repo = web::Data::new(cluster_repo);
     let node_repo = web::Data::new(node_repo);
     let ops_svc = web::Data::new(ops_svc);
+    let rollout_svc = web::Data::new(rollout_svc);
+    let schedule_svc = web::Data::new(schedule_svc);
+    let authenticator: Arc<dyn Authenticator> = Arc::new(api_key_svc.clone());
+    let authenticator = web::Data::from(authenticator);
+    let api_key_svc = web::Data::new(api_key_svc);
+    let grants_resolver: Arc<dyn GrantsResolver> = Arc::new(rbac_svc.clone());
+    let grants_resolver = web::Data::from(grants_resolver);
+    let rbac_svc = web::Data::new(rbac_svc);
+    let organization_repo = web::Data::new(organization_repo);
+    let audit_log = web::Data::from(audit_log);
+    let audit_repo = web::Data::new(audit_repo);
+    let http_metrics = metrics.clone();
+    let metrics = web::Data::new(metrics);
 
     // building address
     let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
@@ -55,15 +351,40 @@ async fn main() -> std::io::Result<()> {
     HttpServer::new(move || {
         let cors = Cors::default().allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"]);
         App::new()
+            .wrap(HttpMetrics::new(http_metrics.clone()))
             .wrap(middleware::NormalizePath::trim())
             .wrap(cors)
+            .wrap(RequestId)
             .app_data(cluster_repo.clone())
             .app_data(node_repo.clone())
             .app_data(ops_svc.clone())
-            .configure(controllers::clusters::configuration::<PostgresClusterRepository>)
-            .configure(controllers::nodes::configuration::<PostgresNodeRepository>)
-            .configure(controllers::operations::configuration::<PostgresNodeRepository>)
+            .app_data(rollout_svc.clone())
+            .app_data(schedule_svc.clone())
+            .app_data(idempotency_keys.clone())
+            .app_data(authenticator.clone())
+            .app_data(api_key_svc.clone())
+            .app_data(grants_resolver.clone())
+            .app_data(rbac_svc.clone())
+            .app_data(organization_repo.clone())
+            .app_data(audit_log.clone())
+            .app_data(audit_repo.clone())
+            .app_data(metrics.clone())
+            .configure(|cfg| {
+                if let Some(jwt_validator) = &jwt_validator {
+                    cfg.app_data(jwt_validator.clone());
+                }
+            })
+            .configure(controllers::clusters::configuration::<C, N>)
+            .configure(controllers::nodes::configuration::<N>)
+            .configure(controllers::operations::configuration::<N>)
+            .configure(controllers::rollouts::configuration::<C, N, R>)
+            .configure(controllers::schedules::configuration::<C, N, S>)
+            .configure(controllers::api_keys::configuration::<K>)
+            .configure(controllers::rbac::configuration::<B>)
+            .configure(controllers::organizations::configuration::<O, K>)
+            .configure(controllers::audit::configuration::<A>)
             .configure(controllers::health::configuration)
+            .configure(controllers::metrics::configuration)
             .configure(controllers::features::configuration)
     })
     .bind(&address)